@@ -1,3 +1,84 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+// Arguments Tauri fills in itself rather than reading from the payload
+const INJECTED_ARGS: &[&str] = &["AppHandle", "WebviewWindow", "Window", "Webview", "State"];
+
 fn main() {
+    write_command_args();
     tauri_build::build()
 }
+
+/// Writes `command_args.rs` for `strict_ipc`: every `#[command]` in commands.rs with
+/// the arguments the frontend passes, taken from the function signatures so the
+/// list can't fall behind them
+fn write_command_args() {
+    println!("cargo:rerun-if-changed=src/commands.rs");
+    let source = std::fs::read_to_string("src/commands.rs").expect("read src/commands.rs");
+
+    let mut table = String::from("&[\n");
+    for (name, params) in commands(&source) {
+        write!(table, "    ({:?}, &[", name).unwrap();
+        for (arg, optional) in params {
+            write!(table, "({:?}, {}), ", arg, optional).unwrap();
+        }
+        table.push_str("]),\n");
+    }
+    table.push(']');
+
+    let out = Path::new(&std::env::var("OUT_DIR").unwrap()).join("command_args.rs");
+    std::fs::write(out, table).expect("write command_args.rs");
+}
+
+/// Each command's name with its (argument, optional) pairs
+fn commands(source: &str) -> Vec<(String, Vec<(String, bool)>)> {
+    let mut commands = Vec::new();
+    let mut rest = source;
+    while let Some(at) = rest.find("#[command]") {
+        rest = &rest[at..];
+        let fn_at = rest.find("fn ").expect("#[command] without a function") + 3;
+        rest = &rest[fn_at..];
+        let name_end = rest.find(['(', '<']).expect("command without arguments");
+        let name = rest[..name_end].trim().to_string();
+        let open = rest.find('(').unwrap() + 1;
+        let (params, len) = split_params(&rest[open..]);
+        rest = &rest[open + len..];
+
+        let args = params.iter()
+            .filter_map(|param| param.split_once(':'))
+            .map(|(arg, ty)| (arg.trim().trim_start_matches("mut ").to_string(), ty.trim()))
+            .filter(|(_, ty)| !INJECTED_ARGS.iter().any(|injected| ty.trim_start_matches("tauri::").starts_with(injected)))
+            .map(|(arg, ty)| (arg, ty.starts_with("Option<")))
+            .collect();
+        commands.push((name, args));
+    }
+    commands
+}
+
+/// The comma-separated parameters up to the closing parenthesis, and how far that is
+fn split_params(source: &str) -> (Vec<String>, usize) {
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut prev = ' ';
+    for (i, c) in source.char_indices() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            '>' if prev == '-' => {}
+            ')' if depth == 0 => {
+                params.push(current);
+                return (params.into_iter().filter(|p| !p.trim().is_empty()).collect(), i + 1);
+            }
+            ')' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                params.push(std::mem::take(&mut current));
+                prev = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        prev = c;
+    }
+    panic!("unterminated argument list");
+}
@@ -0,0 +1,106 @@
+use crate::database::SnapshotWithStats;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DEFAULT_THRESHOLD_PERCENT: f64 = 50.0;
+pub const DEFAULT_WINDOW: usize = 5;
+// Fewer prior snapshots than this make the average too noisy to flag anything
+const MIN_HISTORY: usize = 3;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyMetric {
+    TotalSize,
+    TotalFileCount,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotAnomaly {
+    pub snapshot_id: String,
+    pub short_id: String,
+    pub time: String,
+    pub hostname: String,
+    pub paths: Vec<String>,
+    pub metric: AnomalyMetric,
+    pub value: u64,
+    pub trailing_average: f64,
+    pub deviation_percent: f64,
+}
+
+fn group_key(snapshot: &SnapshotWithStats) -> (String, Vec<String>) {
+    let mut paths = snapshot.snapshot.paths.clone();
+    paths.sort();
+    (snapshot.snapshot.hostname.clone(), paths)
+}
+
+fn check_metric(
+    snapshot: &SnapshotWithStats,
+    metric: AnomalyMetric,
+    value: Option<u64>,
+    history: &[u64],
+    threshold_percent: f64,
+) -> Option<SnapshotAnomaly> {
+    let value = value?;
+    if history.len() < MIN_HISTORY {
+        return None;
+    }
+
+    let average = history.iter().sum::<u64>() as f64 / history.len() as f64;
+    if average <= 0.0 {
+        return None;
+    }
+
+    let deviation_percent = (value as f64 - average) / average * 100.0;
+    if deviation_percent.abs() <= threshold_percent {
+        return None;
+    }
+
+    Some(SnapshotAnomaly {
+        snapshot_id: snapshot.snapshot.id.clone(),
+        short_id: snapshot.snapshot.short_id.clone(),
+        time: snapshot.snapshot.time.clone(),
+        hostname: snapshot.snapshot.hostname.clone(),
+        paths: snapshot.snapshot.paths.clone(),
+        metric,
+        value,
+        trailing_average: average,
+        deviation_percent,
+    })
+}
+
+/// Flags snapshots whose size or file count deviates from the trailing average
+/// of the previous `window` snapshots of the same host and path set.
+pub fn detect_anomalies(
+    snapshots: &[SnapshotWithStats],
+    threshold_percent: f64,
+    window: usize,
+) -> Vec<SnapshotAnomaly> {
+    let mut ordered: Vec<&SnapshotWithStats> = snapshots.iter().collect();
+    ordered.sort_by(|a, b| a.snapshot.time.cmp(&b.snapshot.time));
+
+    let mut size_history: HashMap<(String, Vec<String>), Vec<u64>> = HashMap::new();
+    let mut count_history: HashMap<(String, Vec<String>), Vec<u64>> = HashMap::new();
+    let mut anomalies = Vec::new();
+
+    for snapshot in ordered {
+        let key = group_key(snapshot);
+
+        let sizes = size_history.entry(key.clone()).or_default();
+        let recent_sizes = &sizes[sizes.len().saturating_sub(window)..];
+        anomalies.extend(check_metric(snapshot, AnomalyMetric::TotalSize, snapshot.total_size, recent_sizes, threshold_percent));
+        if let Some(size) = snapshot.total_size {
+            sizes.push(size);
+        }
+
+        let counts = count_history.entry(key).or_default();
+        let recent_counts = &counts[counts.len().saturating_sub(window)..];
+        anomalies.extend(check_metric(snapshot, AnomalyMetric::TotalFileCount, snapshot.total_file_count, recent_counts, threshold_percent));
+        if let Some(count) = snapshot.total_file_count {
+            counts.push(count);
+        }
+    }
+
+    // Newest first, matching the snapshot list order
+    anomalies.reverse();
+    anomalies
+}
@@ -0,0 +1,166 @@
+use crate::error::{AppError, Result};
+use crate::storage::{load_config, RepoPriority};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, Once};
+use tracing::{debug, error, warn};
+
+/// Cache work that runs on the background worker rather than in a command
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    SnapshotRefresh,
+    StatsBackfill,
+    FileIndex,
+}
+
+struct Job {
+    repo_id: String,
+    kind: JobKind,
+    priority: RepoPriority,
+    seq: u64,
+    run: Box<dyn FnOnce() + Send>,
+}
+
+// Higher priority first, then first come first served
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+    }
+}
+
+impl Eq for Job {}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BinaryHeap<Job>,
+    next_seq: u64,
+}
+
+static QUEUE: Lazy<(Mutex<Queue>, Condvar)> = Lazy::new(|| (Mutex::new(Queue::default()), Condvar::new()));
+static WORKER: Once = Once::new();
+
+#[derive(Debug, Serialize, Clone)]
+pub struct QueuedJob {
+    pub repo_id: String,
+    pub kind: JobKind,
+    pub priority: RepoPriority,
+}
+
+fn priority_of(repo_id: &str) -> RepoPriority {
+    load_config().ok()
+        .and_then(|config| config.repositories.into_iter().find(|r| r.id == repo_id))
+        .and_then(|repo| repo.priority)
+        .unwrap_or_default()
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, Queue>> {
+    QUEUE.0.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock background queue: {}", e)))
+}
+
+fn start_worker() {
+    WORKER.call_once(|| {
+        std::thread::spawn(|| loop {
+            let job = {
+                let Ok(mut queue) = QUEUE.0.lock() else { return };
+                loop {
+                    if let Some(job) = queue.jobs.pop() {
+                        break job;
+                    }
+                    queue = match QUEUE.1.wait(queue) {
+                        Ok(queue) => queue,
+                        Err(_) => return,
+                    };
+                }
+            };
+            debug!("Running {:?} for repo {} ({:?} priority)", job.kind, job.repo_id, job.priority);
+            // A panicking job must not take the worker, and every job queued behind it, down
+            if catch_unwind(AssertUnwindSafe(job.run)).is_err() {
+                error!("Background {:?} for repo {} panicked", job.kind, job.repo_id);
+            }
+        });
+    });
+}
+
+fn push(repo_id: &str, kind: JobKind, dedupe: bool, run: Box<dyn FnOnce() + Send>) -> Result<bool> {
+    let priority = priority_of(repo_id);
+    let mut queue = lock()?;
+    if dedupe && queue.jobs.iter().any(|job| job.repo_id == repo_id && job.kind == kind) {
+        debug!("{:?} for repo {} is already queued", kind, repo_id);
+        return Ok(false);
+    }
+    let seq = queue.next_seq;
+    queue.next_seq += 1;
+    queue.jobs.push(Job { repo_id: repo_id.to_string(), kind, priority, seq, run });
+    drop(queue);
+
+    start_worker();
+    QUEUE.1.notify_one();
+    Ok(true)
+}
+
+/// Queues fire-and-forget work. Returns false when the same kind of work is
+/// already waiting for the repository, since running it twice gains nothing.
+pub fn submit<F: FnOnce() + Send + 'static>(repo_id: &str, kind: JobKind, run: F) -> bool {
+    match push(repo_id, kind, true, Box::new(run)) {
+        Ok(queued) => queued,
+        Err(e) => {
+            warn!("{}", e);
+            false
+        }
+    }
+}
+
+/// Queues work and waits for its result
+pub async fn run<T, F>(repo_id: &str, kind: JobKind, run: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    push(repo_id, kind, false, Box::new(move || {
+        let _ = sender.send(run());
+    }))?;
+    receiver.await
+        .map_err(|_| AppError::Storage(format!("Background {:?} for repo {} did not finish", kind, repo_id)))?
+}
+
+/// Moves the repository's waiting jobs to their place for its new priority
+pub fn reprioritize(repo_id: &str, priority: RepoPriority) {
+    let Ok(mut queue) = lock() else { return };
+    let jobs = std::mem::take(&mut queue.jobs);
+    queue.jobs = jobs.into_iter()
+        .map(|mut job| {
+            if job.repo_id == repo_id {
+                job.priority = priority;
+            }
+            job
+        })
+        .collect();
+}
+
+/// Jobs waiting to run, in the order they will run
+pub fn pending() -> Vec<QueuedJob> {
+    let Ok(queue) = lock() else { return Vec::new() };
+    let mut jobs: Vec<&Job> = queue.jobs.iter().collect();
+    jobs.sort_by(|a, b| b.cmp(a));
+    jobs.into_iter()
+        .map(|job| QueuedJob { repo_id: job.repo_id.clone(), kind: job.kind, priority: job.priority })
+        .collect()
+}
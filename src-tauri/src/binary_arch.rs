@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Enough for Mach-O fat headers with several slices and typical PE stubs
+const HEADER_BYTES: usize = 4096;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Arch {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+}
+
+impl Arch {
+    pub fn name(self) -> &'static str {
+        match self {
+            Arch::X86 => "x86",
+            Arch::X86_64 => "x86_64",
+            Arch::Arm => "arm",
+            Arch::Aarch64 => "arm64",
+        }
+    }
+
+    pub fn host() -> Option<Arch> {
+        match std::env::consts::ARCH {
+            "x86" => Some(Arch::X86),
+            "x86_64" => Some(Arch::X86_64),
+            "arm" => Some(Arch::Arm),
+            "aarch64" => Some(Arch::Aarch64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchCompatibility {
+    Native,
+    /// Runs through Rosetta 2 or Windows on ARM's x64 emulation
+    Emulated,
+    Incompatible,
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BinaryArchitecture {
+    /// Every architecture the binary contains; more than one for universal binaries
+    pub architectures: Vec<Arch>,
+    pub host: Option<Arch>,
+    pub compatibility: ArchCompatibility,
+}
+
+fn u16_le(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u16_be(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u32_be(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn mach_cpu_type(cpu_type: u32) -> Option<Arch> {
+    match cpu_type {
+        7 => Some(Arch::X86),
+        0x0100_0007 => Some(Arch::X86_64),
+        12 => Some(Arch::Arm),
+        0x0100_000c => Some(Arch::Aarch64),
+        _ => None,
+    }
+}
+
+fn pe_machine(machine: u16) -> Option<Arch> {
+    match machine {
+        0x014c => Some(Arch::X86),
+        0x8664 => Some(Arch::X86_64),
+        0x01c4 => Some(Arch::Arm),
+        0xaa64 => Some(Arch::Aarch64),
+        _ => None,
+    }
+}
+
+fn elf_machine(machine: u16) -> Option<Arch> {
+    match machine {
+        3 => Some(Arch::X86),
+        62 => Some(Arch::X86_64),
+        40 => Some(Arch::Arm),
+        183 => Some(Arch::Aarch64),
+        _ => None,
+    }
+}
+
+/// Reads the architectures from a Mach-O (thin or universal), PE or ELF header
+pub fn parse_header(header: &[u8]) -> Option<Vec<Arch>> {
+    let magic = header.get(..4)?;
+    match magic {
+        // 32 and 64-bit thin Mach-O, little endian
+        [0xce, 0xfa, 0xed, 0xfe] | [0xcf, 0xfa, 0xed, 0xfe] => {
+            Some(mach_cpu_type(u32_le(header, 4)?).into_iter().collect())
+        }
+        // Universal binaries; fat_arch entries are 20 bytes, fat_arch_64 entries 32
+        [0xca, 0xfe, 0xba, 0xbe] | [0xca, 0xfe, 0xba, 0xbf] => {
+            let entry_size = if magic[3] == 0xbe { 20 } else { 32 };
+            let count = u32_be(header, 4)? as usize;
+            // Java class files share the magic but have a version number here
+            if count == 0 || count > 20 {
+                return None;
+            }
+            Some((0..count)
+                .filter_map(|i| u32_be(header, 8 + i * entry_size).and_then(mach_cpu_type))
+                .collect())
+        }
+        [b'M', b'Z', ..] => {
+            let pe_offset = u32_le(header, 0x3c)? as usize;
+            if header.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+                return None;
+            }
+            Some(pe_machine(u16_le(header, pe_offset + 4)?).into_iter().collect())
+        }
+        [0x7f, b'E', b'L', b'F'] => {
+            let machine = match header.get(5)? {
+                2 => u16_be(header, 18)?,
+                _ => u16_le(header, 18)?,
+            };
+            Some(elf_machine(machine).into_iter().collect())
+        }
+        _ => None,
+    }
+}
+
+fn compatibility(architectures: &[Arch], host: Option<Arch>) -> ArchCompatibility {
+    let Some(host) = host else {
+        return ArchCompatibility::Unknown;
+    };
+    if architectures.is_empty() {
+        return ArchCompatibility::Unknown;
+    }
+    if architectures.contains(&host) {
+        return ArchCompatibility::Native;
+    }
+
+    let emulated = match host {
+        // Rosetta 2 and Windows on ARM run x86-64 code; Windows on ARM also runs 32-bit x86
+        Arch::Aarch64 if cfg!(any(target_os = "macos", target_os = "windows")) => {
+            architectures.contains(&Arch::X86_64)
+                || (cfg!(target_os = "windows") && architectures.contains(&Arch::X86))
+        }
+        Arch::X86_64 => architectures.contains(&Arch::X86),
+        _ => false,
+    };
+    if emulated {
+        ArchCompatibility::Emulated
+    } else {
+        ArchCompatibility::Incompatible
+    }
+}
+
+/// Inspects the binary at `path`. Returns None when the file can't be read or isn't an executable we recognize.
+pub fn inspect(path: &Path) -> Option<BinaryArchitecture> {
+    let mut header = Vec::with_capacity(HEADER_BYTES);
+    File::open(path).ok()?
+        .take(HEADER_BYTES as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+
+    let architectures = parse_header(&header)?;
+    let host = Arch::host();
+    Some(BinaryArchitecture {
+        compatibility: compatibility(&architectures, host),
+        architectures,
+        host,
+    })
+}
+
+/// Lower is better: native binaries first, then unknown, emulated and incompatible ones
+pub fn rank(path: &Path) -> u8 {
+    match inspect(path).map(|a| a.compatibility) {
+        Some(ArchCompatibility::Native) => 0,
+        Some(ArchCompatibility::Unknown) | None => 1,
+        Some(ArchCompatibility::Emulated) => 2,
+        Some(ArchCompatibility::Incompatible) => 3,
+    }
+}
@@ -0,0 +1,145 @@
+use crate::error::{AppError, Result};
+use crate::node_cache;
+use crate::models::{BrowseFilter, BrowseSort, FileNode, FilePage, SortOrder};
+use regex::Regex;
+use std::cmp::Ordering;
+
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+pub const MAX_PAGE_SIZE: usize = 5_000;
+/// Most nodes `get_snapshot_details` returns; larger snapshots are browsed page by page
+pub const MAX_DETAIL_NODES: usize = 50_000;
+
+/// restic lists the directory itself too, and everything below it without a path
+pub fn is_child_of(dir: &str, node: &FileNode) -> bool {
+    node.path != dir && node_cache::parent_dir(&node.path) == dir
+}
+
+fn mtime(node: &FileNode) -> Option<(i64, u32)> {
+    node.mtime.as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| (t.timestamp(), t.timestamp_subsec_nanos()))
+}
+
+fn by_name(a: &FileNode, b: &FileNode) -> Ordering {
+    a.name.to_lowercase().cmp(&b.name.to_lowercase()).then_with(|| a.name.cmp(&b.name))
+}
+
+/// Directories come first in either order
+pub fn compare(sort: BrowseSort, order: SortOrder, a: &FileNode, b: &FileNode) -> Ordering {
+    let dirs_first = (b.node_type == "dir").cmp(&(a.node_type == "dir"));
+    let key = match sort {
+        BrowseSort::Name => Ordering::Equal,
+        BrowseSort::Size => a.size.unwrap_or(0).cmp(&b.size.unwrap_or(0)),
+        BrowseSort::Mtime => mtime(a).cmp(&mtime(b)),
+        BrowseSort::Type => a.node_type.cmp(&b.node_type),
+    };
+    let key = key.then_with(|| by_name(a, b));
+    dirs_first.then(match order {
+        SortOrder::Asc => key,
+        SortOrder::Desc => key.reverse(),
+    })
+}
+
+const NODE_TYPES: &[&str] = &["file", "dir", "symlink", "dev", "chardev", "fifo", "socket", "irregular"];
+
+/// A `BrowseFilter` with its glob compiled
+pub struct NodeFilter {
+    name: Option<Regex>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    node_type: Option<String>,
+}
+
+fn glob_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|_| AppError::InvalidFilterValue(glob.to_string()))
+}
+
+impl NodeFilter {
+    pub fn new(filter: &BrowseFilter) -> Result<Self> {
+        if let (Some(min), Some(max)) = (filter.min_size, filter.max_size) {
+            if min > max {
+                return Err(AppError::InvalidFilterValue(format!("{} > {}", min, max)));
+            }
+        }
+        if let Some(node_type) = &filter.node_type {
+            if !NODE_TYPES.contains(&node_type.as_str()) {
+                return Err(AppError::InvalidFilterValue(node_type.clone()));
+            }
+        }
+        Ok(NodeFilter {
+            name: filter.name.as_deref().map(str::trim).filter(|g| !g.is_empty()).map(glob_regex).transpose()?,
+            min_size: filter.min_size,
+            max_size: filter.max_size,
+            node_type: filter.node_type.clone(),
+        })
+    }
+
+    pub fn accepts(&self, node: &FileNode) -> bool {
+        if self.node_type.as_ref().is_some_and(|t| *t != node.node_type) {
+            return false;
+        }
+        if node.node_type == "dir" {
+            return true;
+        }
+        let size = node.size.unwrap_or(0);
+        self.name.as_ref().is_none_or(|re| re.is_match(&node.name))
+            && self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+    }
+}
+
+/// Keeps only the entries of one page while a listing streams past, so huge
+/// directories never have to be held in memory as a whole
+pub struct PageCollector {
+    offset: usize,
+    limit: usize,
+    sort: BrowseSort,
+    order: SortOrder,
+    total: usize,
+    kept: Vec<FileNode>,
+}
+
+impl PageCollector {
+    pub fn new(offset: usize, limit: usize, sort: BrowseSort, order: SortOrder) -> Self {
+        PageCollector { offset, limit, sort, order, total: 0, kept: Vec::new() }
+    }
+
+    fn window(&self) -> usize {
+        self.offset.saturating_add(self.limit)
+    }
+
+    pub fn push(&mut self, node: FileNode) {
+        self.total += 1;
+        self.kept.push(node);
+        // Anything past the page can never move back into it, so trim once the
+        // buffer has doubled; sorting is amortized over the trimmed entries
+        let window = self.window();
+        if self.kept.len() >= window.saturating_mul(2).max(1024) {
+            let (sort, order) = (self.sort, self.order);
+            self.kept.sort_by(|a, b| compare(sort, order, a, b));
+            self.kept.truncate(window);
+        }
+    }
+
+    pub fn finish(mut self, path: String) -> FilePage {
+        let (sort, order) = (self.sort, self.order);
+        self.kept.sort_by(|a, b| compare(sort, order, a, b));
+        let entries: Vec<FileNode> = self.kept.into_iter().skip(self.offset).take(self.limit).collect();
+        FilePage {
+            path,
+            has_more: self.offset + entries.len() < self.total,
+            offset: self.offset,
+            total: self.total,
+            entries,
+        }
+    }
+}
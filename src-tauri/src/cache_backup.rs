@@ -0,0 +1,161 @@
+use crate::database;
+use crate::error::{AppError, Result};
+use crate::storage::get_config_dir;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+
+const BACKUP_SUFFIX: &str = ".db.gz";
+// Backups kept per repository; older ones are deleted when a new one is made
+const MAX_BACKUPS_PER_REPO: usize = 3;
+// Backups older than this are deleted whatever their count
+const MAX_BACKUP_AGE_SECS: i64 = 30 * 24 * 3600;
+
+/// A repository's cached rows, saved before its cache was cleared
+#[derive(Debug, Serialize, Clone)]
+pub struct CacheBackup {
+    pub repo_id: String,
+    pub file_name: String,
+    pub created_at: i64,
+    pub size_bytes: u64,
+}
+
+pub fn backup_dir() -> Result<PathBuf> {
+    Ok(get_config_dir().map_err(AppError::Storage)?.join("cache_backups"))
+}
+
+// Backups are named `<repo_id>-<unix millis>.db.gz`
+fn parse_name(file_name: &str) -> Option<(&str, i64)> {
+    let stem = file_name.strip_suffix(BACKUP_SUFFIX)?;
+    let (repo_id, millis) = stem.rsplit_once('-')?;
+    Some((repo_id, millis.parse().ok()?))
+}
+
+fn all_backups() -> Result<Vec<(CacheBackup, PathBuf)>> {
+    let entries = match fs::read_dir(backup_dir()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some((repo_id, millis)) = parse_name(&file_name) else {
+            continue;
+        };
+        let backup = CacheBackup {
+            repo_id: repo_id.to_string(),
+            created_at: millis / 1000,
+            size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            file_name,
+        };
+        backups.push((backup, entry.path()));
+    }
+    backups.sort_by(|(a, _), (b, _)| b.file_name.cmp(&a.file_name));
+    Ok(backups)
+}
+
+/// A repository's backups, newest first
+pub fn list(repo_id: &str) -> Result<Vec<CacheBackup>> {
+    Ok(all_backups()?.into_iter()
+        .filter(|(b, _)| b.repo_id == repo_id)
+        .map(|(b, _)| b)
+        .collect())
+}
+
+fn compress(src: &Path, dest: &Path) -> Result<()> {
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(dest)?), Compression::default());
+    io::copy(&mut BufReader::new(File::open(src)?), &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+fn decompress(src: &Path, dest: &Path) -> Result<()> {
+    let mut decoder = GzDecoder::new(BufReader::new(File::open(src)?));
+    io::copy(&mut decoder, &mut BufWriter::new(File::create(dest)?))?;
+    Ok(())
+}
+
+/// Saves the repository's cached rows to a compressed backup. Returns None when
+/// there's nothing cached worth keeping.
+#[instrument]
+pub fn create(repo_id: &str) -> Result<Option<CacheBackup>> {
+    let dir = backup_dir()?;
+    fs::create_dir_all(&dir)?;
+    let millis = chrono::Utc::now().timestamp_millis();
+    let file_name = format!("{}-{}{}", repo_id, millis, BACKUP_SUFFIX);
+    let raw = dir.join(format!("{}-{}.db.partial", repo_id, millis));
+
+    let exported = database::export_repo_cache(repo_id, &raw)
+        .and_then(|snapshots| match snapshots {
+            0 => Ok(None),
+            n => compress(&raw, &dir.join(&file_name)).map(|_| Some(n)),
+        });
+    if let Err(e) = fs::remove_file(&raw) {
+        warn!("Failed to remove {:?}: {}", raw, e);
+    }
+    let Some(snapshots) = exported? else {
+        info!("Nothing cached for repo {}, no backup made", repo_id);
+        return Ok(None);
+    };
+
+    prune();
+    info!("Backed up {} cached snapshots of repo {} to {}", snapshots, repo_id, file_name);
+    Ok(list(repo_id)?.into_iter().find(|b| b.file_name == file_name))
+}
+
+/// Replaces the repository's cache with its newest backup. Returns the number of snapshots restored.
+#[instrument]
+pub fn restore(repo_id: &str) -> Result<usize> {
+    let backup = list(repo_id)?.into_iter().next()
+        .ok_or_else(|| AppError::NoCacheBackup(repo_id.to_string()))?;
+    let dir = backup_dir()?;
+    let raw = dir.join(backup.file_name.replace(BACKUP_SUFFIX, ".db.partial"));
+
+    let restored = decompress(&dir.join(&backup.file_name), &raw)
+        .and_then(|_| database::import_repo_cache(repo_id, &raw));
+    if let Err(e) = fs::remove_file(&raw) {
+        warn!("Failed to remove {:?}: {}", raw, e);
+    }
+    restored
+}
+
+/// Deletes backups past the per-repository count or age limit; failures are logged
+pub fn prune() {
+    let backups = match all_backups() {
+        Ok(backups) => backups,
+        Err(e) => {
+            warn!("Failed to list cache backups: {}", e);
+            return;
+        }
+    };
+    let cutoff = chrono::Utc::now().timestamp() - MAX_BACKUP_AGE_SECS;
+    let mut kept = std::collections::HashMap::<&str, usize>::new();
+    // Newest first, so the count limit drops the oldest
+    for (backup, path) in &backups {
+        let count = kept.entry(backup.repo_id.as_str()).or_default();
+        if *count < MAX_BACKUPS_PER_REPO && backup.created_at >= cutoff {
+            *count += 1;
+            continue;
+        }
+        info!("Deleting cache backup {}", backup.file_name);
+        if let Err(e) = fs::remove_file(path) {
+            warn!("Failed to delete cache backup {}: {}", backup.file_name, e);
+        }
+    }
+}
+
+/// Deletes every backup of a repository, for when it's removed for good
+pub fn delete_all(repo_id: &str) -> Result<()> {
+    for (backup, path) in all_backups()? {
+        if backup.repo_id == repo_id {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::include_paths;
+use crate::models::FileNode;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileListFormat {
+    /// One original path per line
+    #[default]
+    Paths,
+    /// Path, type, size and modification time, tab-separated with a header row
+    Table,
+}
+
+/// Something the user copies out of the app, in the form it's copied in
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum ClipboardContent {
+    SnapshotId {
+        snapshot_id: String,
+    },
+    /// A path inside a snapshot, copied as where it was backed up from
+    OriginalPath {
+        path: String,
+    },
+    FileList {
+        nodes: Vec<FileNode>,
+        #[serde(default)]
+        format: FileListFormat,
+    },
+}
+
+fn original_path(tree_path: &str) -> String {
+    include_paths::to_original_path(tree_path).to_string_lossy().to_string()
+}
+
+// Tabs and newlines in names would break the table's columns and rows
+fn table_cell(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+impl ClipboardContent {
+    pub fn text(&self) -> String {
+        match self {
+            ClipboardContent::SnapshotId { snapshot_id } => snapshot_id.trim().to_string(),
+            ClipboardContent::OriginalPath { path } => original_path(path),
+            ClipboardContent::FileList { nodes, format: FileListFormat::Paths } => nodes.iter()
+                .map(|node| original_path(&node.path))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            ClipboardContent::FileList { nodes, format: FileListFormat::Table } => {
+                let mut lines = vec!["path\ttype\tsize\tmtime".to_string()];
+                lines.extend(nodes.iter().map(|node| format!(
+                    "{}\t{}\t{}\t{}",
+                    table_cell(&original_path(&node.path)),
+                    node.node_type,
+                    node.size.map(|s| s.to_string()).unwrap_or_default(),
+                    node.mtime.as_deref().unwrap_or_default(),
+                )));
+                lines.join("\n")
+            }
+        }
+    }
+}
@@ -1,13 +1,60 @@
-use crate::error::{AppError, Result};
-use crate::models::{Snapshot, FileNode};
-use crate::storage::{SavedRepository, save_config, load_config};
-use crate::database::{self, SnapshotWithStats as DbSnapshotWithStats, RepoMeta};
-use std::process::Command;
+use crate::error::{AppError, CommandError, Result};
+use crate::models::{Snapshot, BrowseFilter, ChangeSummary, CartItem, CartItemResult, CartItemStatus, CartRestoreResult, BrowseSort, SortOrder, FileNode, FilePage, RepoKey, RepoLock, CheckResult, ConflictPolicy, MismatchKind, VerifyMismatch, VerifyReport, PreviewItem, RestorePreview, DiffEntry, DiffKind, FileOccurrence, FindOptions, FoundFile, MtimeRange, SnapshotDiff, ForgetPolicy, ForgetResult, KeptSnapshot, MetadataSummary, MigrationResult, MultiRestoreResult, PlannedRestore, PruneResult, RetentionReason, RetentionReport, RestoreErrorKind, RestoreOptions, RestorePathError, RestoreResult, SnapshotFilter, SnapshotGroup, SnapshotGroupBy};
+use crate::storage::{self, AppConfig, BackendCredentials, ConfigBundle, PasswordSource, RepoPriority, RestorePoint, PreConnectHook, SavedRepository, SftpOptions, TlsOptions, SnapshotSelector, edit_config, load_config, find_repository_by_path, password_source_for};
+use crate::anomalies::{self, SnapshotAnomaly};
+use crate::background::{self, JobKind, QueuedJob};
+use crate::browse;
+use crate::compatibility::{self, CompatibilityReport, MaintenanceOp};
+use crate::binary_arch::{self, ArchCompatibility, BinaryArchitecture};
+use crate::clipboard::ClipboardContent;
+use crate::demo;
+use crate::notifications::{self, NotificationSettings};
+use crate::include_paths;
+use crate::limiter;
+use crate::logs::{self, OperationLogEntry};
+use crate::cache_backup::{self, CacheBackup};
+use crate::debug_bundle::{self, DebugBundle};
+use crate::experiments::{self, Experiment, ExperimentState};
+use crate::quick_open;
+use crate::relocate::{self, RelocationReport};
+use crate::node_cache;
+use crate::manifest::{ManifestExport, ManifestFormat, ManifestWriter};
+use crate::env_import::{self, PasswordOrigin};
+use crate::content_search::{self, ContentSearchLimits, ContentSearchResult, FileContentMatch};
+use crate::elevation::{self, PasswordFile};
+use crate::health::{self, RepoHealth};
+use crate::hooks;
+use crate::messages::{self, tr};
+use crate::mounts::{self, MountStatus};
+use crate::operations::{self, OperationInfo};
+use crate::policies::{self, EffectivePolicy, PolicyTemplate, RepositoryPolicy};
+use crate::query_log::{self, SlowQueryReport};
+use crate::restic_args::{self, CommandPreview, ResticOperation};
+use crate::restic_download::{self, InstalledRestic};
+use crate::restic_errors;
+use crate::restic_version::{self, Feature, ResticCapabilities, ResticVersion};
+use crate::repo_status::{self, RepoStatusReport};
+use crate::restore_metadata;
+use crate::restore_suggestions::{self, RestoreTargetSuggestion};
+use crate::secrets::{self, SecretBackend, SecretBackendStatus, SecretString, SecretWipeReport};
+use crate::thumbnails::{self, Thumbnail};
+use crate::target_dirs::{self, CreatedDirs};
+use crate::verbosity::{self, CapturedOutput, OperationKind, VerbositySettings};
+use crate::win_paths;
+use crate::window_scope;
+use crate::database::{self, SnapshotWithStats as DbSnapshotWithStats, AuditEvent, RestoreHistoryEntry, RestoreOutcome, RepoMeta, QuotaEvent, CachedStats, CachedNodeMatch, MtimeBounds, FileSearchFilters, SnapshotFacets, FileSearchHit, FileIndexStatus, NodeCacheStats, SnapshotPin, SnapshotDelta, DeltaSource};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::process::{Command, ExitStatus, Output, Stdio};
 use std::path::{Path, PathBuf, Component};
-use tauri::command;
+use tauri::{command, AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+use tauri_plugin_clipboard_manager::ClipboardExt;
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
-use dirs;
 use tracing::{info, debug, warn, error, instrument};
 
 #[cfg(target_os = "windows")]
@@ -22,27 +69,20 @@ fn validate_repository_path(repo: &str) -> Result<()> {
         return Err(AppError::InvalidRepositoryPath);
     }
 
-    if repo.contains(':') && !repo.starts_with("C:") && !repo.starts_with("c:") {
+    // Drive letters and verbatim prefixes have colons too
+    if repo.contains(':') && !win_paths::is_windows_path(repo) {
         let valid_protocols = ["s3:", "rest:", "sftp:", "b2:", "azure:", "gs:", "rclone:"];
         if !valid_protocols.iter().any(|p| repo.starts_with(p)) {
-            if cfg!(windows) && repo.len() >= 2 && repo.chars().nth(1) == Some(':') {
-            } else {
-                return Err(AppError::UnsupportedProtocol(valid_protocols.join(", ")));
-            }
-        } else {
-            if repo.len() < 5 {
-                return Err(AppError::RemotePathTooShort);
-            }
-            return Ok(());
+            return Err(AppError::UnsupportedProtocol(valid_protocols.join(", ")));
+        }
+        if repo.len() < 5 {
+            return Err(AppError::RemotePathTooShort);
         }
+        return Ok(());
     }
 
-    let path = Path::new(repo);
-
-    for component in path.components() {
-        if matches!(component, Component::ParentDir) {
-            return Err(AppError::PathTraversal);
-        }
+    if has_parent_component(repo) {
+        return Err(AppError::PathTraversal);
     }
 
     // Don't check if path exists - user might be initializing a new repo or it may be temporarily unavailable
@@ -74,7 +114,8 @@ fn validate_snapshot_id(snapshot_id: &str) -> Result<()> {
     Ok(())
 }
 
-fn validate_target_path(target: &str) -> Result<PathBuf> {
+// Checks the target's form without touching the filesystem
+fn validate_target_syntax(target: &str) -> Result<PathBuf> {
     if target.trim().is_empty() {
         return Err(AppError::EmptyTargetPath);
     }
@@ -83,27 +124,34 @@ fn validate_target_path(target: &str) -> Result<PathBuf> {
         return Err(AppError::InvalidTargetPath);
     }
 
+    // Mixed separators and paths past MAX_PATH are put in the form the Windows APIs take
+    let target = &win_paths::native_form(target);
     let path = PathBuf::from(target);
 
     if !path.is_absolute() {
         return Err(AppError::RelativeTargetPath);
     }
 
-    for component in path.components() {
-        match component {
-            Component::ParentDir => {
-                return Err(AppError::PathTraversal);
-            }
-            Component::Normal(s) => {
-                let component_str = s.to_string_lossy();
-                if component_str.contains('\0') {
-                    return Err(AppError::InvalidTargetPath);
-                }
-            }
-            _ => {}
-        }
+    if win_paths::is_device_path(&path) {
+        return Err(AppError::InvalidTargetPath);
+    }
+
+    // Verbatim paths aren't normalized by the path parser, so `..` is looked for by hand too
+    if has_parent_component(target) || path.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err(AppError::PathTraversal);
     }
 
+    Ok(path)
+}
+
+// Either separator counts, so `\\server\share\..\x` is caught on every platform
+fn has_parent_component(path: &str) -> bool {
+    path.split(['/', '\\']).any(|segment| segment == "..")
+}
+
+fn validate_target_path(target: &str) -> Result<PathBuf> {
+    let path = validate_target_syntax(target)?;
+
     // Target itself might not exist yet, but parent should
     if let Some(parent) = path.parent() {
         if !parent.exists() && parent.components().count() > 1 {
@@ -114,6 +162,21 @@ fn validate_target_path(target: &str) -> Result<PathBuf> {
     Ok(path)
 }
 
+/// A validated target as restic's `--target` argument. It started out as a string, but
+/// canonicalizing it can resolve symlinks into a path that isn't valid UTF-8.
+fn target_arg(target: &Path) -> Result<&str> {
+    target.to_str().ok_or(AppError::InvalidTargetPath)
+}
+
+/// Restore targets may have missing parents when the caller asks for them to be created
+fn validate_restore_target(target: &str, create_missing_dirs: bool) -> Result<PathBuf> {
+    if create_missing_dirs {
+        validate_target_syntax(target)
+    } else {
+        validate_target_path(target)
+    }
+}
+
 fn validate_include_path(include_path: &str) -> Result<()> {
     if include_path.trim().is_empty() {
         return Err(AppError::EmptyIncludePath);
@@ -137,6 +200,31 @@ fn validate_include_path(include_path: &str) -> Result<()> {
     Ok(())
 }
 
+// Paths inside a snapshot, as printed by `restic ls` (absolute, forward slashes)
+fn validate_snapshot_path(path: &str) -> Result<()> {
+    if path.trim().is_empty() || path.contains('\0') {
+        return Err(AppError::InvalidSnapshotPath(path.to_string()));
+    }
+
+    if path.split(['/', '\\']).any(|segment| segment == "..") {
+        return Err(AppError::InvalidSnapshotPath(path.to_string()));
+    }
+
+    Ok(())
+}
+
+fn validate_repository_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::EmptyRepositoryName);
+    }
+
+    if name.len() > 200 {
+        return Err(AppError::RepositoryNameTooLong);
+    }
+
+    Ok(())
+}
+
 fn validate_repo_id(repo_id: &str) -> Result<()> {
     if repo_id.trim().is_empty() {
         return Err(AppError::EmptyRepoId);
@@ -170,6 +258,160 @@ fn validate_password(password: &str) -> Result<()> {
     Ok(())
 }
 
+// Repositories whose password restic reads from a file or command don't need one from the frontend
+fn validate_credentials(repo: &str, password: &str) -> Result<()> {
+    if password.is_empty() && password_source_for(repo).is_external() {
+        return Ok(());
+    }
+    validate_password(password)
+}
+
+fn validate_password_source(source: &PasswordSource) -> Result<()> {
+    match source {
+        PasswordSource::PasswordFile { path } => {
+            if !Path::new(path).is_absolute() || !Path::new(path).is_file() {
+                return Err(AppError::InvalidPasswordSource(format!("password file {} does not exist", path)));
+            }
+        }
+        PasswordSource::PasswordCommand { command } => {
+            if command.trim().is_empty() || command.contains('\0') {
+                return Err(AppError::InvalidPasswordSource("password command is empty".to_string()));
+            }
+        }
+        PasswordSource::Inline | PasswordSource::Prompt => {}
+    }
+    Ok(())
+}
+
+// The password is always set by the app, and loader or search path variables would
+// let a repository run arbitrary code inside restic
+const DENIED_ENV_VARS: &[&str] = &[
+    "RESTIC_PASSWORD",
+    "RESTIC_PASSWORD_FILE",
+    "RESTIC_PASSWORD_COMMAND",
+    "PATH",
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "LD_AUDIT",
+    "GODEBUG",
+];
+
+fn is_denied_env_var(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    DENIED_ENV_VARS.contains(&upper.as_str()) || upper.starts_with("DYLD_")
+}
+
+fn validate_extra_env(env: &HashMap<String, String>) -> Result<()> {
+    for (name, value) in env {
+        if name.is_empty() || name.contains(['=', '\0']) || name.chars().any(char::is_whitespace) {
+            return Err(AppError::InvalidEnvironmentVariable(name.clone()));
+        }
+        if is_denied_env_var(name) {
+            return Err(AppError::InvalidEnvironmentVariable(format!("{} can't be set for a repository", name)));
+        }
+        if value.contains('\0') {
+            return Err(AppError::InvalidEnvironmentVariable(format!("{} contains invalid characters", name)));
+        }
+    }
+    Ok(())
+}
+
+/// Hands restic the password the way the repository is configured for, plus any
+/// saved backend credentials and extra variables. Inherited password variables are
+/// cleared so they can't override or conflict with it.
+fn apply_repository_env(cmd: &mut Command, repo: &str, password: &str) {
+    let saved = find_repository_by_path(repo);
+    cmd.env_remove("RESTIC_PASSWORD")
+       .env_remove("RESTIC_PASSWORD_FILE")
+       .env_remove("RESTIC_PASSWORD_COMMAND");
+
+    // Set first so saved credentials and the password take precedence
+    if let Some(saved) = &saved {
+        for (name, value) in &saved.extra_env {
+            if is_denied_env_var(name) {
+                warn!("Ignoring {} from the environment of repository {}", name, saved.id);
+                continue;
+            }
+            cmd.env(name, value);
+        }
+    }
+    cmd.envs(repository_env(saved, repo, password).iter().map(|(name, value)| (name, &**value)));
+}
+
+// Values are wiped once the command has copied them; the copy in the child's environment can't be
+fn repository_env(saved: Option<SavedRepository>, repo: &str, password: &str) -> Vec<(&'static str, SecretString)> {
+    let mut vars: Vec<(&'static str, SecretString)> = saved.as_ref()
+        .and_then(secrets::backend_credentials)
+        .map(|credentials| credentials.env_vars(repo))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value)| (name, value.into()))
+        .collect();
+
+    vars.push(match saved.map(|r| r.password_source).unwrap_or_default() {
+        PasswordSource::PasswordFile { path } if password.is_empty() => ("RESTIC_PASSWORD_FILE", path.into()),
+        PasswordSource::PasswordCommand { command } if password.is_empty() => ("RESTIC_PASSWORD_COMMAND", command.into()),
+        _ => ("RESTIC_PASSWORD", password.into()),
+    });
+    vars
+}
+
+/// The key and known_hosts files must exist, and extra options be plain `Name=value` pairs
+fn validate_sftp_options(sftp: &SftpOptions) -> Result<()> {
+    if sftp.port == Some(0) {
+        return Err(AppError::InvalidSftpOption("port 0".to_string()));
+    }
+    for path in [&sftp.identity_file, &sftp.known_hosts_file].into_iter().flatten() {
+        // Quotes would end the quoting around paths with spaces in `sftp.args`
+        if path.contains(['"', '\'', '\0']) {
+            return Err(AppError::InvalidSftpOption(path.clone()));
+        }
+    }
+    if let Some(identity) = &sftp.identity_file {
+        if !Path::new(identity).is_file() {
+            return Err(AppError::SftpKeyFileNotFound(identity.clone()));
+        }
+    }
+    if let Some(known_hosts) = &sftp.known_hosts_file {
+        if !Path::new(known_hosts).is_file() {
+            return Err(AppError::InvalidSftpOption(format!("known_hosts file not found: {}", known_hosts)));
+        }
+    }
+    for option in &sftp.ssh_options {
+        let valid = option.split_once('=').is_some_and(|(name, value)| {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric())
+                && !value.contains(['"', '\'', '\0', '\n', '\r'])
+        });
+        if !valid {
+            return Err(AppError::InvalidSftpOption(option.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_backend_credentials(credentials: &BackendCredentials) -> Result<()> {
+    let fields = [
+        &credentials.access_key_id,
+        &credentials.secret,
+        &credentials.username,
+        &credentials.region,
+        &credentials.account_id,
+        &credentials.sas_token,
+        &credentials.service_account_json,
+    ];
+    if fields.iter().any(|f| f.as_ref().is_some_and(|v| v.contains('\0'))) {
+        return Err(AppError::InvalidBackendCredentials("values contain invalid characters".to_string()));
+    }
+
+    if let Some(path) = &credentials.service_account_json {
+        if !Path::new(path).is_file() {
+            return Err(AppError::InvalidBackendCredentials(format!("service account file {} does not exist", path)));
+        }
+    }
+    Ok(())
+}
+
 fn validate_restic_binary(path: &str) -> bool {
     let mut cmd = Command::new(path);
     cmd.arg("--version");
@@ -240,42 +482,105 @@ fn find_restic_binary() -> String {
         locations
     };
 
-    for location in &platform_locations {
-        if Path::new(location).exists() {
-            debug!("Auto-detected restic binary at: {}", location);
-            return location.to_string();
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let platform_locations: Vec<String> = {
+        let mut locations = vec![
+            "/usr/local/bin/restic".to_string(),
+            "/usr/bin/restic".to_string(),
+            "/snap/bin/restic".to_string(),
+        ];
+
+        if let Some(home_dir) = dirs::home_dir() {
+            if let Some(home_str) = home_dir.to_str() {
+                locations.push(format!("{}/bin/restic", home_str));
+                locations.push(format!("{}/.local/bin/restic", home_str));
+            }
         }
+
+        locations
+    };
+
+    // Prefer binaries built for this machine over ones that need emulation
+    let best = platform_locations.iter()
+        .filter(|location| Path::new(location).exists())
+        .min_by_key(|location| binary_arch::rank(Path::new(location)));
+    if let Some(location) = best {
+        debug!("Auto-detected restic binary at: {}", location);
+        return location.to_string();
     }
 
     debug!("Using default restic binary from PATH");
     "restic".to_string()
 }
 
+// Bare names like "restic" are looked up on PATH the way the OS would run them
+fn resolve_binary(path: &str) -> Option<PathBuf> {
+    let direct = PathBuf::from(path);
+    if direct.is_file() {
+        return Some(direct);
+    }
+    if direct.components().count() != 1 {
+        return None;
+    }
+    let names: Vec<String> = if cfg!(target_os = "windows") {
+        vec![path.to_string(), format!("{}.exe", path)]
+    } else {
+        vec![path.to_string()]
+    };
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+fn inspect_restic_binary(path: &str) -> (Option<BinaryArchitecture>, Option<String>) {
+    let Some(architecture) = resolve_binary(path).and_then(|p| binary_arch::inspect(&p)) else {
+        return (None, None);
+    };
+
+    let built_for = architecture.architectures.iter().map(|a| a.name()).collect::<Vec<_>>().join(", ");
+    let host = architecture.host.map(|a| a.name()).unwrap_or_default().to_string();
+    let warning = match architecture.compatibility {
+        ArchCompatibility::Emulated => Some(tr("setup.arch_emulated", &[built_for, host])),
+        ArchCompatibility::Incompatible => Some(tr("setup.arch_incompatible", &[built_for, host])),
+        ArchCompatibility::Native | ArchCompatibility::Unknown => None,
+    };
+    if let Some(w) = &warning {
+        warn!("{}: {}", path, w);
+    }
+    (Some(architecture), warning)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResticSetupStatus {
     pub found: bool,
     pub path: Option<String>,
     pub valid: bool,
     pub setup_completed: bool,
+    pub architecture: Option<BinaryArchitecture>,
+    /// Set when the binary doesn't match this machine's architecture
+    pub arch_warning: Option<String>,
 }
 
 #[command]
 #[instrument]
-pub async fn check_restic_setup_status() -> std::result::Result<ResticSetupStatus, String> {
+pub async fn check_restic_setup_status() -> std::result::Result<ResticSetupStatus, CommandError> {
     info!("Checking restic setup status");
 
-    let config = load_config().map_err(|e| AppError::Storage(e))?;
+    let config = load_config().map_err(AppError::Storage)?;
     let setup_completed = config.setup_completed.unwrap_or(false);
 
     if let Some(custom_path) = &config.restic_binary_path {
         if !custom_path.is_empty() {
             let valid = validate_restic_binary(custom_path);
             debug!("User-configured binary at {}: valid={}", custom_path, valid);
+            let (architecture, arch_warning) = inspect_restic_binary(custom_path);
             return Ok(ResticSetupStatus {
                 found: true,
                 path: Some(custom_path.clone()),
                 valid,
                 setup_completed,
+                architecture,
+                arch_warning,
             });
         }
     }
@@ -287,11 +592,14 @@ pub async fn check_restic_setup_status() -> std::result::Result<ResticSetupStatu
         debug!("Using restic from PATH: valid={}", valid);
 
         if valid {
+            let (architecture, arch_warning) = inspect_restic_binary(&detected_path);
             return Ok(ResticSetupStatus {
                 found: true,
                 path: Some(detected_path),
                 valid: true,
                 setup_completed,
+                architecture,
+                arch_warning,
             });
         } else {
             return Ok(ResticSetupStatus {
@@ -299,27 +607,82 @@ pub async fn check_restic_setup_status() -> std::result::Result<ResticSetupStatu
                 path: None,
                 valid: false,
                 setup_completed,
+                architecture: None,
+                arch_warning: None,
             });
         }
     }
 
     let valid = validate_restic_binary(&detected_path);
     debug!("Auto-detected binary at {}: valid={}", detected_path, valid);
+    let (architecture, arch_warning) = inspect_restic_binary(&detected_path);
 
     Ok(ResticSetupStatus {
         found: true,
         path: Some(detected_path),
         valid,
         setup_completed,
+        architecture,
+        arch_warning,
     })
 }
 
+/// Runs database work on the blocking thread pool so a slow query can't stall
+/// the async runtime
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::Storage(format!("Database task failed: {}", e)))?
+}
+
+/// Runs streamed restic commands, which block their thread until restic exits, on
+/// the blocking thread pool
+async fn streaming<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| AppError::ResticExecution(e.to_string()))?
+}
+
+/// `database::record_audit_event` on the blocking thread pool
+async fn record_audit_event(event: &'static str, detail: Option<String>) -> Result<()> {
+    blocking(move || database::record_audit_event(event, detail.as_deref())).await
+}
+
+/// Wakes or mounts whatever the repository needs before its first restic call
+fn run_pre_connect_hooks(repo: &str) -> Result<()> {
+    match find_repository_by_path(repo) {
+        Some(saved) => hooks::ensure_ready(&saved),
+        None => Ok(()),
+    }
+}
+
+async fn run_pre_connect_hooks_async(repo: &str) -> Result<()> {
+    let repo = repo.to_string();
+    tauri::async_runtime::spawn_blocking(move || run_pre_connect_hooks(&repo))
+        .await
+        .map_err(|e| AppError::PreConnectHookFailed(e.to_string()))?
+}
+
+fn restic_capabilities() -> ResticCapabilities {
+    restic_version::capabilities(&find_restic_binary())
+}
+
+#[derive(Clone, Copy)]
 enum ErrorHandling {
     Strict,
     Lenient, // Treat some errors as warnings during restore operations
 }
 
-fn run_restic_command(
+// Upper bound for a policy's `max_retries`
+const MAX_RETRIES: u32 = 10;
+// Wait before the first retry; doubles with each further one
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Runs restic and buffers its output. Network errors are retried with exponential
+/// backoff as often as the repository's policy allows, and so are timeouts of
+/// commands that only read metadata; a restore that ran out of time isn't repeated.
+async fn run_restic_command(
     repo: &str,
     password: &str,
     args: &[&str],
@@ -327,12 +690,44 @@ fn run_restic_command(
 ) -> Result<String> {
     let restic_bin = find_restic_binary();
     debug!("Executing restic command: {} -r {} {}", restic_bin, repo, args.join(" "));
+    let policy = repository_policy(repo);
+    run_pre_connect_hooks_async(repo).await?;
 
-    let mut cmd = Command::new(&restic_bin);
-    cmd.arg("-r")
-       .arg(repo)
-       .args(args)
-       .env("RESTIC_PASSWORD", password);
+    let timeout = policy.timeout_for(args);
+    let retry_timeouts = !restic_args::moves_file_data(args);
+    let mut attempt = 0;
+    loop {
+        let result = run_restic_once(&restic_bin, repo, password, args, timeout).await
+            .and_then(|output| handle_restic_output(&output, error_mode));
+        let retryable = match &result {
+            Err(AppError::Timeout(_)) => retry_timeouts,
+            Err(e) => restic_errors::is_transient(e),
+            Ok(_) => false,
+        };
+        if !retryable || attempt >= policy.max_retries() {
+            return result;
+        }
+
+        let delay = RETRY_BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(RETRY_MAX_DELAY);
+        attempt += 1;
+        if let Err(e) = &result {
+            warn!("restic {} failed ({}), retry {} in {:?}", args.first().unwrap_or(&""), e, attempt, delay);
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+async fn run_restic_once(
+    restic_bin: &str,
+    repo: &str,
+    password: &str,
+    args: &[&str],
+    timeout: Option<u64>,
+) -> Result<Output> {
+    let mut cmd = Command::new(restic_bin);
+    cmd.args(restic_repository_args(repo))
+       .args(args);
+    apply_repository_env(&mut cmd, repo, password);
 
     #[cfg(target_os = "windows")]
     {
@@ -340,26 +735,85 @@ fn run_restic_command(
         cmd.creation_flags(CREATE_NO_WINDOW);
     }
 
-    let output = cmd
-        .output()
-        .map_err(|e| {
-            error!("Failed to execute restic binary: {}", e);
-            AppError::ResticExecution(e.to_string())
-        })?;
+    let _permit = limiter::acquire(repo).await?;
+    let invocation = logs::Invocation::start(repo, args);
+    let output = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
+        .output();
+    // Dropping the future on timeout kills restic
+    let output = match timeout {
+        Some(secs) => tokio::time::timeout(Duration::from_secs(secs), output).await
+            .unwrap_or(Err(std::io::ErrorKind::TimedOut.into())),
+        None => output.await,
+    };
+    match output {
+        Ok(output) => {
+            invocation.finish(output.status, &String::from_utf8_lossy(&output.stderr));
+            Ok(output)
+        }
+        Err(e) => {
+            let error = match (e.kind(), timeout) {
+                (std::io::ErrorKind::TimedOut, Some(secs)) => AppError::Timeout(secs),
+                _ => {
+                    error!("Failed to execute restic binary: {}", e);
+                    AppError::ResticExecution(e.to_string())
+                }
+            };
+            invocation.fail(&error);
+            Err(error)
+        }
+    }
+}
+
+/// The repository's policy, for restic commands that only know its path
+fn repository_policy(repo: &str) -> RepositoryPolicy {
+    load_config().map(|config| policies::for_path(&config, repo)).unwrap_or_default()
+}
+
+/// What restic is given before an operation's own arguments: the repository, its
+/// bandwidth limits, and how its backend is reached (ssh settings for `sftp:`, TLS
+/// options for HTTPS backends). Every runner and `render_command_preview` use this.
+fn repository_args(config: &AppConfig, repo: &str) -> Vec<String> {
+    let mut args = vec!["-r".to_string(), repo.to_string()];
+    args.extend(policies::for_path(config, repo).restic_flags());
+    if let Some(saved) = config.repositories.iter().find(|r| !r.is_deleted() && r.path.trim() == repo.trim()) {
+        args.extend(saved.sftp.as_ref().map(|sftp| sftp.restic_flags(repo)).unwrap_or_default());
+        args.extend(saved.tls.as_ref().map(TlsOptions::restic_flags).unwrap_or_default());
+    }
+    args
+}
+
+fn restic_repository_args(repo: &str) -> Vec<String> {
+    repository_args(&load_config().unwrap_or_default(), repo)
+}
 
+fn ensure_not_safe_mode(repo: &str) -> Result<()> {
+    if repository_policy(repo).is_safe_mode() {
+        return Err(AppError::SafeModeEnabled(repo.to_string()));
+    }
+    Ok(())
+}
+
+fn handle_restic_output(output: &Output, error_mode: ErrorHandling) -> Result<String> {
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    handle_restic_result(output.status, stdout, stderr, error_mode)
+}
 
-    if !output.status.success() {
+fn handle_restic_result(status: ExitStatus, stdout: String, stderr: String, error_mode: ErrorHandling) -> Result<String> {
+    if !status.success() {
+        let classified = restic_errors::classify(status.code(), &stderr);
         match error_mode {
             ErrorHandling::Strict => {
                 error!("Restic command failed: {}", stderr);
-                Err(AppError::ResticError(stderr))
+                Err(classified.unwrap_or(AppError::ResticError(stderr)))
             }
             ErrorHandling::Lenient => {
-                let is_fatal = stderr.contains("repository does not exist")
-                    || stderr.contains("wrong password")
-                    || stderr.contains("unable to open repository")
+                if let Some(error) = classified.filter(restic_errors::is_repository_failure) {
+                    error!("Restore failed with fatal error: {}", stderr);
+                    return Err(error);
+                }
+                let is_fatal = stderr.contains("unable to open repository")
                     || (stderr.contains("snapshot") && stderr.contains("not found"));
 
                 if is_fatal {
@@ -367,7 +821,7 @@ fn run_restic_command(
                     Err(AppError::RestoreFailed(stderr))
                 } else {
                     warn!("Restore completed with warnings: {}", stderr);
-                    Ok(format!("Restored with warnings:\n{}", stderr))
+                    Ok(tr("restore.completed_with_warnings", &[stderr]))
                 }
             }
         }
@@ -377,263 +831,5580 @@ fn run_restic_command(
     }
 }
 
-fn run_restic(repo: &str, password: &str, args: &[&str]) -> Result<String> {
-    run_restic_command(repo, password, args, ErrorHandling::Strict)
-}
+// Longer stdout lines are dropped so one pathological line can't exhaust memory
+const MAX_LINE_BYTES: u64 = 1024 * 1024;
 
-// Fatal errors (wrong password, missing repo) still fail, but warnings are allowed
-fn run_restic_restore(repo: &str, password: &str, args: &[&str]) -> Result<String> {
-    run_restic_command(repo, password, args, ErrorHandling::Lenient)
-}
+// Restarts of a stalled streamed command before giving up
+const MAX_STALL_RETRIES: u32 = 2;
 
-#[command]
-#[instrument(skip(password))]
-pub async fn connect_repository(repo: String, password: String) -> std::result::Result<String, String> {
-    info!("Connecting to repository");
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
+/// Reads newline-separated output one line at a time, reusing a single buffer.
+fn read_bounded_lines<R: BufRead, F: FnMut(&str)>(mut reader: R, mut on_line: F) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.by_ref().take(MAX_LINE_BYTES).read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
 
-    run_restic(&repo, &password, &["snapshots", "--latest", "1", "--json"])?;
-    info!("Successfully connected to repository");
-    Ok("Connected successfully".to_string())
-}
+        if buf.last() != Some(&b'\n') && read as u64 == MAX_LINE_BYTES {
+            warn!("Skipping restic output line longer than {} bytes", MAX_LINE_BYTES);
+            let mut rest = Vec::new();
+            reader.read_until(b'\n', &mut rest)?;
+            continue;
+        }
 
-#[command]
-#[instrument(skip(password))]
-pub async fn list_snapshots(repo: String, password: String) -> std::result::Result<Vec<Snapshot>, String> {
-    info!("Listing snapshots");
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
+        let line = String::from_utf8_lossy(&buf);
+        on_line(line.trim_end_matches(['\r', '\n']));
+    }
+}
 
-    let output = run_restic(&repo, &password, &["snapshots", "--json"])?;
-    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
-        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
-    info!("Found {} snapshots", snapshots.len());
-    Ok(snapshots)
+/// Like `run_restic_command`, but hands each stdout line to `on_line` as it arrives
+/// instead of buffering it, for long-running commands that report progress as JSON lines.
+/// The process is registered under `operation_id` so it can be cancelled. Blocks until
+/// restic exits, so async commands run it through `streaming`.
+fn run_restic_streaming<F: FnMut(&str)>(
+    repo: &str,
+    password: &str,
+    args: &[&str],
+    error_mode: ErrorHandling,
+    operation_id: &str,
+    on_line: F,
+) -> Result<String> {
+    run_restic_streaming_with_env(repo, password, args, &[], error_mode, operation_id, on_line)
 }
 
-#[command]
-pub async fn get_snapshot_details(repo: String, password: String, snapshot_id: String) -> std::result::Result<Vec<FileNode>, String> {
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
-    validate_snapshot_id(&snapshot_id)?;
+/// `run_restic_streaming` with extra environment variables for restic
+fn run_restic_streaming_with_env<F: FnMut(&str)>(
+    repo: &str,
+    password: &str,
+    args: &[&str],
+    env: &[(&str, &str)],
+    error_mode: ErrorHandling,
+    operation_id: &str,
+    mut on_line: F,
+) -> Result<String> {
+    let restic_bin = find_restic_binary();
+    debug!("Streaming restic command: {} -r {} {}", restic_bin, repo, args.join(" "));
+    run_pre_connect_hooks(repo)?;
 
-    let output = run_restic(&repo, &password, &["ls", "--json", &snapshot_id])?;
+    // Streamed commands run for as long as they need, so only the bandwidth limits apply;
+    // hangs are left to the stall watchdog
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut cmd = Command::new(&restic_bin);
+        cmd.args(restic_repository_args(repo))
+           .args(args)
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+        apply_repository_env(&mut cmd, repo, password);
+        cmd.envs(env.iter().copied());
 
-    let mut files = Vec::new();
-    for line in output.lines() {
-        if line.trim().is_empty() { continue; }
-        if let Ok(node) = serde_json::from_str::<FileNode>(line) {
-            files.push(node);
+        #[cfg(target_os = "windows")]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
         }
-    }
 
-    Ok(files)
-}
+        // Runs on the caller's thread, so the slot is waited for without the async runtime
+        let _permit = limiter::acquire_blocking(repo)?;
+        let invocation = logs::Invocation::start(repo, args);
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                error!("Failed to execute restic binary: {}", e);
+                let error = AppError::ResticExecution(e.to_string());
+                invocation.fail(&error);
+                return Err(error);
+            }
+        };
 
-#[command]
-#[instrument(skip(password))]
-pub async fn restore_snapshot(repo: String, password: String, snapshot_id: String, target: String) -> std::result::Result<String, String> {
-    info!("Starting full snapshot restore to {}", target);
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
-    validate_snapshot_id(&snapshot_id)?;
-    let validated_target = validate_target_path(&target)?;
+        // Drain stderr on its own thread so a chatty stderr can't block stdout
+        let stderr_reader = child.stderr.take().map(|mut stderr| {
+            std::thread::spawn(move || {
+                let mut buf = String::new();
+                let _ = stderr.read_to_string(&mut buf);
+                buf
+            })
+        });
 
-    run_restic_restore(&repo, &password, &["restore", &snapshot_id, "--target", validated_target.to_str().unwrap()])?;
-    info!("Restore completed successfully");
-    Ok("Restore completed".to_string())
-}
+        let stdout = child.stdout.take();
+        let operation = operations::register(operation_id, args.first().copied().unwrap_or("restic"), child)?;
 
-#[command]
-#[instrument(skip(password), fields(num_paths = include_paths.len()))]
-pub async fn restore_selective(
-    repo: String,
-    password: String,
-    snapshot_id: String,
-    target: String,
-    include_paths: Vec<String>
-) -> std::result::Result<String, String> {
-    info!("Starting selective restore of {} paths to {}", include_paths.len(), target);
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
-    validate_snapshot_id(&snapshot_id)?;
-    let validated_target = validate_target_path(&target)?;
+        let mut delivered = false;
+        if let Some(stdout) = stdout {
+            let result = read_bounded_lines(BufReader::new(stdout), |line| {
+                operation.touch();
+                delivered = true;
+                on_line(line);
+            });
+            if let Err(e) = result {
+                warn!("Failed to read restic output: {}", e);
+            }
+        }
 
-    for include_path in &include_paths {
-        validate_include_path(include_path)?;
-    }
+        let status = operation.wait()?;
+        let stderr = stderr_reader
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        invocation.finish(status, &stderr);
 
-    if include_paths.is_empty() {
-        return Err(AppError::NoIncludePaths.into());
+        if operation.was_cancelled() {
+            info!("Operation {} was cancelled", operation_id);
+            return Err(AppError::OperationCancelled(operation_id.to_string()));
+        }
+        if operation.was_retried() {
+            // Running again would hand the caller lines it has already seen
+            if delivered || attempt > MAX_STALL_RETRIES {
+                return Err(AppError::OperationStalled(operation_id.to_string()));
+            }
+            info!("Restarting stalled operation {} (attempt {})", operation_id, attempt + 1);
+            continue;
+        }
+
+        return handle_restic_result(status, String::new(), stderr, error_mode);
     }
+}
 
-    let target_str = validated_target.to_str().unwrap();
-    let mut args = vec!["restore", &snapshot_id, "--target", target_str];
+/// Runs a restic command with `--json` output that prints one JSON document per
+/// line (NDJSON) and hands each parsed document to `on_value` without buffering
+/// the whole output. The command shows up in `list_operations` and can be cancelled.
+fn run_restic_ndjson<F: FnMut(Value)>(repo: &str, password: &str, args: &[&str], mut on_value: F) -> Result<()> {
+    let operation_id = operations::new_operation_id();
+    run_restic_streaming(repo, password, args, ErrorHandling::Strict, &operation_id, |line| {
+        if line.trim().is_empty() {
+            return;
+        }
+        match serde_json::from_str::<Value>(line) {
+            Ok(value) => on_value(value),
+            Err(e) => debug!("Skipping non-JSON restic output: {}", e),
+        }
+    })?;
+    Ok(())
+}
 
-    let include_path_refs: Vec<String> = include_paths.iter()
-        .flat_map(|p| vec!["--include".to_string(), p.clone()])
-        .collect();
+/// Runs a restic command whose output is binary, e.g. `restic dump`, and returns it
+/// unaltered. Output past `max_bytes` cancels the command.
+fn run_restic_bytes(repo: &str, password: &str, args: &[&str], max_bytes: u64) -> Result<Vec<u8>> {
+    let restic_bin = find_restic_binary();
+    debug!("Executing restic command: {} -r {} {}", restic_bin, repo, args.join(" "));
+    run_pre_connect_hooks(repo)?;
 
-    let include_args: Vec<&str> = include_path_refs.iter().map(|s| s.as_str()).collect();
-    args.extend(include_args);
+    let mut cmd = Command::new(&restic_bin);
+    cmd.args(restic_repository_args(repo))
+       .args(args)
+       .stdout(Stdio::piped())
+       .stderr(Stdio::piped());
+    apply_repository_env(&mut cmd, repo, password);
 
-    run_restic_restore(&repo, &password, &args)?;
-    info!("Selective restore completed successfully");
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
 
-    Ok(format!("Restored {} item(s) successfully", include_paths.len()))
+    let _permit = limiter::acquire_blocking(repo)?;
+    let invocation = logs::Invocation::start(repo, args);
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Failed to execute restic binary: {}", e);
+            let error = AppError::ResticExecution(e.to_string());
+            invocation.fail(&error);
+            return Err(error);
+        }
+    };
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        })
+    });
+
+    let stdout = child.stdout.take();
+    let operation_id = operations::new_operation_id();
+    let operation = operations::register(&operation_id, args.first().copied().unwrap_or("restic"), child)?;
+
+    let mut bytes = Vec::new();
+    if let Some(stdout) = stdout {
+        stdout.take(max_bytes + 1).read_to_end(&mut bytes)
+            .map_err(|e| AppError::ResticExecution(e.to_string()))?;
+    }
+    if bytes.len() as u64 > max_bytes {
+        operations::cancel(&operation_id)?;
+        let _ = operation.wait();
+        let error = AppError::FileTooLarge(format!("more than {} bytes", max_bytes));
+        invocation.fail(&error);
+        return Err(error);
+    }
+
+    let status = operation.wait()?;
+    let stderr = stderr_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    invocation.finish(status, &stderr);
+    handle_restic_result(status, String::new(), stderr, ErrorHandling::Strict)?;
+    Ok(bytes)
+}
+
+/// Streams the file nodes printed by `restic ls --json`
+// `restic ls --json` starts with the snapshot itself, followed by one line per node
+fn ls_node(value: Value) -> Option<FileNode> {
+    if value.get("struct_type").is_some_and(|t| t == "node") {
+        let mut node: FileNode = serde_json::from_value(value).ok()?;
+        node.fill_permissions();
+        Some(node)
+    } else {
+        None
+    }
+}
+
+fn for_each_ls_node<F: FnMut(FileNode)>(repo: &str, password: &str, args: &[&str], mut on_node: F) -> Result<()> {
+    run_restic_ndjson(repo, password, args, |value| {
+        if let Some(node) = ls_node(value) {
+            on_node(node);
+        }
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct OperationStarted<'a> {
+    operation_id: &'a str,
+    kind: &'a str,
+}
+
+// Callers may pick the ID themselves so they can cancel before the command returns
+fn start_operation(window: &WebviewWindow, operation_id: Option<String>, kind: &str) -> Result<String> {
+    let operation_id = match operation_id {
+        Some(id) => {
+            operations::validate_operation_id(&id)?;
+            id
+        }
+        None => operations::new_operation_id(),
+    };
+    window_scope::emit_to_window(window, "operation-started", OperationStarted { operation_id: &operation_id, kind });
+    Ok(operation_id)
+}
+
+async fn run_restic(repo: &str, password: &str, args: &[&str]) -> Result<String> {
+    run_restic_command(repo, password, args, ErrorHandling::Strict).await
+}
+
+/// `run_restic` for threads outside the async runtime, like the scheduler's blocking tasks
+fn run_restic_blocking(repo: &str, password: &str, args: &[&str]) -> Result<String> {
+    tauri::async_runtime::block_on(run_restic(repo, password, args))
+}
+
+/// Elevation helpers start restic with a clean environment, and restic only reads
+/// backend credentials from there. Fails for repositories that need any.
+fn ensure_elevation_supported(repo: &str) -> Result<()> {
+    let Some(saved) = find_repository_by_path(repo) else { return Ok(()) };
+    let mut needed: Vec<String> = secrets::backend_credentials(&saved)
+        .map(|credentials| credentials.env_vars(repo).into_iter().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default();
+    needed.extend(saved.extra_env.keys().filter(|name| !is_denied_env_var(name)).cloned());
+    if needed.is_empty() {
+        return Ok(());
+    }
+    needed.sort();
+    Err(AppError::ElevationNeedsEnvironment(needed.join(", ")))
+}
+
+// Elevated restores can't inherit RESTIC_PASSWORD, so the password goes through a temp file
+fn run_restic_restore_elevated(repo: &str, password: &str, args: &[&str]) -> Result<String> {
+    ensure_elevation_supported(repo)?;
+    let restic_bin = find_restic_binary();
+    run_pre_connect_hooks(repo)?;
+
+    let mut full_args = restic_repository_args(repo);
+    let mut _password_file = None;
+    match password_source_for(repo) {
+        PasswordSource::PasswordFile { path } if password.is_empty() => {
+            full_args.extend(["--password-file".to_string(), path]);
+        }
+        PasswordSource::PasswordCommand { command } if password.is_empty() => {
+            full_args.extend(["--password-command".to_string(), command]);
+        }
+        _ => {
+            let file = PasswordFile::create(password)?;
+            full_args.extend(["--password-file".to_string(), file.path().to_string_lossy().to_string()]);
+            _password_file = Some(file);
+        }
+    }
+    full_args.extend(restore_run_args(args, true).iter().map(|a| a.to_string()));
+
+    info!("Requesting administrator rights for restic {}", args.join(" "));
+    let _permit = limiter::acquire_blocking(repo)?;
+    let invocation = logs::Invocation::start(repo, &full_args[2..].iter().map(String::as_str).collect::<Vec<_>>());
+    let output = match elevation::run_elevated(&restic_bin, &full_args) {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Failed to launch elevation helper: {}", e);
+            let error = AppError::ElevationUnavailable(e.to_string());
+            invocation.fail(&error);
+            return Err(error);
+        }
+    };
+    invocation.finish(output.status, &String::from_utf8_lossy(&output.stderr));
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if let Some(err) = elevation::elevation_failure(output.status.code(), &stderr) {
+            warn!("Elevated restore not started: {}", err);
+            return Err(err);
+        }
+    }
+
+    handle_restic_output(&output, ErrorHandling::Lenient)
+}
+
+fn classify_restore_error(message: &str) -> RestoreErrorKind {
+    let lower = message.to_lowercase();
+    if lower.contains("permission denied")
+        || lower.contains("access is denied")
+        || lower.contains("operation not permitted")
+    {
+        RestoreErrorKind::Permissions
+    } else if lower.contains("file name too long")
+        || lower.contains("filename or extension is too long")
+        || lower.contains("path too long")
+    {
+        RestoreErrorKind::PathTooLong
+    } else if lower.contains("no space left on device")
+        || lower.contains("not enough space on the disk")
+        || lower.contains("disk quota exceeded")
+    {
+        RestoreErrorKind::DiskFull
+    } else {
+        RestoreErrorKind::Other
+    }
+}
+
+fn parse_restore_error_line(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+
+    // With --json, restic reports per-file problems as error messages carrying the item path
+    if line.starts_with('{') {
+        let val: Value = serde_json::from_str(line).ok()?;
+        if val.get("message_type")? != "error" {
+            return None;
+        }
+        let path = val.get("item")?.as_str()?.to_string();
+        let message = val.get("error")?.get("message")?.as_str()?.to_string();
+        return Some((path, message));
+    }
+
+    // Otherwise as "ignoring error for <path>: <error>"
+    let (path, message) = line.strip_prefix("ignoring error for ")?.split_once(": ")?;
+    Some((path.to_string(), message.to_string()))
+}
+
+fn parse_restore_errors(output: &str) -> Vec<RestorePathError> {
+    output
+        .lines()
+        .filter_map(parse_restore_error_line)
+        .map(|(path, message)| RestorePathError {
+            path,
+            error_kind: classify_restore_error(&message),
+            message,
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RestoreProgress {
+    pub snapshot_id: String,
+    pub target: String,
+    pub percent_done: f64,
+    pub files_restored: u64,
+    pub total_files: u64,
+    pub bytes_restored: u64,
+    pub total_bytes: u64,
+    pub seconds_elapsed: u64,
+    pub done: bool,
+}
+
+impl RestoreProgress {
+    // Parses restic's --json "status" and "summary" messages; other lines are ignored
+    fn from_line(line: &str, snapshot_id: &str, target: &str) -> Option<Self> {
+        let val: Value = serde_json::from_str(line).ok()?;
+        let done = match val.get("message_type")?.as_str()? {
+            "status" => false,
+            "summary" => true,
+            _ => return None,
+        };
+        let number = |key: &str| val.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+
+        let total_bytes = number("total_bytes");
+        let bytes_restored = number("bytes_restored");
+        let percent_done = match val.get("percent_done").and_then(|v| v.as_f64()) {
+            Some(p) => p * 100.0,
+            None if done => 100.0,
+            None if total_bytes > 0 => bytes_restored as f64 / total_bytes as f64 * 100.0,
+            None => 0.0,
+        };
+
+        Some(RestoreProgress {
+            snapshot_id: snapshot_id.to_string(),
+            target: target.to_string(),
+            percent_done,
+            files_restored: number("files_restored"),
+            total_files: number("total_files"),
+            bytes_restored,
+            total_bytes,
+            seconds_elapsed: number("seconds_elapsed"),
+            done,
+        })
+    }
+}
+
+/// What a finished restore printed, and how much it wrote if restic said
+struct RestoreRun {
+    output: String,
+    bytes_restored: Option<u64>,
+}
+
+fn run_restore_with_progress(
+    window: &WebviewWindow,
+    operation_id: &str,
+    repo: &str,
+    password: &str,
+    snapshot_id: &str,
+    target: &str,
+    args: &[&str],
+) -> Result<RestoreRun> {
+    let json_args = restore_run_args(args, false);
+
+    // Fatal errors (wrong password, missing repo) still fail, but warnings are allowed
+    let mut bytes_restored = None;
+    let output = run_restic_streaming(repo, password, &json_args, ErrorHandling::Lenient, operation_id, |line| {
+        if let Some(progress) = RestoreProgress::from_line(line, snapshot_id, target) {
+            if progress.done {
+                bytes_restored = Some(progress.bytes_restored);
+            }
+            window_scope::emit_to_window(window, "restore-progress", progress);
+        }
+    })?;
+    Ok(RestoreRun { output, bytes_restored })
+}
+
+/// A restore's arguments as restic runs them: with the restore verbosity flags and,
+/// unless elevated (the helper can't stream it), `--json` progress
+fn restore_run_args<'a>(args: &[&'a str], elevated: bool) -> Vec<&'a str> {
+    let mut run_args = verbosity::with_flags(args, OperationKind::Restore);
+    // Older restic restores fine but can't report progress; errors are parsed from text then
+    if !elevated && restic_capabilities().supports(Feature::RestoreJsonProgress) {
+        run_args.push("--json");
+    }
+    run_args
+}
+
+/// Runs a full or selective restore, creating the target's missing directories first
+/// when asked and removing them again if the restore is cancelled before writing anything
+#[allow(clippy::too_many_arguments)]
+async fn run_restore(
+    window: &WebviewWindow,
+    operation_id: &str,
+    repo: &str,
+    password: &str,
+    snapshot_id: &str,
+    target: &Path,
+    args: &[&str],
+    options: &RestoreOptions,
+) -> Result<RestoreRun> {
+    let (window, operation_id, repo, password) = (window.clone(), operation_id.to_string(), repo.to_string(), SecretString::from(password));
+    let (snapshot_id, target, options) = (snapshot_id.to_string(), target.to_path_buf(), options.clone());
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    streaming(move || {
+        let args = restic_args::as_strs(&args);
+        run_restore_blocking(&window, &operation_id, &repo, &password, &snapshot_id, &target, &args, &options)
+    }).await
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_restore_blocking(
+    window: &WebviewWindow,
+    operation_id: &str,
+    repo: &str,
+    password: &str,
+    snapshot_id: &str,
+    target: &Path,
+    args: &[&str],
+    options: &RestoreOptions,
+) -> Result<RestoreRun> {
+    if options.elevate {
+        ensure_elevation_supported(repo)?;
+    }
+    let target_str = target_arg(target)?;
+    let created = if options.create_missing_dirs {
+        target_dirs::create_missing(target)?
+    } else {
+        CreatedDirs::default()
+    };
+
+    // The elevated helper owns the restic process, so elevated restores can't be cancelled
+    let result = if options.elevate {
+        run_restic_restore_elevated(repo, password, args).map(|output| RestoreRun { output, bytes_restored: None })
+    } else {
+        run_restore_with_progress(window, operation_id, repo, password, snapshot_id, target_str, args)
+    };
+    if matches!(result, Err(AppError::OperationCancelled(_))) {
+        created.roll_back();
+    }
+    result
+}
+
+/// `restore` arguments for `snapshot_id` followed by the flags `options` ask for
+fn restore_args(snapshot_id: &str, target: &str, include_paths: &[String], options: &RestoreOptions) -> Result<Vec<String>> {
+    let mut args = restic_args::restore(snapshot_id, target, include_paths);
+    args.extend(restore_option_flags(options)?);
+    Ok(args)
+}
+
+/// restic flags for the restore options that have one
+fn restore_option_flags(options: &RestoreOptions) -> Result<Vec<String>> {
+    let mut flags = Vec::new();
+    if options.sparse {
+        restic_capabilities().require(Feature::RestoreSparse)?;
+        flags.push("--sparse".to_string());
+    }
+    Ok(flags)
+}
+
+/// Runs the options' post-restore metadata step over the restored `paths`
+async fn normalize_restored_metadata(options: &RestoreOptions, target: &Path, paths: &[String]) -> Result<Option<MetadataSummary>> {
+    let Some(normalization) = options.metadata.clone().filter(|m| !m.is_empty()) else {
+        return Ok(None);
+    };
+    let roots = restore_metadata::restored_roots(target, paths);
+    let summary = tauri::async_runtime::spawn_blocking(move || restore_metadata::apply(&roots, &normalization))
+        .await
+        .map_err(|e| AppError::Storage(format!("Metadata normalization failed: {}", e)))?;
+    if !summary.failed.is_empty() {
+        warn!("Couldn't apply metadata to {} restored entries", summary.failed.len());
+    }
+    Ok(Some(summary))
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn connect_repository(repo: String, password: SecretString) -> std::result::Result<String, CommandError> {
+    info!("Connecting to repository");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    run_restic(&repo, &password, &["snapshots", "--latest", "1", "--json"]).await?;
+    remember_connection(&repo).await;
+    info!("Successfully connected to repository");
+    Ok(tr("repository.connected", &[]))
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuickOpenSession {
+    pub session_id: String,
+    /// Set when the repository is saved already, so the UI can offer to switch to it
+    pub saved_repo_id: Option<String>,
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// Opens a repository without saving it, for a one-off recovery. The password
+/// stays in memory for the session; the quick open commands browse and restore
+/// from it and nothing reaches the config or the database unless it's saved.
+#[command]
+#[instrument(skip(password))]
+pub async fn quick_open_repository(repo: String, password: SecretString) -> std::result::Result<QuickOpenSession, CommandError> {
+    info!("Quick opening a repository");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    let output = run_restic(&repo, &password, &["snapshots", "--json"]).await?;
+    let mut snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    snapshots.sort_by_key(|s| std::cmp::Reverse(snapshot_instant(&s.time)));
+
+    let saved_repo_id = find_repository_by_path(&repo).map(|saved| saved.id);
+    let session_id = quick_open::open(repo, password, snapshots.clone())?;
+    info!("Quick opened repository with {} snapshots", snapshots.len());
+    Ok(QuickOpenSession { session_id, saved_repo_id, snapshots })
+}
+
+#[command]
+pub async fn quick_open_snapshots(session_id: String) -> std::result::Result<Vec<Snapshot>, CommandError> {
+    Ok(quick_open::with_session(&session_id, |session| session.snapshots.clone())?)
+}
+
+#[command]
+#[instrument]
+pub async fn quick_open_browse(
+    session_id: String,
+    snapshot_id: String,
+    path: Option<String>,
+) -> std::result::Result<Vec<FileNode>, CommandError> {
+    let (repo, password) = quick_open::with_session(&session_id, |s| (s.repo.clone(), s.password.clone()))?;
+    browse_snapshot(repo, password, snapshot_id, path, None, None, None, None).await
+}
+
+#[command]
+#[instrument(skip(window))]
+pub async fn quick_open_restore(
+    window: WebviewWindow,
+    session_id: String,
+    snapshot_id: String,
+    target: String,
+    options: Option<RestoreOptions>,
+) -> std::result::Result<RestoreResult, CommandError> {
+    let (repo, password) = quick_open::with_session(&session_id, |s| (s.repo.clone(), s.password.clone()))?;
+    restore_snapshot(window, repo, password, snapshot_id, target, options).await
+}
+
+/// Forgets the session and its password
+#[command]
+#[instrument]
+pub async fn close_quick_open(session_id: String) -> std::result::Result<(), CommandError> {
+    Ok(quick_open::close(&session_id)?)
+}
+
+/// Keeps a quick-opened repository: saves it with its password and caches the
+/// snapshots already listed. The session is closed.
+#[command]
+#[instrument]
+pub async fn save_quick_open(session_id: String, name: Option<String>) -> std::result::Result<SavedRepository, CommandError> {
+    let (repo, password, snapshots) = quick_open::with_session(&session_id, |s| {
+        (s.repo.clone(), s.password.clone(), s.snapshots.clone())
+    })?;
+    let name = name.unwrap_or_else(|| env_import::default_name(&repo));
+    validate_repository_name(&name)?;
+
+    let saved = SavedRepository {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        path: repo,
+        password,
+        ..Default::default()
+    };
+
+    {
+        let mut config = edit_config().map_err(AppError::Storage)?;
+        let mut stored = saved.clone();
+        secrets::stash_password(&config, &mut stored)?;
+        config.repositories.push(stored);
+        config.save().map_err(AppError::Storage)?;
+    }
+
+    quick_open::close(&session_id)?;
+    let repo_id = saved.id.clone();
+    blocking(move || database::save_snapshots_metadata_only(&repo_id, &snapshots, |_| {})).await?;
+    info!("Saved quick-opened repository as {}", saved.id);
+    Ok(saved)
+}
+
+/// Saved-repository entry to create once `init_repository` succeeds
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepositoryRegistration {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InitRepositoryResult {
+    pub fingerprint: Option<String>,
+    pub saved: Option<SavedRepository>,
+}
+
+fn classify_init_error(repo: &str, error: AppError) -> AppError {
+    if matches!(error, AppError::PermissionDenied(_)) {
+        return AppError::RepositoryPermissionDenied(repo.to_string());
+    }
+    let AppError::ResticError(stderr) = &error else { return error };
+    let lower = stderr.to_lowercase();
+
+    if lower.contains("already exists") || lower.contains("already initialized") {
+        AppError::RepositoryAlreadyInitialized(repo.to_string())
+    } else if lower.contains("permission denied") || lower.contains("access is denied") {
+        AppError::RepositoryPermissionDenied(repo.to_string())
+    } else {
+        error
+    }
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn init_repository(
+    repo: String,
+    password: SecretString,
+    register: Option<RepositoryRegistration>,
+) -> std::result::Result<InitRepositoryResult, CommandError> {
+    info!("Initializing repository");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if let Some(registration) = &register {
+        validate_repo_id(&registration.id)?;
+        validate_repository_name(&registration.name)?;
+    }
+
+    run_restic(&repo, &password, &["init"]).await.map_err(|e| classify_init_error(&repo, e))?;
+    info!("Repository initialized");
+
+    let fingerprint = match fetch_repository_fingerprint(&repo, &password).await {
+        Ok(fingerprint) => Some(fingerprint),
+        Err(e) => {
+            warn!("Failed to read fingerprint of new repository: {}", e);
+            None
+        }
+    };
+
+    let saved = match register {
+        Some(registration) => {
+            let saved = SavedRepository {
+                id: registration.id,
+                name: registration.name,
+                path: repo,
+                password,
+                fingerprint: fingerprint.clone(),
+                ..Default::default()
+            };
+
+            let mut config = edit_config().map_err(AppError::Storage)?;
+            let mut stored = saved.clone();
+            secrets::stash_password(&config, &mut stored)?;
+            config.repositories.retain(|r| r.id != stored.id);
+            config.repositories.push(stored);
+            config.save().map_err(AppError::Storage)?;
+            Some(saved)
+        }
+        None => None,
+    };
+
+    Ok(InitRepositoryResult { fingerprint, saved })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvironmentImport {
+    pub repository: SavedRepository,
+    pub password_origin: PasswordOrigin,
+    /// Backend credential variables that were found and saved with the repository
+    pub backend_variables: Vec<String>,
+}
+
+/// Creates a saved repository from restic's environment variables, read from
+/// a pasted `.env` block or, when none is given, from the app's own environment.
+#[command]
+#[instrument(skip(env_block))]
+pub async fn import_from_environment(
+    env_block: Option<String>,
+    name: Option<String>,
+) -> std::result::Result<EnvironmentImport, CommandError> {
+    info!("Importing repository from environment");
+    let env = match &env_block {
+        Some(block) => env_import::parse_env_block(block),
+        None => env_import::process_environment(),
+    };
+
+    let repository = match env.get("RESTIC_REPOSITORY").filter(|r| !r.is_empty()) {
+        Some(repository) => repository.clone(),
+        None => {
+            let path = env.get("RESTIC_REPOSITORY_FILE").filter(|p| !p.is_empty())
+                .ok_or_else(|| AppError::EnvironmentImport("RESTIC_REPOSITORY is not set".to_string()))?;
+            std::fs::read_to_string(path)
+                .map_err(|e| AppError::EnvironmentImport(format!("Failed to read RESTIC_REPOSITORY_FILE {}: {}", path, e)))?
+                .trim()
+                .to_string()
+        }
+    };
+    validate_repository_path(&repository)?;
+
+    let (password, password_origin) = env_import::resolve_password(&env)?;
+    validate_password(&password)?;
+
+    // Keep reading the password from where restic did instead of saving a copy
+    let password_source = match password_origin {
+        PasswordOrigin::PasswordFile => PasswordSource::PasswordFile {
+            path: env.get("RESTIC_PASSWORD_FILE").cloned().unwrap_or_default(),
+        },
+        PasswordOrigin::PasswordCommand => PasswordSource::PasswordCommand {
+            command: env.get("RESTIC_PASSWORD_COMMAND").cloned().unwrap_or_default(),
+        },
+        PasswordOrigin::Password => PasswordSource::Inline,
+    };
+    validate_password_source(&password_source)?;
+
+    let name = name.unwrap_or_else(|| env_import::default_name(&repository));
+    validate_repository_name(&name)?;
+
+    let saved = SavedRepository {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        path: repository,
+        password,
+        password_source,
+        backend_credentials: env_import::backend_credentials(&env),
+        ..Default::default()
+    };
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let mut stored = saved.clone();
+    secrets::stash_password(&config, &mut stored)?;
+    config.repositories.push(stored);
+    config.save().map_err(AppError::Storage)?;
+
+    let backend_variables = env_import::backend_variables(&env);
+    info!("Imported repository {} ({} backend variables found)", saved.id, backend_variables.len());
+    Ok(EnvironmentImport { repository: saved, password_origin, backend_variables })
+}
+
+/// Parses a filter date as UTC; a bare `before` date includes that whole day
+fn filter_time_bound(value: &str, end_of_day: bool) -> Result<i64> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time.and_utc().timestamp());
+        }
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| AppError::InvalidFilterValue(value.to_string()))?;
+    let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+    Ok(time.map(|t| t.and_utc().timestamp()).unwrap_or_default())
+}
+
+/// Validates the filter and returns its date range as Unix timestamps
+fn validate_snapshot_filter(filter: &SnapshotFilter) -> Result<(Option<i64>, Option<i64>)> {
+    for value in filter.hosts.iter().chain(&filter.tags) {
+        validate_filter_value(value)?;
+    }
+    if let Some(prefix) = &filter.path_prefix {
+        validate_snapshot_path(prefix)?;
+    }
+    let after = filter.after.as_deref().map(|t| filter_time_bound(t, false)).transpose()?;
+    let before = filter.before.as_deref().map(|t| filter_time_bound(t, true)).transpose()?;
+    Ok((after, before))
+}
+
+fn under_path_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path == prefix || path.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Lists snapshots from restic, narrowed by `filter` when given
+async fn fetch_snapshots(repo: &str, password: &str, filter: &SnapshotFilter) -> Result<Vec<Snapshot>> {
+    let (after, before) = validate_snapshot_filter(filter)?;
+    let args = restic_args::snapshots_filtered(filter);
+    let output = run_restic(repo, password, &restic_args::as_strs(&args)).await?;
+    let mut snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+
+    snapshots.retain(|snapshot| {
+        let in_range = match snapshot_instant(&snapshot.time).map(|t| t.timestamp()) {
+            Some(time) => after.is_none_or(|a| time >= a) && before.is_none_or(|b| time <= b),
+            None => after.is_none() && before.is_none(),
+        };
+        let has_path = filter.path_prefix.as_deref()
+            .is_none_or(|prefix| snapshot.paths.iter().any(|p| under_path_prefix(p, prefix)));
+        in_range && has_path
+    });
+    Ok(snapshots)
+}
+
+fn group_snapshots(snapshots: Vec<Snapshot>, group_by: SnapshotGroupBy) -> Vec<SnapshotGroup<Snapshot>> {
+    // `None` keys sort last, after every named group
+    let mut groups: BTreeMap<(bool, String), Vec<Snapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let keys: Vec<Option<String>> = match group_by {
+            SnapshotGroupBy::Host => vec![Some(snapshot.hostname.clone()).filter(|h| !h.is_empty())],
+            SnapshotGroupBy::Tag => match snapshot.tags.as_ref().filter(|t| !t.is_empty()) {
+                Some(tags) => tags.iter().cloned().map(Some).collect(),
+                None => vec![None],
+            },
+            SnapshotGroupBy::Path => snapshot.paths.iter().cloned().map(Some).collect(),
+        };
+        for key in keys {
+            groups.entry((key.is_none(), key.unwrap_or_default())).or_default().push(snapshot.clone());
+        }
+    }
+    groups.into_iter()
+        .map(|((is_none, key), mut snapshots)| {
+            snapshots.sort_by_key(|s| std::cmp::Reverse(snapshot_instant(&s.time)));
+            SnapshotGroup { key: (!is_none).then_some(key), snapshots }
+        })
+        .collect()
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn list_snapshots(
+    repo: String,
+    password: SecretString,
+    filter: Option<SnapshotFilter>,
+) -> std::result::Result<Vec<Snapshot>, CommandError> {
+    info!("Listing snapshots");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    let snapshots = fetch_snapshots(&repo, &password, &filter.unwrap_or_default()).await?;
+    info!("Found {} snapshots", snapshots.len());
+
+    remember_connection(&repo).await;
+    remember_fingerprint(&repo, &password).await;
+    Ok(snapshots)
+}
+
+/// Lists snapshots from restic grouped by host, tag or path
+#[command]
+#[instrument(skip(password))]
+pub async fn list_snapshot_groups(
+    repo: String,
+    password: SecretString,
+    filter: Option<SnapshotFilter>,
+    group_by: SnapshotGroupBy,
+) -> std::result::Result<Vec<SnapshotGroup<Snapshot>>, CommandError> {
+    info!("Listing snapshots grouped by {:?}", group_by);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    let snapshots = fetch_snapshots(&repo, &password, &filter.unwrap_or_default()).await?;
+    let groups = group_snapshots(snapshots, group_by);
+    info!("Found {} groups", groups.len());
+    Ok(groups)
+}
+
+/// Filters and groups the cached snapshots without running restic
+#[command]
+#[instrument]
+pub async fn query_cached_snapshots(
+    repo_id: String,
+    filter: Option<SnapshotFilter>,
+    group_by: Option<SnapshotGroupBy>,
+) -> std::result::Result<Vec<SnapshotGroup<DbSnapshotWithStats>>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let filter = filter.unwrap_or_default();
+    let (after, before) = validate_snapshot_filter(&filter)?;
+    Ok(blocking(move || database::query_snapshot_groups(&repo_id, &filter, after, before, group_by)).await?)
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum PrimingStage {
+    FetchingList,
+    Parsing,
+    Writing,
+    BuildingFacets,
+    Done,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct CachePrimingProgress<'a> {
+    repo_id: &'a str,
+    stage: PrimingStage,
+    done: usize,
+    total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachePrimingResult {
+    pub snapshots: Vec<Snapshot>,
+    pub facets: SnapshotFacets,
+}
+
+// Snapshots written between progress events while a large list is saved
+const PRIMING_CHUNK: usize = 500;
+
+/// Fills the snapshot cache from restic: lists the snapshots, replaces the cached
+/// list with them in one transaction and counts the filter values, reporting each
+/// stage as `cache-priming-progress` so a first sync of a large repository isn't silent.
+#[command]
+#[instrument(skip(app, password))]
+pub async fn prime_snapshot_cache(
+    app: AppHandle,
+    repo_id: String,
+    repo: String,
+    password: SecretString,
+) -> std::result::Result<CachePrimingResult, CommandError> {
+    info!("Priming snapshot cache for repo {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    let progress = {
+        let app = app.clone();
+        let repo_id = repo_id.clone();
+        move |stage, done, total| {
+            window_scope::emit_repo_event(&app, &repo_id, "cache-priming-progress", CachePrimingProgress {
+                repo_id: &repo_id,
+                stage,
+                done,
+                total,
+            });
+        }
+    };
+
+    progress(PrimingStage::FetchingList, 0, 0);
+    let output = run_restic(&repo, &password, &restic_args::as_strs(&restic_args::snapshots())).await?;
+    progress(PrimingStage::Parsing, 0, 0);
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    let total = snapshots.len();
+    info!("Found {} snapshots", total);
+
+    progress(PrimingStage::Writing, 0, total);
+    let snapshots = {
+        let (app, repo_id) = (app.clone(), repo_id.clone());
+        let progress = progress.clone();
+        blocking(move || {
+            let summary = database::replace_snapshots(&repo_id, &snapshots, PRIMING_CHUNK, |written| {
+                progress(PrimingStage::Writing, written, total);
+            })?;
+            let added: HashSet<&String> = summary.added.iter().collect();
+            emit_snapshots_delta(&app, SnapshotsDelta {
+                repo_id: repo_id.clone(),
+                added: snapshots.iter().filter(|s| added.contains(&s.id)).cloned().collect(),
+                removed: summary.removed,
+            });
+            Ok(snapshots)
+        }).await?
+    };
+
+    progress(PrimingStage::BuildingFacets, total, total);
+    let id = repo_id.clone();
+    let facets = blocking(move || database::get_snapshot_facets(&id)).await?;
+    progress(PrimingStage::Done, total, total);
+
+    remember_connection(&repo).await;
+    remember_fingerprint(&repo, &password).await;
+    Ok(CachePrimingResult { snapshots, facets })
+}
+
+#[command]
+#[instrument]
+pub async fn get_snapshot_facets(repo_id: String) -> std::result::Result<SnapshotFacets, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || database::get_snapshot_facets(&repo_id)).await?)
+}
+
+async fn fetch_repository_fingerprint(repo: &str, password: &str) -> Result<String> {
+    let output = run_restic(repo, password, &["cat", "config"]).await?;
+    let config: Value = serde_json::from_str(&output)?;
+    config.get("id")
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string())
+        .ok_or_else(|| AppError::ResticError("Repository config has no id".to_string()))
+}
+
+async fn remember_connection(repo: &str) {
+    let Some(saved) = find_repository_by_path(repo) else { return };
+    if let Err(e) = blocking(move || database::record_connection(&saved.id)).await {
+        warn!("Failed to record repository connection: {}", e);
+    }
+}
+
+// Record the fingerprint while the saved path still works so a later relink can be verified
+async fn remember_fingerprint(repo: &str, password: &str) {
+    let Some(saved) = find_repository_by_path(repo) else { return };
+    if saved.fingerprint.is_some() {
+        return;
+    }
+
+    match fetch_repository_fingerprint(repo, password).await {
+        Ok(fingerprint) => {
+            let result = edit_config().and_then(|mut config| {
+                if let Some(r) = config.repositories.iter_mut().find(|r| r.id == saved.id) {
+                    r.fingerprint = Some(fingerprint);
+                }
+                config.save()
+            });
+            if let Err(e) = result {
+                warn!("Failed to store repository fingerprint: {}", e);
+            }
+        }
+        Err(e) => warn!("Failed to read repository fingerprint: {}", e),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelinkVerification {
+    Fingerprint,
+    CachedSnapshots,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelinkResult {
+    pub repo_id: String,
+    pub path: String,
+    pub verified_by: RelinkVerification,
+    pub matched_snapshots: usize,
+}
+
+#[command]
+#[instrument]
+pub async fn relink_repository(repo_id: String, new_path: String) -> std::result::Result<RelinkResult, CommandError> {
+    info!("Relinking repository {} to {}", repo_id, new_path);
+    validate_repo_id(&repo_id)?;
+    validate_repository_path(&new_path)?;
+
+    let saved = secrets::saved_repository(&repo_id)?;
+
+    let fingerprint = fetch_repository_fingerprint(&new_path, &saved.password).await?;
+
+    let (verified_by, matched_snapshots) = match &saved.fingerprint {
+        Some(known) => {
+            if *known != fingerprint {
+                warn!("Fingerprint mismatch for {}: expected {}, found {}", repo_id, known, fingerprint);
+                return Err(AppError::RepositoryMismatch(new_path).into());
+            }
+            (RelinkVerification::Fingerprint, 0)
+        }
+        None => {
+            let id = repo_id.clone();
+            let cached_ids = blocking(move || database::get_all_snapshot_ids(&id)).await?;
+            if cached_ids.is_empty() {
+                return Err(AppError::RepositoryIdentityUnknown.into());
+            }
+
+            let output = run_restic(&new_path, &saved.password, &["snapshots", "--json"]).await?;
+            let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+                .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+            let matched = snapshots.iter().filter(|s| cached_ids.contains(&s.id)).count();
+            if matched == 0 {
+                warn!("None of the {} cached snapshots exist at {}", cached_ids.len(), new_path);
+                return Err(AppError::RepositoryMismatch(new_path).into());
+            }
+            (RelinkVerification::CachedSnapshots, matched)
+        }
+    };
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    if let Some(repo) = config.repositories.iter_mut().find(|r| r.id == repo_id) {
+        repo.path = new_path.clone();
+        repo.fingerprint = Some(fingerprint);
+    }
+    config.save().map_err(AppError::Storage)?;
+
+    info!("Repository {} relinked, cached data preserved", repo_id);
+    Ok(RelinkResult {
+        repo_id,
+        path: new_path,
+        verified_by,
+        matched_snapshots,
+    })
+}
+
+/// Every node in a snapshot, up to `limit`, from the node cache or file index when
+/// the snapshot is in one and otherwise streamed from `restic ls`
+#[command]
+pub async fn get_snapshot_details(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    limit: Option<usize>,
+) -> std::result::Result<Vec<FileNode>, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let limit = limit.unwrap_or(browse::MAX_DETAIL_NODES).clamp(1, browse::MAX_DETAIL_NODES);
+
+    if let Some(mut files) = browse_cached(&repo, &snapshot_id, None).await? {
+        files.truncate(limit);
+        return Ok(files);
+    }
+    let files = streaming(move || {
+        let mut files = Vec::new();
+        for_each_ls_node(&repo, &password, &["ls", "--json", &snapshot_id], |node| {
+            if files.len() < limit {
+                files.push(node);
+            }
+        })?;
+        Ok(files)
+    }).await?;
+    Ok(files)
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn restore_snapshot(
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    target: String,
+    options: Option<RestoreOptions>,
+) -> std::result::Result<RestoreResult, CommandError> {
+    info!("Starting full snapshot restore to {}", target);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let options = options.unwrap_or_default();
+    let validated_target = validate_restore_target(&target, options.create_missing_dirs)?;
+    if let Some(metadata) = &options.metadata {
+        restore_metadata::validate(metadata)?;
+    }
+    let target_str = target_arg(&validated_target)?;
+    let elevated = options.elevate;
+    let operation_id = start_operation(&window, options.operation_id.clone(), "restore")?;
+
+    let restore_args = restore_args(&snapshot_id, target_str, &[], &options)?;
+    let args = restic_args::as_strs(&restore_args);
+    let started_at = chrono::Utc::now().timestamp();
+    let run = run_restore(&window, &operation_id, &repo, &password, &snapshot_id, &validated_target, &args, &options).await;
+    let (run, snapshot_paths) = {
+        let (repo, snapshot_id, target) = (repo.clone(), snapshot_id.clone(), target_str.to_string());
+        blocking(move || {
+            let snapshot_paths = find_repository_by_path(&repo)
+                .and_then(|saved| database::get_snapshot_paths(&saved.id, &snapshot_id).ok().flatten())
+                .unwrap_or_default();
+            remember_restore(&repo, &snapshot_id, &snapshot_paths, &target, started_at, &run);
+            Ok((run, snapshot_paths))
+        }).await?
+    };
+    let errors = parse_restore_errors(&run?.output);
+    if errors.is_empty() {
+        info!("Restore completed successfully");
+    } else {
+        warn!("Restore completed with {} path error(s)", errors.len());
+    }
+    let metadata = normalize_restored_metadata(&options, &validated_target, &snapshot_paths).await?;
+
+    Ok(RestoreResult {
+        message: tr("restore.completed", &[]),
+        errors,
+        elevated,
+        operation_id,
+        metadata,
+    })
+}
+
+#[command]
+#[instrument(skip(password), fields(num_paths = include_paths.len()))]
+pub async fn restore_selective(
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    target: String,
+    include_paths: Vec<String>,
+    options: Option<RestoreOptions>,
+) -> std::result::Result<RestoreResult, CommandError> {
+    info!("Starting selective restore of {} paths to {}", include_paths.len(), target);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let options = options.unwrap_or_default();
+    let validated_target = validate_restore_target(&target, options.create_missing_dirs)?;
+
+    for include_path in &include_paths {
+        validate_include_path(include_path)?;
+    }
+
+    if include_paths.is_empty() {
+        return Err(AppError::NoIncludePaths.into());
+    }
+    if let Some(metadata) = &options.metadata {
+        restore_metadata::validate(metadata)?;
+    }
+
+    let target_str = target_arg(&validated_target)?;
+    let restore_args = restore_args(&snapshot_id, target_str, &include_paths, &options)?;
+    let args = restic_args::as_strs(&restore_args);
+
+    let elevated = options.elevate;
+    let operation_id = start_operation(&window, options.operation_id.clone(), "restore")?;
+    let started_at = chrono::Utc::now().timestamp();
+    let run = run_restore(&window, &operation_id, &repo, &password, &snapshot_id, &validated_target, &args, &options).await;
+    let (run, include_paths) = {
+        let (repo, snapshot_id, target) = (repo.clone(), snapshot_id.clone(), target_str.to_string());
+        blocking(move || {
+            remember_restore(&repo, &snapshot_id, &include_paths, &target, started_at, &run);
+            Ok((run, include_paths))
+        }).await?
+    };
+    let errors = parse_restore_errors(&run?.output);
+    if errors.is_empty() {
+        info!("Selective restore completed successfully");
+    } else {
+        warn!("Selective restore completed with {} path error(s)", errors.len());
+    }
+    let metadata = normalize_restored_metadata(&options, &validated_target, &include_paths).await?;
+
+    Ok(RestoreResult {
+        message: tr("restore.selective_completed", &[include_paths.len().to_string()]),
+        errors,
+        elevated,
+        operation_id,
+        metadata,
+    })
+}
+
+// Snapshot times from different hosts can carry different UTC offsets
+fn snapshot_instant(time: &str) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    chrono::DateTime::parse_from_rfc3339(time).ok()
+}
+
+/// Picks the newest snapshot containing each path and groups the paths by it, so
+/// every snapshot is restored from once. Returns the plan and the paths no snapshot has.
+async fn plan_newest_versions(repo: &str, password: &str, paths: &[String]) -> Result<(Vec<PlannedRestore>, Vec<String>)> {
+    let output = run_restic(repo, password, &restic_args::as_strs(&restic_args::snapshots())).await?;
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    let times: HashMap<String, String> = snapshots.into_iter().map(|s| (s.id, s.time)).collect();
+
+    let patterns: Vec<String> = paths.iter().map(|p| p.trim_end_matches('/').to_string()).collect();
+    let output = run_restic(repo, password, &restic_args::as_strs(&restic_args::find_paths(&patterns))).await?;
+    let groups: Vec<FindSnapshotMatches> = if output.trim().is_empty() {
+        Vec::new()
+    } else {
+        serde_json::from_str(&output).map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?
+    };
+
+    let mut newest: HashMap<&str, &str> = HashMap::new();
+    for group in &groups {
+        let Some(time) = times.get(&group.snapshot) else { continue };
+        for m in &group.matches {
+            // Glob characters in a path can make it match others too
+            let Some(path) = paths.iter().find(|p| p.trim_end_matches('/') == m.path) else { continue };
+            let is_newer = newest.get(path.as_str())
+                .and_then(|current| times.get(*current))
+                .is_none_or(|current| match (snapshot_instant(time), snapshot_instant(current)) {
+                    (Some(candidate), Some(current)) => candidate > current,
+                    _ => time > current,
+                });
+            if is_newer {
+                newest.insert(path, &group.snapshot);
+            }
+        }
+    }
+
+    let mut plan: Vec<PlannedRestore> = Vec::new();
+    let mut unresolved = Vec::new();
+    for path in paths {
+        let Some(snapshot_id) = newest.get(path.as_str()) else {
+            unresolved.push(path.clone());
+            continue;
+        };
+        match plan.iter_mut().find(|p| p.snapshot_id == *snapshot_id) {
+            Some(step) => step.paths.push(path.clone()),
+            None => plan.push(PlannedRestore {
+                snapshot_id: snapshot_id.to_string(),
+                snapshot_time: times[*snapshot_id].clone(),
+                paths: vec![path.clone()],
+            }),
+        }
+    }
+    plan.sort_by(|a, b| match (snapshot_instant(&a.snapshot_time), snapshot_instant(&b.snapshot_time)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.snapshot_time.cmp(&b.snapshot_time),
+    });
+    Ok((plan, unresolved))
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct MultiRestoreProgress<'a> {
+    operation_id: &'a str,
+    /// 1-based index of the restore that is running
+    step: usize,
+    steps: usize,
+    snapshot_id: &'a str,
+    percent_done: f64,
+    done: bool,
+}
+
+/// Runs the planned restores one after another under a single operation ID. Progress
+/// is reported across all of them, each restore weighted by how many paths it covers.
+/// Returns the path errors of each restore, in plan order.
+async fn run_multi_restore(
+    window: &WebviewWindow,
+    operation_id: &str,
+    repo: &str,
+    password: &str,
+    plan: &[PlannedRestore],
+    target: &Path,
+    options: &RestoreOptions,
+) -> Result<Vec<Vec<RestorePathError>>> {
+    let (window, operation_id, repo, password) = (window.clone(), operation_id.to_string(), repo.to_string(), SecretString::from(password));
+    let (plan, target, options) = (plan.to_vec(), target.to_path_buf(), options.clone());
+    streaming(move || run_multi_restore_blocking(&window, &operation_id, &repo, &password, &plan, &target, &options)).await
+}
+
+fn run_multi_restore_blocking(
+    window: &WebviewWindow,
+    operation_id: &str,
+    repo: &str,
+    password: &str,
+    plan: &[PlannedRestore],
+    target: &Path,
+    options: &RestoreOptions,
+) -> Result<Vec<Vec<RestorePathError>>> {
+    if options.elevate {
+        ensure_elevation_supported(repo)?;
+    }
+    let target_str = target_arg(target)?;
+    let created = if options.create_missing_dirs {
+        target_dirs::create_missing(target)?
+    } else {
+        CreatedDirs::default()
+    };
+    let total_paths: usize = plan.iter().map(|step| step.paths.len()).sum();
+    let mut paths_done = 0;
+    let mut errors = Vec::new();
+
+    for (index, step) in plan.iter().enumerate() {
+        info!("Restoring {} paths from snapshot {} ({}/{})", step.paths.len(), step.snapshot_id, index + 1, plan.len());
+        let restore_args = restic_args::restore(&step.snapshot_id, target_str, &step.paths);
+        let args = restic_args::as_strs(&restore_args);
+        let progress = |fraction: f64, done: bool| MultiRestoreProgress {
+            operation_id,
+            step: index + 1,
+            steps: plan.len(),
+            snapshot_id: &step.snapshot_id,
+            percent_done: (paths_done as f64 + fraction * step.paths.len() as f64) / total_paths as f64 * 100.0,
+            done,
+        };
+
+        let started_at = chrono::Utc::now().timestamp();
+        let run = if options.elevate {
+            run_restic_restore_elevated(repo, password, &args).map(|output| RestoreRun { output, bytes_restored: None })
+        } else {
+            let mut args = verbosity::with_flags(&args, OperationKind::Restore);
+            args.push("--json");
+            let mut bytes_restored = None;
+            run_restic_streaming(repo, password, &args, ErrorHandling::Lenient, operation_id, |line| {
+                match RestoreProgress::from_line(line, &step.snapshot_id, target_str) {
+                    Some(p) if p.done => bytes_restored = Some(p.bytes_restored),
+                    Some(p) => window_scope::emit_to_window(window, "multi-restore-progress", progress(p.percent_done / 100.0, false)),
+                    None => {}
+                }
+            }).map(|output| RestoreRun { output, bytes_restored })
+        };
+        remember_restore(repo, &step.snapshot_id, &step.paths, target_str, started_at, &run);
+        let output = match run {
+            Ok(run) => run.output,
+            Err(e) => {
+                if matches!(e, AppError::OperationCancelled(_)) {
+                    created.roll_back();
+                }
+                return Err(e);
+            }
+        };
+
+        errors.push(parse_restore_errors(&output));
+        window_scope::emit_to_window(window, "multi-restore-progress", progress(1.0, index + 1 == plan.len()));
+        paths_done += step.paths.len();
+    }
+    Ok(errors)
+}
+
+/// Restores the newest version of each path, whichever snapshot it is in. The
+/// paths are grouped by snapshot and restored with as few restic runs as possible,
+/// all under one cancellable operation. With `dry_run` only the plan is returned.
+#[command]
+#[instrument(skip(window, paths), fields(count = paths.len()))]
+pub async fn plan_multi_snapshot_restore(
+    window: WebviewWindow,
+    repo_id: String,
+    paths: Vec<String>,
+    target: String,
+    options: Option<RestoreOptions>,
+    dry_run: bool,
+) -> std::result::Result<MultiRestoreResult, CommandError> {
+    info!("Planning restore of the newest version of {} paths", paths.len());
+    validate_repo_id(&repo_id)?;
+    if paths.is_empty() {
+        return Err(AppError::NoIncludePaths.into());
+    }
+    for path in &paths {
+        validate_include_path(path)?;
+    }
+    let options = options.unwrap_or_default();
+    let validated_target = validate_restore_target(&target, options.create_missing_dirs)?;
+
+    let saved = secrets::saved_repository(&repo_id)?;
+    let (plan, unresolved) = plan_newest_versions(&saved.path, &saved.password, &paths).await?;
+    info!("{} paths resolve to {} snapshots, {} not found", paths.len() - unresolved.len(), plan.len(), unresolved.len());
+
+    let mut result = MultiRestoreResult { dry_run, plan, unresolved, errors: Vec::new(), operation_id: None };
+    if dry_run || result.plan.is_empty() {
+        return Ok(result);
+    }
+
+    let operation_id = start_operation(&window, options.operation_id.clone(), "multi_restore")?;
+    result.errors = run_multi_restore(&window, &operation_id, &saved.path, &saved.password, &result.plan, &validated_target, &options).await?
+        .into_iter()
+        .flatten()
+        .collect();
+    if result.errors.is_empty() {
+        info!("Multi-snapshot restore completed successfully");
+    } else {
+        warn!("Multi-snapshot restore completed with {} path error(s)", result.errors.len());
+    }
+    result.operation_id = Some(operation_id);
+    Ok(result)
+}
+
+// Whether restic's error for `error_path` belongs to the cart item at `item_path`
+fn error_under_item(error_path: &str, item_path: &str, target: &Path) -> bool {
+    let item = item_path.trim_end_matches('/');
+    let restored = restored_location(target, item_path);
+    under_path_prefix(error_path, item) || Path::new(error_path).starts_with(&restored)
+}
+
+/// Restores a cart of files picked from different snapshots as one job. Items are
+/// grouped by snapshot and restored one snapshot at a time, oldest first, so where
+/// items overlap the newer version wins. Errors are reported per item.
+#[command]
+#[instrument(skip(window, items), fields(count = items.len()))]
+pub async fn restore_cart(
+    window: WebviewWindow,
+    repo_id: String,
+    items: Vec<CartItem>,
+    target: String,
+    options: Option<RestoreOptions>,
+) -> std::result::Result<CartRestoreResult, CommandError> {
+    info!("Restoring a cart of {} items to {}", items.len(), target);
+    validate_repo_id(&repo_id)?;
+    if items.is_empty() {
+        return Err(AppError::NoIncludePaths.into());
+    }
+    for item in &items {
+        validate_snapshot_id(&item.snapshot_id)?;
+        validate_include_path(&item.path)?;
+    }
+    let options = options.unwrap_or_default();
+    let validated_target = validate_restore_target(&target, options.create_missing_dirs)?;
+
+    let (full_ids, snapshots) = {
+        let repo_id = repo_id.clone();
+        let short_ids: Vec<String> = items.iter().map(|item| item.snapshot_id.clone()).collect();
+        blocking(move || {
+            // Items may name snapshots by their short ID
+            let mut full_ids = Vec::with_capacity(short_ids.len());
+            for short_id in short_ids {
+                let full_id = database::resolve_snapshot_id(&repo_id, &short_id)?
+                    .ok_or(AppError::SnapshotNotCached(short_id))?;
+                full_ids.push(full_id);
+            }
+            let mut snapshot_ids: Vec<String> = full_ids.clone();
+            snapshot_ids.sort();
+            snapshot_ids.dedup();
+            let snapshots = database::get_snapshots_by_ids(&repo_id, &snapshot_ids)?;
+            Ok((full_ids, snapshots))
+        }).await?
+    };
+    let mut plan: Vec<PlannedRestore> = snapshots
+        .into_iter()
+        .map(|snapshot| PlannedRestore {
+            paths: items.iter().zip(&full_ids)
+                .filter(|(_, id)| **id == snapshot.id)
+                .map(|(item, _)| item.path.clone())
+                .collect(),
+            snapshot_id: snapshot.id,
+            snapshot_time: snapshot.time,
+        })
+        .collect();
+    plan.sort_by_key(|step| snapshot_instant(&step.snapshot_time));
+    for step in &mut plan {
+        step.paths.sort();
+        step.paths.dedup();
+    }
+
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    let operation_id = start_operation(&window, options.operation_id.clone(), "cart_restore")?;
+    let step_errors = run_multi_restore(&window, &operation_id, &saved.path, &saved.password, &plan, &validated_target, &options).await?;
+
+    let mut results = Vec::with_capacity(items.len());
+    for (item, full_id) in items.into_iter().zip(&full_ids) {
+        let errors: Vec<RestorePathError> = plan.iter().zip(&step_errors)
+            .filter(|(step, _)| step.snapshot_id == *full_id)
+            .flat_map(|(_, errors)| errors)
+            .filter(|e| error_under_item(&e.path, &item.path, &validated_target))
+            .cloned()
+            .collect();
+        let status = match errors.is_empty() {
+            true => CartItemStatus::Restored,
+            false => CartItemStatus::RestoredWithErrors,
+        };
+        results.push(CartItemResult { id: item.id, snapshot_id: item.snapshot_id, path: item.path, status, errors });
+    }
+
+    let failed = results.iter().filter(|r| r.status != CartItemStatus::Restored).count();
+    if failed == 0 {
+        info!("Cart restore completed successfully");
+    } else {
+        warn!("Cart restore completed with errors for {} item(s)", failed);
+    }
+    Ok(CartRestoreResult { plan, items: results, operation_id })
+}
+
+const VERIFY_MISMATCH_LIMIT: usize = 1_000;
+
+// Where `restic restore --target` puts a snapshot path: /C/Users/... becomes <target>\C\Users\...
+fn restored_location(target: &Path, snapshot_path: &str) -> PathBuf {
+    snapshot_path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(target.to_path_buf(), |path, segment| path.join(segment))
+}
+
+fn compare_restored_node(node: &FileNode, local: &Path) -> Option<VerifyMismatch> {
+    let mismatch = |kind, expected: Option<String>, actual: Option<String>| Some(VerifyMismatch {
+        path: node.path.clone(),
+        kind,
+        expected,
+        actual,
+    });
+
+    let Ok(meta) = local.symlink_metadata() else {
+        return mismatch(MismatchKind::Missing, Some(node.node_type.clone()), None);
+    };
+    let actual_type = if meta.is_symlink() { "symlink" } else if meta.is_dir() { "dir" } else { "file" };
+    if ["file", "dir", "symlink"].contains(&node.node_type.as_str()) && node.node_type != actual_type {
+        return mismatch(MismatchKind::TypeMismatch, Some(node.node_type.clone()), Some(actual_type.to_string()));
+    }
+    // Directory sizes and mtimes change as their contents are restored
+    if node.node_type != "file" {
+        return None;
+    }
+
+    if let Some(size) = node.size.filter(|s| *s != meta.len()) {
+        return mismatch(MismatchKind::SizeMismatch, Some(size.to_string()), Some(meta.len().to_string()));
+    }
+
+    let expected = node.mtime.as_deref().and_then(|m| chrono::DateTime::parse_from_rfc3339(m).ok());
+    let actual = meta.modified().ok().map(chrono::DateTime::<chrono::Utc>::from);
+    if let (Some(expected), Some(actual)) = (expected, actual) {
+        // Some filesystems only keep whole (or even two) seconds
+        if (expected.timestamp() - actual.timestamp()).abs() > 2 {
+            return mismatch(MismatchKind::MtimeMismatch, Some(expected.to_rfc3339()), Some(actual.to_rfc3339()));
+        }
+    }
+    None
+}
+
+#[command]
+#[instrument(skip(password, include_paths))]
+pub async fn verify_restore(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    target: String,
+    include_paths: Option<Vec<String>>,
+) -> std::result::Result<VerifyReport, CommandError> {
+    info!("Verifying restore of snapshot {} in {}", snapshot_id, target);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let target = validate_target_path(&target)?;
+    let include_paths = include_paths.unwrap_or_default();
+    for include_path in &include_paths {
+        validate_include_path(include_path)?;
+    }
+    let scopes: Vec<String> = include_paths.iter().map(|p| format!("/{}", p.trim_matches('/'))).collect();
+
+    let report = streaming(move || -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        for_each_ls_node(&repo, &password, &["ls", "--json", &snapshot_id], |node| {
+            if !scopes.is_empty() && !scopes.iter().any(|scope| is_within(&node.path, scope)) {
+                return;
+            }
+            report.checked += 1;
+            if let Some(mismatch) = compare_restored_node(&node, &restored_location(&target, &node.path)) {
+                if report.mismatches.len() < VERIFY_MISMATCH_LIMIT {
+                    report.mismatches.push(mismatch);
+                } else {
+                    report.truncated = true;
+                }
+            }
+        })?;
+        Ok(report)
+    }).await?;
+
+    if report.mismatches.is_empty() {
+        info!("All {} restored items match the snapshot", report.checked);
+    } else {
+        warn!("{} of {} restored items don't match the snapshot", report.mismatches.len(), report.checked);
+    }
+    Ok(report)
+}
+
+// Items listed in a preview; the totals still cover everything
+const PREVIEW_ITEM_LIMIT: usize = 10_000;
+
+#[command]
+#[instrument(skip(password, include_paths))]
+pub async fn preview_restore(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    target: String,
+    include_paths: Option<Vec<String>>,
+) -> std::result::Result<RestorePreview, CommandError> {
+    info!("Previewing restore of snapshot {} to {}", snapshot_id, target);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let validated_target = validate_target_path(&target)?;
+    let include_paths = include_paths.unwrap_or_default();
+    for include_path in &include_paths {
+        validate_include_path(include_path)?;
+    }
+
+    restic_capabilities().require(Feature::RestoreDryRun)?;
+
+    let preview = streaming(move || -> Result<RestorePreview> {
+        let target_str = validated_target.to_string_lossy();
+        // -vv makes restic report every item it would write
+        let mut args = vec!["restore", &snapshot_id, "--target", &target_str, "--dry-run", "--json", "-vv"];
+        for include_path in &include_paths {
+            args.extend(["--include", include_path.as_str()]);
+        }
+
+        let mut preview = RestorePreview { target: target_str.to_string(), ..Default::default() };
+        run_restic_ndjson(&repo, &password, &args, |value| {
+            match value.get("message_type").and_then(Value::as_str) {
+                Some("verbose_status") => {
+                    if preview.items.len() >= PREVIEW_ITEM_LIMIT {
+                        preview.truncated = true;
+                        return;
+                    }
+                    let field = |name: &str| value.get(name).and_then(Value::as_str).unwrap_or_default().to_string();
+                    preview.items.push(PreviewItem {
+                        path: field("item"),
+                        action: field("action"),
+                        size: value.get("size").and_then(Value::as_u64).unwrap_or(0),
+                    });
+                }
+                Some("summary") => {
+                    let count = |name: &str| value.get(name).and_then(Value::as_u64).unwrap_or(0);
+                    preview.total_files = count("total_files");
+                    preview.total_bytes = count("total_bytes");
+                    preview.files_skipped = count("files_skipped");
+                    preview.bytes_skipped = count("bytes_skipped");
+                }
+                _ => {}
+            }
+        })?;
+        Ok(preview)
+    }).await?;
+
+    info!("Restore would write {} files ({} bytes)", preview.total_files, preview.total_bytes);
+    Ok(preview)
+}
+
+/// One `restic restore` run for an in-place restore: the part of the snapshot tree
+/// to restore and where it goes
+struct InPlaceTarget {
+    tree_root: String,
+    target: PathBuf,
+}
+
+// restic stores Windows paths as /C/Users/..., so each drive is restored on its own
+fn in_place_targets(snapshot_paths: &[String]) -> Result<Vec<InPlaceTarget>> {
+    if !cfg!(target_os = "windows") {
+        return Ok(vec![InPlaceTarget { tree_root: String::new(), target: PathBuf::from("/") }]);
+    }
+
+    let mut drives: Vec<char> = Vec::new();
+    for path in snapshot_paths {
+        let mut chars = path.chars();
+        match (chars.next(), chars.next()) {
+            (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+                let drive = drive.to_ascii_uppercase();
+                if !drives.contains(&drive) {
+                    drives.push(drive);
+                }
+            }
+            _ => return Err(AppError::InPlaceRestoreUnsupported(path.clone())),
+        }
+    }
+    Ok(drives.into_iter()
+        .map(|drive| InPlaceTarget {
+            tree_root: format!("/{}", drive),
+            target: PathBuf::from(format!("{}:\\", drive)),
+        })
+        .collect())
+}
+
+// Checks the closest existing directory of each original path by creating a file in it
+fn validate_destination_writable(path: &Path) -> Result<()> {
+    let dir = path.ancestors()
+        .find(|p| p.is_dir())
+        .ok_or_else(|| AppError::ParentDirectoryNotFound(path.to_path_buf()))?;
+
+    let probe = dir.join(format!(".restic-restore-{}", uuid::Uuid::new_v4()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            Ok(())
+        }
+        Err(e) => {
+            debug!("Write check in {} failed: {}", dir.display(), e);
+            Err(AppError::DestinationNotWritable(dir.display().to_string()))
+        }
+    }
+}
+
+fn backup_name(path: &Path) -> PathBuf {
+    let base = path.as_os_str().to_string_lossy();
+    let mut candidate = PathBuf::from(format!("{}.bak", base));
+    let mut n = 1;
+    while candidate.symlink_metadata().is_ok() {
+        candidate = PathBuf::from(format!("{}.bak.{}", base, n));
+        n += 1;
+    }
+    candidate
+}
+
+// Moves files that the restore would replace out of the way; directories are merged
+fn rename_existing_files(repo: &str, password: &str, snapshot_id: &str) -> Result<usize> {
+    let mut existing = Vec::new();
+    for_each_ls_node(repo, password, &["ls", "--json", snapshot_id], |node| {
+        if node.node_type == "dir" {
+            return;
+        }
+        let local = include_paths::to_original_path(&node.path);
+        if local.symlink_metadata().is_ok_and(|m| !m.is_dir()) {
+            existing.push(local);
+        }
+    })?;
+
+    for path in &existing {
+        let backup = backup_name(path);
+        debug!("Renaming {} to {}", path.display(), backup.display());
+        std::fs::rename(path, &backup)?;
+    }
+    Ok(existing.len())
+}
+
+#[command]
+#[instrument(skip(window, password))]
+pub async fn restore_in_place(
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    conflict_policy: ConflictPolicy,
+    options: Option<RestoreOptions>,
+) -> std::result::Result<RestoreResult, CommandError> {
+    info!("Restoring snapshot {} to its original location ({:?})", snapshot_id, conflict_policy);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    experiments::require(Experiment::InPlaceRestore)?;
+    ensure_not_safe_mode(&repo)?;
+
+    let output = run_restic(&repo, &password, &["snapshots", "--json", &snapshot_id]).await?;
+    let snapshot = serde_json::from_str::<Vec<Snapshot>>(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::SnapshotJsonParse(format!("snapshot {} not found", snapshot_id)))?;
+
+    let targets = in_place_targets(&snapshot.paths)?;
+    for path in &snapshot.paths {
+        validate_destination_writable(Path::new(path))?;
+    }
+
+    // Before --overwrite existed restic always overwrote, so only the other policies need it
+    let overwrite_flag = restic_capabilities().supports(Feature::RestoreOverwrite);
+    if !overwrite_flag && conflict_policy.overwrite_arg() != "always" {
+        restic_capabilities().require(Feature::RestoreOverwrite)?;
+    }
+
+    let options = options.unwrap_or_default();
+    let elevated = options.elevate;
+    if elevated {
+        ensure_elevation_supported(&repo)?;
+    }
+    let operation_id = start_operation(&window, options.operation_id, "restore")?;
+
+    let run_id = operation_id.clone();
+    let (renamed, errors) = streaming(move || -> Result<(usize, Vec<RestorePathError>)> {
+        let renamed = if conflict_policy == ConflictPolicy::RenameExisting {
+            rename_existing_files(&repo, &password, &snapshot_id)?
+        } else {
+            0
+        };
+
+        let mut errors = Vec::new();
+        for target in &targets {
+            let source = if target.tree_root.is_empty() {
+                snapshot_id.clone()
+            } else {
+                format!("{}:{}", snapshot_id, target.tree_root)
+            };
+            let target_str = target.target.to_string_lossy();
+            let mut args = vec!["restore", source.as_str(), "--target", &target_str];
+            if overwrite_flag {
+                args.extend(["--overwrite", conflict_policy.overwrite_arg()]);
+            }
+            let output = if elevated {
+                run_restic_restore_elevated(&repo, &password, &args)?
+            } else {
+                run_restore_with_progress(&window, &run_id, &repo, &password, &snapshot_id, &target_str, &args)?.output
+            };
+            errors.extend(parse_restore_errors(&output));
+        }
+        Ok((renamed, errors))
+    }).await?;
+
+    if errors.is_empty() {
+        info!("In-place restore completed successfully");
+    } else {
+        warn!("In-place restore completed with {} path error(s)", errors.len());
+    }
+
+    let message = if renamed > 0 {
+        tr("restore.in_place_renamed", &[renamed.to_string()])
+    } else {
+        tr("restore.in_place_completed", &[])
+    };
+    Ok(RestoreResult {
+        message,
+        errors,
+        elevated,
+        operation_id,
+        metadata: None,
+    })
+}
+
+fn validate_restore_point(point: &RestorePoint) -> Result<()> {
+    validate_repository_name(&point.name)?;
+    validate_repo_id(&point.repo_id)?;
+    match &point.snapshot {
+        SnapshotSelector::Id { id } => validate_snapshot_id(id)?,
+        SnapshotSelector::LatestMatching { host, tags, path } => {
+            for value in host.iter().chain(tags).chain(path) {
+                validate_filter_value(value)?;
+            }
+        }
+    }
+    for include_path in &point.include_paths {
+        validate_include_path(include_path)?;
+    }
+    validate_target_path(&point.target)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn list_restore_points(repo_id: Option<String>) -> std::result::Result<Vec<RestorePoint>, CommandError> {
+    let mut points = load_config().map_err(AppError::Storage)?.restore_points;
+    if let Some(repo_id) = repo_id {
+        points.retain(|p| p.repo_id == repo_id);
+    }
+    Ok(points)
+}
+
+/// Creates a restore point when its ID is empty, otherwise replaces the one with that ID
+#[command]
+#[instrument]
+pub async fn save_restore_point(point: RestorePoint) -> std::result::Result<RestorePoint, CommandError> {
+    validate_restore_point(&point)?;
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    if !config.repositories.iter().any(|r| r.id == point.repo_id && !r.is_deleted()) {
+        return Err(AppError::RepositoryNotFound(point.repo_id).into());
+    }
+
+    let mut point = point;
+    if point.id.is_empty() {
+        point.id = uuid::Uuid::new_v4().to_string();
+        info!("Creating restore point {}", point.name);
+        config.restore_points.push(point.clone());
+    } else {
+        let existing = config.restore_points.iter_mut()
+            .find(|p| p.id == point.id)
+            .ok_or_else(|| AppError::RestorePointNotFound(point.id.clone()))?;
+        info!("Updating restore point {}", point.name);
+        *existing = point.clone();
+    }
+    config.save().map_err(AppError::Storage)?;
+    Ok(point)
+}
+
+#[command]
+#[instrument]
+pub async fn delete_restore_point(id: String) -> std::result::Result<(), CommandError> {
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let before = config.restore_points.len();
+    config.restore_points.retain(|p| p.id != id);
+    if config.restore_points.len() == before {
+        return Err(AppError::RestorePointNotFound(id).into());
+    }
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+// Resolves "latest matching" to a concrete snapshot so the result says what was restored
+async fn resolve_snapshot_selector(repo: &str, password: &str, selector: &SnapshotSelector) -> Result<String> {
+    let (host, tags, path) = match selector {
+        SnapshotSelector::Id { id } => return Ok(id.clone()),
+        SnapshotSelector::LatestMatching { host, tags, path } => (host, tags, path),
+    };
+
+    let mut args = vec!["snapshots".to_string(), "--json".to_string(), "--latest".to_string(), "1".to_string()];
+    if let Some(host) = host {
+        args.extend(["--host".to_string(), host.clone()]);
+    }
+    if !tags.is_empty() {
+        args.extend(["--tag".to_string(), tags.join(",")]);
+    }
+    if let Some(path) = path {
+        args.extend(["--path".to_string(), path.clone()]);
+    }
+
+    let output = run_restic(repo, password, &restic_args::as_strs(&args)).await?;
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    // --latest keeps one snapshot per host and path set, so pick the newest overall
+    snapshots.into_iter()
+        .max_by(|a, b| a.time.cmp(&b.time))
+        .map(|s| s.id)
+        .ok_or_else(|| AppError::NoMatchingSnapshot(args[4..].join(" ")))
+}
+
+/// Runs a restore point as a full or selective restore
+#[command]
+#[instrument(skip(window))]
+pub async fn execute_restore_point(
+    window: WebviewWindow,
+    id: String,
+    operation_id: Option<String>,
+) -> std::result::Result<RestoreResult, CommandError> {
+    let point = load_config().map_err(AppError::Storage)?
+        .restore_points
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| AppError::RestorePointNotFound(id.clone()))?;
+    info!("Executing restore point {}", point.name);
+    validate_restore_point(&point)?;
+
+    let saved = secrets::saved_repository(&point.repo_id)?;
+    let snapshot_id = resolve_snapshot_selector(&saved.path, &saved.password, &point.snapshot).await?;
+    info!("Restore point {} resolved to snapshot {}", point.name, snapshot_id);
+
+    let options = Some(RestoreOptions { elevate: point.elevate, operation_id, ..Default::default() });
+    if point.include_paths.is_empty() {
+        restore_snapshot(window, saved.path, saved.password, snapshot_id, point.target, options).await
+    } else {
+        restore_selective(window, saved.path, saved.password, snapshot_id, point.target, point.include_paths, options).await
+    }
+}
+
+#[command]
+#[instrument(fields(count = selected_node_paths.len()))]
+pub async fn build_include_paths(
+    repo_id: String,
+    snapshot_id: String,
+    selected_node_paths: Vec<String>,
+) -> std::result::Result<Vec<String>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    validate_snapshot_id(&snapshot_id)?;
+    if selected_node_paths.is_empty() {
+        return Err(AppError::NoIncludePaths.into());
+    }
+
+    let roots = blocking(move || {
+        let snapshot_id = database::resolve_snapshot_id(&repo_id, &snapshot_id)?.unwrap_or(snapshot_id);
+        Ok(database::get_snapshot_paths(&repo_id, &snapshot_id)?.unwrap_or_else(|| {
+            debug!("Snapshot {} isn't cached, include paths aren't checked against its roots", snapshot_id);
+            Vec::new()
+        }))
+    }).await?;
+
+    let include_paths = include_paths::build(&roots, &selected_node_paths)?;
+    for include_path in &include_paths {
+        validate_include_path(include_path)?;
+    }
+    Ok(include_paths)
+}
+
+// Restore history is an audit trail and feeds target suggestions; it's only kept for saved repositories
+fn remember_restore(
+    repo: &str,
+    snapshot_id: &str,
+    source_paths: &[String],
+    target: &str,
+    started_at: i64,
+    run: &Result<RestoreRun>,
+) {
+    let Some(saved) = find_repository_by_path(repo) else { return };
+    let warnings = run.as_ref().map(|run| parse_restore_errors(&run.output)).unwrap_or_default();
+    let (outcome, error) = match run {
+        Ok(_) if warnings.is_empty() => (RestoreOutcome::Success, None),
+        Ok(_) => (RestoreOutcome::CompletedWithWarnings, None),
+        Err(AppError::OperationCancelled(_)) => (RestoreOutcome::Cancelled, None),
+        Err(e) => (RestoreOutcome::Failed, Some(e.to_string())),
+    };
+    let record = database::RestoreRecord {
+        repo_id: &saved.id,
+        snapshot_id,
+        source_paths,
+        target,
+        started_at,
+        bytes_restored: run.as_ref().ok().and_then(|run| run.bytes_restored),
+        outcome,
+        warnings: &warnings,
+        error: error.as_deref(),
+    };
+    if let Err(e) = database::record_restore(&record) {
+        warn!("Failed to record restore history: {}", e);
+    }
+}
+
+#[command]
+#[instrument]
+pub async fn suggest_restore_target(
+    repo_id: String,
+    snapshot_paths: Vec<String>,
+) -> std::result::Result<Option<RestoreTargetSuggestion>, CommandError> {
+    validate_repo_id(&repo_id)?;
+
+    let history = blocking(move || database::get_restore_history(&repo_id, restore_suggestions::HISTORY_WINDOW)).await?;
+    let suggestion = restore_suggestions::suggest(&history, &snapshot_paths);
+    if let Some(s) = &suggestion {
+        debug!("Suggesting restore target {} (confidence {:.2})", s.target, s.confidence);
+    }
+    Ok(suggestion)
+}
+
+/// Lists a snapshot's contents. With `mtime`, only entries modified in that window
+/// are returned; directories are always kept so the tree stays navigable. `filter`
+/// and `sort_by` are applied here so the frontend only receives what it shows.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn browse_snapshot(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    path: Option<String>,
+    mtime: Option<MtimeRange>,
+    sort_by: Option<BrowseSort>,
+    order: Option<SortOrder>,
+    filter: Option<BrowseFilter>,
+) -> std::result::Result<Vec<FileNode>, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+
+    if let Some(p) = &path {
+        validate_include_path(p)?;
+    }
+    let filter = filter.as_ref().map(browse::NodeFilter::new).transpose()?;
+    let mtime = mtime.filter(|m| !m.is_empty());
+    let window = match &mtime {
+        Some(range) => Some(mtime_window(&repo, &password, &snapshot_id, range).await?),
+        None => None,
+    };
+
+    let mut files = match browse_cached(&repo, &snapshot_id, path.as_deref()).await? {
+        Some(files) => files,
+        None => {
+            let args = restic_args::ls(&snapshot_id, path.as_deref());
+            streaming(move || {
+                let mut files = Vec::new();
+                for_each_ls_node(&repo, &password, &restic_args::as_strs(&args), |node| files.push(node))?;
+                Ok(files)
+            }).await?
+        }
+    };
+
+    if let Some((after, before)) = window {
+        files.retain(|node| {
+            if node.node_type == "dir" {
+                return true;
+            }
+            let Some(time) = node.mtime.as_deref().and_then(snapshot_instant).map(|t| t.timestamp()) else {
+                return false;
+            };
+            after.is_none_or(|a| time >= a) && before.is_none_or(|b| time <= b)
+        });
+    }
+    if let Some(filter) = &filter {
+        files.retain(|node| filter.accepts(node));
+    }
+    // Without `sort_by` the listing keeps restic's order
+    if let Some(sort) = sort_by {
+        let order = order.unwrap_or_default();
+        files.sort_by(|a, b| browse::compare(sort, order, a, b));
+    }
+    Ok(files)
+}
+
+/// One page of a directory in a snapshot, for directories too large to list at once.
+/// Only the directory's direct children are returned; the listing is streamed and
+/// only the requested page is kept.
+#[command]
+#[instrument(skip(password))]
+#[allow(clippy::too_many_arguments)]
+pub async fn browse_snapshot_page(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    path: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    sort: Option<BrowseSort>,
+    order: Option<SortOrder>,
+) -> std::result::Result<FilePage, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    if let Some(p) = &path {
+        validate_include_path(p)?;
+    }
+
+    let dir = format!("/{}", path.as_deref().unwrap_or("").trim_matches('/'));
+    let mut page = browse::PageCollector::new(
+        offset.unwrap_or(0),
+        limit.unwrap_or(browse::DEFAULT_PAGE_SIZE).clamp(1, browse::MAX_PAGE_SIZE),
+        sort.unwrap_or_default(),
+        order.unwrap_or_default(),
+    );
+
+    if let Some(files) = browse_cached(&repo, &snapshot_id, Some(&dir)).await? {
+        files.into_iter().filter(|n| browse::is_child_of(&dir, n)).for_each(|node| page.push(node));
+        return Ok(page.finish(dir));
+    }
+
+    let page = tauri::async_runtime::spawn_blocking(move || -> Result<FilePage> {
+        let args = restic_args::ls(&snapshot_id, Some(&dir));
+        for_each_ls_node(&repo, &password, &restic_args::as_strs(&args), |node| {
+            if browse::is_child_of(&dir, &node) {
+                page.push(node);
+            }
+        })?;
+        Ok(page.finish(dir))
+    })
+    .await
+    .map_err(|e| AppError::ResticExecution(e.to_string()))??;
+    debug!("Listed {} of {} entries in {}", page.entries.len(), page.total, page.path);
+    Ok(page)
+}
+
+/// A downscaled JPEG preview of an image file in a snapshot, for gallery-style browsing.
+/// Thumbnails are cached on disk per snapshot and path.
+#[command]
+#[instrument]
+pub async fn get_file_thumbnail(
+    repo_id: String,
+    snapshot_id: String,
+    path: String,
+    max_dim: Option<u32>,
+) -> std::result::Result<Thumbnail, CommandError> {
+    validate_repo_id(&repo_id)?;
+    validate_snapshot_id(&snapshot_id)?;
+    validate_snapshot_path(&path)?;
+    if !thumbnails::is_image(&path) {
+        return Err(AppError::ThumbnailUnavailable(path).into());
+    }
+    let max_dim = thumbnails::clamp_dim(max_dim);
+    let full_id = full_snapshot_id(&repo_id, snapshot_id).await?;
+    if let Some(thumbnail) = thumbnails::load_cached(&repo_id, &full_id, &path, max_dim) {
+        return Ok(thumbnail);
+    }
+
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    let thumbnail = tauri::async_runtime::spawn_blocking(move || -> Result<Thumbnail> {
+        let source = run_restic_bytes(&saved.path, &saved.password, &["dump", &full_id, &path], thumbnails::MAX_SOURCE_BYTES)?;
+        thumbnails::render(&repo_id, &full_id, &path, &source, max_dim)
+    })
+    .await
+    .map_err(|e| AppError::ThumbnailUnavailable(e.to_string()))??;
+    Ok(thumbnail)
+}
+
+/// Normalizes an mtime range's bounds to RFC 3339 for SQL queries
+fn mtime_bounds(range: &MtimeRange) -> Result<MtimeBounds> {
+    if range.days_before_snapshot == Some(0) {
+        return Err(AppError::InvalidFilterValue("0".to_string()));
+    }
+    let bound = |value: &Option<String>, end_of_day: bool| -> Result<Option<String>> {
+        value.as_deref()
+            .map(|v| filter_time_bound(v.trim(), end_of_day))
+            .transpose()
+            .map(|t| t.and_then(|t| chrono::DateTime::from_timestamp(t, 0)).map(|t| t.to_rfc3339()))
+    };
+    Ok(MtimeBounds {
+        after: bound(&range.after, false)?,
+        before: bound(&range.before, true)?,
+        days_before_snapshot: range.days_before_snapshot,
+    })
+}
+
+/// An mtime range as Unix timestamps for one snapshot. A `days_before_snapshot`
+/// window is combined with explicit bounds by taking the narrower of each.
+async fn mtime_window(repo: &str, password: &str, snapshot_id: &str, range: &MtimeRange) -> Result<(Option<i64>, Option<i64>)> {
+    let bounds = mtime_bounds(range)?;
+    let parse = |value: Option<String>| value.as_deref().and_then(snapshot_instant).map(|t| t.timestamp());
+    let mut after = parse(bounds.after);
+    let mut before = parse(bounds.before);
+
+    if let Some(days) = bounds.days_before_snapshot {
+        let cached = {
+            let (repo, snapshot_id) = (repo.to_string(), snapshot_id.to_string());
+            blocking(move || {
+                Ok(find_repository_by_path(&repo)
+                    .and_then(|saved| database::resolve_snapshot_id(&saved.id, &snapshot_id).ok().flatten().map(|id| (saved.id, id)))
+                    .and_then(|(repo_id, id)| database::get_snapshots_by_ids(&repo_id, &[id]).ok())
+                    .and_then(|snapshots| snapshots.into_iter().next()))
+            }).await?
+        };
+        let snapshot = match cached {
+            Some(snapshot) => snapshot,
+            None => fetch_snapshot(repo, password, snapshot_id).await?,
+        };
+        let taken = snapshot_instant(&snapshot.time)
+            .ok_or_else(|| AppError::SnapshotJsonParse(format!("invalid snapshot time {}", snapshot.time)))?
+            .timestamp();
+        let start = taken - i64::from(days) * 24 * 60 * 60;
+        after = Some(after.map_or(start, |a| a.max(start)));
+        before = Some(before.map_or(taken, |b| b.min(taken)));
+    }
+    Ok((after, before))
+}
+
+/// Expands a short snapshot ID from the cache, keeping the ID as given when it isn't cached
+async fn full_snapshot_id(repo_id: &str, snapshot_id: String) -> Result<String> {
+    let repo_id = repo_id.to_string();
+    blocking(move || Ok(database::resolve_snapshot_id(&repo_id, &snapshot_id)?.unwrap_or(snapshot_id))).await
+}
+
+/// `browse_from_cache` on the blocking thread pool
+async fn browse_cached(repo: &str, snapshot_id: &str, path: Option<&str>) -> Result<Option<Vec<FileNode>>> {
+    let (repo, snapshot_id, path) = (repo.to_string(), snapshot_id.to_string(), path.map(str::to_string));
+    blocking(move || Ok(browse_from_cache(&repo, &snapshot_id, path.as_deref()))).await
+}
+
+// Serves a browse from the node cache in the same shape `restic ls` would: the
+// whole tree without a path, otherwise the directory itself and its children
+fn browse_from_cache(repo: &str, snapshot_id: &str, path: Option<&str>) -> Option<Vec<FileNode>> {
+    let saved = find_repository_by_path(repo)?;
+    let snapshot_id = database::resolve_snapshot_id(&saved.id, snapshot_id).ok().flatten()?;
+    if let Some(files) = browse_from_index(&saved.id, &snapshot_id, path) {
+        return Some(files);
+    }
+    if !database::has_node_tree(&saved.id, &snapshot_id).ok()? {
+        return None;
+    }
+
+    let result = match path {
+        None => database::load_node_tree(&saved.id, &snapshot_id),
+        Some(p) => {
+            let dir = format!("/{}", p.trim_matches('/'));
+            let parent = node_cache::parent_dir(&dir);
+            database::load_node_dir(&saved.id, &snapshot_id, parent).and_then(|siblings| {
+                let mut files: Vec<FileNode> = siblings.unwrap_or_default().into_iter()
+                    .filter(|n| n.path == dir)
+                    .collect();
+                files.extend(database::load_node_dir(&saved.id, &snapshot_id, &dir)?.unwrap_or_default());
+                Ok(files)
+            })
+        }
+    };
+    match result {
+        Ok(files) => {
+            debug!("Served {} nodes of snapshot {} from cache", files.len(), snapshot_id);
+            Some(files)
+        }
+        Err(e) => {
+            warn!("Falling back to restic after cache read failed: {}", e);
+            None
+        }
+    }
+}
+
+fn browse_from_index(repo_id: &str, snapshot_id: &str, path: Option<&str>) -> Option<Vec<FileNode>> {
+    let snapshot_pk = database::indexed_snapshot_pk(repo_id, snapshot_id).ok().flatten()?;
+    let dir = path.map(|p| format!("/{}", p.trim_matches('/')));
+    match database::load_indexed_dir(snapshot_pk, dir.as_deref()) {
+        Ok(files) => {
+            debug!("Served {} nodes of snapshot {} from the file index", files.len(), snapshot_id);
+            Some(files)
+        }
+        Err(e) => {
+            warn!("Ignoring file index after read failed: {}", e);
+            None
+        }
+    }
+}
+
+// Rows written to the file index per transaction
+const FILE_INDEX_BATCH: usize = 5000;
+
+#[derive(Debug, Serialize, Clone)]
+struct FilesIndexed {
+    repo_id: String,
+    snapshot_id: String,
+    files: usize,
+}
+
+/// Rebuilds one snapshot's file index from `restic ls`; runs on the background queue
+fn index_files(app: &AppHandle, saved: &SavedRepository, snapshot_pk: i64, snapshot_id: String) -> Result<usize> {
+    database::reset_file_index(snapshot_pk)?;
+
+    let mut batch = Vec::with_capacity(FILE_INDEX_BATCH);
+    let mut count = 0;
+    let mut failure = None;
+    let args = verbosity::with_flags(&["ls", "--json", &snapshot_id], OperationKind::Background);
+    for_each_ls_node(&saved.path, &saved.password, &args, |node| {
+        if failure.is_some() {
+            return;
+        }
+        batch.push(node);
+        if batch.len() >= FILE_INDEX_BATCH {
+            count += batch.len();
+            failure = database::insert_indexed_files(snapshot_pk, &batch).err();
+            batch.clear();
+        }
+    })?;
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    count += batch.len();
+    database::insert_indexed_files(snapshot_pk, &batch)?;
+    database::mark_file_index_complete(snapshot_pk)?;
+
+    window_scope::emit_repo_event(app, &saved.id, "files-indexed", FilesIndexed {
+        repo_id: saved.id.clone(),
+        snapshot_id,
+        files: count,
+    });
+    Ok(count)
+}
+
+// Snapshots found by delta syncs that still have to be indexed, per repository
+static PENDING_INDEX: Lazy<Mutex<HashMap<String, Vec<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Indexes snapshots a delta sync just added, but only for repositories whose
+/// files are indexed already; forgotten snapshots leave the index with their rows.
+fn index_new_snapshots(app: &AppHandle, repo_id: &str, added: &[Snapshot]) -> Result<()> {
+    if added.is_empty() || database::get_file_index_status(repo_id)?.indexed_snapshots == 0 {
+        return Ok(());
+    }
+    PENDING_INDEX.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock pending index: {}", e)))?
+        .entry(repo_id.to_string())
+        .or_default()
+        .extend(added.iter().map(|s| s.id.clone()));
+
+    // A job that is still queued picks up the snapshots added above
+    let app = app.clone();
+    let job_repo_id = repo_id.to_string();
+    background::submit(repo_id, JobKind::FileIndex, move || {
+        let pending = match PENDING_INDEX.lock() {
+            Ok(mut pending) => pending.remove(&job_repo_id).unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to lock pending index: {}", e);
+                return;
+            }
+        };
+        let saved = match secrets::saved_repository(&job_repo_id) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Not indexing new snapshots of repo {}: {}", job_repo_id, e);
+                return;
+            }
+        };
+        for snapshot_id in pending {
+            let snapshot_pk = match database::get_snapshot_pk(&job_repo_id, &snapshot_id) {
+                Ok(Some(pk)) => pk,
+                // Forgotten again before its turn came
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to look up snapshot {}: {}", snapshot_id, e);
+                    continue;
+                }
+            };
+            match index_files(&app, &saved, snapshot_pk, snapshot_id.clone()) {
+                Ok(count) => info!("Indexed {} files of new snapshot {}", count, snapshot_id),
+                Err(e) => warn!("Failed to index new snapshot {}: {}", snapshot_id, e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// How much of the repository the file search covers
+#[command]
+#[instrument]
+pub async fn get_index_status(repo_id: String) -> std::result::Result<FileIndexStatus, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || database::get_file_index_status(&repo_id)).await?)
+}
+
+/// Ingests a snapshot's full listing into the files table so browsing it no longer needs restic
+#[command]
+#[instrument(skip(app))]
+pub async fn index_snapshot_files(app: AppHandle, repo_id: String, snapshot_id: String) -> std::result::Result<usize, CommandError> {
+    info!("Indexing files of snapshot {}", snapshot_id);
+    validate_repo_id(&repo_id)?;
+    validate_snapshot_id(&snapshot_id)?;
+
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    let full_id = full_snapshot_id(&repo_id, snapshot_id).await?;
+    let (id, snapshot) = (repo_id.clone(), full_id.clone());
+    let snapshot_pk = blocking(move || database::get_snapshot_pk(&id, &snapshot)).await?
+        .ok_or_else(|| AppError::SnapshotNotCached(full_id.clone()))?;
+
+    let job_repo_id = repo_id.clone();
+    let count = background::run(&job_repo_id, JobKind::FileIndex, move || {
+        index_files(&app, &saved, snapshot_pk, full_id)
+    })
+    .await?;
+
+    info!("Indexed {} files", count);
+    Ok(count)
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn cache_snapshot_tree(
+    repo: String,
+    password: SecretString,
+    repo_id: String,
+    snapshot_id: String,
+) -> std::result::Result<usize, CommandError> {
+    info!("Caching file listing of snapshot {}", snapshot_id);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_repo_id(&repo_id)?;
+    validate_snapshot_id(&snapshot_id)?;
+
+    let full_id = full_snapshot_id(&repo_id, snapshot_id).await?;
+    let compress = load_config().map_err(AppError::Storage)?.compress_node_cache.unwrap_or(true);
+    let count = streaming(move || {
+        let mut nodes = Vec::new();
+        for_each_ls_node(&repo, &password, &["ls", "--json", &full_id], |node| nodes.push(node))?;
+        database::save_node_tree(&repo_id, &full_id, &nodes, compress)?;
+        Ok(nodes.len())
+    }).await?;
+    Ok(count)
+}
+
+#[command]
+#[instrument]
+pub async fn search_cached_nodes(
+    repo_id: String,
+    query: String,
+    snapshot_id: Option<String>,
+    mtime: Option<MtimeRange>,
+    limit: Option<i64>,
+) -> std::result::Result<Vec<CachedNodeMatch>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    if query.trim().is_empty() || query.contains('\0') {
+        return Err(AppError::InvalidSearchPattern(query).into());
+    }
+    if let Some(id) = &snapshot_id {
+        validate_snapshot_id(id)?;
+    }
+    let snapshot_id = match snapshot_id {
+        Some(id) => Some(full_snapshot_id(&repo_id, id).await?),
+        None => None,
+    };
+    let mtime = mtime_bounds(&mtime.unwrap_or_default())?;
+    let limit = limit.unwrap_or(500).clamp(1, 10_000);
+    Ok(blocking(move || database::search_node_names(&repo_id, snapshot_id.as_deref(), query.trim(), &mtime, limit)).await?)
+}
+
+const FILE_SEARCH_LIMIT: i64 = 1_000;
+
+/// Finds files by name across every snapshot in the file index, without running restic
+#[command]
+#[instrument]
+pub async fn search_files(
+    repo_id: String,
+    query: String,
+    filters: Option<FileSearchFilters>,
+) -> std::result::Result<Vec<FileSearchHit>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let query = query.trim();
+    if query.is_empty() || query.contains('\0') {
+        return Err(AppError::InvalidSearchPattern(query.to_string()).into());
+    }
+
+    let mut filters = filters.unwrap_or_default();
+    for snapshot_id in &filters.snapshot_ids {
+        validate_snapshot_id(snapshot_id)?;
+    }
+    if let Some(node_type) = &filters.node_type {
+        if !matches!(node_type.as_str(), "file" | "dir" | "symlink") {
+            return Err(AppError::InvalidFilterValue(node_type.clone()).into());
+        }
+    }
+    for time in filters.modified_after.iter().chain(&filters.modified_before) {
+        if chrono::DateTime::parse_from_rfc3339(time).is_err() {
+            validate_find_time(time)?;
+        }
+    }
+    if filters.modified_days_before_snapshot == Some(0) {
+        return Err(AppError::InvalidFilterValue("0".to_string()).into());
+    }
+    if let Some(prefix) = &filters.path_prefix {
+        validate_snapshot_path(prefix)?;
+    }
+
+    let limit = filters.limit.unwrap_or(FILE_SEARCH_LIMIT).clamp(1, 10 * FILE_SEARCH_LIMIT);
+    let search = query.to_string();
+    let hits = blocking(move || {
+        for snapshot_id in &mut filters.snapshot_ids {
+            if let Some(full_id) = database::resolve_snapshot_id(&repo_id, snapshot_id)? {
+                *snapshot_id = full_id;
+            }
+        }
+        database::search_indexed_files(&repo_id, &search, &filters, limit)
+    }).await?;
+    debug!("Found {} indexed files matching {}", hits.len(), query);
+    Ok(hits)
+}
+
+#[command]
+#[instrument]
+pub async fn get_node_cache_stats(repo_id: String) -> std::result::Result<NodeCacheStats, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || database::get_node_cache_stats(&repo_id)).await?)
+}
+
+#[command]
+#[instrument]
+pub async fn set_node_cache_compression(enabled: bool) -> std::result::Result<(), CommandError> {
+    info!("Setting node cache compression to {}", enabled);
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.compress_node_cache = Some(enabled);
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn export_snapshot_manifest(
+    repo_id: String,
+    snapshot_id: String,
+    path_scope: Option<String>,
+    format: ManifestFormat,
+    out_path: String,
+) -> std::result::Result<ManifestExport, CommandError> {
+    info!("Exporting manifest of snapshot {} to {}", snapshot_id, out_path);
+    validate_repo_id(&repo_id)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let path_scope = path_scope.filter(|p| !p.trim().is_empty());
+    if let Some(scope) = &path_scope {
+        validate_snapshot_path(scope)?;
+    }
+    let out = validate_target_path(&out_path)?;
+
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    let scope = path_scope.map(|p| format!("/{}", p.trim_matches('/')));
+    let cached = browse_cached(&saved.path, &snapshot_id, None).await?;
+    let full_id = full_snapshot_id(&repo_id, snapshot_id).await?;
+    let compress = load_config().map_err(AppError::Storage)?.compress_node_cache.unwrap_or(true);
+
+    let (entries, total_size) = streaming(move || {
+        // Written next to the destination and moved into place once complete
+        let partial = out.with_extension("partial");
+        let mut writer = ManifestWriter::new(std::fs::File::create(&partial).map_err(AppError::from)?, format)?;
+        let mut write_error = None;
+        let mut write = |node: &FileNode| {
+            if write_error.is_none() && scope.as_deref().is_none_or(|scope| is_within(&node.path, scope)) {
+                write_error = writer.write(node).err();
+            }
+        };
+
+        // Uncached snapshots are listed whole so the listing can fill the node cache
+        let listed = match cached {
+            Some(nodes) => {
+                nodes.iter().for_each(&mut write);
+                Ok(())
+            }
+            None => {
+                let mut nodes = Vec::new();
+                for_each_ls_node(&saved.path, &saved.password, &["ls", "--json", &full_id], |node| {
+                    write(&node);
+                    nodes.push(node);
+                }).map(|()| {
+                    if let Err(e) = database::save_node_tree(&repo_id, &full_id, &nodes, compress) {
+                        warn!("Couldn't cache the listing of snapshot {}: {}", full_id, e);
+                    }
+                })
+            }
+        };
+
+        let finished = match (listed, write_error) {
+            (Err(e), _) | (Ok(()), Some(e)) => Err(e),
+            (Ok(()), None) => writer.finish(),
+        };
+        let (entries, total_size) = match finished {
+            Ok(counts) => counts,
+            Err(e) => {
+                let _ = std::fs::remove_file(&partial);
+                return Err(e);
+            }
+        };
+        std::fs::rename(&partial, &out).map_err(AppError::from)?;
+        Ok((entries, total_size))
+    }).await?;
+
+    info!("Wrote {} entries to {}", entries, out_path);
+    Ok(ManifestExport { out_path, entries, total_size })
+}
+
+fn is_within(path: &str, subpath: &str) -> bool {
+    let subpath = subpath.trim_end_matches('/');
+    subpath.is_empty() || path == subpath || path.strip_prefix(subpath).is_some_and(|rest| rest.starts_with('/'))
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn diff_snapshots(
+    repo: String,
+    password: SecretString,
+    snapshot_a: String,
+    snapshot_b: String,
+    subpath: Option<String>,
+) -> std::result::Result<SnapshotDiff, CommandError> {
+    info!("Comparing snapshots {} and {}", snapshot_a, snapshot_b);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_a)?;
+    validate_snapshot_id(&snapshot_b)?;
+    let subpath = subpath.filter(|p| !p.trim().is_empty());
+    if let Some(sub) = &subpath {
+        validate_snapshot_path(sub)?;
+    }
+
+    let args = restic_args::diff(&snapshot_a, &snapshot_b);
+    let scope = subpath.clone();
+    let (entries, bytes_added, bytes_removed) = streaming(move || {
+        let mut entries = Vec::new();
+        let mut bytes_added = None;
+        let mut bytes_removed = None;
+        run_restic_ndjson(&repo, &password, &restic_args::as_strs(&args), |value| {
+            match value.get("message_type").and_then(Value::as_str) {
+                Some("change") => {
+                    let path = value.get("path").and_then(Value::as_str).unwrap_or_default();
+                    let modifier = value.get("modifier").and_then(Value::as_str).unwrap_or_default();
+                    let entry = DiffEntry::from_change(path, modifier);
+                    if scope.as_deref().is_none_or(|sub| is_within(&entry.path, sub)) {
+                        entries.push(entry);
+                    }
+                }
+                Some("statistics") => {
+                    bytes_added = value.pointer("/added/bytes").and_then(Value::as_u64);
+                    bytes_removed = value.pointer("/removed/bytes").and_then(Value::as_u64);
+                }
+                _ => {}
+            }
+        })?;
+        Ok((entries, bytes_added, bytes_removed))
+    }).await?;
+
+    let count = |kind: DiffKind| entries.iter().filter(|e| e.kind == kind).count();
+    let (added, removed, modified) = (count(DiffKind::Added), count(DiffKind::Removed), count(DiffKind::Modified));
+    debug!("{} added, {} removed, {} modified", added, removed, modified);
+
+    // restic's byte totals cover the whole snapshots, which would be misleading for a subpath
+    let whole_snapshot = subpath.is_none();
+    Ok(SnapshotDiff {
+        snapshot_a,
+        snapshot_b,
+        subpath,
+        entries,
+        added,
+        removed,
+        modified,
+        bytes_added: bytes_added.filter(|_| whole_snapshot),
+        bytes_removed: bytes_removed.filter(|_| whole_snapshot),
+    })
+}
+
+/// The change summary restic recorded in the snapshot itself, if it is new enough
+/// to have one. Removals are not part of it.
+fn recorded_changes(snapshot: &Snapshot, parent_id: &str) -> Option<ChangeSummary> {
+    snapshot.summary.as_ref().map(|summary| ChangeSummary {
+        snapshot_id: snapshot.id.clone(),
+        snapshot_time: snapshot.time.clone(),
+        parent_id: parent_id.to_string(),
+        files_added: summary.files_new,
+        files_changed: summary.files_changed,
+        files_removed: None,
+        bytes_added: summary.data_added,
+        bytes_removed: None,
+    })
+}
+
+/// What the newest backup changed over its parent, for a "last backup" summary. Taken
+/// from the summary restic records in snapshots since 0.17; older snapshots are diffed
+/// against their parent, which is cached per snapshot for saved repositories since a
+/// diff of a large backup takes a while. `None` when there are no snapshots or the
+/// newest one has no parent to compare with, including a parent that was forgotten.
+#[command]
+#[instrument(skip(password))]
+pub async fn get_latest_changes(repo: String, password: SecretString) -> std::result::Result<Option<ChangeSummary>, CommandError> {
+    info!("Summarizing the latest changes in {}", repo);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    // The snapshot cache usually knows the newest snapshot already, so restic is only
+    // asked when neither it nor the summary cache has the answer
+    let saved = find_repository_by_path(&repo);
+    if let Some(saved) = &saved {
+        let repo_id = saved.id.clone();
+        let cached = blocking(move || {
+            let newest = database::load_all_snapshots(std::slice::from_ref(&repo_id), None, None, 1)?;
+            let Some((_, newest)) = newest.into_iter().next() else {
+                return Ok(None);
+            };
+            let Some(parent_id) = newest.snapshot.parent.clone() else {
+                return Ok(None);
+            };
+            match recorded_changes(&newest.snapshot, &parent_id) {
+                Some(summary) => Ok(Some(summary)),
+                None => database::get_change_summary(&repo_id, &newest.snapshot.id),
+            }
+        }).await?;
+        if cached.is_some() {
+            return Ok(cached);
+        }
+    }
+
+    let output = run_restic(&repo, &password, &restic_args::as_strs(&restic_args::latest_snapshots())).await?;
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    let Some(latest) = snapshots.into_iter().max_by_key(|s| snapshot_instant(&s.time)) else {
+        return Ok(None);
+    };
+    let Some(parent_id) = latest.parent.clone() else {
+        debug!("Snapshot {} has no parent to compare with", latest.short_id);
+        return Ok(None);
+    };
+    if let Some(summary) = recorded_changes(&latest, &parent_id) {
+        return Ok(Some(summary));
+    }
+
+    if let Some(saved) = &saved {
+        let (repo_id, snapshot_id) = (saved.id.clone(), latest.id.clone());
+        if let Some(summary) = blocking(move || database::get_change_summary(&repo_id, &snapshot_id)).await? {
+            return Ok(Some(summary));
+        }
+    }
+
+    let args = restic_args::diff(&parent_id, &latest.id);
+    let (snapshot, parent) = (latest.clone(), parent_id.clone());
+    let diffed = streaming(move || {
+        let mut summary = None;
+        run_restic_ndjson(&repo, &password, &restic_args::as_strs(&args), |value| {
+            if value.get("message_type").and_then(Value::as_str) == Some("statistics") {
+                let field = |pointer: &str| value.pointer(pointer).and_then(Value::as_u64).unwrap_or(0);
+                summary = Some(ChangeSummary {
+                    snapshot_id: snapshot.id.clone(),
+                    snapshot_time: snapshot.time.clone(),
+                    parent_id: parent.clone(),
+                    files_added: field("/added/files"),
+                    files_changed: field("/changed_files"),
+                    files_removed: Some(field("/removed/files")),
+                    bytes_added: field("/added/bytes"),
+                    bytes_removed: Some(field("/removed/bytes")),
+                });
+            }
+        })?;
+        Ok(summary)
+    }).await;
+    let summary = match diffed {
+        Err(e) if restic_errors::is_snapshot_missing(&e) => {
+            debug!("The parent {} of snapshot {} no longer exists", parent_id, latest.short_id);
+            return Ok(None);
+        }
+        result => result?,
+    };
+    let summary = summary
+        .ok_or_else(|| AppError::ResticError(format!("restic diff {} {} printed no statistics", parent_id, latest.id)))?;
+
+    if let Some(saved) = &saved {
+        let (repo_id, cached) = (saved.id.clone(), summary.clone());
+        if let Err(e) = blocking(move || database::save_change_summary(&repo_id, &cached)).await {
+            warn!("Failed to cache the change summary of {}: {}", latest.short_id, e);
+        }
+    }
+    Ok(Some(summary))
+}
+
+#[command]
+#[instrument(skip(password, limits))]
+pub async fn search_file_contents(
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    path_scope: Option<String>,
+    pattern: String,
+    limits: Option<ContentSearchLimits>,
+) -> std::result::Result<ContentSearchResult, CommandError> {
+    info!("Searching file contents in snapshot {}", snapshot_id);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    if let Some(scope) = &path_scope {
+        validate_snapshot_path(scope)?;
+    }
+
+    let limits = limits.unwrap_or_default().clamped();
+    let regex = content_search::build_pattern(&pattern, limits.case_insensitive)?;
+
+    let mut ls_args = vec!["ls".to_string(), "--json".to_string(), snapshot_id.clone()];
+    if let Some(scope) = &path_scope {
+        ls_args.push(scope.clone());
+        ls_args.push("--recursive".to_string());
+    }
+    let (ls_repo, ls_password, ls_limits) = (repo.clone(), password.clone(), limits.clone());
+    let candidates = streaming(move || {
+        let mut candidates = Vec::new();
+        for_each_ls_node(&ls_repo, &ls_password, &restic_args::as_strs(&ls_args), |node| {
+            if ls_limits.accepts(&node) {
+                candidates.push(node);
+            }
+        })?;
+        Ok(candidates)
+    }).await?;
+    debug!("{} candidate files for content search", candidates.len());
+
+    let mut result = ContentSearchResult::default();
+    for node in &candidates {
+        if result.files_scanned >= limits.max_files
+            || result.matches.len() >= limits.max_matches
+            || result.bytes_scanned + node.size.unwrap_or(0) > limits.max_total_bytes
+        {
+            result.truncated = true;
+            break;
+        }
+
+        let contents = match run_restic(&repo, &password, &["dump", &snapshot_id, &node.path]).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Skipping {} in content search: {}", node.path, e);
+                continue;
+            }
+        };
+        result.files_scanned += 1;
+        result.bytes_scanned += contents.len() as u64;
+
+        if content_search::looks_binary(&contents) {
+            result.skipped_binary += 1;
+            continue;
+        }
+
+        let snippets = content_search::find_snippets(&contents, &regex, limits.max_snippets_per_file);
+        if !snippets.is_empty() {
+            result.matches.push(FileContentMatch {
+                path: node.path.clone(),
+                size: node.size,
+                snippets,
+            });
+        }
+    }
+
+    info!("Content search scanned {} files, {} matched", result.files_scanned, result.matches.len());
+    Ok(result)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagResult {
+    /// restic rewrites the snapshot when its tags change, so this is usually a new ID
+    pub snapshot_id: String,
+    pub previous_id: String,
+    pub tags: Vec<String>,
+}
+
+fn validate_tags(tags: &[String]) -> Result<()> {
+    for tag in tags {
+        validate_filter_value(tag)?;
+        // restic splits tag lists on commas
+        if tag.contains(',') {
+            return Err(AppError::InvalidFilterValue(tag.clone()));
+        }
+    }
+    Ok(())
+}
+
+async fn fetch_snapshot(repo: &str, password: &str, snapshot_id: &str) -> Result<Snapshot> {
+    let output = run_restic(repo, password, &["snapshots", "--json", snapshot_id]).await?;
+    serde_json::from_str::<Vec<Snapshot>>(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NoMatchingSnapshot(snapshot_id.to_string()))
+}
+
+async fn retag_snapshot(
+    app: &AppHandle,
+    repo: &str,
+    password: &str,
+    snapshot_id: &str,
+    flag: &str,
+    tags: &[String],
+) -> Result<TagResult> {
+    validate_repository_path(repo)?;
+    validate_credentials(repo, password)?;
+    validate_snapshot_id(snapshot_id)?;
+
+    let before = fetch_snapshot(repo, password, snapshot_id).await?;
+    let args = restic_args::tag(flag, tags, &before.id);
+    run_restic(repo, password, &restic_args::as_strs(&args)).await?;
+
+    // The rewritten snapshot keeps the first ID in the chain as its original
+    let original = before.original.clone().unwrap_or_else(|| before.id.clone());
+    let output = run_restic(repo, password, &["snapshots", "--json"]).await?;
+    let after = serde_json::from_str::<Vec<Snapshot>>(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?
+        .into_iter()
+        .find(|s| s.id == before.id || s.original.as_deref() == Some(original.as_str()))
+        .ok_or_else(|| AppError::NoMatchingSnapshot(before.id.clone()))?;
+
+    if after.id != before.id {
+        info!("Snapshot {} was rewritten as {}", before.short_id, after.short_id);
+    }
+    if let Some(repo_id) = find_repository_by_path(repo).map(|r| r.id) {
+        let (id, previous_id, snapshot) = (repo_id.clone(), before.id.clone(), after.clone());
+        blocking(move || database::replace_snapshot_id(&id, &previous_id, &snapshot)).await?;
+        window_scope::emit_repo_event(app, &repo_id, "snapshot-tags-changed", SnapshotTagsChanged {
+            repo_id: repo_id.clone(),
+            previous_id: before.id.clone(),
+            snapshot_id: after.id.clone(),
+        });
+    }
+
+    Ok(TagResult {
+        snapshot_id: after.id,
+        previous_id: before.id,
+        tags: after.tags.unwrap_or_default(),
+    })
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct SnapshotTagsChanged {
+    repo_id: String,
+    previous_id: String,
+    snapshot_id: String,
+}
+
+#[command]
+#[instrument(skip(app, password))]
+pub async fn add_snapshot_tags(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<TagResult, CommandError> {
+    info!("Adding tags {:?} to snapshot {}", tags, snapshot_id);
+    if tags.is_empty() {
+        return Err(AppError::InvalidFilterValue(String::new()).into());
+    }
+    validate_tags(&tags)?;
+    Ok(retag_snapshot(&app, &repo, &password, &snapshot_id, "--add", &tags).await?)
+}
+
+#[command]
+#[instrument(skip(app, password))]
+pub async fn remove_snapshot_tags(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<TagResult, CommandError> {
+    info!("Removing tags {:?} from snapshot {}", tags, snapshot_id);
+    if tags.is_empty() {
+        return Err(AppError::InvalidFilterValue(String::new()).into());
+    }
+    validate_tags(&tags)?;
+    Ok(retag_snapshot(&app, &repo, &password, &snapshot_id, "--remove", &tags).await?)
+}
+
+/// Replaces all tags of the snapshot; an empty list removes them all
+#[command]
+#[instrument(skip(app, password))]
+pub async fn set_snapshot_tags(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    tags: Vec<String>,
+) -> std::result::Result<TagResult, CommandError> {
+    info!("Setting tags of snapshot {} to {:?}", snapshot_id, tags);
+    validate_tags(&tags)?;
+    // `--set ""` is how restic clears every tag
+    let tags = if tags.is_empty() { vec![String::new()] } else { tags };
+    Ok(retag_snapshot(&app, &repo, &password, &snapshot_id, "--set", &tags).await?)
+}
+
+/// restic's stats output as it is, with the operation it ran under
+#[derive(Debug, Serialize)]
+pub struct SnapshotStatsResult {
+    pub operation_id: String,
+    pub stats: Value,
+}
+
+#[command]
+pub async fn get_snapshot_stats(
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    snapshot_id: String,
+    operation_id: Option<String>,
+) -> std::result::Result<SnapshotStatsResult, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_snapshot_id(&snapshot_id)?;
+    let operation_id = start_operation(&window, operation_id, "stats")?;
+
+    let args = restic_args::stats(&snapshot_id);
+    let run_id = operation_id.clone();
+    let output = streaming(move || {
+        let mut output = String::new();
+        run_restic_streaming(&repo, &password, &restic_args::as_strs(&args), ErrorHandling::Strict, &run_id, |line| {
+            output.push_str(line);
+            output.push('\n');
+        })?;
+        Ok(output)
+    }).await?;
+    let stats: serde_json::Value = serde_json::from_str(&output)
+        .map_err(|e| AppError::StatsJsonParse(e.to_string()))?;
+    Ok(SnapshotStatsResult { operation_id, stats })
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct StatsPrefetched {
+    repo_id: String,
+    snapshot_id: String,
+    total_size: Option<u64>,
+    total_file_count: Option<u64>,
+    error: Option<String>,
+    completed: usize,
+    total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsPrefetchResult {
+    pub fetched: usize,
+    /// Snapshots that already had stats and were skipped
+    pub already_cached: usize,
+    pub failed: Vec<String>,
+}
+
+// Shared by the prefetch workers
+struct StatsPrefetch {
+    app: AppHandle,
+    repo: SavedRepository,
+    queue: Mutex<VecDeque<String>>,
+    total: usize,
+    completed: AtomicUsize,
+    failed: Mutex<Vec<String>>,
+}
+
+impl StatsPrefetch {
+    fn next(&self) -> Option<String> {
+        self.queue.lock().ok()?.pop_front()
+    }
+
+    async fn fetch(&self, snapshot_id: &str) -> Result<(Option<u64>, Option<u64>)> {
+        let stats_args = restic_args::stats(snapshot_id);
+        let args = verbosity::with_flags(&restic_args::as_strs(&stats_args), OperationKind::Background);
+        let output = run_restic(&self.repo.path, &self.repo.password, &args).await?;
+        let stats: Value = serde_json::from_str(&output).map_err(|e| AppError::StatsJsonParse(e.to_string()))?;
+        let total_size = stats.get("total_size").and_then(Value::as_u64);
+        let total_file_count = stats.get("total_file_count").and_then(Value::as_u64);
+        let (repo_id, snapshot_id) = (self.repo.id.clone(), snapshot_id.to_string());
+        blocking(move || database::save_snapshot_stats(&repo_id, &snapshot_id, total_size, total_file_count)).await?;
+        Ok((total_size, total_file_count))
+    }
+
+    async fn work(&self) {
+        while let Some(snapshot_id) = self.next() {
+            let result = self.fetch(&snapshot_id).await;
+            let completed = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+            let (total_size, total_file_count, error) = match result {
+                Ok((size, count)) => (size, count, None),
+                Err(e) => {
+                    warn!("Failed to fetch stats for snapshot {}: {}", snapshot_id, e);
+                    if let Ok(mut failed) = self.failed.lock() {
+                        failed.push(snapshot_id.clone());
+                    }
+                    (None, None, Some(e.to_string()))
+                }
+            };
+            window_scope::emit_repo_event(&self.app, &self.repo.id, "snapshot-stats-prefetched", StatsPrefetched {
+                repo_id: self.repo.id.clone(),
+                snapshot_id,
+                total_size,
+                total_file_count,
+                error,
+                completed,
+                total: self.total,
+            });
+        }
+    }
+}
+
+/// Fetches stats for the snapshots that don't have them cached yet, several at a
+/// time, saving and announcing each one as it arrives. Runs as many restic
+/// processes at once as the per-repository limit allows.
+#[command]
+#[instrument(skip(app, snapshot_ids), fields(count = snapshot_ids.len()))]
+pub async fn prefetch_snapshot_stats(
+    app: AppHandle,
+    repo_id: String,
+    snapshot_ids: Vec<String>,
+) -> std::result::Result<StatsPrefetchResult, CommandError> {
+    validate_repo_id(&repo_id)?;
+    for snapshot_id in &snapshot_ids {
+        validate_snapshot_id(snapshot_id)?;
+    }
+    let repo = secrets::saved_repository(&repo_id)?;
+
+    let (id, ids) = (repo_id.clone(), snapshot_ids.clone());
+    let cached: HashSet<String> = blocking(move || database::get_cached_stats(&id, &ids)).await?
+        .into_iter()
+        .map(|stats| stats.snapshot_id)
+        .collect();
+    let mut seen = HashSet::new();
+    let queue: VecDeque<String> = snapshot_ids.into_iter()
+        .filter(|id| !cached.contains(id) && seen.insert(id.clone()))
+        .collect();
+    let total = queue.len();
+    info!("Prefetching stats for {} snapshots ({} already cached)", total, cached.len());
+
+    let prefetch = Arc::new(StatsPrefetch {
+        app,
+        repo,
+        queue: Mutex::new(queue),
+        total,
+        completed: AtomicUsize::new(0),
+        failed: Mutex::new(Vec::new()),
+    });
+    let workers: Vec<_> = (0..limiter::max_per_repo().min(total))
+        .map(|_| {
+            let prefetch = prefetch.clone();
+            tauri::async_runtime::spawn(async move { prefetch.work().await })
+        })
+        .collect();
+    for worker in workers {
+        worker.await.map_err(|e| AppError::Storage(format!("Stats prefetch worker failed: {}", e)))?;
+    }
+
+    let failed = std::mem::take(&mut *prefetch.failed.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock prefetch results: {}", e)))?);
+    info!("Prefetched stats for {} snapshots, {} failed", total - failed.len(), failed.len());
+    Ok(StatsPrefetchResult { fetched: total - failed.len(), already_cached: cached.len(), failed })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeltaComputation {
+    pub from_stats: usize,
+    pub from_diff: usize,
+    /// Snapshots whose `restic diff` failed; retried on the next run
+    pub failed: Vec<String>,
+}
+
+/// Size and file count change of `snapshot_id` over its parent, from the
+/// statistics line of `restic diff`
+fn diff_delta(repo: &SavedRepository, parent_id: &str, snapshot_id: &str) -> Result<SnapshotDelta> {
+    let mut totals = None;
+    let diff_args = restic_args::diff(parent_id, snapshot_id);
+    let args = verbosity::with_flags(&restic_args::as_strs(&diff_args), OperationKind::Background);
+    run_restic_ndjson(&repo.path, &repo.password, &args, |value| {
+        if value.get("message_type").and_then(Value::as_str) == Some("statistics") {
+            let field = |pointer: &str| value.pointer(pointer).and_then(Value::as_i64).unwrap_or(0);
+            totals = Some((
+                field("/added/bytes") - field("/removed/bytes"),
+                field("/added/files") - field("/removed/files"),
+            ));
+        }
+    })?;
+    let (size_delta, file_count_delta) = totals
+        .ok_or_else(|| AppError::ResticError(format!("restic diff {} {} printed no statistics", parent_id, snapshot_id)))?;
+    Ok(SnapshotDelta { parent_id: parent_id.to_string(), size_delta, file_count_delta, source: DeltaSource::Diff })
+}
+
+/// Works out how much each snapshot added over its parent. Cached stats of both
+/// snapshots are used where they exist; `restic diff` fills in the rest.
+#[command]
+#[instrument]
+pub async fn compute_snapshot_deltas(repo_id: String) -> std::result::Result<DeltaComputation, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let repo = secrets::saved_repository(&repo_id)?;
+
+    let job_repo_id = repo_id.clone();
+    let computation = background::run(&job_repo_id, JobKind::StatsBackfill, move || -> Result<DeltaComputation> {
+        let candidates = database::get_delta_candidates(&repo_id)?;
+        info!("Computing deltas of {} snapshots", candidates.len());
+
+        let mut computation = DeltaComputation { from_stats: 0, from_diff: 0, failed: Vec::new() };
+        for candidate in candidates {
+            let delta = match (candidate.stats, candidate.parent_stats) {
+                (Some((size, files)), Some((parent_size, parent_files))) => SnapshotDelta {
+                    parent_id: candidate.parent_id,
+                    size_delta: size as i64 - parent_size as i64,
+                    file_count_delta: files as i64 - parent_files as i64,
+                    source: DeltaSource::Stats,
+                },
+                _ => match diff_delta(&repo, &candidate.parent_id, &candidate.snapshot_id) {
+                    Ok(delta) => delta,
+                    Err(e) => {
+                        warn!("Failed to diff snapshot {} against its parent: {}", candidate.snapshot_id, e);
+                        computation.failed.push(candidate.snapshot_id);
+                        continue;
+                    }
+                },
+            };
+            match delta.source {
+                DeltaSource::Stats => computation.from_stats += 1,
+                DeltaSource::Diff => computation.from_diff += 1,
+            }
+            database::save_snapshot_delta(&repo_id, &candidate.snapshot_id, &delta)?;
+        }
+        Ok(computation)
+    })
+    .await?;
+
+    info!("Computed {} snapshot deltas from stats and {} from diffs, {} failed",
+        computation.from_stats, computation.from_diff, computation.failed.len());
+    Ok(computation)
+}
+
+#[command]
+#[instrument]
+pub async fn cancel_operation(operation_id: String) -> std::result::Result<(), CommandError> {
+    operations::validate_operation_id(&operation_id)?;
+    operations::cancel(&operation_id)?;
+    Ok(())
+}
+
+#[command]
+pub async fn list_operations() -> std::result::Result<Vec<OperationInfo>, CommandError> {
+    Ok(operations::list())
+}
+
+/// Kills a stalled operation and starts its restic command again. Commands that had
+/// already produced output fail with `operation_stalled` instead and need to be rerun.
+#[command]
+#[instrument]
+pub async fn retry_operation(operation_id: String) -> std::result::Result<(), CommandError> {
+    operations::validate_operation_id(&operation_id)?;
+    operations::retry(&operation_id)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_stall_threshold() -> std::result::Result<u64, CommandError> {
+    Ok(operations::stall_threshold_secs())
+}
+
+#[command]
+#[instrument]
+pub async fn set_stall_threshold(threshold_secs: u64) -> std::result::Result<(), CommandError> {
+    info!("Treating restic processes as stalled after {} s without output", threshold_secs);
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.stall_threshold_secs = Some(threshold_secs);
+    config.save().map_err(AppError::Storage)?;
+    operations::set_stall_threshold_secs(threshold_secs);
+    Ok(())
+}
+
+// restic exits within this time when the repository or mount point is unusable
+const MOUNT_STARTUP: Duration = Duration::from_secs(3);
+
+fn prepare_mountpoint(path: &Path) -> Result<()> {
+    if !path.exists() {
+        std::fs::create_dir_all(path)?;
+        return Ok(());
+    }
+    let is_empty_dir = path.is_dir() && std::fs::read_dir(path)?.next().is_none();
+    if !is_empty_dir {
+        return Err(AppError::MountpointNotEmpty(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Mounts the repository with `restic mount` so its snapshots can be browsed in
+/// the file manager. The mount stays up until unmounted or the app exits.
+#[command]
+#[instrument(skip(password))]
+pub async fn mount_repository(repo: String, password: SecretString, mountpoint: String) -> std::result::Result<MountStatus, CommandError> {
+    info!("Mounting repository at {}", mountpoint);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if cfg!(target_os = "windows") {
+        return Err(AppError::MountUnsupported.into());
+    }
+    let path = validate_target_path(&mountpoint)?;
+    let mountpoint = path.to_string_lossy().to_string();
+    if mounts::is_mounted(&mountpoint) {
+        return Err(AppError::MountpointInUse(mountpoint).into());
+    }
+    prepare_mountpoint(&path)?;
+    run_pre_connect_hooks_async(&repo).await?;
+
+    let restic_bin = find_restic_binary();
+    let mut cmd = Command::new(&restic_bin);
+    cmd.args(restic_repository_args(&repo))
+       .args(["mount", &mountpoint])
+       .stdin(Stdio::null())
+       .stdout(Stdio::null())
+       .stderr(Stdio::piped());
+    apply_repository_env(&mut cmd, &repo, &password);
+
+    let invocation = logs::Invocation::start(&repo, &["mount", &mountpoint]);
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            error!("Failed to execute restic binary: {}", e);
+            let error = AppError::ResticExecution(e.to_string());
+            invocation.fail(&error);
+            return Err(error.into());
+        }
+    };
+
+    // restic keeps logging while mounted, so stderr is drained for as long as it runs
+    let stderr_log = Arc::new(Mutex::new(String::new()));
+    if let Some(stderr) = child.stderr.take() {
+        let stderr_log = stderr_log.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(std::result::Result::ok) {
+                debug!("restic mount: {}", line);
+                if let Ok(mut log) = stderr_log.lock() {
+                    if log.len() < MAX_LINE_BYTES as usize {
+                        log.push_str(&line);
+                        log.push('\n');
+                    }
+                }
+            }
+        });
+    }
+
+    let deadline = std::time::Instant::now() + MOUNT_STARTUP;
+    while std::time::Instant::now() < deadline {
+        if child.try_wait().map_err(AppError::from)?.is_some() {
+            // Give the drain thread a moment to pick up restic's last words
+            std::thread::sleep(Duration::from_millis(100));
+            let stderr = stderr_log.lock().map(|log| log.clone()).unwrap_or_default();
+            error!("restic mount failed: {}", stderr);
+            let status = child.try_wait().ok().flatten();
+            if let Some(status) = status {
+                invocation.finish(status, &stderr);
+            }
+            let status = status.and_then(|status| status.code());
+            return Err(restic_errors::classify(status, &stderr).unwrap_or(AppError::ResticError(stderr)).into());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    invocation.started();
+    Ok(mounts::register(&mountpoint, &repo, child)?)
+}
+
+const RECENT_OPERATIONS_LIMIT: usize = 100;
+
+/// The newest restic invocations from the operation log, newest first
+#[command]
+#[instrument]
+pub async fn get_recent_operations(limit: Option<usize>) -> std::result::Result<Vec<OperationLogEntry>, CommandError> {
+    let limit = limit.unwrap_or(RECENT_OPERATIONS_LIMIT).clamp(1, 10 * RECENT_OPERATIONS_LIMIT);
+    Ok(blocking(move || logs::recent(limit)).await?)
+}
+
+/// Where the operation log files are, for attaching them to a bug report
+#[command]
+pub async fn get_operation_log_dir() -> std::result::Result<String, CommandError> {
+    Ok(logs::log_dir()?.to_string_lossy().to_string())
+}
+
+/// Zips the sanitized configuration, a database summary, the operation logs,
+/// `restic version` and platform details into `path` for attaching to an issue
+#[command]
+#[instrument]
+pub async fn export_debug_bundle(path: String) -> std::result::Result<DebugBundle, CommandError> {
+    info!("Exporting debug bundle to {}", path);
+    let out = validate_target_path(&path)?;
+
+    // Written next to the destination and moved into place once complete
+    let partial = out.with_extension("partial");
+    let written = blocking({
+        let partial = partial.clone();
+        move || debug_bundle::export(&partial, &find_restic_binary())
+    }).await;
+    let entries = match written {
+        Ok(entries) => entries,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e.into());
+        }
+    };
+    std::fs::rename(&partial, &out).map_err(AppError::from)?;
+    if let Err(e) = record_audit_event("debug_bundle.exported", Some(path.clone())).await {
+        warn!("Failed to record debug bundle export: {}", e);
+    }
+    Ok(DebugBundle { path, entries })
+}
+
+/// Moves the app's data directory to `new_dir`, which must be empty or not exist yet.
+/// Later launches find the data there; on failure the old directory stays in use.
+#[command]
+#[instrument]
+pub async fn relocate_app_data(new_dir: String) -> std::result::Result<RelocationReport, CommandError> {
+    info!("Relocating app data to {}", new_dir);
+    let to = validate_target_path(&new_dir)?;
+    let report = blocking(move || relocate::relocate(&to)).await?;
+    if let Err(e) = record_audit_event("data_dir.relocated", Some(report.to.clone())).await {
+        warn!("Failed to record data directory relocation: {}", e);
+    }
+    Ok(report)
+}
+
+#[command]
+#[instrument]
+pub async fn unmount_repository(mountpoint: String) -> std::result::Result<(), CommandError> {
+    info!("Unmounting {}", mountpoint);
+    Ok(mounts::unmount(&mountpoint)?)
+}
+
+#[command]
+pub async fn list_mounts() -> std::result::Result<Vec<MountStatus>, CommandError> {
+    Ok(mounts::status())
+}
+
+#[command]
+pub async fn get_repository_stats(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    repo_id: Option<String>,
+) -> std::result::Result<serde_json::Value, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+
+    let stats = fetch_repository_stats(&repo, &password, OperationKind::Interactive).await?;
+
+    let saved = match &repo_id {
+        Some(id) => load_config().ok().and_then(|c| c.repositories.into_iter().find(|r| &r.id == id)),
+        None => find_repository_by_path(&repo),
+    };
+    if let Some(saved) = saved {
+        let usage = stats.clone();
+        blocking(move || {
+            record_usage(&app, &saved, &usage);
+            Ok(())
+        }).await?;
+    }
+
+    Ok(stats)
+}
+
+async fn fetch_repository_stats(repo: &str, password: &str, kind: OperationKind) -> Result<Value> {
+    let args = verbosity::with_flags(&["stats", "--json", "--mode", "raw-data"], kind);
+    let output = run_restic(repo, password, &args).await?;
+    serde_json::from_str(&output).map_err(|e| AppError::RepoStatsJsonParse(e.to_string()))
+}
+
+fn record_usage(app: &AppHandle, saved: &SavedRepository, stats: &Value) {
+    let Some(total_size) = stats.get("total_size").and_then(|v| v.as_u64()) else { return };
+    match database::record_repo_usage(&saved.id, total_size, saved.size_budget) {
+        Ok(Some(event)) => notify_quota_event(app, &saved.name, &event),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to record repository usage: {}", e),
+    }
+}
+
+/// Everything a repository dashboard shows, measured in one go. Saved repositories get
+/// the report cached for `health::TTL_SECS`; `refresh` measures again regardless.
+#[command]
+#[instrument(skip(password))]
+pub async fn get_repository_health(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    refresh: Option<bool>,
+) -> std::result::Result<RepoHealth, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    let saved = find_repository_by_path(&repo);
+
+    if let (Some(saved), false) = (&saved, refresh.unwrap_or(false)) {
+        let repo_id = saved.id.clone();
+        if let Some(mut health) = blocking(move || database::load_repo_health(&repo_id)).await? {
+            if chrono::Utc::now().timestamp() - health.measured_at < health::TTL_SECS {
+                health.cached = true;
+                return Ok(health);
+            }
+        }
+    }
+
+    let kind = OperationKind::Interactive;
+    let raw_data: health::RawDataStats = serde_json::from_value(fetch_repository_stats(&repo, &password, kind).await?)
+        .map_err(|e| AppError::RepoStatsJsonParse(e.to_string()))?;
+    let args = verbosity::with_flags(&["stats", "--json", "--mode", "restore-size"], kind);
+    let restore_size: health::RestoreSizeStats = serde_json::from_str(&run_restic(&repo, &password, &args).await?)
+        .map_err(|e| AppError::RepoStatsJsonParse(e.to_string()))?;
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&run_restic(&repo, &password, &restic_args::as_strs(&restic_args::snapshots())).await?)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    let (lock_count, stale_lock_count, exclusive_locked) = health::lock_summary(&fetch_locks(&repo, &password).await?);
+
+    let fingerprint = match saved.as_ref().and_then(|s| s.fingerprint.clone()) {
+        Some(fingerprint) => Some(fingerprint),
+        None => fetch_repository_fingerprint(&repo, &password).await.ok(),
+    };
+    let cache_override = saved.as_ref()
+        .and_then(|s| s.extra_env.get("RESTIC_CACHE_DIR").cloned())
+        .or_else(|| std::env::var("RESTIC_CACHE_DIR").ok());
+    let restic_cache_bytes = fingerprint
+        .and_then(|f| health::restic_cache_dir(cache_override.as_deref(), &f))
+        .and_then(|dir| health::dir_size(&dir));
+
+    let now = chrono::Utc::now().timestamp();
+    let mut health = RepoHealth {
+        raw_data,
+        restore_size,
+        snapshot_count: snapshots.len(),
+        hosts: health::host_backups(&snapshots, now),
+        lock_count,
+        stale_lock_count,
+        exclusive_locked,
+        restic_cache_bytes,
+        app_cache_bytes: 0,
+        measured_at: now,
+        cached: false,
+    };
+
+    if let Some(saved) = saved {
+        let report = health.clone();
+        health.app_cache_bytes = blocking(move || {
+            let app_cache_bytes = database::get_node_cache_stats(&saved.id)?.stored_bytes.max(0) as u64;
+            database::save_repo_health(&saved.id, &RepoHealth { app_cache_bytes, ..report })?;
+            repo_status::refresh(&app, &saved.id);
+            Ok(app_cache_bytes)
+        }).await?;
+    }
+    Ok(health)
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct AggregatesStale {
+    repo_id: String,
+    new_snapshots: usize,
+    removed_snapshots: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct AggregatesRefreshed {
+    repo_id: String,
+    stats: Value,
+}
+
+/// Called whenever snapshots are added to or removed from the cache: marks the
+/// repository-level aggregates stale and re-measures them in the background.
+fn invalidate_repo_aggregates(app: &AppHandle, repo_id: &str, new_snapshots: usize, removed_snapshots: usize) {
+    info!(
+        "Repo {} gained {} and lost {} snapshots, invalidating aggregates",
+        repo_id, new_snapshots, removed_snapshots
+    );
+    if let Err(e) = database::mark_aggregates_stale(repo_id) {
+        warn!("Failed to mark aggregates stale: {}", e);
+    }
+    window_scope::emit_repo_event(app, repo_id, "repository-aggregates-stale", AggregatesStale {
+        repo_id: repo_id.to_string(),
+        new_snapshots,
+        removed_snapshots,
+    });
+    schedule_aggregate_refresh(app, repo_id);
+}
+
+fn schedule_aggregate_refresh(app: &AppHandle, repo_id: &str) {
+    let Ok(saved) = secrets::saved_repository(repo_id) else {
+        debug!("Repo {} is not saved, aggregates refresh on the next stats request", repo_id);
+        return;
+    };
+
+    let app = app.clone();
+    background::submit(repo_id, JobKind::StatsBackfill, move || {
+        match tauri::async_runtime::block_on(fetch_repository_stats(&saved.path, &saved.password, OperationKind::Background)) {
+            Ok(stats) => {
+                record_usage(&app, &saved, &stats);
+                window_scope::emit_repo_event(&app, &saved.id, "repository-aggregates-refreshed", AggregatesRefreshed {
+                    repo_id: saved.id.clone(),
+                    stats,
+                });
+                info!("Refreshed aggregates for repo {}", saved.id);
+            }
+            Err(e) => warn!("Failed to refresh aggregates for repo {}: {}", saved.id, e),
+        }
+    });
+}
+
+fn count_new_snapshots<'a>(repo_id: &str, ids: impl Iterator<Item = &'a String>) -> Result<usize> {
+    let known: HashSet<String> = database::get_all_snapshot_ids(repo_id)?.into_iter().collect();
+    Ok(ids.filter(|id| !known.contains(*id)).count())
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct QuotaWarning<'a> {
+    #[serde(flatten)]
+    event: &'a QuotaEvent,
+    message: String,
+}
+
+fn notify_quota_event(app: &AppHandle, repo_name: &str, event: &QuotaEvent) {
+    let (kind, args) = match event.threshold {
+        t if t >= 100 => ("quota.exceeded", vec![repo_name.to_string()]),
+        t if t > 0 => ("quota.approaching", vec![repo_name.to_string(), t.to_string()]),
+        _ => ("quota.cleared", vec![repo_name.to_string()]),
+    };
+    let message = tr(kind, &args);
+
+    if event.threshold > 0 {
+        warn!("Repository {} reached {}% of its size budget", event.repo_id, event.threshold);
+    }
+    window_scope::emit_repo_event(app, &event.repo_id, "quota-warning", QuotaWarning { event, message: message.clone() });
+    send_alert(app, &event.repo_id, kind, message);
+}
+
+// User-facing alerts go through the governor so they don't repeat on every check
+fn send_alert(app: &AppHandle, repo_id: &str, kind: &str, message: String) {
+    let settings = load_config()
+        .map(|config| policies::notification_settings(&config, repo_id))
+        .unwrap_or_default();
+    notifications::submit(app, &settings, repo_id, kind, message);
+}
+
+fn validate_filter_value(value: &str) -> Result<()> {
+    if value.trim().is_empty() || value.contains('\0') || value.starts_with('-') {
+        return Err(AppError::InvalidFilterValue(value.to_string()));
+    }
+    Ok(())
+}
+
+// restic accepts dates with optional minutes and seconds
+fn validate_find_time(value: &str) -> Result<()> {
+    let valid = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok()
+        || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").is_ok()
+        || chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").is_ok();
+    if !valid {
+        return Err(AppError::InvalidFilterValue(value.to_string()));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct FindSnapshotMatches {
+    snapshot: String,
+    #[serde(default)]
+    matches: Vec<FindMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FindMatch {
+    path: String,
+    #[serde(rename = "type", default)]
+    node_type: String,
+    size: Option<u64>,
+    mtime: Option<String>,
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn find_in_repository(
+    repo: String,
+    password: SecretString,
+    pattern: String,
+    options: Option<FindOptions>,
+) -> std::result::Result<Vec<FoundFile>, CommandError> {
+    info!("Finding {} in repository", pattern);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if pattern.trim().is_empty() || pattern.contains('\0') {
+        return Err(AppError::InvalidSearchPattern(pattern).into());
+    }
+    let options = options.unwrap_or_default();
+    for time in options.oldest.iter().chain(&options.newest) {
+        validate_find_time(time)?;
+    }
+    for snapshot_id in &options.snapshots {
+        validate_snapshot_id(snapshot_id)?;
+    }
+    for value in options.tags.iter().chain(&options.hosts).chain(&options.paths) {
+        validate_filter_value(value)?;
+    }
+
+    let args = restic_args::find(&pattern, &options);
+    let output = run_restic(&repo, &password, &restic_args::as_strs(&args)).await?;
+    let groups: Vec<FindSnapshotMatches> = if output.trim().is_empty() {
+        Vec::new()
+    } else {
+        serde_json::from_str(&output).map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?
+    };
+
+    let mut files: Vec<FoundFile> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for group in groups {
+        for m in group.matches {
+            let i = *index.entry(m.path.clone()).or_insert_with(|| {
+                files.push(FoundFile { path: m.path.clone(), node_type: m.node_type.clone(), occurrences: Vec::new() });
+                files.len() - 1
+            });
+            files[i].occurrences.push(FileOccurrence {
+                snapshot_id: group.snapshot.clone(),
+                size: m.size,
+                mtime: m.mtime,
+            });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    info!("Found {} matching paths", files.len());
+    Ok(files)
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgetGroup {
+    keep: Option<Vec<Snapshot>>,
+    remove: Option<Vec<Snapshot>>,
+    #[serde(default)]
+    reasons: Option<Vec<ForgetReason>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgetReason {
+    snapshot: Snapshot,
+    #[serde(default)]
+    matches: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreviewTarget {
+    repo_id: String,
+}
+
+// The same checks the command itself applies before running restic
+fn validate_operation(operation: &ResticOperation) -> Result<()> {
+    match operation {
+        ResticOperation::Snapshots {} | ResticOperation::Prune { .. } => {}
+        ResticOperation::Browse { snapshot_id, path } => {
+            validate_snapshot_id(snapshot_id)?;
+            if let Some(p) = path {
+                validate_include_path(p)?;
+            }
+        }
+        ResticOperation::Restore { snapshot_id, target, include_paths, .. } => {
+            validate_snapshot_id(snapshot_id)?;
+            validate_target_path(target)?;
+            for include_path in include_paths {
+                validate_include_path(include_path)?;
+            }
+        }
+        ResticOperation::Forget { policy, .. } => {
+            if !policy.has_keep_rule() {
+                return Err(AppError::EmptyForgetPolicy);
+            }
+            for value in policy.tags.iter().chain(&policy.hosts) {
+                validate_filter_value(value)?;
+            }
+        }
+        ResticOperation::Check { read_data_subset } => {
+            if let Some(percent) = read_data_subset {
+                if !(*percent > 0.0 && *percent <= 100.0) {
+                    return Err(AppError::InvalidReadDataSubset(percent.to_string()));
+                }
+            }
+        }
+        ResticOperation::Stats { snapshot_id } => validate_snapshot_id(snapshot_id)?,
+        ResticOperation::Diff { snapshot_a, snapshot_b } => {
+            validate_snapshot_id(snapshot_a)?;
+            validate_snapshot_id(snapshot_b)?;
+        }
+        ResticOperation::Find { pattern, options } => {
+            if pattern.trim().is_empty() || pattern.contains('\0') {
+                return Err(AppError::InvalidSearchPattern(pattern.clone()));
+            }
+            for time in options.oldest.iter().chain(&options.newest) {
+                validate_find_time(time)?;
+            }
+            for snapshot_id in &options.snapshots {
+                validate_snapshot_id(snapshot_id)?;
+            }
+            for value in options.tags.iter().chain(&options.hosts).chain(&options.paths) {
+                validate_filter_value(value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shows the restic command an operation would run, with secrets replaced by
+/// placeholders, so it can be reviewed or run by hand. `params` carries the
+/// repository's `repo_id` next to the operation's own parameters.
+#[command]
+#[instrument(skip(params))]
+pub async fn render_command_preview(operation: String, params: Value) -> std::result::Result<CommandPreview, CommandError> {
+    let target: PreviewTarget = serde_json::from_value(params.clone()).map_err(AppError::from)?;
+    validate_repo_id(&target.repo_id)?;
+    let operation: ResticOperation = serde_json::from_value(serde_json::json!({
+        "operation": operation,
+        "params": params,
+    })).map_err(AppError::from)?;
+    validate_operation(&operation)?;
+
+    let saved = secrets::saved_repository(&target.repo_id)?;
+    let mut env = restic_args::redact_extra_env(&saved.extra_env);
+    env.extend(restic_args::redact_env(repository_env(Some(saved.clone()), &saved.path, &saved.password)));
+    if let ResticOperation::Check { .. } = operation {
+        let (name, value) = CHECK_PROGRESS_ENV;
+        env.push(restic_args::PreviewEnvVar { name: name.to_string(), value: value.to_string(), redacted: false });
+    }
+    let config = load_config().map_err(AppError::Storage)?;
+    Ok(restic_args::render(&find_restic_binary(), &preview_args(&config, &saved.path, &operation)?, env))
+}
+
+/// The full argument list the command behind `operation` runs restic with
+fn preview_args(config: &AppConfig, repo: &str, operation: &ResticOperation) -> Result<Vec<String>> {
+    let mut args = repository_args(config, repo);
+    args.extend(operation_args(operation)?);
+    Ok(args)
+}
+
+/// An operation's arguments plus the option and verbosity flags its command adds
+fn operation_args(operation: &ResticOperation) -> Result<Vec<String>> {
+    let args = match operation {
+        ResticOperation::Restore { snapshot_id, target, include_paths, elevate, sparse } => {
+            let options = RestoreOptions { sparse: *sparse, elevate: *elevate, ..Default::default() };
+            restore_args(snapshot_id, target, include_paths, &options)?
+        }
+        _ => operation.args(),
+    };
+    let run_args = match operation {
+        ResticOperation::Restore { elevate, .. } => restore_run_args(&restic_args::as_strs(&args), *elevate),
+        ResticOperation::Prune { .. } | ResticOperation::Check { .. } => {
+            verbosity::with_flags(&restic_args::as_strs(&args), OperationKind::Maintenance)
+        }
+        _ => return Ok(args),
+    };
+    Ok(run_args.into_iter().map(str::to_string).collect())
+}
+
+fn resolve_repo_id(repo: &str, repo_id: Option<String>) -> Option<String> {
+    repo_id.or_else(|| find_repository_by_path(repo).map(|r| r.id))
+}
+
+fn validate_key_id(key_id: &str) -> Result<()> {
+    if key_id.is_empty() || key_id.len() > 64 || !key_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidKeyId(key_id.to_string()));
+    }
+    Ok(())
+}
+
+async fn fetch_keys(repo: &str, password: &str) -> Result<Vec<RepoKey>> {
+    let output = run_restic(repo, password, &["key", "list", "--json"]).await?;
+    Ok(serde_json::from_str(&output)?)
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn list_keys(repo: String, password: SecretString) -> std::result::Result<Vec<RepoKey>, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    Ok(fetch_keys(&repo, &password).await?)
+}
+
+/// Adds a key with another password; returns the repository's keys afterwards
+#[command]
+#[instrument(skip(password, new_password))]
+pub async fn add_key(repo: String, password: SecretString, new_password: SecretString) -> std::result::Result<Vec<RepoKey>, CommandError> {
+    info!("Adding a repository key");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_password(&new_password)?;
+
+    // A file keeps the new password off the command line and out of the environment
+    let new_password_file = PasswordFile::create(&new_password)?;
+    let file_arg = new_password_file.path().to_string_lossy().to_string();
+    run_restic(&repo, &password, &["key", "add", "--new-password-file", &file_arg]).await?;
+    Ok(fetch_keys(&repo, &password).await?)
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn remove_key(repo: String, password: SecretString, key_id: String) -> std::result::Result<Vec<RepoKey>, CommandError> {
+    info!("Removing repository key {}", key_id);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    validate_key_id(&key_id)?;
+    ensure_not_safe_mode(&repo)?;
+
+    // restic refuses to remove the key in use, which surfaces as its error message
+    run_restic(&repo, &password, &["key", "remove", &key_id]).await?;
+    Ok(fetch_keys(&repo, &password).await?)
+}
+
+/// Replaces the key the old password opens with one for the new password.
+/// Returns whether the saved password was updated too; repositories whose
+/// password comes from a file or command must have that updated separately.
+#[command]
+#[instrument(skip(old_password, new_password))]
+pub async fn change_password(repo: String, old_password: SecretString, new_password: SecretString) -> std::result::Result<bool, CommandError> {
+    info!("Changing repository password");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &old_password)?;
+    validate_password(&new_password)?;
+
+    let new_password_file = PasswordFile::create(&new_password)?;
+    let file_arg = new_password_file.path().to_string_lossy().to_string();
+    run_restic(&repo, &old_password, &["key", "passwd", "--new-password-file", &file_arg]).await?;
+    info!("Repository password changed");
+
+    let Some(saved) = find_repository_by_path(&repo).filter(|r| r.password_source.is_stored()) else {
+        return Ok(false);
+    };
+    let config = load_config().map_err(AppError::Storage)?;
+    secrets::active_store(&config)?.set(&secrets::password_key(&saved.id), &new_password)?;
+    Ok(true)
+}
+
+// restic treats locks that haven't been refreshed for this long as stale
+const STALE_LOCK_AGE: chrono::Duration = chrono::Duration::minutes(30);
+
+async fn fetch_locks(repo: &str, password: &str) -> Result<Vec<RepoLock>> {
+    // Listing must not take a lock itself, or it would show up in its own result
+    let output = run_restic(repo, password, &["list", "locks", "--no-lock"]).await?;
+    let mut locks = Vec::new();
+    for id in output.lines().map(str::trim).filter(|id| !id.is_empty()) {
+        // A lock can disappear between listing and reading it when its owner finishes
+        let contents = match run_restic(repo, password, &["cat", "lock", id, "--no-lock"]).await {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("Skipping lock {}: {}", id, e);
+                continue;
+            }
+        };
+        let mut lock: RepoLock = serde_json::from_str(&contents)?;
+        lock.id = id.to_string();
+        lock.stale = chrono::DateTime::parse_from_rfc3339(&lock.time)
+            .is_ok_and(|time| chrono::Utc::now().signed_duration_since(time) > STALE_LOCK_AGE);
+        locks.push(lock);
+    }
+    Ok(locks)
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn list_locks(repo: String, password: SecretString) -> std::result::Result<Vec<RepoLock>, CommandError> {
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    Ok(fetch_locks(&repo, &password).await?)
+}
+
+/// Removes stale locks, or every lock with `remove_all`, so a repository left
+/// locked by a crashed backup can be used again. Returns the locks that remain.
+#[command]
+#[instrument(skip(password))]
+pub async fn unlock_repository(repo: String, password: SecretString, remove_all: bool) -> std::result::Result<Vec<RepoLock>, CommandError> {
+    info!("Unlocking repository (remove all: {})", remove_all);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+
+    let args = restic_args::unlock(remove_all);
+    run_restic(&repo, &password, &restic_args::as_strs(&args)).await?;
+    if remove_all {
+        if let Err(e) = record_audit_event("repository.locks_removed", Some(repo.clone())).await {
+            warn!("Failed to record lock removal: {}", e);
+        }
+    }
+    Ok(fetch_locks(&repo, &password).await?)
+}
+
+fn validate_forget_policy(policy: &ForgetPolicy) -> Result<()> {
+    if !policy.has_keep_rule() {
+        return Err(AppError::EmptyForgetPolicy);
+    }
+    for value in policy.tags.iter().chain(&policy.hosts) {
+        validate_filter_value(value)?;
+    }
+    Ok(())
+}
+
+/// A policy without keep rules takes them from the repository's retention
+/// default, keeping its own tag and host filters
+fn with_default_retention(repo: &str, policy: ForgetPolicy) -> ForgetPolicy {
+    if policy.has_keep_rule() {
+        return policy;
+    }
+    let Some(mut retention) = repository_policy(repo).retention else {
+        return policy;
+    };
+    if !policy.tags.is_empty() {
+        retention.tags = policy.tags;
+    }
+    if !policy.hosts.is_empty() {
+        retention.hosts = policy.hosts;
+    }
+    retention
+}
+
+async fn run_forget(repo: &str, password: &str, policy: &ForgetPolicy, dry_run: bool) -> Result<Vec<ForgetGroup>> {
+    let args = restic_args::forget(policy, dry_run);
+    let output = run_restic(repo, password, &restic_args::as_strs(&args)).await?;
+    // restic prints nothing when no snapshots matched the filters
+    if output.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&output).map_err(|e| AppError::SnapshotJsonParse(e.to_string()))
+}
+
+fn retention_report(groups: Vec<ForgetGroup>) -> RetentionReport {
+    let mut report = RetentionReport { kept: Vec::new(), removed: Vec::new(), by_reason: Vec::new() };
+    for group in groups {
+        let mut reasons: HashMap<String, Vec<String>> = group.reasons.unwrap_or_default()
+            .into_iter()
+            .map(|reason| (reason.snapshot.id, reason.matches))
+            .collect();
+        for snapshot in group.keep.unwrap_or_default() {
+            let reasons = reasons.remove(&snapshot.id).unwrap_or_default();
+            for reason in &reasons {
+                match report.by_reason.iter_mut().find(|r| &r.reason == reason) {
+                    Some(existing) => existing.snapshot_ids.push(snapshot.id.clone()),
+                    None => report.by_reason.push(RetentionReason {
+                        reason: reason.clone(),
+                        snapshot_ids: vec![snapshot.id.clone()],
+                    }),
+                }
+            }
+            report.kept.push(KeptSnapshot { snapshot, reasons });
+        }
+        report.removed.extend(group.remove.unwrap_or_default());
+    }
+    report
+}
+
+/// Shows which snapshots a retention policy would keep and why, and which it
+/// would remove, so the policy can be tuned before `forget_snapshots` applies it
+#[command]
+#[instrument(skip(password))]
+pub async fn simulate_retention(
+    repo: String,
+    password: SecretString,
+    policy: ForgetPolicy,
+) -> std::result::Result<RetentionReport, CommandError> {
+    info!("Simulating retention policy");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    let policy = with_default_retention(&repo, policy);
+    validate_forget_policy(&policy)?;
+
+    let report = retention_report(run_forget(&repo, &password, &policy, true).await?);
+    info!("Policy keeps {} snapshots and removes {}", report.kept.len(), report.removed.len());
+    Ok(report)
+}
+
+#[command]
+#[instrument(skip(app, password))]
+pub async fn forget_snapshots(
+    app: AppHandle,
+    repo: String,
+    password: SecretString,
+    repo_id: Option<String>,
+    policy: ForgetPolicy,
+    dry_run: bool,
+) -> std::result::Result<ForgetResult, CommandError> {
+    info!("Forgetting snapshots (dry run: {})", dry_run);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+    let policy = with_default_retention(&repo, policy);
+    validate_forget_policy(&policy)?;
+    if !dry_run {
+        ensure_not_safe_mode(&repo)?;
+    }
+
+    let groups = run_forget(&repo, &password, &policy, dry_run).await?;
+
+    let mut result = ForgetResult { dry_run, kept: Vec::new(), removed: Vec::new() };
+    for group in groups {
+        result.kept.extend(group.keep.unwrap_or_default());
+        result.removed.extend(group.remove.unwrap_or_default());
+    }
+    info!("{} snapshots kept, {} {}", result.kept.len(), result.removed.len(),
+        if dry_run { "would be removed" } else { "removed" });
+
+    if !dry_run && !result.removed.is_empty() {
+        if let Some(repo_id) = resolve_repo_id(&repo, repo_id) {
+            let ids: Vec<String> = result.removed.iter().map(|s| s.id.clone()).collect();
+            blocking(move || {
+                let deleted = database::delete_snapshots(&repo_id, &ids)?;
+                invalidate_repo_aggregates(&app, &repo_id, 0, deleted);
+                Ok(())
+            }).await?;
+        }
+    }
+
+    Ok(result)
+}
+
+#[command]
+#[instrument(skip(app, window, password))]
+pub async fn prune_repository(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    repo_id: Option<String>,
+    dry_run: bool,
+    operation_id: Option<String>,
+) -> std::result::Result<PruneResult, CommandError> {
+    info!("Pruning repository (dry run: {})", dry_run);
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+    if !dry_run {
+        ensure_not_safe_mode(&repo)?;
+    }
+    compatibility_report(&repo, &password).await?.require(MaintenanceOp::Prune)?;
+    let operation_id = start_operation(&window, operation_id, "prune")?;
+
+    let prune_args = restic_args::prune(dry_run);
+    let (run_repo, run_id) = (repo.clone(), operation_id.clone());
+    let output = streaming(move || {
+        let args = verbosity::with_flags(&restic_args::as_strs(&prune_args), OperationKind::Maintenance);
+        let mut output = CapturedOutput::new(verbosity::for_kind(OperationKind::Maintenance));
+        run_restic_streaming(&run_repo, &password, &args, ErrorHandling::Strict, &run_id, |line| output.push(line))?;
+        Ok(output.finish())
+    }).await?;
+
+    if !dry_run {
+        if let Some(repo_id) = resolve_repo_id(&repo, repo_id) {
+            blocking(move || {
+                invalidate_repo_aggregates(&app, &repo_id, 0, 0);
+                Ok(())
+            }).await?;
+        }
+    }
+
+    info!("Prune finished");
+    Ok(PruneResult { dry_run, output, operation_id })
+}
+
+async fn compatibility_report(repo: &str, password: &str) -> Result<CompatibilityReport> {
+    let capabilities = restic_capabilities();
+    let config: Value = serde_json::from_str(&run_restic(repo, password, &["cat", "config"]).await?)?;
+    let repository_version = config.get("version").and_then(Value::as_u64).map(|v| v as u32);
+
+    // Without a migration name, restic only lists the ones that apply to the repository
+    let migrations = match run_restic(repo, password, &["migrate"]).await {
+        Ok(output) => compatibility::parse_migrations(&output),
+        Err(e) => {
+            warn!("Failed to list available migrations: {}", e);
+            Vec::new()
+        }
+    };
+    Ok(CompatibilityReport::new(&capabilities, repository_version, migrations))
+}
+
+/// What the installed restic can do with the repository, and which maintenance
+/// operations are blocked until restic is upgraded or the repository migrated
+#[command]
+#[instrument]
+pub async fn get_compatibility_report(repo_id: String) -> std::result::Result<CompatibilityReport, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    Ok(compatibility_report(&saved.path, &saved.password).await?)
+}
+
+/// Runs `restic migrate <migration>`, e.g. `upgrade_repo_v2`. Only migrations restic
+/// reports as applicable can be run.
+#[command]
+#[instrument(skip(window))]
+pub async fn migrate_repository(
+    window: WebviewWindow,
+    repo_id: String,
+    migration: String,
+    operation_id: Option<String>,
+) -> std::result::Result<MigrationResult, CommandError> {
+    info!("Running migration {} on repository {}", migration, repo_id);
+    validate_repo_id(&repo_id)?;
+    let saved = secrets::saved_repository(&repo_id)?;
+    validate_credentials(&saved.path, &saved.password)?;
+    ensure_not_safe_mode(&saved.path)?;
+    compatibility_report(&saved.path, &saved.password).await?.require_migration(&migration)?;
+    let operation_id = start_operation(&window, operation_id, "migrate")?;
+
+    let (run_migration, run_id) = (migration.clone(), operation_id.clone());
+    let output = streaming(move || {
+        let args = verbosity::with_flags(&["migrate", &run_migration], OperationKind::Maintenance);
+        let mut output = CapturedOutput::new(verbosity::for_kind(OperationKind::Maintenance));
+        run_restic_streaming(&saved.path, &saved.password, &args, ErrorHandling::Strict, &run_id, |line| output.push(line))?;
+        Ok(output.finish())
+    }).await?;
+    if let Err(e) = record_audit_event("repository.migrated", Some(format!("{} {}", repo_id, migration))).await {
+        warn!("Failed to record migration: {}", e);
+    }
+
+    info!("Migration {} finished", migration);
+    Ok(MigrationResult { migration, output, operation_id })
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct CheckProgress<'a> {
+    operation_id: &'a str,
+    message: &'a str,
+    done: Option<u64>,
+    total: Option<u64>,
+}
+
+// restic only prints progress to a terminal unless it's given a rate for it
+const CHECK_PROGRESS_ENV: (&str, &str) = ("RESTIC_PROGRESS_FPS", "2");
+
+// Progress lines look like "[0:03] 100.00%  12 / 12 packs"
+fn parse_check_counter(line: &str, unit: &str) -> Option<(u64, u64)> {
+    let rest = line.trim().strip_suffix(unit)?.trim_end();
+    let mut parts = rest.rsplit(' ').filter(|p| !p.is_empty());
+    let total = parts.next()?.parse().ok()?;
+    if parts.next()? != "/" {
+        return None;
+    }
+    let done = parts.next()?.parse().ok()?;
+    Some((done, total))
+}
+
+fn parse_check_errors(stderr: &str) -> Vec<String> {
+    stderr.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("Fatal: repository contains errors"))
+        .map(str::to_string)
+        .collect()
+}
+
+#[command]
+#[instrument(skip(window, password))]
+pub async fn check_repository(
+    window: WebviewWindow,
+    repo: String,
+    password: SecretString,
+    read_data_subset: Option<f64>,
+    operation_id: Option<String>,
+) -> std::result::Result<CheckResult, CommandError> {
+    info!("Checking repository integrity");
+    validate_repository_path(&repo)?;
+    validate_credentials(&repo, &password)?;
+    if let Some(percent) = read_data_subset {
+        if !(percent > 0.0 && percent <= 100.0) {
+            return Err(AppError::InvalidReadDataSubset(percent.to_string()).into());
+        }
+    }
+    let operation_id = start_operation(&window, operation_id, "check")?;
+
+    let check_args = restic_args::check(read_data_subset);
+    let (run_window, run_repo, run_id) = (window.clone(), repo.clone(), operation_id.clone());
+    let (outcome, snapshots_checked, packs_checked) = streaming(move || {
+        let args = verbosity::with_flags(&restic_args::as_strs(&check_args), OperationKind::Maintenance);
+        let mut snapshots_checked = None;
+        let mut packs_checked = None;
+        let env = [CHECK_PROGRESS_ENV];
+        let outcome = run_restic_streaming_with_env(&run_repo, &password, &args, &env, ErrorHandling::Strict, &run_id, |line| {
+            let snapshots = parse_check_counter(line, "snapshots");
+            let packs = parse_check_counter(line, "packs");
+            if let Some((done, _)) = snapshots {
+                snapshots_checked = Some(done);
+            }
+            if let Some((done, _)) = packs {
+                packs_checked = Some(done);
+            }
+
+            let counter = snapshots.or(packs);
+            window_scope::emit_to_window(&run_window, "check-progress", CheckProgress {
+                operation_id: &run_id,
+                message: line.trim(),
+                done: counter.map(|(done, _)| done),
+                total: counter.map(|(_, total)| total),
+            });
+        });
+        Ok((outcome, snapshots_checked, packs_checked))
+    }).await?;
+
+    // A failed check is a result, not an error; anything else (wrong password, ...) still fails
+    let errors = match outcome {
+        Ok(_) => Vec::new(),
+        Err(AppError::ResticError(stderr)) if stderr.contains("repository contains errors") => {
+            parse_check_errors(&stderr)
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if errors.is_empty() {
+        info!("Repository check found no errors");
+    } else {
+        warn!("Repository check found {} errors", errors.len());
+    }
+    if let Some(saved) = find_repository_by_path(&repo) {
+        let (app, ok) = (window.app_handle().clone(), errors.is_empty());
+        blocking(move || {
+            if let Err(e) = database::record_check_result(&saved.id, ok) {
+                warn!("Failed to record check result: {}", e);
+            }
+            repo_status::refresh(&app, &saved.id);
+            Ok(())
+        }).await?;
+    }
+
+    Ok(CheckResult {
+        ok: errors.is_empty(),
+        errors,
+        snapshots_checked,
+        packs_checked,
+        read_data_subset,
+        operation_id,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupHealth {
+    pub repo_id: String,
+    pub name: String,
+    pub size_budget: Option<u64>,
+    pub total_size: Option<u64>,
+    pub usage_percent: Option<f64>,
+    pub measured_at: Option<i64>,
+    /// New snapshots arrived since `total_size` was measured
+    pub stale: bool,
+    pub warnings: Vec<String>,
+    pub quota_events: Vec<QuotaEvent>,
+}
+
+#[command]
+#[instrument]
+pub async fn get_backup_health() -> std::result::Result<Vec<BackupHealth>, CommandError> {
+    info!("Collecting backup health");
+    let config = load_config().map_err(AppError::Storage)?;
+
+    Ok(blocking(move || {
+        let mut health = Vec::with_capacity(config.repositories.len());
+        for repo in config.repositories.iter().filter(|r| !r.is_deleted()) {
+            let usage = database::get_repo_usage(&repo.id)?;
+            let quota_events = database::get_quota_events(&repo.id, 10)?;
+            let stale = database::get_repo_meta(&repo.id)?.aggregates_stale;
+
+            let usage_percent = match (&usage, repo.size_budget) {
+                (Some(u), Some(budget)) if budget > 0 => Some(u.total_size as f64 / budget as f64 * 100.0),
+                _ => None,
+            };
+
+            // Only describes the budget; the alert went out when the usage was measured
+            let mut warnings = Vec::new();
+            if let (Some(u), Some(percent)) = (&usage, usage_percent) {
+                let kind = match u.quota_level {
+                    l if l >= 100 => Some("health.budget_exceeded"),
+                    l if l >= 80 => Some("health.budget_approaching"),
+                    _ => None,
+                };
+                warnings.extend(kind.map(|kind| tr(kind, &[format!("{:.0}", percent)])));
+            }
+
+            health.push(BackupHealth {
+                repo_id: repo.id.clone(),
+                name: repo.name.clone(),
+                size_budget: repo.size_budget,
+                total_size: usage.as_ref().map(|u| u.total_size),
+                usage_percent,
+                measured_at: usage.as_ref().map(|u| u.measured_at),
+                stale,
+                warnings,
+                quota_events,
+            });
+        }
+
+        Ok(health)
+    }).await?)
+}
+
+/// The repository's traffic-light status, evaluated from cached signals only
+#[command]
+#[instrument(skip(app))]
+pub async fn get_repository_status(app: AppHandle, repo_id: String) -> std::result::Result<RepoStatusReport, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || repo_status::update(&app, &repo_id)).await?)
+}
+
+#[command]
+#[instrument(skip(app))]
+pub async fn get_repository_statuses(app: AppHandle) -> std::result::Result<Vec<RepoStatusReport>, CommandError> {
+    let config = load_config().map_err(AppError::Storage)?;
+    let repo_ids: Vec<String> = config.repositories.iter()
+        .filter(|r| !r.is_deleted())
+        .map(|r| r.id.clone())
+        .collect();
+    Ok(blocking(move || repo_ids.iter().map(|id| repo_status::update(&app, id)).collect()).await?)
+}
+
+#[command]
+#[instrument]
+pub async fn set_repository_budget(repo_id: String, size_budget: Option<u64>) -> std::result::Result<(), CommandError> {
+    info!("Setting size budget for repository {}: {:?}", repo_id, size_budget);
+    validate_repo_id(&repo_id)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.size_budget = size_budget.filter(|b| *b > 0);
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn set_refresh_interval(repo_id: String, minutes: Option<u64>) -> std::result::Result<(), CommandError> {
+    info!("Setting refresh interval for repository {}: {:?}", repo_id, minutes);
+    validate_repo_id(&repo_id)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.refresh_interval_minutes = minutes;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+/// Sets the order in which the repository gets background cache work. Making a
+/// repository primary demotes the previous primary one to normal.
+#[command]
+#[instrument]
+pub async fn set_repo_priority(repo_id: String, priority: RepoPriority) -> std::result::Result<(), CommandError> {
+    info!("Setting priority of repository {} to {:?}", repo_id, priority);
+    validate_repo_id(&repo_id)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    if !config.repositories.iter().any(|r| r.id == repo_id) {
+        return Err(AppError::RepositoryNotFound(repo_id).into());
+    }
+    for repo in config.repositories.iter_mut() {
+        if repo.id == repo_id {
+            repo.priority = Some(priority);
+        } else if priority == RepoPriority::Primary && repo.priority == Some(RepoPriority::Primary) {
+            repo.priority = Some(RepoPriority::Normal);
+            background::reprioritize(&repo.id, RepoPriority::Normal);
+        }
+    }
+    config.save().map_err(AppError::Storage)?;
+    background::reprioritize(&repo_id, priority);
+    Ok(())
+}
+
+fn validate_labels(labels: &[String]) -> Result<()> {
+    for label in labels {
+        if label.trim().is_empty() || label.len() > 64 || label.chars().any(char::is_control) {
+            return Err(AppError::InvalidLabel(label.clone()));
+        }
+    }
+    Ok(())
+}
+
+fn validate_repository_policy(policy: &RepositoryPolicy) -> Result<()> {
+    if let Some(retention) = &policy.retention {
+        validate_forget_policy(retention)
+            .map_err(|_| AppError::InvalidPolicy("the retention default has no keep rule".to_string()))?;
+    }
+    if policy.notifications.as_ref().is_some_and(|n| n.cooldown_overrides.keys().any(|k| k.is_empty())) {
+        return Err(AppError::InvalidPolicy("notification overrides need a kind".to_string()));
+    }
+    if policy.max_retries.is_some_and(|retries| retries > MAX_RETRIES) {
+        return Err(AppError::InvalidPolicy(format!("at most {} retries are allowed", MAX_RETRIES)));
+    }
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn list_policy_templates() -> std::result::Result<Vec<PolicyTemplate>, CommandError> {
+    Ok(load_config().map_err(AppError::Storage)?.policy_templates)
+}
+
+/// Creates a template when its ID is empty, otherwise replaces the one with that ID.
+/// The change applies to every repository with a matching label from the next command on.
+#[command]
+#[instrument]
+pub async fn save_policy_template(template: PolicyTemplate) -> std::result::Result<PolicyTemplate, CommandError> {
+    if template.name.trim().is_empty() {
+        return Err(AppError::InvalidPolicy("the template has no name".to_string()).into());
+    }
+    if template.labels.is_empty() {
+        return Err(AppError::InvalidPolicy("the template isn't attached to a label".to_string()).into());
+    }
+    validate_labels(&template.labels)?;
+    validate_repository_policy(&template.policy)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let mut template = template;
+    if template.id.is_empty() {
+        template.id = uuid::Uuid::new_v4().to_string();
+        info!("Creating policy template {}", template.name);
+        config.policy_templates.push(template.clone());
+    } else {
+        let existing = config.policy_templates.iter_mut()
+            .find(|t| t.id == template.id)
+            .ok_or_else(|| AppError::PolicyTemplateNotFound(template.id.clone()))?;
+        info!("Updating policy template {}", template.name);
+        *existing = template.clone();
+    }
+    config.save().map_err(AppError::Storage)?;
+    Ok(template)
+}
+
+#[command]
+#[instrument]
+pub async fn delete_policy_template(id: String) -> std::result::Result<(), CommandError> {
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let before = config.policy_templates.len();
+    config.policy_templates.retain(|t| t.id != id);
+    if config.policy_templates.len() == before {
+        return Err(AppError::PolicyTemplateNotFound(id).into());
+    }
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn set_repository_labels(repo_id: String, labels: Vec<String>) -> std::result::Result<(), CommandError> {
+    info!("Setting labels of repository {} to {:?}", repo_id, labels);
+    validate_repo_id(&repo_id)?;
+    validate_labels(&labels)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    let mut labels = labels;
+    labels.sort();
+    labels.dedup();
+    repo.labels = labels;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+/// Sets the repository's own overrides; `None` leaves it to its templates
+#[command]
+#[instrument]
+pub async fn set_repository_policy(repo_id: String, policy: Option<RepositoryPolicy>) -> std::result::Result<(), CommandError> {
+    info!("Setting policy overrides of repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    if let Some(policy) = &policy {
+        validate_repository_policy(policy)?;
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.policy = policy;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_effective_policy(repo_id: String) -> std::result::Result<EffectivePolicy, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let config = load_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    Ok(policies::effective(&config, repo))
+}
+
+#[command]
+#[instrument]
+pub async fn list_background_jobs() -> std::result::Result<Vec<QueuedJob>, CommandError> {
+    Ok(background::pending())
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct SnapshotsUpdated {
+    repo_id: String,
+    new_snapshots: usize,
+}
+
+/// How a sync changed a repository's snapshot list, newest first
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotsDelta {
+    pub repo_id: String,
+    pub added: Vec<Snapshot>,
+    pub removed: Vec<Snapshot>,
+}
+
+/// Tells the repository's windows what a sync changed, as `snapshots-delta`, unless
+/// nothing did, and marks the repository's aggregates as outdated
+fn emit_snapshots_delta(app: &AppHandle, delta: SnapshotsDelta) -> SnapshotsDelta {
+    if delta.added.is_empty() && delta.removed.is_empty() {
+        return delta;
+    }
+    info!("Repo {}: {} snapshots added, {} removed", delta.repo_id, delta.added.len(), delta.removed.len());
+    invalidate_repo_aggregates(app, &delta.repo_id, delta.added.len(), delta.removed.len());
+    window_scope::emit_repo_event(app, &delta.repo_id, "snapshots-delta", delta.clone());
+    delta
+}
+
+/// Brings the cached list in line with `snapshots` from restic: new snapshots are
+/// added and ones restic no longer has are dropped
+fn apply_snapshot_list(app: &AppHandle, repo_id: &str, snapshots: Vec<Snapshot>) -> Result<SnapshotsDelta> {
+    let fresh: HashSet<&String> = snapshots.iter().map(|s| &s.id).collect();
+    let known: HashSet<String> = database::get_all_snapshot_ids(repo_id)?.into_iter().collect();
+    let gone: Vec<String> = known.iter().filter(|id| !fresh.contains(id)).cloned().collect();
+
+    let mut added: Vec<Snapshot> = snapshots.into_iter().filter(|s| !known.contains(&s.id)).collect();
+    added.sort_by_key(|s| std::cmp::Reverse(snapshot_instant(&s.time)));
+    if !added.is_empty() {
+        database::save_snapshots_metadata_only(repo_id, &added, |_| {})?;
+    }
+    let removed = database::get_snapshots_by_ids(repo_id, &gone)?;
+    if !gone.is_empty() {
+        database::delete_snapshots(repo_id, &gone)?;
+    }
+    database::update_last_delta_check(repo_id)?;
+    if let Err(e) = index_new_snapshots(app, repo_id, &added) {
+        warn!("Failed to queue indexing of new snapshots: {}", e);
+    }
+    repo_status::refresh(app, repo_id);
+
+    Ok(emit_snapshots_delta(app, SnapshotsDelta { repo_id: repo_id.to_string(), added, removed }))
+}
+
+/// Remembers that a repository couldn't be reached, for its status
+pub(crate) fn record_connection_error(app: &AppHandle, repo_id: &str, error: &AppError) {
+    // A cancelled run says nothing about the repository
+    if matches!(error, AppError::OperationCancelled(_)) {
+        return;
+    }
+    if let Err(e) = database::record_connection_error(repo_id, &error.to_string()) {
+        warn!("Failed to record connection error: {}", e);
+    }
+    repo_status::refresh(app, repo_id);
+}
+
+/// Delta check used by the background scheduler: caches snapshots that aren't
+/// known yet, drops forgotten ones and tells the repository's windows about them.
+pub(crate) fn refresh_repository_snapshots(app: &AppHandle, repo: &SavedRepository) -> Result<usize> {
+    let args = verbosity::with_flags(&["snapshots", "--json"], OperationKind::Background);
+    let output = run_restic_blocking(&repo.path, &repo.password, &args)?;
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+
+    let delta = apply_snapshot_list(app, &repo.id, snapshots)?;
+    window_scope::emit_repo_event(app, &repo.id, "snapshots-updated", SnapshotsUpdated {
+        repo_id: repo.id.clone(),
+        new_snapshots: delta.added.len(),
+    });
+    Ok(delta.added.len())
 }
 
+/// Delta check run by an open window: fetches the snapshot list and applies only
+/// what changed to the cache. Also emitted as `snapshots-delta`.
 #[command]
-pub async fn browse_snapshot(repo: String, password: String, snapshot_id: String, path: Option<String>) -> std::result::Result<Vec<FileNode>, String> {
+#[instrument(skip(app, password))]
+pub async fn sync_snapshots(
+    app: AppHandle,
+    repo_id: String,
+    repo: String,
+    password: SecretString,
+) -> std::result::Result<SnapshotsDelta, CommandError> {
+    validate_repo_id(&repo_id)?;
     validate_repository_path(&repo)?;
-    validate_password(&password)?;
-    validate_snapshot_id(&snapshot_id)?;
+    validate_credentials(&repo, &password)?;
 
-    if let Some(p) = &path {
-        validate_include_path(p)?;
+    let output = match run_restic(&repo, &password, &restic_args::as_strs(&restic_args::snapshots())).await {
+        Ok(output) => output,
+        Err(e) => {
+            let e = blocking(move || {
+                record_connection_error(&app, &repo_id, &e);
+                Ok(e)
+            }).await?;
+            return Err(e.into());
+        }
+    };
+    let snapshots: Vec<Snapshot> = serde_json::from_str(&output)
+        .map_err(|e| AppError::SnapshotJsonParse(e.to_string()))?;
+    remember_connection(&repo).await;
+    Ok(blocking(move || apply_snapshot_list(&app, &repo_id, snapshots)).await?)
+}
+
+#[command]
+#[instrument(skip(repositories))]
+pub async fn save_repositories(repositories: Vec<SavedRepository>) -> std::result::Result<(), CommandError> {
+    info!("Saving {} repositories", repositories.len());
+    for repo in &repositories {
+        validate_repo_id(&repo.id)?;
+        validate_repository_path(&repo.path)?;
+        if repo.password_source.is_stored() {
+            validate_password(&repo.password)?;
+        }
+        validate_password_source(&repo.password_source)?;
+        validate_repository_name(&repo.name)?;
+        validate_extra_env(&repo.extra_env)?;
+        validate_labels(&repo.labels)?;
+        if let Some(policy) = &repo.policy {
+            validate_repository_policy(policy)?;
+        }
     }
 
-    let mut args = vec!["ls", "--json", &snapshot_id];
-    if let Some(p) = &path {
-        args.push(p);
+    // Preserve existing restic_binary_path and per-repository settings when saving repositories
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let mut repositories = repositories;
+    for repo in &mut repositories {
+        if let Some(existing) = config.repositories.iter().find(|r| r.id == repo.id) {
+            repo.merge_backend_settings(existing);
+        }
+        secrets::stash_password(&config, repo)?;
+    }
+    // Removed repositories aren't shown, so the frontend can't send them back
+    let removed: Vec<SavedRepository> = config.repositories.drain(..)
+        .filter(|r| r.is_deleted() && !repositories.iter().any(|saved| saved.id == r.id))
+        .collect();
+    config.repositories = repositories;
+    config.repositories.extend(removed);
+    config.save().map_err(AppError::Storage)?;
+    info!("Repositories saved successfully");
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn load_repositories() -> std::result::Result<Vec<SavedRepository>, CommandError> {
+    info!("Loading saved repositories");
+    let mut config = load_config().map_err(AppError::Storage)?;
+    config.repositories.retain(|r| !r.is_deleted());
+    secrets::hydrate_passwords(&mut config);
+    info!("Loaded {} repositories", config.repositories.len());
+    Ok(config.repositories)
+}
+
+/// A saved repository with its cached status, for rendering the repository list in one call
+#[derive(Debug, Serialize)]
+pub struct RepositoryWithStatus {
+    #[serde(flatten)]
+    pub repository: SavedRepository,
+    pub snapshot_count: i64,
+    pub last_delta_check: i64,
+    pub last_connected_at: Option<i64>,
+    pub aggregates_stale: bool,
+}
+
+#[command]
+#[instrument]
+pub async fn load_repositories_with_status() -> std::result::Result<Vec<RepositoryWithStatus>, CommandError> {
+    let repositories = load_repositories().await?;
+    let mut metas = blocking(database::get_all_repo_meta).await?;
+
+    Ok(repositories.into_iter()
+        .map(|repository| {
+            let meta = metas.remove(&repository.id);
+            RepositoryWithStatus {
+                snapshot_count: meta.as_ref().map_or(0, |m| m.snapshot_count),
+                last_delta_check: meta.as_ref().map_or(0, |m| m.last_delta_check),
+                last_connected_at: meta.as_ref().and_then(|m| m.last_connected_at),
+                aggregates_stale: meta.is_some_and(|m| m.aggregates_stale),
+                repository,
+            }
+        })
+        .collect())
+}
+
+#[command]
+pub async fn get_config_path() -> std::result::Result<String, CommandError> {
+    let path = crate::storage::get_config_file_path().map_err(AppError::Storage)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Writes the repositories, their passwords and settings, restore points, policy
+/// templates and pins to `path`, encrypted with `passphrase`
+#[command]
+#[instrument(skip(passphrase))]
+pub async fn export_config(path: String, passphrase: SecretString) -> std::result::Result<ConfigExportSummary, CommandError> {
+    info!("Exporting configuration to {}", path);
+    let out = validate_target_path(&path)?;
+
+    let mut config = load_config().map_err(AppError::Storage)?;
+    config.repositories.retain(|r| !r.is_deleted());
+    secrets::hydrate_passwords(&mut config);
+
+    let repo_ids: Vec<String> = config.repositories.iter().map(|r| r.id.clone()).collect();
+    let pins = blocking(move || {
+        let mut pins = HashMap::new();
+        for repo_id in repo_ids {
+            let ids: Vec<String> = database::get_snapshot_pins(&repo_id)?.into_iter().map(|p| p.snapshot_id).collect();
+            if !ids.is_empty() {
+                pins.insert(repo_id, ids);
+            }
+        }
+        Ok(pins)
+    }).await?;
+    let summary = ConfigExportSummary {
+        repositories: config.repositories.len(),
+        restore_points: config.restore_points.len(),
+        policy_templates: config.policy_templates.len(),
+        pins: pins.values().map(Vec::len).sum(),
+    };
+    let bundle = ConfigBundle {
+        repositories: config.repositories,
+        restic_binary_path: config.restic_binary_path,
+        restore_points: config.restore_points,
+        policy_templates: config.policy_templates,
+        pins,
+    };
+
+    // Key derivation is deliberately slow
+    tauri::async_runtime::spawn_blocking(move || storage::seal_bundle(&bundle, &passphrase, &out))
+        .await
+        .map_err(|e| AppError::Storage(e.to_string()))??;
+    if let Err(e) = record_audit_event("config.exported", Some(path.clone())).await {
+        warn!("Failed to record config export: {}", e);
     }
+    Ok(summary)
+}
 
-    let output = run_restic(&repo, &password, &args)?;
+#[derive(Debug, Serialize, Default)]
+pub struct ConfigExportSummary {
+    pub repositories: usize,
+    pub restore_points: usize,
+    pub policy_templates: usize,
+    pub pins: usize,
+}
 
-    let mut files = Vec::new();
-    for line in output.lines() {
-        if line.trim().is_empty() { continue; }
-        if let Ok(val) = serde_json::from_str::<Value>(line) {
-            if let Some(struct_type) = val.get("struct_type") {
-                if struct_type == "node" {
-                    if let Ok(node) = serde_json::from_value::<FileNode>(val) {
-                        files.push(node);
-                    }
+#[derive(Debug, Serialize, Default)]
+pub struct ConfigImportSummary {
+    pub repositories_added: usize,
+    pub repositories_updated: usize,
+    pub restore_points: usize,
+    pub policy_templates: usize,
+    pub pins: usize,
+    /// The exported binary path is only used when it exists on this machine
+    pub restic_binary_path_applied: bool,
+}
+
+/// Reads a file written by `export_config`. Entries with the same ID as existing
+/// ones replace them; everything else is kept. Passwords go to this machine's
+/// secret store.
+#[command]
+#[instrument(skip(passphrase))]
+pub async fn import_config(path: String, passphrase: SecretString) -> std::result::Result<ConfigImportSummary, CommandError> {
+    info!("Importing configuration from {}", path);
+    let source = PathBuf::from(&path);
+
+    let bundle = tauri::async_runtime::spawn_blocking(move || storage::open_bundle(&source, &passphrase))
+        .await
+        .map_err(|e| AppError::Storage(e.to_string()))??;
+    for repo in &bundle.repositories {
+        validate_repo_id(&repo.id)?;
+        validate_repository_path(&repo.path)?;
+        validate_extra_env(&repo.extra_env)?;
+    }
+
+    let mut summary = ConfigImportSummary::default();
+    {
+        let mut config = edit_config().map_err(AppError::Storage)?;
+        for mut repo in bundle.repositories {
+            repo.deleted_at = None;
+            secrets::stash_password(&config, &mut repo)?;
+            match config.repositories.iter_mut().find(|r| r.id == repo.id) {
+                Some(existing) => {
+                    *existing = repo;
+                    summary.repositories_updated += 1;
+                }
+                None => {
+                    config.repositories.push(repo);
+                    summary.repositories_added += 1;
                 }
             }
         }
+        for point in bundle.restore_points {
+            config.restore_points.retain(|p| p.id != point.id);
+            config.restore_points.push(point);
+            summary.restore_points += 1;
+        }
+        for template in bundle.policy_templates {
+            config.policy_templates.retain(|t| t.id != template.id);
+            config.policy_templates.push(template);
+            summary.policy_templates += 1;
+        }
+        if let Some(binary) = bundle.restic_binary_path.filter(|p| validate_restic_binary(p)) {
+            config.restic_binary_path = Some(binary);
+            config.setup_completed = Some(true);
+            summary.restic_binary_path_applied = true;
+        }
+        config.save().map_err(AppError::Storage)?;
     }
 
-    Ok(files)
+    summary.pins = blocking(move || {
+        let mut pins = 0;
+        for (repo_id, snapshot_ids) in &bundle.pins {
+            database::set_pinned_order(repo_id, snapshot_ids)?;
+            pins += snapshot_ids.len();
+        }
+        Ok(pins)
+    }).await?;
+    if let Err(e) = record_audit_event("config.imported", Some(path.clone())).await {
+        warn!("Failed to record config import: {}", e);
+    }
+    info!("Imported {} new and {} updated repositories", summary.repositories_added, summary.repositories_updated);
+    Ok(summary)
+}
+
+#[command]
+#[instrument]
+pub async fn remove_repository(repo_id: String) -> std::result::Result<(), CommandError> {
+    info!("Removing repository: {}", repo_id);
+    validate_repo_id(&repo_id)?;
+
+    // Only hidden for now; secrets and cache stay until the grace period ends
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id && !r.is_deleted())
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.deleted_at = Some(chrono::Utc::now().timestamp());
+    config.save().map_err(AppError::Storage)?;
+    info!("Repository marked as removed");
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn undo_remove_repository(repo_id: String) -> std::result::Result<(), CommandError> {
+    info!("Restoring removed repository: {}", repo_id);
+    validate_repo_id(&repo_id)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id && r.is_deleted())
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.deleted_at = None;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemovedRepository {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub deleted_at: i64,
+    pub purge_at: i64,
+}
+
+#[command]
+#[instrument]
+pub async fn list_removed_repositories() -> std::result::Result<Vec<RemovedRepository>, CommandError> {
+    let config = load_config().map_err(AppError::Storage)?;
+    let grace = config.deletion_grace_secs();
+    Ok(config.repositories.into_iter()
+        .filter_map(|r| r.deleted_at.map(|deleted_at| RemovedRepository {
+            id: r.id,
+            name: r.name,
+            path: r.path,
+            deleted_at,
+            purge_at: deleted_at.saturating_add(grace),
+        }))
+        .collect())
+}
+
+#[command]
+#[instrument]
+pub async fn purge_repository(repo_id: String) -> std::result::Result<(), CommandError> {
+    validate_repo_id(&repo_id)?;
+    blocking(move || purge(&repo_id)).await?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn set_deletion_grace_days(days: u64) -> std::result::Result<(), CommandError> {
+    info!("Keeping removed repositories for {} days", days);
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.deletion_grace_days = Some(days);
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+fn purge(repo_id: &str) -> Result<()> {
+    info!("Purging repository: {}", repo_id);
+
+    // The plaintext store edits the config file itself, so reload it afterwards
+    let config = load_config().map_err(AppError::Storage)?;
+    let removed = secrets::active_store(&config).and_then(|store| {
+        store.delete(&secrets::password_key(repo_id))?;
+        store.delete(&secrets::credentials_key(repo_id))
+    });
+    if let Err(e) = removed {
+        warn!("Failed to remove stored secrets: {}", e);
+    }
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.repositories.retain(|r| r.id != repo_id);
+    config.restore_points.retain(|p| p.repo_id != repo_id);
+    config.save().map_err(AppError::Storage)?;
+    database::clear_repo_cache(repo_id)?;
+    database::delete_snapshot_pins(repo_id)?;
+    if let Err(e) = cache_backup::delete_all(repo_id) {
+        warn!("Failed to delete cache backups: {}", e);
+    }
+    info!("Repository purged");
+    Ok(())
+}
+
+/// Permanently removes repositories whose undo window has passed. Runs at startup
+/// and on every scheduler tick.
+pub fn purge_expired_repositories() {
+    let Ok(config) = load_config() else { return };
+    let cutoff = chrono::Utc::now().timestamp() - config.deletion_grace_secs();
+    let expired: Vec<String> = config.repositories.iter()
+        .filter(|r| r.deleted_at.is_some_and(|at| at <= cutoff))
+        .map(|r| r.id.clone())
+        .collect();
+
+    for repo_id in expired {
+        if let Err(e) = purge(&repo_id) {
+            warn!("Failed to purge repository {}: {}", repo_id, e);
+        }
+    }
+}
+
+#[command]
+#[instrument(skip(password))]
+pub async fn store_repo_password(repo_id: String, password: SecretString) -> std::result::Result<(), CommandError> {
+    info!("Storing password for repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    validate_password(&password)?;
+
+    let config = load_config().map_err(AppError::Storage)?;
+    secrets::active_store(&config)?.set(&secrets::password_key(&repo_id), &password)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_repo_password(repo_id: String) -> std::result::Result<Option<SecretString>, CommandError> {
+    validate_repo_id(&repo_id)?;
+
+    let config = load_config().map_err(AppError::Storage)?;
+    if let Some(repo) = config.repositories.iter().find(|r| r.id == repo_id && !r.password.is_empty()) {
+        return Ok(Some(repo.password.clone()));
+    }
+    Ok(secrets::active_store(&config)?.get(&secrets::password_key(&repo_id))?)
+}
+
+#[command]
+#[instrument]
+pub async fn set_password_source(repo_id: String, source: PasswordSource) -> std::result::Result<(), CommandError> {
+    info!("Setting password source for repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    validate_password_source(&source)?;
+
+    if !source.is_stored() {
+        // Drop any saved copy so the password really isn't kept anywhere.
+        // This runs before taking the edit: the plaintext store edits the config itself.
+        let config = load_config().map_err(AppError::Storage)?;
+        secrets::active_store(&config)?.delete(&secrets::password_key(&repo_id))?;
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    if !source.is_stored() {
+        repo.password.clear();
+    }
+    repo.password_source = source;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument(skip(credentials))]
+pub async fn set_backend_credentials(
+    repo_id: String,
+    credentials: Option<BackendCredentials>,
+) -> std::result::Result<(), CommandError> {
+    info!("Updating backend credentials for repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    if let Some(c) = &credentials {
+        validate_backend_credentials(c)?;
+    }
+
+    let config = load_config().map_err(AppError::Storage)?;
+    if !config.repositories.iter().any(|r| r.id == repo_id) {
+        return Err(AppError::RepositoryNotFound(repo_id).into());
+    }
+
+    let store = secrets::active_store(&config)?;
+    let key = secrets::credentials_key(&repo_id);
+    match credentials.filter(|c| *c != BackendCredentials::default()) {
+        Some(c) => store.set(&key, &serde_json::to_string(&c).map_err(AppError::from)?)?,
+        None => store.delete(&key)?,
+    }
+    Ok(())
+}
+
+#[command]
+#[instrument(skip(extra_env))]
+pub async fn set_repository_env(
+    repo_id: String,
+    extra_env: HashMap<String, String>,
+) -> std::result::Result<(), CommandError> {
+    info!("Setting {} environment variables for repository {}", extra_env.len(), repo_id);
+    validate_repo_id(&repo_id)?;
+    validate_extra_env(&extra_env)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.extra_env = extra_env;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+/// Sets the port, key and ssh options restic connects to an `sftp:` repository with
+#[command]
+#[instrument]
+pub async fn set_repository_sftp(
+    repo_id: String,
+    sftp: Option<SftpOptions>,
+) -> std::result::Result<(), CommandError> {
+    info!("Setting SFTP options for repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    if let Some(sftp) = &sftp {
+        validate_sftp_options(sftp)?;
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.sftp = sftp.filter(|s| *s != SftpOptions::default());
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+/// Sets the CA certificate and certificate checking for the repository's HTTPS backend
+#[command]
+#[instrument]
+pub async fn set_repository_tls(
+    repo_id: String,
+    tls: Option<TlsOptions>,
+) -> std::result::Result<(), CommandError> {
+    info!("Setting TLS options for repository {}", repo_id);
+    validate_repo_id(&repo_id)?;
+    if let Some(cacert) = tls.as_ref().and_then(|t| t.cacert.as_ref()) {
+        if !Path::new(cacert).is_file() {
+            return Err(AppError::CaCertNotFound(cacert.clone()).into());
+        }
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.tls = tls.filter(|t| *t != TlsOptions::default());
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+/// Sets what runs before the repository's first restic call, e.g. waking the NAS it lives on
+#[command]
+#[instrument]
+pub async fn set_repository_hooks(
+    repo_id: String,
+    pre_connect: Vec<PreConnectHook>,
+) -> std::result::Result<(), CommandError> {
+    info!("Setting {} pre-connect hooks for repository {}", pre_connect.len(), repo_id);
+    validate_repo_id(&repo_id)?;
+    hooks::validate(&pre_connect)?;
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter_mut()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    repo.pre_connect = pre_connect;
+    config.save().map_err(AppError::Storage)?;
+    hooks::forget(&repo_id);
+    Ok(())
+}
+
+/// Runs hooks right away so they can be tried out before they're saved
+#[command]
+#[instrument]
+pub async fn test_pre_connect_hooks(pre_connect: Vec<PreConnectHook>) -> std::result::Result<(), CommandError> {
+    hooks::validate(&pre_connect)?;
+    tauri::async_runtime::spawn_blocking(move || hooks::run_all(&pre_connect))
+        .await
+        .map_err(|e| AppError::PreConnectHookFailed(e.to_string()))??;
+    Ok(())
 }
 
 #[command]
-pub async fn get_snapshot_stats(repo: String, password: String, snapshot_id: String) -> std::result::Result<serde_json::Value, String> {
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
-    validate_snapshot_id(&snapshot_id)?;
-
-    let output = run_restic(&repo, &password, &["stats", "--json", &snapshot_id])?;
-    let stats: serde_json::Value = serde_json::from_str(&output)
-        .map_err(|e| AppError::StatsJsonParse(e.to_string()))?;
-    Ok(stats)
+#[instrument]
+pub async fn get_repository_env(repo_id: String) -> std::result::Result<HashMap<String, String>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let saved = load_config().map_err(AppError::Storage)?
+        .repositories
+        .into_iter()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    Ok(saved.extra_env)
 }
 
 #[command]
-pub async fn get_repository_stats(repo: String, password: String) -> std::result::Result<serde_json::Value, String> {
-    validate_repository_path(&repo)?;
-    validate_password(&password)?;
+#[instrument]
+pub async fn get_backend_credentials(repo_id: String) -> std::result::Result<Option<BackendCredentials>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let saved = load_config().map_err(AppError::Storage)?
+        .repositories
+        .into_iter()
+        .find(|r| r.id == repo_id)
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+    Ok(secrets::backend_credentials(&saved))
+}
 
-    let output = run_restic(&repo, &password, &["stats", "--json", "--mode", "raw-data"])?;
-    let stats: serde_json::Value = serde_json::from_str(&output)
-        .map_err(|e| AppError::RepoStatsJsonParse(e.to_string()))?;
-    Ok(stats)
+#[command]
+#[instrument]
+pub async fn get_secret_backend() -> std::result::Result<SecretBackendStatus, CommandError> {
+    let config = load_config().map_err(AppError::Storage)?;
+    Ok(blocking(move || Ok(secrets::backend_status(&config))).await?)
 }
 
 #[command]
-#[instrument(skip(repositories))]
-pub async fn save_repositories(repositories: Vec<SavedRepository>) -> std::result::Result<(), String> {
-    info!("Saving {} repositories", repositories.len());
-    for repo in &repositories {
-        validate_repo_id(&repo.id)?;
-        validate_repository_path(&repo.path)?;
-        validate_password(&repo.password)?;
+#[instrument]
+pub async fn set_secret_backend(backend: SecretBackend) -> std::result::Result<(), CommandError> {
+    info!("Switching secret backend to {:?}", backend);
+    secrets::migrate_backend(backend)?;
+    Ok(())
+}
 
-        if repo.name.trim().is_empty() {
-            return Err(AppError::EmptyRepositoryName.into());
-        }
+// How long a token from prepare_secret_wipe stays valid
+const WIPE_TOKEN_TTL: Duration = Duration::from_secs(120);
+
+static WIPE_TOKEN: Lazy<Mutex<Option<(String, std::time::Instant)>>> = Lazy::new(|| Mutex::new(None));
+
+/// First half of `wipe_all_secrets`: hands out a short-lived token the user confirms with
+#[command]
+#[instrument]
+pub async fn prepare_secret_wipe() -> std::result::Result<String, CommandError> {
+    let token = uuid::Uuid::new_v4().to_string();
+    let mut pending = WIPE_TOKEN.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock wipe token: {}", e)))?;
+    *pending = Some((token.clone(), std::time::Instant::now() + WIPE_TOKEN_TTL));
+    Ok(token)
+}
 
-        if repo.name.len() > 200 {
-            return Err(AppError::RepositoryNameTooLong.into());
+/// Removes every stored password and backend credential, for decommissioning a
+/// shared machine. Repository entries and cached snapshots are kept.
+#[command]
+#[instrument(skip(confirmation_token))]
+pub async fn wipe_all_secrets(confirmation_token: String) -> std::result::Result<SecretWipeReport, CommandError> {
+    {
+        let mut pending = WIPE_TOKEN.lock()
+            .map_err(|e| AppError::Storage(format!("Failed to lock wipe token: {}", e)))?;
+        // A token is single-use, whether or not it matches
+        let valid = pending.take()
+            .is_some_and(|(token, expires)| token == confirmation_token && std::time::Instant::now() < expires);
+        if !valid {
+            return Err(AppError::InvalidConfirmationToken.into());
         }
     }
 
-    // Preserve existing restic_binary_path when saving repositories
-    let mut config = load_config().map_err(|e| AppError::Storage(e)).unwrap_or_default();
-    config.repositories = repositories;
-    save_config(&config).map_err(|e| AppError::Storage(e))?;
-    info!("Repositories saved successfully");
-    Ok(())
+    warn!("Wiping all stored secrets");
+    let report = secrets::wipe_all()?;
+    let detail = format!(
+        "{} secrets of {} repositories removed, {} failures",
+        report.secrets_removed, report.repositories, report.failures.len()
+    );
+    if let Err(e) = record_audit_event("secrets.wiped", Some(detail.clone())).await {
+        error!("Failed to record secret wipe in the audit log: {}", e);
+    }
+    Ok(report)
 }
 
+/// Restores recorded for one repository, or for all of them, most recent first
 #[command]
 #[instrument]
-pub async fn load_repositories() -> std::result::Result<Vec<SavedRepository>, String> {
-    info!("Loading saved repositories");
-    let config = load_config().map_err(|e| AppError::Storage(e))?;
-    info!("Loaded {} repositories", config.repositories.len());
-    Ok(config.repositories)
+pub async fn list_restore_history(
+    repo_id: Option<String>,
+    limit: Option<i64>,
+) -> std::result::Result<Vec<RestoreHistoryEntry>, CommandError> {
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+    let limit = limit.unwrap_or(100).clamp(1, 10_000);
+    Ok(blocking(move || database::list_restore_history(repo_id.as_deref(), limit)).await?)
 }
 
+/// Returns how many entries were removed
 #[command]
-pub async fn get_config_path() -> std::result::Result<String, String> {
-    let path = crate::storage::get_config_file_path().map_err(|e| AppError::Storage(e))?;
-    Ok(path.to_string_lossy().to_string())
+#[instrument]
+pub async fn clear_restore_history(repo_id: Option<String>) -> std::result::Result<usize, CommandError> {
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+    let id = repo_id.clone();
+    let removed = blocking(move || database::clear_restore_history(id.as_deref())).await?;
+    if let Err(e) = record_audit_event("restore_history.cleared", repo_id.clone()).await {
+        warn!("Failed to record clearing the restore history: {}", e);
+    }
+    Ok(removed)
 }
 
 #[command]
 #[instrument]
-pub async fn remove_repository(repo_id: String) -> std::result::Result<(), String> {
-    info!("Removing repository: {}", repo_id);
-    validate_repo_id(&repo_id)?;
+pub async fn get_audit_log(limit: Option<i64>) -> std::result::Result<Vec<AuditEvent>, CommandError> {
+    let limit = limit.unwrap_or(100).clamp(1, 10_000);
+    Ok(blocking(move || database::get_audit_log(limit)).await?)
+}
 
-    let mut config = load_config().map_err(|e| AppError::Storage(e))?;
-    config.repositories.retain(|r| r.id != repo_id);
-    save_config(&config).map_err(|e| AppError::Storage(e))?;
-    database::clear_repo_cache(&repo_id)?;
-    info!("Repository removed successfully");
-    Ok(())
+/// Copies an app object to the system clipboard in its standard text form and
+/// returns the copied text
+#[command]
+#[instrument(skip(app, payload))]
+pub async fn copy_to_clipboard(app: AppHandle, kind: String, payload: Value) -> std::result::Result<String, CommandError> {
+    let content: ClipboardContent = serde_json::from_value(serde_json::json!({
+        "kind": kind,
+        "payload": payload,
+    })).map_err(AppError::from)?;
+    match &content {
+        ClipboardContent::SnapshotId { snapshot_id } => validate_snapshot_id(snapshot_id)?,
+        ClipboardContent::OriginalPath { path } => validate_snapshot_path(path)?,
+        ClipboardContent::FileList { nodes, .. } => {
+            for node in nodes {
+                validate_snapshot_path(&node.path)?;
+            }
+        }
+    }
+
+    let text = content.text();
+    app.clipboard().write_text(text.clone())
+        .map_err(|e| AppError::Clipboard(e.to_string()))?;
+    debug!("Copied {} characters to the clipboard", text.len());
+    Ok(text)
 }
 
 #[command]
 #[instrument]
-pub async fn get_restic_binary_path() -> std::result::Result<Option<String>, String> {
+pub async fn get_restic_binary_path() -> std::result::Result<Option<String>, CommandError> {
     info!("Getting configured restic binary path");
-    let config = load_config().map_err(|e| AppError::Storage(e))?;
+    let config = load_config().map_err(AppError::Storage)?;
     Ok(config.restic_binary_path)
 }
 
 #[command]
 #[instrument]
-pub async fn set_restic_binary_path(path: Option<String>) -> std::result::Result<(), String> {
+pub async fn set_restic_binary_path(path: Option<String>) -> std::result::Result<(), CommandError> {
     if let Some(ref p) = path {
         info!("Setting restic binary path to: {}", p);
 
         if !p.is_empty() && !Path::new(p).exists() {
             warn!("Restic binary path does not exist: {}", p);
-            return Err(format!("Restic binary not found at: {}", p));
+            return Err(AppError::ResticBinaryNotFound(p.clone()).into());
         }
 
         if !validate_restic_binary(p) {
             warn!("Path exists but is not a valid restic binary: {}", p);
-            return Err(format!("File is not a valid restic binary: {}", p));
+            return Err(AppError::InvalidResticBinary(p.clone()).into());
         }
+        // Emulated binaries work, so only warn; check_restic_setup_status reports it to the UI
+        inspect_restic_binary(p);
     } else {
         info!("Clearing restic binary path (will use auto-detection)");
     }
 
-    let mut config = load_config().map_err(|e| AppError::Storage(e))?;
+    let mut config = edit_config().map_err(AppError::Storage)?;
     config.restic_binary_path = path;
     config.setup_completed = Some(true);
-    save_config(&config).map_err(|e| AppError::Storage(e))?;
+    config.save().map_err(AppError::Storage)?;
     info!("Restic binary path updated successfully, setup marked as completed");
     Ok(())
 }
 
+/// Downloads restic from its GitHub releases into the app data directory and uses
+/// it from then on. `version` defaults to the latest release.
+#[command]
+#[instrument]
+pub async fn download_restic(
+    version: Option<String>,
+    require_signature: Option<bool>,
+) -> std::result::Result<InstalledRestic, CommandError> {
+    let version = match version {
+        Some(v) => v.trim().trim_start_matches('v').to_string(),
+        None => restic_download::latest_version().await?,
+    };
+    let installed = restic_download::install(&version, require_signature.unwrap_or(true)).await?;
+
+    if !validate_restic_binary(&installed.path) {
+        return Err(AppError::InvalidResticBinary(installed.path).into());
+    }
+    {
+        let mut config = edit_config().map_err(AppError::Storage)?;
+        config.restic_binary_path = Some(installed.path.clone());
+        config.setup_completed = Some(true);
+        config.save().map_err(AppError::Storage)?;
+    }
+    if let Err(e) = record_audit_event("restic.downloaded", Some(installed.version.clone())).await {
+        warn!("Failed to record restic download: {}", e);
+    }
+    Ok(installed)
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResticUpdateStatus {
+    /// None when no working restic binary was found
+    pub installed: Option<String>,
+    pub latest: String,
+    pub update_available: bool,
+    /// Whether the binary in use was downloaded by the app, so `download_restic` can replace it
+    pub managed: bool,
+}
+
+#[command]
+#[instrument]
+pub async fn check_restic_update() -> std::result::Result<ResticUpdateStatus, CommandError> {
+    let binary = find_restic_binary();
+    let installed = blocking({
+        let binary = binary.clone();
+        move || Ok(restic_download::installed_version(&binary))
+    }).await?;
+    let latest = restic_download::latest_version().await?;
+    let update_available = match installed.as_deref() {
+        Some(current) => ResticVersion::parse(current) < ResticVersion::parse(&latest),
+        None => true,
+    };
+    info!("restic installed: {:?}, latest: {}", installed, latest);
+    Ok(ResticUpdateStatus {
+        installed,
+        latest,
+        update_available,
+        managed: restic_download::is_managed(&binary),
+    })
+}
+
+/// Version of the restic binary in use and which of the features the app relies on it supports
+#[command]
+#[instrument]
+pub async fn get_restic_capabilities() -> std::result::Result<ResticCapabilities, CommandError> {
+    Ok(restic_capabilities())
+}
+
 #[command]
 #[instrument]
-pub async fn get_detected_restic_path() -> std::result::Result<String, String> {
+pub async fn get_detected_restic_path() -> std::result::Result<String, CommandError> {
     info!("Detecting restic binary path");
     let path = find_restic_binary();
     info!("Detected restic binary at: {}", path);
@@ -642,73 +6413,545 @@ pub async fn get_detected_restic_path() -> std::result::Result<String, String> {
 
 #[command]
 #[instrument]
-pub async fn mark_setup_completed() -> std::result::Result<(), String> {
+pub async fn get_language() -> std::result::Result<String, CommandError> {
+    Ok(messages::language())
+}
+
+#[command]
+#[instrument]
+pub async fn set_language(language: String) -> std::result::Result<(), CommandError> {
+    info!("Setting message language to {}", language);
+    if !messages::is_supported(&language) {
+        return Err(AppError::UnsupportedLanguage(language).into());
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.language = Some(language.clone());
+    config.save().map_err(AppError::Storage)?;
+    messages::set_language(&language);
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn mark_setup_completed() -> std::result::Result<(), CommandError> {
     info!("Marking restic setup as completed");
-    let mut config = load_config().map_err(|e| AppError::Storage(e))?;
+    let mut config = edit_config().map_err(AppError::Storage)?;
     config.setup_completed = Some(true);
-    save_config(&config).map_err(|e| AppError::Storage(e))?;
+    config.save().map_err(AppError::Storage)?;
     info!("Setup marked as completed");
     Ok(())
 }
 
+#[command]
+#[instrument]
+pub async fn create_demo_repository() -> std::result::Result<SavedRepository, CommandError> {
+    info!("Creating demo repository");
+    let data_dir = crate::storage::get_config_dir().map_err(AppError::Storage)?;
+    let repo_dir = data_dir.join(demo::DEMO_REPO_ID);
+    let source_dir = data_dir.join("demo-source");
+
+    // The demo is throwaway, so start from scratch every time
+    for dir in [&repo_dir, &source_dir] {
+        if dir.exists() {
+            std::fs::remove_dir_all(dir).map_err(AppError::Io)?;
+        }
+    }
+    blocking(|| database::clear_repo_cache(demo::DEMO_REPO_ID)).await?;
+
+    let repo = repo_dir.to_string_lossy().to_string();
+    let source = source_dir.to_string_lossy().to_string();
+    run_restic(&repo, demo::DEMO_PASSWORD, &["init"]).await?;
+
+    let now = chrono::Local::now();
+    for generation in 0..demo::DEMO_GENERATIONS {
+        demo::write_sample_files(&source_dir, generation)?;
+        let days_ago = (demo::DEMO_GENERATIONS - generation) as i64;
+        let time = (now - chrono::Duration::days(days_ago)).format("%Y-%m-%d %H:%M:%S").to_string();
+        run_restic(&repo, demo::DEMO_PASSWORD, &[
+            "backup", &source,
+            "--host", demo::DEMO_HOST,
+            "--tag", "demo",
+            "--time", &time,
+        ]).await?;
+    }
+
+    if let Err(e) = std::fs::remove_dir_all(&source_dir) {
+        warn!("Failed to remove demo source files: {}", e);
+    }
+
+    let saved = SavedRepository {
+        id: demo::DEMO_REPO_ID.to_string(),
+        name: demo::DEMO_REPO_NAME.to_string(),
+        path: repo,
+        password: demo::DEMO_PASSWORD.into(),
+        ..Default::default()
+    };
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let mut stored = saved.clone();
+    secrets::stash_password(&config, &mut stored)?;
+    config.repositories.retain(|r| r.id != stored.id);
+    config.repositories.push(stored);
+    config.save().map_err(AppError::Storage)?;
+
+    info!("Demo repository created with {} snapshots", demo::DEMO_GENERATIONS);
+    Ok(saved)
+}
+
 // ========== SQLite Database Commands ==========
 
 #[command]
 #[instrument]
-pub async fn init_database_command() -> std::result::Result<(), String> {
-    database::init_database()?;
+pub async fn init_database_command() -> std::result::Result<(), CommandError> {
+    blocking(database::init_database).await?;
     Ok(())
 }
 
 #[command]
 #[instrument]
-pub async fn load_snapshots_from_db(repo_id: String) -> std::result::Result<Vec<DbSnapshotWithStats>, String> {
+pub async fn load_snapshots_from_db(repo_id: String) -> std::result::Result<Vec<DbSnapshotWithStats>, CommandError> {
     validate_repo_id(&repo_id)?;
-    Ok(database::load_snapshots_from_db(&repo_id)?)
+    Ok(blocking(move || database::load_snapshots_from_db(&repo_id)).await?)
+}
+
+// Snapshots returned by `load_all_snapshots` when no limit is given
+const ALL_SNAPSHOTS_LIMIT: usize = 1000;
+
+/// A cached snapshot with the repository it belongs to
+#[derive(Debug, Serialize, Clone)]
+pub struct RepoSnapshot {
+    pub repo_id: String,
+    pub repo_name: String,
+    #[serde(flatten)]
+    pub snapshot: DbSnapshotWithStats,
+}
+
+/// One timeline of the cached snapshots of every saved repository, newest first.
+/// `after` and `before` take the same dates as a snapshot filter.
+#[command]
+#[instrument]
+pub async fn load_all_snapshots(
+    after: Option<String>,
+    before: Option<String>,
+    limit: Option<usize>,
+) -> std::result::Result<Vec<RepoSnapshot>, CommandError> {
+    let after = after.as_deref().map(|t| filter_time_bound(t, false)).transpose()?;
+    let before = before.as_deref().map(|t| filter_time_bound(t, true)).transpose()?;
+    let limit = limit.unwrap_or(ALL_SNAPSHOTS_LIMIT).clamp(1, 10 * ALL_SNAPSHOTS_LIMIT);
+
+    let config = load_config().map_err(AppError::Storage)?;
+    let names: HashMap<String, String> = config.repositories.into_iter()
+        .filter(|r| !r.is_deleted())
+        .map(|r| (r.id, r.name))
+        .collect();
+    let repo_ids: Vec<String> = names.keys().cloned().collect();
+
+    let snapshots = blocking(move || database::load_all_snapshots(&repo_ids, after, before, limit)).await?;
+    Ok(snapshots.into_iter()
+        .map(|(repo_id, snapshot)| RepoSnapshot {
+            repo_name: names.get(&repo_id).cloned().unwrap_or_default(),
+            repo_id,
+            snapshot,
+        })
+        .collect())
 }
 
 #[command]
 #[instrument]
-pub async fn get_cached_snapshot_ids(repo_id: String) -> std::result::Result<Vec<String>, String> {
+pub async fn get_cached_snapshot_ids(repo_id: String) -> std::result::Result<Vec<String>, CommandError> {
     validate_repo_id(&repo_id)?;
-    Ok(database::get_cached_snapshot_ids(&repo_id)?)
+    Ok(blocking(move || database::get_cached_snapshot_ids(&repo_id)).await?)
+}
+
+/// Reports a large snapshot write as the priming UI's writing stage. Large lists are
+/// written in several transactions, so reads can run between them.
+fn write_progress<'a>(app: &'a AppHandle, repo_id: &'a str, total: usize) -> impl FnMut(usize) + 'a {
+    move |written| {
+        if total > PRIMING_CHUNK {
+            window_scope::emit_repo_event(app, repo_id, "cache-priming-progress", CachePrimingProgress {
+                repo_id,
+                stage: PrimingStage::Writing,
+                done: written,
+                total,
+            });
+        }
+    }
 }
 
 #[command]
 #[instrument(skip(snapshots), fields(count = snapshots.len()))]
-pub async fn save_snapshots_batch(repo_id: String, snapshots: Vec<DbSnapshotWithStats>) -> std::result::Result<(), String> {
+pub async fn save_snapshots_batch(app: AppHandle, repo_id: String, snapshots: Vec<DbSnapshotWithStats>) -> std::result::Result<(), CommandError> {
     validate_repo_id(&repo_id)?;
-    database::save_snapshots_batch(&repo_id, &snapshots)?;
+    blocking(move || {
+        let new_snapshots = count_new_snapshots(&repo_id, snapshots.iter().map(|s| &s.snapshot.id))?;
+        database::save_snapshots_batch(&repo_id, &snapshots, write_progress(&app, &repo_id, snapshots.len()))?;
+        if new_snapshots > 0 {
+            invalidate_repo_aggregates(&app, &repo_id, new_snapshots, 0);
+        }
+        Ok(())
+    }).await?;
     Ok(())
 }
 
 #[command]
 #[instrument(skip(snapshots), fields(count = snapshots.len()))]
-pub async fn save_snapshots_metadata_only(repo_id: String, snapshots: Vec<Snapshot>) -> std::result::Result<(), String> {
+pub async fn save_snapshots_metadata_only(app: AppHandle, repo_id: String, snapshots: Vec<Snapshot>) -> std::result::Result<(), CommandError> {
+    validate_repo_id(&repo_id)?;
+    blocking(move || {
+        let new_snapshots = count_new_snapshots(&repo_id, snapshots.iter().map(|s| &s.id))?;
+        database::save_snapshots_metadata_only(&repo_id, &snapshots, write_progress(&app, &repo_id, snapshots.len()))?;
+        if new_snapshots > 0 {
+            invalidate_repo_aggregates(&app, &repo_id, new_snapshots, 0);
+        }
+        Ok(())
+    }).await?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn update_last_delta_check(repo_id: String) -> std::result::Result<(), CommandError> {
+    validate_repo_id(&repo_id)?;
+    blocking(move || database::update_last_delta_check(&repo_id)).await?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_repo_meta(repo_id: String) -> std::result::Result<RepoMeta, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || database::get_repo_meta(&repo_id)).await?)
+}
+
+#[command]
+#[instrument]
+pub async fn clear_repo_cache(repo_id: String, backup: Option<bool>) -> std::result::Result<Option<CacheBackup>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || {
+        // Backed up by default so a slow remote repository doesn't need a full rescan after a mistake
+        let saved = match backup.unwrap_or(true) {
+            true => cache_backup::create(&repo_id)?,
+            false => None,
+        };
+        database::clear_repo_cache(&repo_id)?;
+        Ok(saved)
+    }).await?)
+}
+
+#[command]
+#[instrument]
+pub async fn list_cache_backups(repo_id: String) -> std::result::Result<Vec<CacheBackup>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || cache_backup::list(&repo_id)).await?)
+}
+
+/// Puts back the cache of a repository from its newest backup; returns the number of snapshots restored
+#[command]
+#[instrument(skip(app))]
+pub async fn restore_cache_backup(app: AppHandle, repo_id: String) -> std::result::Result<usize, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let restored = blocking(move || {
+        let restored = cache_backup::restore(&repo_id)?;
+        repo_status::refresh(&app, &repo_id);
+        Ok(restored)
+    }).await?;
+    Ok(restored)
+}
+
+#[command]
+pub async fn get_experiments() -> std::result::Result<Vec<ExperimentState>, CommandError> {
+    Ok(experiments::states(&load_config().map_err(AppError::Storage)?))
+}
+
+/// Switches an experimental feature on or off for this user
+#[command]
+#[instrument]
+pub async fn set_experiment(name: String, enabled: bool) -> std::result::Result<ExperimentState, CommandError> {
+    let experiment = Experiment::parse(&name)?;
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    // Matching the default is left unset, so a changed default reaches users who never touched it
+    if enabled == experiment.enabled_by_default() {
+        config.experiments.remove(experiment.name());
+    } else {
+        config.experiments.insert(experiment.name().to_string(), enabled);
+    }
+    let state = experiments::state(&config, experiment);
+    config.save().map_err(AppError::Storage)?;
+    info!("Experiment {} is now {}", name, if enabled { "on" } else { "off" });
+    Ok(state)
+}
+
+#[command]
+#[instrument]
+pub async fn get_notification_settings() -> std::result::Result<NotificationSettings, CommandError> {
+    Ok(load_config().map_err(AppError::Storage)?.notifications)
+}
+
+#[command]
+#[instrument]
+pub async fn set_notification_settings(settings: NotificationSettings) -> std::result::Result<(), CommandError> {
+    info!("Updating notification settings");
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.notifications = settings;
+    config.save().map_err(AppError::Storage)?;
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn reset_notification_cooldowns(repo_id: Option<String>) -> std::result::Result<(), CommandError> {
+    if let Some(id) = &repo_id {
+        validate_repo_id(id)?;
+    }
+    notifications::reset_cooldowns(repo_id.as_deref());
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_slow_queries() -> std::result::Result<SlowQueryReport, CommandError> {
+    let slow_queries = blocking(|| {
+        let mut slow_queries = query_log::slow_queries();
+        for query in &mut slow_queries {
+            query.plan = database::explain_query_plan(&query.sql).unwrap_or_else(|e| {
+                debug!("No query plan for {}: {}", query.sql, e);
+                Vec::new()
+            });
+        }
+        Ok(slow_queries)
+    }).await?;
+    Ok(SlowQueryReport {
+        threshold_ms: query_log::threshold_ms(),
+        slow_queries,
+        latencies: query_log::latencies(),
+        connection_waits: query_log::connection_waits(),
+    })
+}
+
+#[command]
+#[instrument]
+pub async fn set_slow_query_threshold(threshold_ms: u64) -> std::result::Result<(), CommandError> {
+    info!("Setting slow query threshold to {} ms", threshold_ms);
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.slow_query_threshold_ms = Some(threshold_ms);
+    config.save().map_err(AppError::Storage)?;
+    query_log::set_threshold_ms(threshold_ms);
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_max_concurrent_restic() -> std::result::Result<usize, CommandError> {
+    Ok(limiter::max_per_repo())
+}
+
+#[command]
+#[instrument]
+pub async fn set_max_concurrent_restic(limit: usize) -> std::result::Result<(), CommandError> {
+    let limit = limit.max(1);
+    info!("Allowing {} concurrent restic processes per repository", limit);
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.max_concurrent_restic = Some(limit);
+    config.save().map_err(AppError::Storage)?;
+    limiter::set_max_per_repo(limit);
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_verbosity_settings() -> std::result::Result<VerbositySettings, CommandError> {
+    Ok(verbosity::current())
+}
+
+/// Applies to restic commands started afterwards
+#[command]
+#[instrument]
+pub async fn set_verbosity_settings(settings: VerbositySettings) -> std::result::Result<(), CommandError> {
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.verbosity = Some(settings);
+    config.save().map_err(AppError::Storage)?;
+    verbosity::set(settings);
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn clear_slow_queries() -> std::result::Result<(), CommandError> {
+    query_log::clear();
+    Ok(())
+}
+
+#[command]
+#[instrument]
+pub async fn get_snapshot_pins(repo_id: String) -> std::result::Result<Vec<SnapshotPin>, CommandError> {
+    validate_repo_id(&repo_id)?;
+    Ok(blocking(move || database::get_snapshot_pins(&repo_id)).await?)
+}
+
+#[command]
+#[instrument]
+pub async fn pin_snapshot(repo_id: String, snapshot_id: String, pinned: bool) -> std::result::Result<(), CommandError> {
     validate_repo_id(&repo_id)?;
-    database::save_snapshots_metadata_only(&repo_id, &snapshots)?;
+    validate_snapshot_id(&snapshot_id)?;
+    blocking(move || database::set_snapshot_pinned(&repo_id, &snapshot_id, pinned)).await?;
     Ok(())
 }
 
 #[command]
 #[instrument]
-pub async fn update_last_delta_check(repo_id: String) -> std::result::Result<(), String> {
+pub async fn set_pinned_snapshots(repo_id: String, snapshot_ids: Vec<String>) -> std::result::Result<(), CommandError> {
     validate_repo_id(&repo_id)?;
-    database::update_last_delta_check(&repo_id)?;
+    for snapshot_id in &snapshot_ids {
+        validate_snapshot_id(snapshot_id)?;
+    }
+    let mut seen = HashSet::new();
+    let snapshot_ids: Vec<String> = snapshot_ids.into_iter().filter(|id| seen.insert(id.clone())).collect();
+    blocking(move || database::set_pinned_order(&repo_id, &snapshot_ids)).await?;
     Ok(())
 }
 
 #[command]
 #[instrument]
-pub async fn get_repo_meta(repo_id: String) -> std::result::Result<RepoMeta, String> {
+pub async fn get_snapshot_anomalies(
+    repo_id: String,
+    threshold_percent: Option<f64>,
+    window: Option<usize>,
+) -> std::result::Result<Vec<SnapshotAnomaly>, CommandError> {
     validate_repo_id(&repo_id)?;
-    Ok(database::get_repo_meta(&repo_id)?)
+    let id = repo_id.clone();
+    let snapshots = blocking(move || database::load_snapshots_from_db(&id)).await?;
+    let anomalies = anomalies::detect_anomalies(
+        &snapshots,
+        threshold_percent.unwrap_or(anomalies::DEFAULT_THRESHOLD_PERCENT),
+        window.unwrap_or(anomalies::DEFAULT_WINDOW).max(1),
+    );
+    info!("Found {} snapshot anomalies for repo {}", anomalies.len(), repo_id);
+    Ok(anomalies)
 }
 
 #[command]
 #[instrument]
-pub async fn clear_repo_cache(repo_id: String) -> std::result::Result<(), String> {
+pub async fn get_cached_stats(repo_id: String, snapshot_ids: Vec<String>) -> std::result::Result<Vec<CachedStats>, CommandError> {
     validate_repo_id(&repo_id)?;
-    database::clear_repo_cache(&repo_id)?;
+    for snapshot_id in &snapshot_ids {
+        validate_snapshot_id(snapshot_id)?;
+    }
+    Ok(blocking(move || database::get_cached_stats(&repo_id, &snapshot_ids)).await?)
+}
+
+// ========== Batched Read Commands ==========
+
+/// Cheap, read-only requests that can be answered together in one IPC round trip
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", content = "args", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BatchRequest {
+    LoadRepositories,
+    GetResticBinaryPath,
+    GetLanguage,
+    GetBackupHealth,
+    GetRepoMeta { repo_id: String },
+    GetCachedSnapshotIds { repo_id: String },
+    LoadSnapshotsFromDb { repo_id: String },
+    GetCachedStats { repo_id: String, snapshot_ids: Vec<String> },
+    GetSnapshotAnomalies { repo_id: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CommandError>,
+}
+
+impl BatchResponse {
+    fn from_result<T: Serialize>(result: std::result::Result<T, CommandError>) -> Self {
+        match result.and_then(|data| serde_json::to_value(data).map_err(|e| AppError::Json(e).into())) {
+            Ok(data) => BatchResponse { ok: true, data: Some(data), error: None },
+            Err(error) => BatchResponse { ok: false, data: None, error: Some(error) },
+        }
+    }
+}
+
+#[command]
+#[instrument(skip(requests), fields(count = requests.len()))]
+pub async fn batch_invoke(app: AppHandle, requests: Vec<BatchRequest>) -> std::result::Result<Vec<BatchResponse>, CommandError> {
+    debug!("Handling batch of {} requests", requests.len());
+
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        let response = match request {
+            BatchRequest::LoadRepositories => BatchResponse::from_result(load_repositories().await),
+            BatchRequest::GetResticBinaryPath => BatchResponse::from_result(get_restic_binary_path().await),
+            BatchRequest::GetLanguage => BatchResponse::from_result(get_language().await),
+            BatchRequest::GetBackupHealth => BatchResponse::from_result(get_backup_health().await),
+            BatchRequest::GetRepoMeta { repo_id } => BatchResponse::from_result(get_repo_meta(repo_id).await),
+            BatchRequest::GetCachedSnapshotIds { repo_id } => BatchResponse::from_result(get_cached_snapshot_ids(repo_id).await),
+            BatchRequest::LoadSnapshotsFromDb { repo_id } => BatchResponse::from_result(load_snapshots_from_db(repo_id).await),
+            BatchRequest::GetCachedStats { repo_id, snapshot_ids } => BatchResponse::from_result(get_cached_stats(repo_id, snapshot_ids).await),
+            BatchRequest::GetSnapshotAnomalies { repo_id } => BatchResponse::from_result(get_snapshot_anomalies(repo_id, None, None).await),
+        };
+        responses.push(response);
+    }
+
+    Ok(responses)
+}
+
+// ========== Window Scope Commands ==========
+
+#[command]
+#[instrument(skip(window))]
+pub async fn bind_window_repository(window: WebviewWindow, repo_id: Option<String>) -> std::result::Result<(), CommandError> {
+    match repo_id {
+        Some(repo_id) => {
+            validate_repo_id(&repo_id)?;
+            window_scope::bind(window.label(), &repo_id);
+        }
+        None => window_scope::unbind(window.label()),
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[command]
+#[instrument(skip(window))]
+pub async fn get_window_repository(window: WebviewWindow) -> std::result::Result<Option<String>, CommandError> {
+    Ok(window_scope::repo_for_window(window.label()))
+}
+
+#[command]
+#[instrument(skip(app))]
+pub async fn open_repository_window(app: AppHandle, repo_id: String) -> std::result::Result<String, CommandError> {
+    validate_repo_id(&repo_id)?;
+    let config = load_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter()
+        .find(|r| r.id == repo_id && !r.is_deleted())
+        .ok_or_else(|| AppError::RepositoryNotFound(repo_id.clone()))?;
+
+    let label = window_scope::repository_window_label(&repo_id);
+    if let Some(existing) = app.get_webview_window(&label) {
+        debug!("Focusing existing window for repository {}", repo_id);
+        existing.set_focus().map_err(|e| AppError::WindowOperation(e.to_string()))?;
+        return Ok(label);
+    }
+
+    info!("Opening window for repository {}", repo_id);
+    let url = WebviewUrl::App(format!("index.html?repo={}", repo_id).into());
+    WebviewWindowBuilder::new(&app, &label, url)
+        .title(format!("Restic Restore - {}", repo.name))
+        .inner_size(1200.0, 800.0)
+        .min_inner_size(1000.0, 700.0)
+        .build()
+        .map_err(|e| AppError::WindowOperation(e.to_string()))?;
+
+    window_scope::bind(&label, &repo_id);
+    Ok(label)
+}
+
+#[cfg(test)]
+mod fixture_tests;
+#[cfg(test)]
+mod path_tests;
+#[cfg(test)]
+mod preview_tests;
@@ -0,0 +1,274 @@
+//! Runs the parsers against output captured from real restic versions, kept under
+//! `tests/fixtures/restic/<version>/`. Adding a version is a matter of dropping in
+//! its files; every fixture kind must exist for at least one version.
+
+use super::*;
+use std::fs;
+
+const FIXTURE_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/restic");
+
+const FIXTURE_KINDS: &[&str] = &[
+    "snapshots.json",
+    "ls.ndjson",
+    "stats.json",
+    "stats_raw_data.json",
+    "restore.ndjson",
+    "restore_stderr.txt",
+    "forget.json",
+    "find.json",
+    "key_list.json",
+    "cat_config.json",
+    "cat_lock.json",
+    "diff.ndjson",
+    "check.txt",
+];
+
+fn versions() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(FIXTURE_ROOT)
+        .expect("fixture directory is missing")
+        .map(|entry| entry.expect("unreadable fixture directory").path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    assert!(!dirs.is_empty(), "no restic versions under {}", FIXTURE_ROOT);
+    dirs
+}
+
+fn version_name(dir: &Path) -> String {
+    dir.file_name().unwrap().to_string_lossy().to_string()
+}
+
+/// Calls `check` with the fixture's contents for every version that has it
+fn for_each_fixture(name: &str, mut check: impl FnMut(&str, &str)) {
+    for dir in versions() {
+        let path = dir.join(name);
+        if path.exists() {
+            let contents = fs::read_to_string(&path).unwrap();
+            check(&version_name(&dir), &contents);
+        }
+    }
+}
+
+fn ndjson(contents: &str) -> impl Iterator<Item = Value> + '_ {
+    contents.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("invalid JSON line {:?}: {}", line, e)))
+}
+
+fn is_hex_id(id: &str) -> bool {
+    id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[test]
+fn every_fixture_kind_is_covered() {
+    for kind in FIXTURE_KINDS {
+        assert!(
+            versions().iter().any(|dir| dir.join(kind).exists()),
+            "no restic version has a {} fixture",
+            kind
+        );
+    }
+}
+
+#[test]
+fn parses_snapshots() {
+    for_each_fixture("snapshots.json", |version, contents| {
+        let snapshots: Vec<Snapshot> = serde_json::from_str(contents)
+            .unwrap_or_else(|e| panic!("{}: {}", version, e));
+        assert!(!snapshots.is_empty(), "{}", version);
+        for snapshot in &snapshots {
+            assert!(is_hex_id(&snapshot.id), "{}: bad id {}", version, snapshot.id);
+            assert!(snapshot.id.starts_with(&snapshot.short_id), "{}: {}", version, snapshot.short_id);
+            assert!(chrono::DateTime::parse_from_rfc3339(&snapshot.time).is_ok(), "{}: {}", version, snapshot.time);
+            // restic started recording the client and a backup summary in 0.17
+            let recorded = version >= "0.17";
+            assert_eq!(snapshot.program_version.is_some(), recorded, "{}: {}", version, snapshot.id);
+            assert_eq!(snapshot.summary.is_some(), recorded, "{}: {}", version, snapshot.id);
+        }
+        if let Some(summary) = snapshots.iter().find_map(|s| s.summary.as_ref()) {
+            assert!(summary.total_files_processed > 0, "{}", version);
+            assert!(summary.backup_start.is_some() && summary.backup_end.is_some(), "{}", version);
+        }
+    });
+}
+
+#[test]
+fn parses_ls_nodes() {
+    for_each_fixture("ls.ndjson", |version, contents| {
+        let lines: Vec<Value> = ndjson(contents).collect();
+        let nodes: Vec<FileNode> = lines.iter().cloned().filter_map(ls_node).collect();
+        // Everything except the leading snapshot line is a node
+        assert_eq!(nodes.len(), lines.len() - 1, "{}", version);
+        assert!(nodes.iter().any(|n| n.node_type == "dir"), "{}", version);
+        assert!(nodes.iter().any(|n| n.node_type == "file" && n.size.is_some()), "{}", version);
+        assert!(nodes.iter().all(|n| n.path.ends_with(&n.name)), "{}", version);
+        assert!(nodes.iter().all(|n| n.uid.is_some() && n.gid.is_some() && n.mode.is_some()), "{}", version);
+    });
+}
+
+#[test]
+fn derives_permissions_from_mode() {
+    for_each_fixture("ls.ndjson", |version, contents| {
+        for node in ndjson(contents).filter_map(ls_node) {
+            let Some(printed) = node.permissions.clone() else { continue };
+            let mut derived = FileNode { permissions: None, ..node };
+            derived.fill_permissions();
+            assert_eq!(derived.permissions, Some(printed), "{} {}", version, derived.path);
+        }
+    });
+}
+
+#[test]
+fn parses_stats() {
+    for name in ["stats.json", "stats_raw_data.json"] {
+        for_each_fixture(name, |version, contents| {
+            let stats: Value = serde_json::from_str(contents).unwrap();
+            assert!(stats.get("total_size").and_then(Value::as_u64).is_some(), "{} {}", version, name);
+        });
+    }
+}
+
+#[test]
+fn parses_restore_progress() {
+    for_each_fixture("restore.ndjson", |version, contents| {
+        let progress: Vec<RestoreProgress> = contents.lines()
+            .filter_map(|line| RestoreProgress::from_line(line, "snapshot", "/restore"))
+            .collect();
+        assert!(progress.len() >= 2, "{}", version);
+        let (last, updates) = progress.split_last().unwrap();
+        assert!(last.done, "{}", version);
+        assert_eq!(last.percent_done, 100.0, "{}", version);
+        assert!(updates.iter().all(|p| !p.done && p.percent_done <= 100.0), "{}", version);
+
+        // Before 0.17 restore errors only went to stderr, as plain text
+        let error_lines = ndjson(contents).filter(|v| v["message_type"] == "error").count();
+        let errors = parse_restore_errors(contents);
+        assert_eq!(errors.len(), error_lines, "{}", version);
+        assert!(errors.iter().all(|e| e.path.starts_with('/')), "{}", version);
+    });
+}
+
+#[test]
+fn parses_restore_errors_from_stderr() {
+    for_each_fixture("restore_stderr.txt", |version, contents| {
+        let errors = parse_restore_errors(contents);
+        assert_eq!(errors.len(), 2, "{}", version);
+        assert!(matches!(errors[0].error_kind, RestoreErrorKind::Permissions), "{}", version);
+        assert!(matches!(errors[1].error_kind, RestoreErrorKind::DiskFull), "{}", version);
+    });
+}
+
+#[test]
+fn parses_forget_groups() {
+    for_each_fixture("forget.json", |version, contents| {
+        let groups: Vec<ForgetGroup> = serde_json::from_str(contents)
+            .unwrap_or_else(|e| panic!("{}: {}", version, e));
+        assert!(groups.iter().any(|g| g.keep.as_ref().is_some_and(|k| !k.is_empty())), "{}", version);
+
+        let report = retention_report(groups);
+        assert!(report.kept.iter().all(|k| !k.reasons.is_empty()), "{}: kept snapshot without a reason", version);
+        let listed: usize = report.by_reason.iter().map(|r| r.snapshot_ids.len()).sum();
+        assert_eq!(listed, report.kept.iter().map(|k| k.reasons.len()).sum::<usize>(), "{}", version);
+    });
+}
+
+#[test]
+fn parses_find_matches() {
+    for_each_fixture("find.json", |version, contents| {
+        let groups: Vec<FindSnapshotMatches> = serde_json::from_str(contents)
+            .unwrap_or_else(|e| panic!("{}: {}", version, e));
+        assert!(!groups.is_empty(), "{}", version);
+        for group in &groups {
+            assert!(is_hex_id(&group.snapshot), "{}", version);
+            assert!(group.matches.iter().all(|m| !m.path.is_empty() && !m.node_type.is_empty()), "{}", version);
+        }
+    });
+}
+
+#[test]
+fn parses_keys() {
+    for_each_fixture("key_list.json", |version, contents| {
+        let keys: Vec<RepoKey> = serde_json::from_str(contents)
+            .unwrap_or_else(|e| panic!("{}: {}", version, e));
+        assert_eq!(keys.iter().filter(|k| k.current).count(), 1, "{}", version);
+    });
+}
+
+#[test]
+fn parses_repository_config() {
+    for_each_fixture("cat_config.json", |version, contents| {
+        let config: Value = serde_json::from_str(contents).unwrap();
+        assert!(config.get("id").and_then(Value::as_str).is_some_and(is_hex_id), "{}", version);
+    });
+}
+
+#[test]
+fn parses_locks() {
+    for_each_fixture("cat_lock.json", |version, contents| {
+        let lock: RepoLock = serde_json::from_str(contents)
+            .unwrap_or_else(|e| panic!("{}: {}", version, e));
+        assert!(chrono::DateTime::parse_from_rfc3339(&lock.time).is_ok(), "{}: {}", version, lock.time);
+        assert!(lock.pid > 0, "{}", version);
+    });
+}
+
+#[test]
+fn parses_diff() {
+    for_each_fixture("diff.ndjson", |version, contents| {
+        let mut entries = Vec::new();
+        let mut bytes_added = None;
+        for value in ndjson(contents) {
+            match value.get("message_type").and_then(Value::as_str) {
+                Some("change") => entries.push(DiffEntry::from_change(
+                    value["path"].as_str().unwrap(),
+                    value["modifier"].as_str().unwrap(),
+                )),
+                Some("statistics") => bytes_added = value.pointer("/added/bytes").and_then(Value::as_u64),
+                _ => {}
+            }
+        }
+        for kind in [DiffKind::Added, DiffKind::Removed, DiffKind::Modified] {
+            assert!(entries.iter().any(|e| e.kind == kind), "{}: no {:?} entry", version, kind);
+        }
+        assert!(entries.iter().any(|e| e.is_dir), "{}", version);
+        assert!(bytes_added.is_some(), "{}", version);
+    });
+}
+
+// Captured with RESTIC_PROGRESS_FPS set, as `check_repository` runs it
+#[test]
+fn parses_check_progress() {
+    for_each_fixture("check.txt", |version, contents| {
+        for unit in ["snapshots", "packs"] {
+            let counters: Vec<(u64, u64)> = contents.lines().filter_map(|line| parse_check_counter(line, unit)).collect();
+            assert!(counters.len() >= 2, "{}: no {} progress", version, unit);
+            let (last, updates) = counters.split_last().unwrap();
+            assert_eq!(last.0, last.1, "{}: {} didn't finish", version, unit);
+            assert!(updates.iter().all(|(done, total)| done < total && total == &last.1), "{}", version);
+        }
+        assert!(contents.lines().all(|line| !line.contains("index files") || parse_check_counter(line, "snapshots").is_none()));
+    });
+}
+
+// Error fixtures are named error.<expected code>.<exit code>.txt
+#[test]
+fn classifies_errors() {
+    let mut checked = 0;
+    for dir in versions() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let Some(parts) = name.strip_prefix("error.").and_then(|n| n.strip_suffix(".txt")) else { continue };
+            let (expected, exit) = parts.rsplit_once('.').expect("error fixture without an exit code");
+            let exit: i32 = exit.parse().expect("error fixture with a non-numeric exit code");
+
+            let stderr = fs::read_to_string(&path).unwrap();
+            let code = restic_errors::classify(Some(exit), &stderr)
+                .map(|e| e.code())
+                .unwrap_or("restic_error");
+            assert_eq!(code, expected, "{}/{}", version_name(&dir), name);
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+}
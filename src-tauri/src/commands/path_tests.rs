@@ -0,0 +1,97 @@
+//! The Windows path forms the validators see: drive, UNC and verbatim paths,
+//! mixed separators and paths past MAX_PATH.
+
+use super::*;
+
+#[test]
+fn repository_paths_accept_windows_forms() {
+    let accepted = [
+        r"C:\backups\repo",
+        r"d:\backups\repo",
+        "E:/backups/repo",
+        r"\\nas\backups\repo",
+        "//nas/backups/repo",
+        r"\\?\C:\backups\repo",
+        r"\\?\UNC\nas\backups\repo",
+        "/srv/backups/repo",
+        "sftp:user@host:/srv/repo",
+        "s3:s3.amazonaws.com/bucket",
+        "rest:https://host:8000/",
+    ];
+    for repo in accepted {
+        assert!(validate_repository_path(repo).is_ok(), "{} was rejected", repo);
+    }
+}
+
+#[test]
+fn repository_paths_reject_traversal_with_either_separator() {
+    for repo in [r"\\nas\backups\..\other", r"C:\backups\..\other", "/srv/../etc", r"\\?\C:\backups\..\x"] {
+        assert!(matches!(validate_repository_path(repo), Err(AppError::PathTraversal)), "{} was accepted", repo);
+    }
+}
+
+#[test]
+fn repository_paths_still_reject_unknown_backends() {
+    assert!(matches!(validate_repository_path("ftp://host/repo"), Err(AppError::UnsupportedProtocol(_))));
+    assert!(matches!(validate_repository_path("s3:"), Err(AppError::RemotePathTooShort)));
+}
+
+#[test]
+fn recognises_windows_paths() {
+    for path in [r"C:\data", "c:", r"\\server\share", "//server/share", r"\\?\C:\data", r"\\.\COM1"] {
+        assert!(win_paths::is_windows_path(path), "{} not recognised", path);
+    }
+    for path in ["/home/user", "s3:bucket", "rest:http://host/", "relative/dir", ""] {
+        assert!(!win_paths::is_windows_path(path), "{} taken for a Windows path", path);
+    }
+}
+
+#[test]
+fn normalizes_separators_of_windows_paths_only() {
+    assert_eq!(win_paths::normalize_separators("C:/Users/me\\docs"), r"C:\Users\me\docs");
+    assert_eq!(win_paths::normalize_separators("//server/share/dir"), r"\\server\share\dir");
+    assert_eq!(win_paths::normalize_separators("/home/user/docs"), "/home/user/docs");
+}
+
+#[test]
+fn long_paths_get_the_verbatim_prefix() {
+    let tail = "d".repeat(win_paths::MAX_PATH);
+    assert_eq!(win_paths::long_path_form(&format!(r"C:\{}", tail)), format!(r"\\?\C:\{}", tail));
+    assert_eq!(win_paths::long_path_form(&format!(r"\\server\share\{}", tail)), format!(r"\\?\UNC\server\share\{}", tail));
+
+    // Already verbatim, a device, relative, or short enough
+    let verbatim = format!(r"\\?\C:\{}", tail);
+    assert_eq!(win_paths::long_path_form(&verbatim), verbatim);
+    let device = format!(r"\\.\{}", tail);
+    assert_eq!(win_paths::long_path_form(&device), device);
+    assert_eq!(win_paths::long_path_form(&tail), tail);
+    assert_eq!(win_paths::long_path_form(r"C:\short"), r"C:\short");
+}
+
+#[test]
+fn target_paths_reject_traversal_with_either_separator() {
+    assert!(matches!(validate_target_syntax(r"/restore\..\etc"), Err(AppError::PathTraversal)));
+    assert!(matches!(validate_target_syntax("/restore/../etc"), Err(AppError::PathTraversal)));
+}
+
+#[cfg(windows)]
+#[test]
+fn target_paths_accept_windows_forms() {
+    for target in [r"C:\restore", "C:/restore/here", r"\\nas\share\restore", "//nas/share/restore", r"\\?\C:\restore", r"\\?\UNC\nas\share\restore"] {
+        assert!(validate_target_syntax(target).is_ok(), "{} was rejected", target);
+    }
+    assert!(matches!(validate_target_syntax(r"C:restore"), Err(AppError::RelativeTargetPath)));
+    assert!(matches!(validate_target_syntax(r"\\.\COM1"), Err(AppError::InvalidTargetPath)));
+}
+
+#[cfg(windows)]
+#[test]
+fn long_targets_are_made_verbatim() {
+    let long = format!(r"C:\{}", "d".repeat(win_paths::MAX_PATH));
+    let path = validate_target_syntax(&long).unwrap();
+    assert_eq!(path, PathBuf::from(format!(r"\\?\{}", long)));
+
+    let unc = format!("//nas/share/{}", "d".repeat(win_paths::MAX_PATH));
+    let path = validate_target_syntax(&unc).unwrap();
+    assert!(path.to_string_lossy().starts_with(r"\\?\UNC\nas\share\"));
+}
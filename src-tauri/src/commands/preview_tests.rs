@@ -0,0 +1,67 @@
+//! The command preview has to show the argv the runners really start restic with.
+
+use super::*;
+use crate::policies::RepositoryPolicy;
+use crate::storage::{SftpOptions, TlsOptions};
+use std::process::Command;
+
+const REPO: &str = "sftp:backup@nas.local:/srv/restic";
+
+fn config() -> AppConfig {
+    AppConfig {
+        repositories: vec![SavedRepository {
+            id: "repo-1".to_string(),
+            name: "NAS".to_string(),
+            path: REPO.to_string(),
+            sftp: Some(SftpOptions { port: Some(2222), ..Default::default() }),
+            tls: Some(TlsOptions { cacert: Some("/etc/ssl/nas.pem".to_string()), insecure_tls: true }),
+            policy: Some(RepositoryPolicy {
+                limit_upload_kib: Some(512),
+                limit_download_kib: Some(2048),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// The argv a runner builds: repository flags, then the command's own run arguments
+fn spawned_argv(config: &AppConfig, run_args: &[&str]) -> Vec<String> {
+    let mut cmd = Command::new("restic");
+    cmd.args(repository_args(config, REPO)).args(run_args);
+    cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn restore_preview_matches_the_spawned_command() {
+    let config = config();
+    let include_paths = vec!["/home/alice/documents".to_string()];
+    let operation = ResticOperation::Restore {
+        snapshot_id: "4bba301e".to_string(),
+        target: "/tmp/restore".to_string(),
+        include_paths: include_paths.clone(),
+        elevate: false,
+        sparse: false,
+    };
+
+    let restore = restore_args("4bba301e", "/tmp/restore", &include_paths, &RestoreOptions::default()).unwrap();
+    let expected = spawned_argv(&config, &restore_run_args(&restic_args::as_strs(&restore), false));
+    let preview = preview_args(&config, REPO, &operation).unwrap();
+    assert_eq!(preview, expected);
+
+    for flag in ["--limit-upload", "--limit-download", "-o", "--cacert", "--insecure-tls", "--include"] {
+        assert!(preview.iter().any(|arg| arg == flag), "{flag} missing from {preview:?}");
+    }
+    assert!(preview.iter().any(|arg| arg.starts_with("sftp.args=") && arg.contains("2222")));
+}
+
+#[test]
+fn check_preview_matches_the_spawned_command() {
+    let config = config();
+    let operation = ResticOperation::Check { read_data_subset: Some(10.0) };
+
+    let check = restic_args::check(Some(10.0));
+    let expected = spawned_argv(&config, &verbosity::with_flags(&restic_args::as_strs(&check), OperationKind::Maintenance));
+    assert_eq!(preview_args(&config, REPO, &operation).unwrap(), expected);
+}
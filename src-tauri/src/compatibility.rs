@@ -0,0 +1,112 @@
+use crate::error::{AppError, Result};
+use crate::restic_version::{Feature, ResticCapabilities};
+use serde::{Deserialize, Serialize};
+
+/// Newest repository format the app knows about
+const LATEST_REPOSITORY_VERSION: u32 = 2;
+
+/// Operations that change a repository's data or format
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceOp {
+    Prune,
+    Rewrite,
+    Migrate,
+}
+
+impl MaintenanceOp {
+    const ALL: [MaintenanceOp; 3] = [MaintenanceOp::Prune, MaintenanceOp::Rewrite, MaintenanceOp::Migrate];
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BlockedOperation {
+    pub operation: MaintenanceOp,
+    pub reason: String,
+}
+
+/// A migration `restic migrate` offers for the repository
+#[derive(Debug, Serialize, Clone)]
+pub struct Migration {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CompatibilityReport {
+    pub restic_version: Option<String>,
+    /// The `version` field of the repository config
+    pub repository_version: Option<u32>,
+    pub supports_compression: bool,
+    /// The repository uses a format the installed restic can't handle
+    pub restic_upgrade_required: bool,
+    pub migrations: Vec<Migration>,
+    /// Operations the app refuses to run, with the reason shown to the user
+    pub blocked: Vec<BlockedOperation>,
+}
+
+impl CompatibilityReport {
+    pub fn new(capabilities: &ResticCapabilities, repository_version: Option<u32>, migrations: Vec<Migration>) -> Self {
+        let too_new = repository_version.is_some_and(|v| {
+            v > LATEST_REPOSITORY_VERSION || (v >= 2 && !capabilities.supports(Feature::RepositoryV2))
+        });
+
+        let blocked = MaintenanceOp::ALL.into_iter()
+            .filter_map(|operation| {
+                let reason = if too_new {
+                    format!("repository format version {} needs a newer restic", repository_version.unwrap_or_default())
+                } else {
+                    match operation {
+                        MaintenanceOp::Prune => return None,
+                        MaintenanceOp::Rewrite if !capabilities.supports(Feature::Rewrite) => {
+                            format!("rewrite needs restic {} or newer", Feature::Rewrite.min_version())
+                        }
+                        MaintenanceOp::Rewrite => return None,
+                        MaintenanceOp::Migrate if migrations.is_empty() => "no migrations are available".to_string(),
+                        MaintenanceOp::Migrate => return None,
+                    }
+                };
+                Some(BlockedOperation { operation, reason })
+            })
+            .collect();
+
+        CompatibilityReport {
+            restic_version: capabilities.version.map(|v| v.to_string()),
+            repository_version,
+            supports_compression: repository_version.is_some_and(|v| v >= 2)
+                && capabilities.supports(Feature::RepositoryV2),
+            restic_upgrade_required: too_new,
+            migrations,
+            blocked,
+        }
+    }
+
+    pub fn require(&self, operation: MaintenanceOp) -> Result<()> {
+        match self.blocked.iter().find(|b| b.operation == operation) {
+            Some(blocked) => Err(AppError::MaintenanceBlocked(blocked.reason.clone())),
+            None => Ok(()),
+        }
+    }
+
+    pub fn require_migration(&self, name: &str) -> Result<()> {
+        self.require(MaintenanceOp::Migrate)?;
+        if !self.migrations.iter().any(|m| m.name == name) {
+            return Err(AppError::UnknownMigration(name.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Parses the list `restic migrate` prints when run without a migration:
+/// a header line, then one `name<TAB>description` line per applicable migration
+pub fn parse_migrations(output: &str) -> Vec<Migration> {
+    output.lines()
+        .filter_map(|line| {
+            let (name, description) = line.trim().split_once('\t')?;
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return None;
+            }
+            Some(Migration { name: name.to_string(), description: description.trim().to_string() })
+        })
+        .collect()
+}
@@ -0,0 +1,119 @@
+use crate::error::{AppError, Result};
+use crate::models::FileNode;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+// Hard caps that caller-supplied limits can't exceed
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_FILES: usize = 2_000;
+const MAX_MATCHES: usize = 1_000;
+const MAX_TOTAL_BYTES: u64 = 200 * 1024 * 1024;
+const MAX_SNIPPET_CHARS: usize = 200;
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContentSearchLimits {
+    pub max_file_size: u64,
+    pub max_files: usize,
+    pub max_matches: usize,
+    pub max_total_bytes: u64,
+    pub max_snippets_per_file: usize,
+    pub case_insensitive: bool,
+    /// Only search files with these extensions (without the dot); all files when empty
+    pub extensions: Vec<String>,
+}
+
+impl Default for ContentSearchLimits {
+    fn default() -> Self {
+        ContentSearchLimits {
+            max_file_size: 1024 * 1024,
+            max_files: 200,
+            max_matches: 100,
+            max_total_bytes: 50 * 1024 * 1024,
+            max_snippets_per_file: 5,
+            case_insensitive: true,
+            extensions: Vec::new(),
+        }
+    }
+}
+
+impl ContentSearchLimits {
+    pub fn clamped(mut self) -> Self {
+        self.max_file_size = self.max_file_size.min(MAX_FILE_SIZE);
+        self.max_files = self.max_files.clamp(1, MAX_FILES);
+        self.max_matches = self.max_matches.clamp(1, MAX_MATCHES);
+        self.max_total_bytes = self.max_total_bytes.min(MAX_TOTAL_BYTES);
+        self.max_snippets_per_file = self.max_snippets_per_file.max(1);
+        self.extensions = self.extensions.iter()
+            .map(|e| e.trim_start_matches('.').to_lowercase())
+            .collect();
+        self
+    }
+
+    pub fn accepts(&self, node: &FileNode) -> bool {
+        if node.node_type != "file" {
+            return false;
+        }
+        if node.size.unwrap_or(0) > self.max_file_size {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        node.name.rsplit_once('.')
+            .map(|(_, ext)| self.extensions.contains(&ext.to_lowercase()))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LineSnippet {
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileContentMatch {
+    pub path: String,
+    pub size: Option<u64>,
+    pub snippets: Vec<LineSnippet>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContentSearchResult {
+    pub matches: Vec<FileContentMatch>,
+    pub files_scanned: usize,
+    pub bytes_scanned: u64,
+    pub skipped_binary: usize,
+    /// A limit was hit before every candidate file was searched
+    pub truncated: bool,
+}
+
+pub fn build_pattern(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    if pattern.trim().is_empty() {
+        return Err(AppError::InvalidSearchPattern("pattern cannot be empty".to_string()));
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .size_limit(1024 * 1024)
+        .build()
+        .map_err(|e| AppError::InvalidSearchPattern(e.to_string()))
+}
+
+pub fn looks_binary(contents: &str) -> bool {
+    contents.bytes().take(BINARY_SNIFF_BYTES).any(|b| b == 0)
+}
+
+pub fn find_snippets(contents: &str, pattern: &Regex, max_snippets: usize) -> Vec<LineSnippet> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| pattern.is_match(line))
+        .take(max_snippets)
+        .map(|(i, line)| LineSnippet {
+            line_number: i + 1,
+            line: line.trim().chars().take(MAX_SNIPPET_CHARS).collect(),
+        })
+        .collect()
+}
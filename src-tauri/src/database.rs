@@ -1,20 +1,81 @@
 use crate::error::{AppError, Result};
-use crate::models::Snapshot;
+use crate::health::RepoHealth;
+use crate::models::{ChangeSummary, FileNode, RestorePathError, Snapshot, SnapshotFilter, SnapshotGroup, SnapshotGroupBy};
+use crate::node_cache::{self, DirBlob};
+use crate::query_log;
 use crate::storage::get_config_dir;
-use rusqlite::{Connection, params};
-use std::sync::Mutex;
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, Transaction, TransactionBehavior, params};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use once_cell::sync::Lazy;
-use tracing::{debug, info, error, instrument};
+use tracing::{debug, info, error, instrument, warn};
 use serde::{Serialize, Deserialize};
 
-// Single global connection to avoid SQLite locking issues
-static DB_CONNECTION: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+// WAL lets readers run alongside the one writer, so each query checks out its own
+// connection; SQLite's busy timeout queues writers behind each other
+static DB_POOL: Lazy<RwLock<Option<Pool<SqliteConnectionManager>>>> = Lazy::new(|| RwLock::new(None));
+const POOL_SIZE: u32 = 4;
+const BUSY_TIMEOUT_MS: u32 = 5_000;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct SnapshotWithStats {
     pub snapshot: Snapshot,
     pub total_size: Option<u64>,
     pub total_file_count: Option<u64>,
+    /// Pinned snapshots are listed first, in their manual order
+    #[serde(default)]
+    pub pinned: bool,
+    /// Change over the parent snapshot, once `compute_snapshot_deltas` has run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<SnapshotDelta>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeltaSource {
+    /// Difference of the cached restore-size stats of both snapshots
+    Stats,
+    /// Totals of `restic diff` between the parent and the snapshot
+    Diff,
+}
+
+impl DeltaSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            DeltaSource::Stats => "stats",
+            DeltaSource::Diff => "diff",
+        }
+    }
+
+    fn parse(source: &str) -> Self {
+        match source {
+            "diff" => DeltaSource::Diff,
+            _ => DeltaSource::Stats,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotDelta {
+    pub parent_id: String,
+    pub size_delta: i64,
+    pub file_count_delta: i64,
+    pub source: DeltaSource,
+}
+
+/// A snapshot with a cached parent and no delta yet, with whatever stats are cached for both
+#[derive(Debug, Clone)]
+pub struct DeltaCandidate {
+    pub snapshot_id: String,
+    pub parent_id: String,
+    pub stats: Option<(u64, u64)>,
+    pub parent_stats: Option<(u64, u64)>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,8 +83,107 @@ pub struct RepoMeta {
     pub repo_id: String,
     pub last_delta_check: i64,
     pub snapshot_count: i64,
+    /// Repository-level aggregates (size, quota level) predate the latest new snapshots
+    pub aggregates_stale: bool,
+    /// Last time restic reached the repository, unix seconds
+    #[serde(default)]
+    pub last_connected_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoUsage {
+    pub repo_id: String,
+    pub total_size: u64,
+    pub quota_level: i64,
+    pub measured_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QuotaEvent {
+    pub repo_id: String,
+    /// Percentage of the budget crossed (80, 100), or 0 when usage dropped back under 80%
+    pub threshold: i64,
+    pub total_size: u64,
+    pub budget: u64,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreOutcome {
+    Success,
+    /// Finished, but some paths could not be restored
+    CompletedWithWarnings,
+    Failed,
+    Cancelled,
+}
+
+impl RestoreOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            RestoreOutcome::Success => "success",
+            RestoreOutcome::CompletedWithWarnings => "completed_with_warnings",
+            RestoreOutcome::Failed => "failed",
+            RestoreOutcome::Cancelled => "cancelled",
+        }
+    }
+
+    // Restores recorded before outcomes were kept only ever succeeded
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("completed_with_warnings") => RestoreOutcome::CompletedWithWarnings,
+            Some("failed") => RestoreOutcome::Failed,
+            Some("cancelled") => RestoreOutcome::Cancelled,
+            _ => RestoreOutcome::Success,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreHistoryEntry {
+    pub id: i64,
+    pub repo_id: String,
+    pub snapshot_id: String,
+    pub source_paths: Vec<String>,
+    pub target: String,
+    /// When the restore finished
+    pub restored_at: i64,
+    /// Unknown for restores recorded by older versions
+    pub started_at: Option<i64>,
+    /// Unknown for elevated restores, which don't report progress
+    pub bytes_restored: Option<u64>,
+    pub outcome: RestoreOutcome,
+    /// Paths restic could not restore
+    pub warnings: Vec<RestorePathError>,
+    /// Why a failed restore failed
+    pub error: Option<String>,
+}
+
+/// A restore to add to the history
+#[derive(Debug)]
+pub struct RestoreRecord<'a> {
+    pub repo_id: &'a str,
+    pub snapshot_id: &'a str,
+    pub source_paths: &'a [String],
+    pub target: &'a str,
+    pub started_at: i64,
+    pub bytes_restored: Option<u64>,
+    pub outcome: RestoreOutcome,
+    pub warnings: &'a [RestorePathError],
+    pub error: Option<&'a str>,
+}
+
+/// A security-relevant action, kept so it can be reviewed later
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub event: String,
+    pub detail: Option<String>,
+    pub recorded_at: i64,
 }
 
+pub const QUOTA_THRESHOLDS: [i64; 2] = [100, 80];
+
 #[instrument]
 pub fn init_database() -> Result<()> {
     info!("Initializing SQLite database");
@@ -40,8 +200,10 @@ pub fn init_database() -> Result<()> {
         info!("Config directory doesn't exist, will be created by rusqlite");
     }
 
-    let conn = Connection::open(&db_path)
+    let existed = db_path.exists();
+    let mut conn = Connection::open(&db_path)
         .map_err(|e| AppError::Storage(format!("Failed to open database: {}", e)))?;
+    conn.profile(Some(query_log::record));
 
     // Enable WAL mode and other pragmas (use execute_batch for PRAGMA statements)
     conn.execute_batch(
@@ -108,63 +270,467 @@ pub fn init_database() -> Result<()> {
         [],
     ).map_err(|e| AppError::Storage(format!("Failed to create meta table: {}", e)))?;
 
-    let mut db_conn = DB_CONNECTION.lock()
-        .map_err(|e| AppError::Storage(format!("Failed to lock database connection: {}", e)))?;
-    *db_conn = Some(conn);
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS files (
+            id INTEGER PRIMARY KEY,
+            snapshot_pk INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            parent_path TEXT NOT NULL,
+            name TEXT NOT NULL,
+            type TEXT NOT NULL,
+            size INTEGER,
+            mtime TEXT,
+            UNIQUE (snapshot_pk, path),
+            FOREIGN KEY (snapshot_pk) REFERENCES snapshots(pk) ON DELETE CASCADE
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create files table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_files_parent ON files(snapshot_pk, parent_path)",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create files index: {}", e)))?;
+
+    // Trigram tokens match any part of a name, so "voice.pd" finds invoice.pdf.
+    // The triggers keep it in step with files, including cascaded deletes.
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(
+            name, content='files', content_rowid='id', tokenize='trigram'
+        );
+        CREATE TRIGGER IF NOT EXISTS files_fts_insert AFTER INSERT ON files BEGIN
+            INSERT INTO files_fts(rowid, name) VALUES (new.id, new.name);
+        END;
+        CREATE TRIGGER IF NOT EXISTS files_fts_delete AFTER DELETE ON files BEGIN
+            INSERT INTO files_fts(files_fts, rowid, name) VALUES ('delete', old.id, old.name);
+        END;
+        CREATE TRIGGER IF NOT EXISTS files_fts_update AFTER UPDATE OF name ON files BEGIN
+            INSERT INTO files_fts(files_fts, rowid, name) VALUES ('delete', old.id, old.name);
+            INSERT INTO files_fts(rowid, name) VALUES (new.id, new.name);
+        END;"
+    ).map_err(|e| AppError::Storage(format!("Failed to create file search index: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS repo_usage (
+            repo_id TEXT PRIMARY KEY,
+            total_size INTEGER NOT NULL,
+            quota_level INTEGER DEFAULT 0,
+            measured_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create repo_usage table: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS quota_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            repo_id TEXT NOT NULL,
+            threshold INTEGER NOT NULL,
+            total_size INTEGER NOT NULL,
+            budget INTEGER NOT NULL,
+            recorded_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create quota_events table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_quota_events_repo ON quota_events(repo_id, recorded_at DESC)",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create quota_events index: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event TEXT NOT NULL,
+            detail TEXT,
+            recorded_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create audit_log table: {}", e)))?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS restore_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            repo_id TEXT NOT NULL,
+            snapshot_id TEXT NOT NULL,
+            source_paths TEXT NOT NULL,
+            target TEXT NOT NULL,
+            restored_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create restore_history table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_restore_history_repo ON restore_history(repo_id, restored_at DESC)",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create restore_history index: {}", e)))?;
+
+    // Cached `restic ls` output, one (optionally compressed) blob per directory
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS node_dirs (
+            repo_id TEXT NOT NULL,
+            snapshot_id TEXT NOT NULL,
+            dir_path TEXT NOT NULL,
+            node_count INTEGER NOT NULL,
+            raw_size INTEGER NOT NULL,
+            compressed INTEGER NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (repo_id, snapshot_id, dir_path)
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create node_dirs table: {}", e)))?;
+
+    // Uncompressed names of cached nodes so searches don't need to decompress every blob
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS node_names (
+            repo_id TEXT NOT NULL,
+            snapshot_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            path TEXT NOT NULL
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create node_names table: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_node_names_repo ON node_names(repo_id, name)",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create node_names index: {}", e)))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_node_names_snapshot ON node_names(repo_id, snapshot_id)",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create node_names index: {}", e)))?;
+
+    // Kept apart from the snapshots table so pins survive cache clears and resyncs
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS snapshot_pins (
+            repo_id TEXT NOT NULL,
+            snapshot_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            pinned_at INTEGER DEFAULT (strftime('%s', 'now')),
+            PRIMARY KEY (repo_id, snapshot_id)
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create snapshot_pins table: {}", e)))?;
+
+    run_migrations(&mut conn, &db_path, existed)?;
+
+    drop(conn);
+
+    let manager = SqliteConnectionManager::file(&db_path).with_init(|conn| {
+        conn.execute_batch(&format!(
+            "PRAGMA synchronous=NORMAL;
+             PRAGMA foreign_keys=ON;
+             PRAGMA busy_timeout={};",
+            BUSY_TIMEOUT_MS
+        ))?;
+        conn.profile(Some(query_log::record));
+        Ok(())
+    });
+    let pool = Pool::builder()
+        .max_size(POOL_SIZE)
+        .build(manager)
+        .map_err(|e| AppError::Storage(format!("Failed to open database connections: {}", e)))?;
+    *DB_POOL.write().map_err(|e| AppError::Storage(format!("Failed to lock database pool: {}", e)))? = Some(pool);
 
     info!("Database initialized successfully");
     Ok(())
 }
 
-fn get_connection() -> Result<std::sync::MutexGuard<'static, Option<Connection>>> {
-    DB_CONNECTION.lock()
-        .map_err(|e| AppError::Storage(format!("Failed to lock database connection: {}", e)))
+/// A schema change on top of the tables `init_database` creates
+struct Migration {
+    version: i64,
+    description: &'static str,
+    sql: &'static str,
+    /// Added after `sql` runs, skipping any the table already has
+    columns: &'static [Column],
+}
+
+/// A column for a migration to add. Versions before migrations existed added some
+/// columns on every start, so databases they left behind may have them already.
+struct Column {
+    table: &'static str,
+    name: &'static str,
+    definition: &'static str,
+}
+
+// Applied in order, each in its own transaction. Released entries must never be
+// edited or reordered; schema changes go into a new entry at the end.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Index short snapshot IDs",
+        sql: "CREATE INDEX IF NOT EXISTS idx_snapshots_short_id ON snapshots(repo_id, short_id);",
+        columns: &[],
+    },
+    Migration {
+        version: 2,
+        description: "Cache repository health reports",
+        sql: "CREATE TABLE IF NOT EXISTS repo_health (
+                repo_id TEXT PRIMARY KEY,
+                report TEXT NOT NULL,
+                measured_at INTEGER NOT NULL
+              );",
+        columns: &[],
+    },
+    Migration {
+        version: 3,
+        description: "Keep every snapshot field restic reports",
+        sql: "ALTER TABLE snapshots ADD COLUMN original TEXT;
+              ALTER TABLE snapshots ADD COLUMN uid INTEGER;
+              ALTER TABLE snapshots ADD COLUMN gid INTEGER;
+              ALTER TABLE snapshots ADD COLUMN excludes TEXT;
+              ALTER TABLE snapshots ADD COLUMN summary TEXT;",
+        columns: &[],
+    },
+    Migration {
+        version: 4,
+        description: "Store what each snapshot added over its parent",
+        sql: "CREATE TABLE IF NOT EXISTS snapshot_deltas (
+                snapshot_pk INTEGER PRIMARY KEY,
+                parent_id TEXT NOT NULL,
+                size_delta INTEGER NOT NULL,
+                file_count_delta INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                computed_at INTEGER DEFAULT (strftime('%s', 'now')),
+                FOREIGN KEY (snapshot_pk) REFERENCES snapshots(pk) ON DELETE CASCADE
+              );",
+        columns: &[],
+    },
+    Migration {
+        version: 5,
+        description: "Remember check results and connection failures",
+        sql: "ALTER TABLE meta ADD COLUMN last_check_at INTEGER;
+              ALTER TABLE meta ADD COLUMN last_check_ok INTEGER;
+              ALTER TABLE meta ADD COLUMN last_error TEXT;
+              ALTER TABLE meta ADD COLUMN last_error_at INTEGER;",
+        columns: &[],
+    },
+    Migration {
+        version: 6,
+        description: "Cache what each snapshot changed over its parent",
+        sql: "CREATE TABLE IF NOT EXISTS change_summaries (
+                snapshot_pk INTEGER PRIMARY KEY,
+                parent_id TEXT NOT NULL,
+                files_added INTEGER NOT NULL,
+                files_changed INTEGER NOT NULL,
+                files_removed INTEGER NOT NULL,
+                bytes_added INTEGER NOT NULL,
+                bytes_removed INTEGER NOT NULL,
+                computed_at INTEGER DEFAULT (strftime('%s', 'now')),
+                FOREIGN KEY (snapshot_pk) REFERENCES snapshots(pk) ON DELETE CASCADE
+              );",
+        columns: &[],
+    },
+    Migration {
+        version: 7,
+        description: "Track stale aggregates, connections and indexed snapshots",
+        sql: "",
+        columns: &[
+            Column { table: "meta", name: "aggregates_stale", definition: "INTEGER DEFAULT 0" },
+            Column { table: "meta", name: "last_connected_at", definition: "INTEGER" },
+            // Set once a snapshot's full file listing is in the files table
+            Column { table: "snapshots", name: "files_indexed_at", definition: "INTEGER" },
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "Record the outcome of each restore",
+        sql: "",
+        columns: &[
+            Column { table: "restore_history", name: "started_at", definition: "INTEGER" },
+            Column { table: "restore_history", name: "bytes_restored", definition: "INTEGER" },
+            Column { table: "restore_history", name: "outcome", definition: "TEXT" },
+            Column { table: "restore_history", name: "warnings", definition: "TEXT" },
+            Column { table: "restore_history", name: "error", definition: "TEXT" },
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "Keep modification times of cached node names",
+        sql: "",
+        columns: &[
+            // Listings cached before this column existed have no times and never match a time filter
+            Column { table: "node_names", name: "mtime", definition: "TEXT" },
+        ],
+    },
+];
+
+fn schema_version(conn: &Connection) -> Result<i64> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+        .map_err(|e| AppError::Storage(format!("Failed to read schema version: {}", e)))
+}
+
+/// Brings the schema up to date, copying the database next to itself first
+/// so a failed migration can be undone by hand
+fn run_migrations(conn: &mut Connection, db_path: &Path, existed: bool) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    ).map_err(|e| AppError::Storage(format!("Failed to create schema_version table: {}", e)))?;
+
+    let current = schema_version(conn)?;
+    let latest = MIGRATIONS.last().map_or(0, |m| m.version);
+    if current > latest {
+        warn!("Database schema version {} is newer than this app knows ({})", current, latest);
+        return Ok(());
+    }
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if existed {
+        // VACUUM INTO gives a consistent copy even with writes still in the WAL
+        let backup = db_path.with_extension(format!("db.v{}.bak", current));
+        if backup.exists() {
+            std::fs::remove_file(&backup)?;
+        }
+        info!("Backing up database to {:?} before migrating", backup);
+        conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()])
+            .map_err(|e| AppError::Storage(format!("Failed to back up database: {}", e)))?;
+    }
+
+    for migration in pending {
+        info!("Migrating database to version {}: {}", migration.version, migration.description);
+        let tx = conn.transaction()
+            .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+        tx.execute_batch(migration.sql)
+            .map_err(|e| AppError::Storage(format!("Migration {} failed: {}", migration.version, e)))?;
+        for column in migration.columns {
+            if table_columns(&tx, column.table)?.iter().any(|c| c == column.name) {
+                continue;
+            }
+            tx.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", column.table, column.name, column.definition), [])
+                .map_err(|e| AppError::Storage(format!("Migration {} failed to add {}.{}: {}", migration.version, column.table, column.name, e)))?;
+        }
+        tx.execute(
+            "INSERT INTO schema_version (version, description) VALUES (?1, ?2)",
+            params![migration.version, migration.description],
+        ).map_err(|e| AppError::Storage(format!("Failed to record migration {}: {}", migration.version, e)))?;
+        tx.commit()
+            .map_err(|e| AppError::Storage(format!("Failed to commit migration {}: {}", migration.version, e)))?;
+    }
+    Ok(())
+}
+
+// CREATE TABLE IF NOT EXISTS leaves databases from older versions without newer columns
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))
+        .map_err(|e| AppError::Storage(format!("Failed to read {} schema: {}", table, e)))?;
+    let columns: std::result::Result<Vec<String>, _> = stmt.query_map([], |row| row.get(1))
+        .map_err(|e| AppError::Storage(format!("Failed to read {} schema: {}", table, e)))?
+        .collect();
+    columns.map_err(|e| AppError::Storage(format!("Failed to read {} schema: {}", table, e)))
 }
 
+/// Folds the WAL into the database file and closes the pool, so the file can be
+/// copied whole. Queries fail with "Database not initialized" until `init_database`
+/// runs again.
 #[instrument]
-pub fn load_snapshots_from_db(repo_id: &str) -> Result<Vec<SnapshotWithStats>> {
-    info!("Loading snapshots from database for repo: {}", repo_id);
+pub fn close_database() -> Result<()> {
+    let mut pool = DB_POOL.write().map_err(|e| AppError::Storage(format!("Failed to lock database pool: {}", e)))?;
+    if let Some(open) = pool.as_ref() {
+        let conn = open.get()
+            .map_err(|e| AppError::Storage(format!("Failed to get database connection: {}", e)))?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| AppError::Storage(format!("Failed to checkpoint database: {}", e)))?;
+    }
+    // Dropping the last pool handle closes its connections; ones checked out close when returned
+    *pool = None;
+    info!("Database closed");
+    Ok(())
+}
+
+/// Runs SQLite's `quick_check` on the database file at `path`
+pub fn quick_check(path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::Storage(format!("Failed to open {}: {}", path.display(), e)))?;
+    let result: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| AppError::Storage(format!("Failed to check {}: {}", path.display(), e)))?;
+    match result.as_str() {
+        "ok" => Ok(()),
+        _ => Err(AppError::Storage(format!("{} failed its integrity check: {}", path.display(), result))),
+    }
+}
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
+fn get_connection() -> Result<PooledConnection<SqliteConnectionManager>> {
+    let pool = DB_POOL.read()
+        .map_err(|e| AppError::Storage(format!("Failed to lock database pool: {}", e)))?
+        .clone()
         .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    let started = Instant::now();
+    let conn = pool.get()
+        .map_err(|e| AppError::Storage(format!("Failed to get database connection: {}", e)))?;
+    query_log::record_connection_wait(started.elapsed());
+    Ok(conn)
+}
 
-    let mut stmt = conn.prepare(
-        "SELECT s.id, s.repo_id, s.short_id, s.time, s.hostname, s.username,
-                s.paths, s.tags, s.parent, s.tree,
-                st.total_size, st.total_file_count, s.pk
-         FROM snapshots s
-         LEFT JOIN stats st ON s.pk = st.snapshot_pk
-         WHERE s.repo_id = ?1
-         ORDER BY s.time DESC"
-    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+// Deferred transactions that start writing after reading can fail with SQLITE_BUSY
+// without waiting when another connection wrote in between; IMMEDIATE takes the
+// write lock up front, where the busy timeout applies
+fn write_transaction(conn: &Connection) -> rusqlite::Result<Transaction<'_>> {
+    Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+}
 
-    let snapshot_iter = stmt.query_map([repo_id], |row| {
-        let paths_str: String = row.get(6)?;
-        let paths: Vec<String> = serde_json::from_str(&paths_str).unwrap_or_default();
+// Read by `snapshot_with_stats_from_row`; callers add the WHERE and ORDER BY
+const SNAPSHOT_WITH_STATS_QUERY: &str =
+    "SELECT s.id, s.repo_id, s.short_id, s.time, s.hostname, s.username,
+            s.paths, s.tags, s.parent, s.tree,
+            st.total_size, st.total_file_count, s.pk, p.position,
+            s.program_version, s.original, s.uid, s.gid, s.excludes, s.summary,
+            d.parent_id, d.size_delta, d.file_count_delta, d.source
+     FROM snapshots s
+     LEFT JOIN stats st ON s.pk = st.snapshot_pk
+     LEFT JOIN snapshot_pins p ON p.repo_id = s.repo_id AND p.snapshot_id = s.id
+     LEFT JOIN snapshot_deltas d ON d.snapshot_pk = s.pk";
+
+fn snapshot_with_stats_from_row(row: &rusqlite::Row) -> rusqlite::Result<SnapshotWithStats> {
+    let paths_str: String = row.get(6)?;
+    let paths: Vec<String> = serde_json::from_str(&paths_str).unwrap_or_default();
+
+    let tags_str: Option<String> = row.get(7)?;
+    let tags: Option<Vec<String>> = tags_str.and_then(|s| serde_json::from_str(&s).ok());
+
+    let time_unix: i64 = row.get(3)?;
+    let time_str = format_unix_timestamp(time_unix);
+
+    Ok(SnapshotWithStats {
+        snapshot: with_details(Snapshot {
+            id: row.get(0)?,
+            short_id: row.get(2)?,
+            time: time_str,
+            hostname: row.get(4)?,
+            username: row.get(5)?,
+            paths,
+            tags,
+            parent: row.get(8)?,
+            tree: row.get(9)?,
+            ..Default::default()
+        }, row, 14)?,
+        total_size: row.get(10)?,
+        total_file_count: row.get(11)?,
+        pinned: row.get::<_, Option<i64>>(13)?.is_some(),
+        delta: delta_from_row(row, 20)?,
+    })
+}
 
-        let tags_str: Option<String> = row.get(7)?;
-        let tags: Option<Vec<String>> = tags_str.and_then(|s| serde_json::from_str(&s).ok());
+#[instrument]
+pub fn load_snapshots_from_db(repo_id: &str) -> Result<Vec<SnapshotWithStats>> {
+    info!("Loading snapshots from database for repo: {}", repo_id);
 
-        let time_unix: i64 = row.get(3)?;
-        let time_str = format_unix_timestamp(time_unix);
+    let conn = get_connection()?;
 
-        Ok(SnapshotWithStats {
-            snapshot: Snapshot {
-                id: row.get(0)?,
-                short_id: row.get(2)?,
-                time: time_str,
-                hostname: row.get(4)?,
-                username: row.get(5)?,
-                paths,
-                tags,
-                parent: row.get(8)?,
-                tree: row.get(9)?,
-            },
-            total_size: row.get(10)?,
-            total_file_count: row.get(11)?,
-        })
-    }).map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?;
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE s.repo_id = ?1
+         ORDER BY p.position IS NULL, p.position, s.time DESC",
+        SNAPSHOT_WITH_STATS_QUERY,
+    )).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let snapshot_iter = stmt.query_map([repo_id], snapshot_with_stats_from_row)
+        .map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?;
 
     let snapshots: std::result::Result<Vec<_>, _> = snapshot_iter.collect();
     let snapshots = snapshots.map_err(|e| AppError::Storage(format!("Failed to fetch snapshots: {}", e)))?;
@@ -175,13 +741,118 @@ pub fn load_snapshots_from_db(repo_id: &str) -> Result<Vec<SnapshotWithStats>> {
     Ok(snapshots)
 }
 
+/// Cached snapshots of the given repositories, newest first, paired with their
+/// repo_id. `after` and `before` are Unix timestamps.
+#[instrument(skip(repo_ids), fields(repos = repo_ids.len()))]
+pub fn load_all_snapshots(repo_ids: &[String], after: Option<i64>, before: Option<i64>, limit: usize) -> Result<Vec<(String, SnapshotWithStats)>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "{} WHERE s.repo_id IN (SELECT value FROM json_each(?1))
+           AND (?2 IS NULL OR s.time >= ?2) AND (?3 IS NULL OR s.time < ?3)
+         ORDER BY s.time DESC LIMIT ?4",
+        SNAPSHOT_WITH_STATS_QUERY,
+    )).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let rows = stmt.query_map(params![serde_json::to_string(repo_ids)?, after, before, limit as i64], |row| {
+        Ok((row.get::<_, String>(1)?, snapshot_with_stats_from_row(row)?))
+    }).map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?;
+
+    let snapshots: std::result::Result<Vec<_>, _> = rows.collect();
+    snapshots.map_err(|e| AppError::Storage(format!("Failed to fetch snapshots: {}", e)))
+}
+
+/// Cached snapshots matching `filter`, grouped by `group_by` in key order, or as a
+/// single unkeyed group. `after` and `before` are Unix timestamps.
+#[instrument(skip(filter))]
+pub fn query_snapshot_groups(
+    repo_id: &str,
+    filter: &SnapshotFilter,
+    after: Option<i64>,
+    before: Option<i64>,
+    group_by: Option<SnapshotGroupBy>,
+) -> Result<Vec<SnapshotGroup<SnapshotWithStats>>> {
+    let conn = get_connection()?;
+
+    let (key, group_join) = match group_by {
+        None => ("NULL", ""),
+        Some(SnapshotGroupBy::Host) => ("s.hostname", ""),
+        Some(SnapshotGroupBy::Tag) => ("g.value", "LEFT JOIN json_each(s.tags) g"),
+        Some(SnapshotGroupBy::Path) => ("g.value", "JOIN json_each(s.paths) g"),
+    };
+    let hosts = serde_json::to_string(&filter.hosts)?;
+    let tags = serde_json::to_string(&filter.tags)?;
+    let path_prefix = filter.path_prefix.as_deref().map(|p| p.trim_end_matches('/').to_string());
+    let path_pattern = path_prefix.as_deref().map(|p| format!("{}/%", like_pattern(p)));
+
+    let sql = format!(
+        "SELECT {key}, s.id, s.short_id, s.time, s.hostname, s.username,
+                s.paths, s.tags, s.parent, s.tree,
+                st.total_size, st.total_file_count, p.position,
+                s.program_version, s.original, s.uid, s.gid, s.excludes, s.summary,
+                d.parent_id, d.size_delta, d.file_count_delta, d.source
+         FROM snapshots s
+         {group_join}
+         LEFT JOIN stats st ON s.pk = st.snapshot_pk
+         LEFT JOIN snapshot_pins p ON p.repo_id = s.repo_id AND p.snapshot_id = s.id
+         LEFT JOIN snapshot_deltas d ON d.snapshot_pk = s.pk
+         WHERE s.repo_id = ?1
+           AND (json_array_length(?2) = 0 OR s.hostname IN (SELECT value FROM json_each(?2)))
+           AND (json_array_length(?3) = 0 OR EXISTS (
+                SELECT 1 FROM json_each(s.tags) t WHERE t.value IN (SELECT value FROM json_each(?3))))
+           AND (?4 IS NULL OR EXISTS (
+                SELECT 1 FROM json_each(s.paths) sp WHERE sp.value = ?4 OR sp.value LIKE ?5 ESCAPE '\\'))
+           AND (?6 IS NULL OR s.time >= ?6)
+           AND (?7 IS NULL OR s.time <= ?7)
+         ORDER BY {key} IS NULL, {key}, s.time DESC"
+    );
+
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let rows: std::result::Result<Vec<(Option<String>, SnapshotWithStats)>, _> = stmt.query_map(
+        params![repo_id, hosts, tags, path_prefix, path_pattern, after, before],
+        |row| {
+            let paths_str: String = row.get(6)?;
+            let tags_str: Option<String> = row.get(7)?;
+            Ok((row.get(0)?, SnapshotWithStats {
+                snapshot: with_details(Snapshot {
+                    id: row.get(1)?,
+                    short_id: row.get(2)?,
+                    time: format_unix_timestamp(row.get(3)?),
+                    hostname: row.get(4)?,
+                    username: row.get(5)?,
+                    paths: serde_json::from_str(&paths_str).unwrap_or_default(),
+                    tags: tags_str.and_then(|s| serde_json::from_str(&s).ok()),
+                    parent: row.get(8)?,
+                    tree: row.get(9)?,
+                    ..Default::default()
+                }, row, 13)?,
+                total_size: row.get(10)?,
+                total_file_count: row.get(11)?,
+                pinned: row.get::<_, Option<i64>>(12)?.is_some(),
+                delta: delta_from_row(row, 19)?,
+            }))
+        },
+    ).map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?
+        .collect();
+    let rows = rows.map_err(|e| AppError::Storage(format!("Failed to fetch snapshots: {}", e)))?;
+
+    // Rows arrive ordered by key, so each group is one run
+    let mut groups: Vec<SnapshotGroup<SnapshotWithStats>> = Vec::new();
+    for (key, snapshot) in rows {
+        match groups.last_mut() {
+            Some(group) if group.key == key => group.snapshots.push(snapshot),
+            _ => groups.push(SnapshotGroup { key, snapshots: vec![snapshot] }),
+        }
+    }
+    Ok(groups)
+}
+
 #[instrument]
 pub fn get_cached_snapshot_ids(repo_id: &str) -> Result<Vec<String>> {
     debug!("Getting cached snapshot IDs for repo: {}", repo_id);
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    let conn = get_connection()?;
 
     let mut stmt = conn.prepare(
         "SELECT s.id FROM snapshots s
@@ -199,56 +870,30 @@ pub fn get_cached_snapshot_ids(repo_id: &str) -> Result<Vec<String>> {
     Ok(ids)
 }
 
-#[instrument(skip(snapshots), fields(count = snapshots.len()))]
-pub fn save_snapshots_batch(repo_id: &str, snapshots: &[SnapshotWithStats]) -> Result<()> {
-    info!("Saving batch of {} snapshots with stats to database for repo {}", snapshots.len(), repo_id);
-
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
-
-    let tx = conn.unchecked_transaction()
-        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+#[instrument]
+pub fn get_all_snapshot_ids(repo_id: &str) -> Result<Vec<String>> {
+    debug!("Getting all snapshot IDs for repo: {}", repo_id);
 
-    for snap_with_stats in snapshots {
-        let snapshot = &snap_with_stats.snapshot;
+    let conn = get_connection()?;
 
-        let time_unix = parse_iso_to_unix(&snapshot.time);
+    let mut stmt = conn.prepare("SELECT id FROM snapshots WHERE repo_id = ?1")
+        .map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
 
-        let paths_json = serde_json::to_string(&snapshot.paths)
-            .map_err(|e| AppError::Storage(format!("Failed to serialize paths: {}", e)))?;
+    let ids_iter = stmt.query_map([repo_id], |row| row.get(0))
+        .map_err(|e| AppError::Storage(format!("Failed to query snapshot IDs: {}", e)))?;
 
-        let tags_json = snapshot.tags.as_ref()
-            .map(|t| serde_json::to_string(t))
-            .transpose()
-            .map_err(|e| AppError::Storage(format!("Failed to serialize tags: {}", e)))?;
+    let ids: std::result::Result<Vec<String>, _> = ids_iter.collect();
+    ids.map_err(|e| AppError::Storage(format!("Failed to fetch snapshot IDs: {}", e)))
+}
 
-        tx.execute(
-            "INSERT OR REPLACE INTO snapshots
-             (id, repo_id, short_id, time, hostname, username, paths, tags, parent, tree)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            params![
-                snapshot.id,
-                repo_id,
-                snapshot.short_id,
-                time_unix,
-                snapshot.hostname,
-                snapshot.username,
-                paths_json,
-                tags_json,
-                snapshot.parent,
-                snapshot.tree,
-            ],
-        ).map_err(|e| AppError::Storage(format!("Failed to insert snapshot: {}", e)))?;
-
-        let snapshot_pk: i64 = tx.query_row(
-            "SELECT pk FROM snapshots WHERE repo_id = ?1 AND id = ?2",
-            params![repo_id, snapshot.id],
-            |row| row.get(0)
-        ).map_err(|e| AppError::Storage(format!("Failed to get snapshot pk: {}", e)))?;
+#[instrument(skip(snapshots, on_written), fields(count = snapshots.len()))]
+pub fn save_snapshots_batch<F: FnMut(usize)>(repo_id: &str, snapshots: &[SnapshotWithStats], on_written: F) -> Result<()> {
+    info!("Saving batch of {} snapshots with stats to database for repo {}", snapshots.len(), repo_id);
 
+    write_in_batches(snapshots, |s| snapshot_payload(&s.snapshot), |conn, snap_with_stats| {
+        let snapshot_pk = upsert_snapshot(conn, repo_id, &snap_with_stats.snapshot)?;
         if snap_with_stats.total_size.is_some() || snap_with_stats.total_file_count.is_some() {
-            tx.execute(
+            conn.execute(
                 "INSERT OR REPLACE INTO stats (snapshot_pk, total_size, total_file_count)
                  VALUES (?1, ?2, ?3)",
                 params![
@@ -258,80 +903,408 @@ pub fn save_snapshots_batch(repo_id: &str, snapshots: &[SnapshotWithStats]) -> R
                 ],
             ).map_err(|e| AppError::Storage(format!("Failed to insert stats: {}", e)))?;
         }
-    }
-
-    tx.commit()
-        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        Ok(())
+    }, on_written)?;
 
     info!("Batch save completed: {} snapshots with stats saved to database", snapshots.len());
     Ok(())
 }
 
-/// Save snapshots metadata only (without stats)
-#[instrument(skip(snapshots), fields(count = snapshots.len()))]
-pub fn save_snapshots_metadata_only(repo_id: &str, snapshots: &[Snapshot]) -> Result<()> {
+#[instrument(skip(snapshots, on_written), fields(count = snapshots.len()))]
+pub fn save_snapshots_metadata_only<F: FnMut(usize)>(repo_id: &str, snapshots: &[Snapshot], on_written: F) -> Result<()> {
     info!("Saving metadata for {} snapshots to database for repo {}", snapshots.len(), repo_id);
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    write_in_batches(snapshots, snapshot_payload, |conn, snapshot| {
+        upsert_snapshot(conn, repo_id, snapshot).map(|_| ())
+    }, on_written)?;
 
-    let tx = conn.unchecked_transaction()
-        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+    info!("Metadata save completed: {} snapshots saved to database", snapshots.len());
+    Ok(())
+}
+
+/// Reads parent_id, size_delta, file_count_delta and source, selected in that
+/// order starting at column `first`
+fn delta_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Option<SnapshotDelta>> {
+    let Some(parent_id) = row.get::<_, Option<String>>(first)? else {
+        return Ok(None);
+    };
+    Ok(Some(SnapshotDelta {
+        parent_id,
+        size_delta: row.get(first + 1)?,
+        file_count_delta: row.get(first + 2)?,
+        source: DeltaSource::parse(&row.get::<_, String>(first + 3)?),
+    }))
+}
+
+/// Fills in program_version, original, uid, gid, excludes and summary, selected
+/// in that order starting at column `first`
+fn with_details(snapshot: Snapshot, row: &rusqlite::Row, first: usize) -> rusqlite::Result<Snapshot> {
+    let excludes: Option<String> = row.get(first + 4)?;
+    let summary: Option<String> = row.get(first + 5)?;
+    Ok(Snapshot {
+        program_version: row.get(first)?,
+        original: row.get(first + 1)?,
+        uid: row.get(first + 2)?,
+        gid: row.get(first + 3)?,
+        excludes: excludes.and_then(|e| serde_json::from_str(&e).ok()).unwrap_or_default(),
+        summary: summary.and_then(|s| serde_json::from_str(&s).ok()),
+        ..snapshot
+    })
+}
+
+/// Inserts or updates a snapshot's metadata and returns its pk. Upserts rather than
+/// REPLACE so the row keeps its pk, which stats and the file index refer to.
+fn upsert_snapshot(conn: &Connection, repo_id: &str, snapshot: &Snapshot) -> Result<i64> {
+    let (paths_json, tags_json) = snapshot_json_columns(snapshot)?;
+    let (excludes_json, summary_json) = snapshot_detail_columns(snapshot)?;
+    conn.execute(
+        "INSERT INTO snapshots
+         (id, repo_id, short_id, time, hostname, username, paths, tags, parent, tree,
+          program_version, original, uid, gid, excludes, summary)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+         ON CONFLICT(repo_id, id) DO UPDATE SET
+            short_id = excluded.short_id, time = excluded.time, hostname = excluded.hostname,
+            username = excluded.username, paths = excluded.paths, tags = excluded.tags,
+            parent = excluded.parent, tree = excluded.tree,
+            program_version = excluded.program_version, original = excluded.original,
+            uid = excluded.uid, gid = excluded.gid, excludes = excluded.excludes,
+            summary = excluded.summary",
+        params![
+            snapshot.id,
+            repo_id,
+            snapshot.short_id,
+            parse_iso_to_unix(&snapshot.time),
+            snapshot.hostname,
+            snapshot.username,
+            paths_json,
+            tags_json,
+            snapshot.parent,
+            snapshot.tree,
+            snapshot.program_version,
+            snapshot.original,
+            snapshot.uid,
+            snapshot.gid,
+            excludes_json,
+            summary_json,
+        ],
+    ).map_err(|e| AppError::Storage(format!("Failed to insert snapshot metadata: {}", e)))?;
+
+    conn.query_row(
+        "SELECT pk FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, snapshot.id],
+        |row| row.get(0),
+    ).map_err(|e| AppError::Storage(format!("Failed to get snapshot pk: {}", e)))
+}
+
+// Rough size of the row a snapshot becomes; the JSON columns dominate
+fn snapshot_payload(snapshot: &Snapshot) -> usize {
+    let paths: usize = snapshot.paths.iter().map(|p| p.len() + 3).sum();
+    let tags: usize = snapshot.tags.iter().flatten().map(|t| t.len() + 3).sum();
+    256 + paths + tags
+}
+
+// Large writes are split into transactions sized to take about BATCH_TARGET each.
+// The connection is released between them so reads queued behind the write get a turn.
+const BATCH_START_ROWS: usize = 500;
+const BATCH_MIN_ROWS: usize = 50;
+const BATCH_MAX_ROWS: usize = 5_000;
+const BATCH_MAX_BYTES: usize = 1024 * 1024;
+const BATCH_TARGET: Duration = Duration::from_millis(50);
+
+/// Writes `items` in transactions whose row count adapts to how long the previous
+/// one took, capped by the estimated `payload` bytes. `on_written` gets the number
+/// of items committed so far. A failure keeps the batches committed before it.
+fn write_in_batches<T>(
+    items: &[T],
+    payload: impl Fn(&T) -> usize,
+    mut write: impl FnMut(&Connection, &T) -> Result<()>,
+    mut on_written: impl FnMut(usize),
+) -> Result<()> {
+    let mut rows = BATCH_START_ROWS;
+    let mut written = 0;
+    while written < items.len() {
+        let mut end = written;
+        let mut bytes = 0;
+        while end < items.len() && end - written < rows {
+            let size = payload(&items[end]);
+            if end > written && bytes + size > BATCH_MAX_BYTES {
+                break;
+            }
+            bytes += size;
+            end += 1;
+        }
+
+        let started = Instant::now();
+        {
+            let conn = get_connection()?;
+            let tx = write_transaction(&conn)
+                .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+            for item in &items[written..end] {
+                write(&tx, item)?;
+            }
+            tx.commit()
+                .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        }
+        let elapsed = started.elapsed();
+
+        rows = if elapsed > BATCH_TARGET {
+            (rows / 2).max(BATCH_MIN_ROWS)
+        } else if elapsed < BATCH_TARGET / 2 {
+            (rows * 2).min(BATCH_MAX_ROWS)
+        } else {
+            rows
+        };
+        debug!("Wrote {} rows in {:?}; next batch up to {} rows", end - written, elapsed, rows);
+        written = end;
+        on_written(written);
+        std::thread::yield_now();
+    }
+    Ok(())
+}
 
-    for snapshot in snapshots {
-        let time_unix = parse_iso_to_unix(&snapshot.time);
+/// What a full resync changed in the cached snapshot list
+#[derive(Debug, Clone, Default)]
+pub struct ResyncSummary {
+    /// IDs of snapshots that weren't cached before
+    pub added: Vec<String>,
+    pub updated: usize,
+    /// Snapshots dropped from the cache, as they were cached
+    pub removed: Vec<Snapshot>,
+}
 
-        let paths_json = serde_json::to_string(&snapshot.paths)
-            .map_err(|e| AppError::Storage(format!("Failed to serialize paths: {}", e)))?;
+/// Makes the cached snapshot list of a repository exactly `snapshots`.
+///
+/// The list is first written to a shadow table, then swapped in: snapshots restic
+/// no longer has are deleted along with their stats and file listings, and the
+/// rest are upserted so existing rows keep their pk. Everything happens in one
+/// transaction, so an interrupted resync leaves the previous list untouched.
+/// `on_written` gets the number of snapshots written to the shadow table so far.
+#[instrument(skip(snapshots, on_written), fields(count = snapshots.len()))]
+pub fn replace_snapshots<F: FnMut(usize)>(
+    repo_id: &str,
+    snapshots: &[Snapshot],
+    chunk_size: usize,
+    mut on_written: F,
+) -> Result<ResyncSummary> {
+    info!("Replacing cached snapshots for repo {} with {} snapshots", repo_id, snapshots.len());
+
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
 
-        let tags_json = snapshot.tags.as_ref()
-            .map(|t| serde_json::to_string(t))
-            .transpose()
-            .map_err(|e| AppError::Storage(format!("Failed to serialize tags: {}", e)))?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS resync_snapshots (
+            id TEXT PRIMARY KEY,
+            short_id TEXT NOT NULL,
+            time INTEGER NOT NULL,
+            hostname TEXT,
+            username TEXT,
+            paths TEXT,
+            tags TEXT,
+            parent TEXT,
+            tree TEXT,
+            program_version TEXT,
+            original TEXT,
+            uid INTEGER,
+            gid INTEGER,
+            excludes TEXT,
+            summary TEXT
+        );
+        DELETE FROM temp.resync_snapshots;"
+    ).map_err(|e| AppError::Storage(format!("Failed to create resync table: {}", e)))?;
+
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO temp.resync_snapshots
+             (id, short_id, time, hostname, username, paths, tags, parent, tree,
+              program_version, original, uid, gid, excludes, summary)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+        let chunk_size = chunk_size.max(1);
+        let mut written = 0;
+        for chunk in snapshots.chunks(chunk_size) {
+            for snapshot in chunk {
+                let (paths_json, tags_json) = snapshot_json_columns(snapshot)?;
+                let (excludes_json, summary_json) = snapshot_detail_columns(snapshot)?;
+                insert.execute(params![
+                    snapshot.id,
+                    snapshot.short_id,
+                    parse_iso_to_unix(&snapshot.time),
+                    snapshot.hostname,
+                    snapshot.username,
+                    paths_json,
+                    tags_json,
+                    snapshot.parent,
+                    snapshot.tree,
+                    snapshot.program_version,
+                    snapshot.original,
+                    snapshot.uid,
+                    snapshot.gid,
+                    excludes_json,
+                    summary_json,
+                ]).map_err(|e| AppError::Storage(format!("Failed to insert snapshot metadata: {}", e)))?;
+            }
+            written += chunk.len();
+            on_written(written);
+        }
+    }
 
-        // Use INSERT OR REPLACE to ensure snapshots are updated if they already exist
+    let removed_ids: Vec<String> = {
+        let mut stmt = tx.prepare(
+            "SELECT id FROM snapshots
+             WHERE repo_id = ?1 AND id NOT IN (SELECT id FROM temp.resync_snapshots)"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+        let ids: std::result::Result<Vec<String>, _> = stmt.query_map(params![repo_id], |row| row.get(0))
+            .map_err(|e| AppError::Storage(format!("Failed to query removed snapshots: {}", e)))?
+            .collect();
+        ids.map_err(|e| AppError::Storage(format!("Failed to fetch removed snapshots: {}", e)))?
+    };
+    let removed = snapshots_with_ids_in(&tx, repo_id, &removed_ids)?;
+    for snapshot_id in &removed_ids {
         tx.execute(
-            "INSERT OR REPLACE INTO snapshots
-             (id, repo_id, short_id, time, hostname, username, paths, tags, parent, tree)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            params![
-                snapshot.id,
-                repo_id,
-                snapshot.short_id,
-                time_unix,
-                snapshot.hostname,
-                snapshot.username,
-                paths_json,
-                tags_json,
-                snapshot.parent,
-                snapshot.tree,
-            ],
-        ).map_err(|e| AppError::Storage(format!("Failed to insert snapshot metadata: {}", e)))?;
-    }
-
-    // Verify BEFORE commit (within transaction) to ensure we see the changes
-    let count_in_tx: i64 = tx.query_row(
-        "SELECT COUNT(*) FROM snapshots WHERE repo_id = ?1",
+            "DELETE FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+            params![repo_id, snapshot_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to delete snapshot: {}", e)))?;
+        delete_node_tree_in(&tx, repo_id, snapshot_id)?;
+    }
+
+    let added: Vec<String> = {
+        let mut stmt = tx.prepare(
+            "SELECT id FROM temp.resync_snapshots
+             WHERE id NOT IN (SELECT id FROM snapshots WHERE repo_id = ?1)"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+        let ids: std::result::Result<Vec<String>, _> = stmt.query_map(params![repo_id], |row| row.get(0))
+            .map_err(|e| AppError::Storage(format!("Failed to query new snapshots: {}", e)))?
+            .collect();
+        ids.map_err(|e| AppError::Storage(format!("Failed to fetch new snapshots: {}", e)))?
+    };
+
+    // `WHERE true` tells SQLite the ON CONFLICT belongs to the INSERT, not a join
+    tx.execute(
+        "INSERT INTO snapshots
+         (id, repo_id, short_id, time, hostname, username, paths, tags, parent, tree,
+          program_version, original, uid, gid, excludes, summary)
+         SELECT id, ?1, short_id, time, hostname, username, paths, tags, parent, tree,
+                program_version, original, uid, gid, excludes, summary
+         FROM temp.resync_snapshots WHERE true
+         ON CONFLICT(repo_id, id) DO UPDATE SET
+            short_id = excluded.short_id, time = excluded.time, hostname = excluded.hostname,
+            username = excluded.username, paths = excluded.paths, tags = excluded.tags,
+            parent = excluded.parent, tree = excluded.tree,
+            program_version = excluded.program_version, original = excluded.original,
+            uid = excluded.uid, gid = excluded.gid, excludes = excluded.excludes,
+            summary = excluded.summary",
         params![repo_id],
-        |row| row.get(0)
-    ).map_err(|e| AppError::Storage(format!("Failed to verify snapshot count in transaction: {}", e)))?;
+    ).map_err(|e| AppError::Storage(format!("Failed to swap in snapshots: {}", e)))?;
+
+    tx.execute("DELETE FROM temp.resync_snapshots", [])
+        .map_err(|e| AppError::Storage(format!("Failed to clear resync table: {}", e)))?;
 
     tx.commit()
         .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
 
-    info!("Metadata save completed: {} snapshots saved to database", snapshots.len());
-    info!("Verification: Database now contains {} total snapshots for repo {}", count_in_tx, repo_id);
-    Ok(())
+    let summary = ResyncSummary {
+        updated: snapshots.len().saturating_sub(added.len()),
+        added,
+        removed,
+    };
+    info!("Resync of repo {}: {} added, {} updated, {} removed",
+        repo_id, summary.added.len(), summary.updated, summary.removed.len());
+    Ok(summary)
+}
+
+fn snapshots_with_ids_in(conn: &Connection, repo_id: &str, snapshot_ids: &[String]) -> Result<Vec<Snapshot>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.id, s.short_id, s.time, s.hostname, s.username, s.paths, s.tags, s.parent, s.tree,
+                s.program_version, s.original, s.uid, s.gid, s.excludes, s.summary
+         FROM snapshots s
+         WHERE repo_id = ?1 AND id IN (SELECT value FROM json_each(?2))
+         ORDER BY time DESC"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let snapshots: std::result::Result<Vec<Snapshot>, _> = stmt.query_map(
+        params![repo_id, serde_json::to_string(snapshot_ids)?],
+        |row| {
+            let paths_str: String = row.get(5)?;
+            let tags_str: Option<String> = row.get(6)?;
+            with_details(Snapshot {
+                id: row.get(0)?,
+                short_id: row.get(1)?,
+                time: format_unix_timestamp(row.get(2)?),
+                hostname: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                username: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                paths: serde_json::from_str(&paths_str).unwrap_or_default(),
+                tags: tags_str.and_then(|s| serde_json::from_str(&s).ok()),
+                parent: row.get(7)?,
+                tree: row.get(8)?,
+                ..Default::default()
+            }, row, 9)
+        },
+    ).map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?
+        .collect();
+    snapshots.map_err(|e| AppError::Storage(format!("Failed to fetch snapshots: {}", e)))
+}
+
+/// Cached metadata of the given snapshots, newest first
+pub fn get_snapshots_by_ids(repo_id: &str, snapshot_ids: &[String]) -> Result<Vec<Snapshot>> {
+    let conn = get_connection()?;
+    snapshots_with_ids_in(&conn, repo_id, snapshot_ids)
+}
+
+fn snapshot_json_columns(snapshot: &Snapshot) -> Result<(String, Option<String>)> {
+    let paths_json = serde_json::to_string(&snapshot.paths)
+        .map_err(|e| AppError::Storage(format!("Failed to serialize paths: {}", e)))?;
+
+    let tags_json = snapshot.tags.as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| AppError::Storage(format!("Failed to serialize tags: {}", e)))?;
+    Ok((paths_json, tags_json))
+}
+
+fn snapshot_detail_columns(snapshot: &Snapshot) -> Result<(Option<String>, Option<String>)> {
+    let excludes_json = (!snapshot.excludes.is_empty())
+        .then(|| serde_json::to_string(&snapshot.excludes))
+        .transpose()
+        .map_err(|e| AppError::Storage(format!("Failed to serialize excludes: {}", e)))?;
+    let summary_json = snapshot.summary.as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| AppError::Storage(format!("Failed to serialize summary: {}", e)))?;
+    Ok((excludes_json, summary_json))
+}
+
+/// Removes forgotten snapshots; their cached stats go with them through the foreign key.
+#[instrument(skip(snapshot_ids), fields(count = snapshot_ids.len()))]
+pub fn delete_snapshots(repo_id: &str, snapshot_ids: &[String]) -> Result<usize> {
+    info!("Deleting {} snapshots from database for repo {}", snapshot_ids.len(), repo_id);
+
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+
+    let mut deleted = 0;
+    for snapshot_id in snapshot_ids {
+        deleted += tx.execute(
+            "DELETE FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+            params![repo_id, snapshot_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to delete snapshot: {}", e)))?;
+        delete_node_tree_in(&tx, repo_id, snapshot_id)?;
+    }
+
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+
+    Ok(deleted)
 }
 
 #[instrument]
 pub fn update_last_delta_check(repo_id: &str) -> Result<()> {
     debug!("Updating last delta check for repo: {}", repo_id);
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    let conn = get_connection()?;
 
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -339,8 +1312,8 @@ pub fn update_last_delta_check(repo_id: &str) -> Result<()> {
         .as_secs() as i64;
 
     conn.execute(
-        "INSERT OR REPLACE INTO meta (repo_id, last_delta_check, snapshot_count)
-         VALUES (?1, ?2, COALESCE((SELECT snapshot_count FROM meta WHERE repo_id = ?1), 0))",
+        "INSERT INTO meta (repo_id, last_delta_check) VALUES (?1, ?2)
+         ON CONFLICT(repo_id) DO UPDATE SET last_delta_check = excluded.last_delta_check",
         params![repo_id, now],
     ).map_err(|e| AppError::Storage(format!("Failed to update last delta check: {}", e)))?;
 
@@ -348,25 +1321,144 @@ pub fn update_last_delta_check(repo_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Flags repository-level aggregates as outdated after new snapshots were cached.
+/// `record_repo_usage` clears the flag once they have been measured again.
 #[instrument]
-pub fn get_repo_meta(repo_id: &str) -> Result<RepoMeta> {
-    debug!("Getting metadata for repo: {}", repo_id);
+pub fn mark_aggregates_stale(repo_id: &str) -> Result<()> {
+    debug!("Marking aggregates stale for repo: {}", repo_id);
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO meta (repo_id, aggregates_stale) VALUES (?1, 1)
+         ON CONFLICT(repo_id) DO UPDATE SET aggregates_stale = 1",
+        params![repo_id],
+    ).map_err(|e| AppError::Storage(format!("Failed to mark aggregates stale: {}", e)))?;
+
+    Ok(())
+}
+
+fn repo_meta_from_row(row: &rusqlite::Row) -> rusqlite::Result<RepoMeta> {
+    Ok(RepoMeta {
+        repo_id: row.get(0)?,
+        last_delta_check: row.get::<_, Option<i64>>(1)?.unwrap_or(0),
+        snapshot_count: row.get(2)?,
+        aggregates_stale: row.get::<_, Option<i64>>(3)?.unwrap_or(0) != 0,
+        last_connected_at: row.get(4)?,
+    })
+}
+
+/// Metadata of every repository with cached data, keyed by repo ID
+#[instrument]
+pub fn get_all_repo_meta() -> Result<HashMap<String, RepoMeta>> {
+    let conn = get_connection()?;
 
     let mut stmt = conn.prepare(
-        "SELECT repo_id, last_delta_check, snapshot_count FROM meta WHERE repo_id = ?1"
+        "SELECT ids.repo_id, m.last_delta_check,
+                (SELECT COUNT(*) FROM snapshots s WHERE s.repo_id = ids.repo_id),
+                m.aggregates_stale, m.last_connected_at
+         FROM (SELECT repo_id FROM meta UNION SELECT repo_id FROM snapshots) ids
+         LEFT JOIN meta m ON m.repo_id = ids.repo_id"
     ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
 
-    let meta = stmt.query_row([repo_id], |row| {
-        Ok(RepoMeta {
-            repo_id: row.get(0)?,
-            last_delta_check: row.get(1)?,
-            snapshot_count: row.get(2)?,
-        })
-    });
+    let metas: std::result::Result<HashMap<_, _>, _> = stmt.query_map([], repo_meta_from_row)
+        .map_err(|e| AppError::Storage(format!("Failed to load repo metadata: {}", e)))?
+        .map(|meta| meta.map(|m| (m.repo_id.clone(), m)))
+        .collect();
+
+    metas.map_err(|e| AppError::Storage(format!("Failed to load repo metadata: {}", e)))
+}
+
+pub fn record_connection(repo_id: &str) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO meta (repo_id, last_connected_at) VALUES (?1, strftime('%s', 'now'))
+         ON CONFLICT(repo_id) DO UPDATE SET last_connected_at = excluded.last_connected_at",
+        params![repo_id],
+    ).map_err(|e| AppError::Storage(format!("Failed to record connection: {}", e)))?;
+    Ok(())
+}
+
+pub fn record_connection_error(repo_id: &str, error: &str) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO meta (repo_id, last_error, last_error_at) VALUES (?1, ?2, strftime('%s', 'now'))
+         ON CONFLICT(repo_id) DO UPDATE SET last_error = excluded.last_error, last_error_at = excluded.last_error_at",
+        params![repo_id, error],
+    ).map_err(|e| AppError::Storage(format!("Failed to record connection error: {}", e)))?;
+    Ok(())
+}
+
+pub fn record_check_result(repo_id: &str, ok: bool) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO meta (repo_id, last_check_at, last_check_ok) VALUES (?1, strftime('%s', 'now'), ?2)
+         ON CONFLICT(repo_id) DO UPDATE SET
+            last_check_at = excluded.last_check_at, last_check_ok = excluded.last_check_ok",
+        params![repo_id, ok],
+    ).map_err(|e| AppError::Storage(format!("Failed to record check result: {}", e)))?;
+    Ok(())
+}
+
+/// Everything cached about a repository that goes into its status
+#[derive(Debug, Clone, Default)]
+pub struct StatusSignals {
+    pub last_connected_at: Option<i64>,
+    pub last_error: Option<(i64, String)>,
+    pub last_check: Option<(i64, bool)>,
+    pub snapshot_count: i64,
+    pub newest_snapshot_at: Option<i64>,
+    pub quota_level: i64,
+    pub health: Option<RepoHealth>,
+}
+
+#[instrument]
+pub fn get_status_signals(repo_id: &str) -> Result<StatusSignals> {
+    let conn = get_connection()?;
+
+    let mut signals = match conn.query_row(
+        "SELECT last_connected_at, last_error_at, last_error, last_check_at, last_check_ok
+         FROM meta WHERE repo_id = ?1",
+        params![repo_id],
+        |row| Ok(StatusSignals {
+            last_connected_at: row.get(0)?,
+            last_error: row.get::<_, Option<i64>>(1)?.zip(row.get::<_, Option<String>>(2)?),
+            last_check: row.get::<_, Option<i64>>(3)?.zip(row.get::<_, Option<bool>>(4)?),
+            ..Default::default()
+        }),
+    ) {
+        Ok(signals) => signals,
+        Err(rusqlite::Error::QueryReturnedNoRows) => StatusSignals::default(),
+        Err(e) => return Err(AppError::Storage(format!("Failed to get repo metadata: {}", e))),
+    };
+
+    (signals.snapshot_count, signals.newest_snapshot_at) = conn.query_row(
+        "SELECT COUNT(*), MAX(time) FROM snapshots WHERE repo_id = ?1",
+        params![repo_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| AppError::Storage(format!("Failed to query snapshots: {}", e)))?;
+    signals.quota_level = get_repo_usage(repo_id)?.map_or(0, |u| u.quota_level);
+    signals.health = load_repo_health(repo_id)?;
+    Ok(signals)
+}
+
+#[instrument]
+pub fn get_repo_meta(repo_id: &str) -> Result<RepoMeta> {
+    debug!("Getting metadata for repo: {}", repo_id);
+
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT repo_id, last_delta_check,
+                (SELECT COUNT(*) FROM snapshots s WHERE s.repo_id = meta.repo_id),
+                aggregates_stale, last_connected_at
+         FROM meta WHERE repo_id = ?1"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let meta = stmt.query_row([repo_id], repo_meta_from_row);
 
     match meta {
         Ok(m) => {
@@ -379,6 +1471,8 @@ pub fn get_repo_meta(repo_id: &str) -> Result<RepoMeta> {
                 repo_id: repo_id.to_string(),
                 last_delta_check: 0,
                 snapshot_count: 0,
+                aggregates_stale: false,
+                last_connected_at: None,
             })
         }
         Err(e) => Err(AppError::Storage(format!("Failed to get repo metadata: {}", e)))
@@ -389,27 +1483,641 @@ pub fn get_repo_meta(repo_id: &str) -> Result<RepoMeta> {
 pub fn clear_repo_cache(repo_id: &str) -> Result<()> {
     info!("Clearing cache for repo: {}", repo_id);
 
-    let conn_guard = get_connection()?;
-    let conn = conn_guard.as_ref()
-        .ok_or_else(|| AppError::Storage("Database not initialized".to_string()))?;
+    let conn = get_connection()?;
 
-    let tx = conn.unchecked_transaction()
+    let tx = write_transaction(&conn)
         .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
 
+    delete_cached_rows(&tx, repo_id)?;
+
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+
+    info!("Cache cleared successfully");
+    Ok(())
+}
+
+fn delete_cached_rows(conn: &Connection, repo_id: &str) -> Result<()> {
     // Delete snapshots (stats will be cascade deleted)
-    tx.execute("DELETE FROM snapshots WHERE repo_id = ?1", params![repo_id])
+    conn.execute("DELETE FROM snapshots WHERE repo_id = ?1", params![repo_id])
         .map_err(|e| AppError::Storage(format!("Failed to delete snapshots: {}", e)))?;
 
-    tx.execute("DELETE FROM meta WHERE repo_id = ?1", params![repo_id])
+    conn.execute("DELETE FROM meta WHERE repo_id = ?1", params![repo_id])
         .map_err(|e| AppError::Storage(format!("Failed to delete metadata: {}", e)))?;
 
+    conn.execute("DELETE FROM repo_usage WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete repository usage: {}", e)))?;
+
+    conn.execute("DELETE FROM quota_events WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete quota events: {}", e)))?;
+
+    conn.execute("DELETE FROM node_dirs WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete cached listings: {}", e)))?;
+
+    conn.execute("DELETE FROM node_names WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete cached listings: {}", e)))?;
+
+    conn.execute("DELETE FROM repo_health WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete repository health: {}", e)))?;
+
+    Ok(())
+}
+
+// Cached tables with a repo_id column, and the generated key each one leaves out on restore
+const REPO_CACHE_TABLES: &[(&str, Option<&str>)] = &[
+    ("meta", None),
+    ("repo_usage", None),
+    ("quota_events", Some("id")),
+    ("node_dirs", None),
+    ("node_names", None),
+    ("repo_health", None),
+];
+
+// Cached tables hanging off a snapshot through snapshot_pk
+const SNAPSHOT_CACHE_TABLES: &[(&str, Option<&str>)] = &[
+    ("stats", None),
+    ("snapshot_deltas", None),
+    ("change_summaries", None),
+    ("files", Some("id")),
+];
+
+/// Runs `f` with the database file at `path` attached as `backup`, detaching it afterwards
+fn with_attached<T>(conn: &Connection, path: &Path, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    let path = path.to_str()
+        .ok_or_else(|| AppError::Storage(format!("Backup path is not valid UTF-8: {:?}", path)))?;
+    conn.execute("ATTACH DATABASE ?1 AS backup", params![path])
+        .map_err(|e| AppError::Storage(format!("Failed to open cache backup: {}", e)))?;
+    let result = f(conn);
+    if let Err(e) = conn.execute("DETACH DATABASE backup", []) {
+        warn!("Failed to close cache backup: {}", e);
+    }
+    result
+}
+
+/// Copies every cached row of a repository into a new database file at `dest`.
+/// Returns the number of snapshots copied.
+#[instrument]
+pub fn export_repo_cache(repo_id: &str, dest: &Path) -> Result<usize> {
+    let conn = get_connection()?;
+    with_attached(&conn, dest, |conn| {
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Deferred)
+            .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+        tx.execute(
+            "CREATE TABLE backup.backup_info AS SELECT ?1 AS repo_id, strftime('%s', 'now') AS created_at",
+            params![repo_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to write cache backup: {}", e)))?;
+        let snapshots = tx.execute(
+            "CREATE TABLE backup.snapshots AS SELECT * FROM main.snapshots WHERE repo_id = ?1",
+            params![repo_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to back up snapshots: {}", e)))?;
+        for (table, _) in REPO_CACHE_TABLES {
+            tx.execute(
+                &format!("CREATE TABLE backup.{0} AS SELECT * FROM main.{0} WHERE repo_id = ?1", table),
+                params![repo_id],
+            ).map_err(|e| AppError::Storage(format!("Failed to back up {}: {}", table, e)))?;
+        }
+        for (table, _) in SNAPSHOT_CACHE_TABLES {
+            tx.execute(
+                &format!("CREATE TABLE backup.{0} AS SELECT t.* FROM main.{0} t
+                          JOIN main.snapshots s ON s.pk = t.snapshot_pk WHERE s.repo_id = ?1", table),
+                params![repo_id],
+            ).map_err(|e| AppError::Storage(format!("Failed to back up {}: {}", table, e)))?;
+        }
+        tx.commit()
+            .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        Ok(snapshots)
+    })
+}
+
+// Columns both copies of a table have, so backups from older schemas still restore
+fn shared_columns(conn: &Connection, table: &str, skip: &[&str]) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA backup.table_info({})", table))
+        .map_err(|e| AppError::Storage(format!("Failed to read backed up {} schema: {}", table, e)))?;
+    let backup_columns: Vec<String> = stmt.query_map([], |row| row.get(1))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| AppError::Storage(format!("Failed to read backed up {} schema: {}", table, e)))?;
+    Ok(table_columns(conn, table)?.into_iter()
+        .filter(|c| backup_columns.contains(c) && !skip.contains(&c.as_str()))
+        .collect())
+}
+
+/// Replaces the cached rows of a repository with those of a backup made by
+/// `export_repo_cache`. Snapshots get new keys; rows hanging off them follow by snapshot id.
+#[instrument]
+pub fn import_repo_cache(repo_id: &str, src: &Path) -> Result<usize> {
+    let conn = get_connection()?;
+    with_attached(&conn, src, |conn| {
+        let backup_repo: String = conn.query_row("SELECT repo_id FROM backup.backup_info", [], |row| row.get(0))
+            .map_err(|e| AppError::Storage(format!("Failed to read cache backup: {}", e)))?;
+        if backup_repo != repo_id {
+            return Err(AppError::Storage(format!("Cache backup belongs to repository {}", backup_repo)));
+        }
+
+        let tx = write_transaction(conn)
+            .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+        delete_cached_rows(&tx, repo_id)?;
+
+        let columns = shared_columns(&tx, "snapshots", &["pk"])?.join(", ");
+        let snapshots = tx.execute(
+            &format!("INSERT INTO main.snapshots ({0}) SELECT {0} FROM backup.snapshots", columns),
+            [],
+        ).map_err(|e| AppError::Storage(format!("Failed to restore snapshots: {}", e)))?;
+
+        for (table, generated) in REPO_CACHE_TABLES {
+            let skip: Vec<&str> = generated.iter().copied().collect();
+            let columns = shared_columns(&tx, table, &skip)?.join(", ");
+            tx.execute(&format!("INSERT INTO main.{0} ({1}) SELECT {1} FROM backup.{0}", table, columns), [])
+                .map_err(|e| AppError::Storage(format!("Failed to restore {}: {}", table, e)))?;
+        }
+
+        for (table, generated) in SNAPSHOT_CACHE_TABLES {
+            let mut skip = vec!["snapshot_pk"];
+            skip.extend(generated.iter().copied());
+            let columns = shared_columns(&tx, table, &skip)?;
+            // Backups made before the table existed don't have it
+            if columns.is_empty() {
+                continue;
+            }
+            let selected: Vec<String> = columns.iter().map(|c| format!("t.{}", c)).collect();
+            tx.execute(
+                &format!("INSERT INTO main.{0} (snapshot_pk, {1}) SELECT s.pk, {2} FROM backup.{0} t
+                          JOIN backup.snapshots b ON b.pk = t.snapshot_pk
+                          JOIN main.snapshots s ON s.repo_id = b.repo_id AND s.id = b.id",
+                         table, columns.join(", "), selected.join(", ")),
+                [],
+            ).map_err(|e| AppError::Storage(format!("Failed to restore {}: {}", table, e)))?;
+        }
+
+        tx.commit()
+            .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+        info!("Restored {} cached snapshots for repo {}", snapshots, repo_id);
+        Ok(snapshots)
+    })
+}
+
+/// Size and row counts of the database, for bug reports that shouldn't carry its contents
+#[derive(Debug, Serialize, Clone)]
+pub struct DatabaseSummary {
+    pub schema_version: i64,
+    pub size_bytes: u64,
+    pub wal_bytes: u64,
+    pub row_counts: std::collections::BTreeMap<String, i64>,
+}
+
+#[instrument]
+pub fn database_summary() -> Result<DatabaseSummary> {
+    let db_path = get_config_dir().map_err(AppError::Storage)?.join("snapshots.db");
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(
+        // The search index's shadow tables only mirror the files table
+        "SELECT name FROM sqlite_master WHERE type = 'table'
+         AND name NOT LIKE 'sqlite_%' AND name NOT LIKE 'files_fts%' ORDER BY name"
+    ).map_err(|e| AppError::Storage(format!("Failed to list tables: {}", e)))?;
+    let tables: Vec<String> = stmt.query_map([], |row| row.get(0))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| AppError::Storage(format!("Failed to list tables: {}", e)))?;
+
+    let mut row_counts = std::collections::BTreeMap::new();
+    for table in tables {
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))
+            .map_err(|e| AppError::Storage(format!("Failed to count {}: {}", table, e)))?;
+        row_counts.insert(table, count);
+    }
+
+    let file_size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    Ok(DatabaseSummary {
+        schema_version: schema_version(&conn)?,
+        size_bytes: file_size(&db_path),
+        wal_bytes: file_size(&db_path.with_extension("db-wal")),
+        row_counts,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedStats {
+    pub snapshot_id: String,
+    pub total_size: Option<u64>,
+    pub total_file_count: Option<u64>,
+}
+
+#[instrument(skip(snapshot_ids), fields(count = snapshot_ids.len()))]
+pub fn get_cached_stats(repo_id: &str, snapshot_ids: &[String]) -> Result<Vec<CachedStats>> {
+    debug!("Getting cached stats for {} snapshots in repo {}", snapshot_ids.len(), repo_id);
+
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT st.total_size, st.total_file_count FROM snapshots s
+         INNER JOIN stats st ON s.pk = st.snapshot_pk
+         WHERE s.repo_id = ?1 AND s.id = ?2"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let mut stats = Vec::with_capacity(snapshot_ids.len());
+    for snapshot_id in snapshot_ids {
+        let row = stmt.query_row(params![repo_id, snapshot_id], |row| {
+            Ok(CachedStats {
+                snapshot_id: snapshot_id.clone(),
+                total_size: row.get(0)?,
+                total_file_count: row.get(1)?,
+            })
+        });
+
+        match row {
+            Ok(s) => stats.push(s),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(AppError::Storage(format!("Failed to get cached stats: {}", e))),
+        }
+    }
+
+    Ok(stats)
+}
+
+/// A value snapshots can be filtered by and how many cached snapshots have it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Filter values across a repository's cached snapshots, most common first
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SnapshotFacets {
+    pub hosts: Vec<FacetCount>,
+    pub tags: Vec<FacetCount>,
+    pub paths: Vec<FacetCount>,
+}
+
+fn facet_counts(conn: &Connection, sql: &str, repo_id: &str) -> Result<Vec<FacetCount>> {
+    let mut stmt = conn.prepare(sql)
+        .map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let counts: std::result::Result<Vec<_>, _> = stmt.query_map(params![repo_id], |row| {
+        Ok(FacetCount { value: row.get(0)?, count: row.get(1)? })
+    }).map_err(|e| AppError::Storage(format!("Failed to query snapshot facets: {}", e)))?
+        .collect();
+    counts.map_err(|e| AppError::Storage(format!("Failed to fetch snapshot facets: {}", e)))
+}
+
+#[instrument]
+pub fn get_snapshot_facets(repo_id: &str) -> Result<SnapshotFacets> {
+    let conn = get_connection()?;
+
+    // Tags and paths are stored as JSON arrays
+    Ok(SnapshotFacets {
+        hosts: facet_counts(&conn,
+            "SELECT hostname, COUNT(*) FROM snapshots
+             WHERE repo_id = ?1 AND hostname IS NOT NULL AND hostname != ''
+             GROUP BY hostname ORDER BY COUNT(*) DESC, hostname",
+            repo_id)?,
+        tags: facet_counts(&conn,
+            "SELECT t.value, COUNT(*) FROM snapshots s, json_each(s.tags) t
+             WHERE s.repo_id = ?1
+             GROUP BY t.value ORDER BY COUNT(*) DESC, t.value",
+            repo_id)?,
+        paths: facet_counts(&conn,
+            "SELECT p.value, COUNT(*) FROM snapshots s, json_each(s.paths) p
+             WHERE s.repo_id = ?1
+             GROUP BY p.value ORDER BY COUNT(*) DESC, p.value",
+            repo_id)?,
+    })
+}
+
+/// Stores measured stats for a cached snapshot. Returns false when the snapshot
+/// isn't in the cache, since stats can only hang off a cached snapshot.
+#[instrument]
+pub fn save_snapshot_stats(
+    repo_id: &str,
+    snapshot_id: &str,
+    total_size: Option<u64>,
+    total_file_count: Option<u64>,
+) -> Result<bool> {
+    let conn = get_connection()?;
+
+    let saved = conn.execute(
+        "INSERT OR REPLACE INTO stats (snapshot_pk, total_size, total_file_count)
+         SELECT pk, ?3, ?4 FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, snapshot_id, total_size, total_file_count],
+    ).map_err(|e| AppError::Storage(format!("Failed to save stats: {}", e)))?;
+    Ok(saved > 0)
+}
+
+/// Snapshots whose parent is cached but whose delta hasn't been computed yet
+#[instrument]
+pub fn get_delta_candidates(repo_id: &str) -> Result<Vec<DeltaCandidate>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT s.id, ps.id, st.total_size, st.total_file_count, pst.total_size, pst.total_file_count
+         FROM snapshots s
+         JOIN snapshots ps ON ps.repo_id = s.repo_id AND ps.id = s.parent
+         LEFT JOIN stats st ON st.snapshot_pk = s.pk
+         LEFT JOIN stats pst ON pst.snapshot_pk = ps.pk
+         WHERE s.repo_id = ?1
+           AND NOT EXISTS (SELECT 1 FROM snapshot_deltas d WHERE d.snapshot_pk = s.pk)
+         ORDER BY s.time DESC"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let candidates: std::result::Result<Vec<DeltaCandidate>, _> = stmt.query_map(params![repo_id], |row| {
+        let pair = |size: Option<u64>, count: Option<u64>| size.zip(count);
+        Ok(DeltaCandidate {
+            snapshot_id: row.get(0)?,
+            parent_id: row.get(1)?,
+            stats: pair(row.get(2)?, row.get(3)?),
+            parent_stats: pair(row.get(4)?, row.get(5)?),
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to query delta candidates: {}", e)))?
+        .collect();
+    candidates.map_err(|e| AppError::Storage(format!("Failed to fetch delta candidates: {}", e)))
+}
+
+pub fn save_snapshot_delta(repo_id: &str, snapshot_id: &str, delta: &SnapshotDelta) -> Result<bool> {
+    let conn = get_connection()?;
+
+    let saved = conn.execute(
+        "INSERT OR REPLACE INTO snapshot_deltas (snapshot_pk, parent_id, size_delta, file_count_delta, source)
+         SELECT pk, ?3, ?4, ?5, ?6 FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, snapshot_id, delta.parent_id, delta.size_delta, delta.file_count_delta, delta.source.as_str()],
+    ).map_err(|e| AppError::Storage(format!("Failed to save snapshot delta: {}", e)))?;
+    Ok(saved > 0)
+}
+
+/// The cached change summary of a snapshot, if `save_change_summary` stored one
+pub fn get_change_summary(repo_id: &str, snapshot_id: &str) -> Result<Option<ChangeSummary>> {
+    let conn = get_connection()?;
+    let summary = conn.query_row(
+        "SELECT s.id, s.time, c.parent_id, c.files_added, c.files_changed, c.files_removed, c.bytes_added, c.bytes_removed
+         FROM change_summaries c JOIN snapshots s ON s.pk = c.snapshot_pk
+         WHERE s.repo_id = ?1 AND s.id = ?2",
+        params![repo_id, snapshot_id],
+        |row| Ok(ChangeSummary {
+            snapshot_id: row.get(0)?,
+            snapshot_time: row.get(1)?,
+            parent_id: row.get(2)?,
+            files_added: row.get(3)?,
+            files_changed: row.get(4)?,
+            files_removed: row.get(5)?,
+            bytes_added: row.get(6)?,
+            bytes_removed: row.get(7)?,
+        }),
+    );
+    match summary {
+        Ok(summary) => Ok(Some(summary)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to load change summary: {}", e))),
+    }
+}
+
+/// Caches the summary; does nothing unless its snapshot is cached too
+pub fn save_change_summary(repo_id: &str, summary: &ChangeSummary) -> Result<bool> {
+    let conn = get_connection()?;
+    let saved = conn.execute(
+        "INSERT OR REPLACE INTO change_summaries
+         (snapshot_pk, parent_id, files_added, files_changed, files_removed, bytes_added, bytes_removed)
+         SELECT pk, ?3, ?4, ?5, ?6, ?7, ?8 FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, summary.snapshot_id, summary.parent_id, summary.files_added, summary.files_changed,
+                summary.files_removed, summary.bytes_added, summary.bytes_removed],
+    ).map_err(|e| AppError::Storage(format!("Failed to save change summary: {}", e)))?;
+    Ok(saved > 0)
+}
+
+/// Stores the latest measured repository size and records a quota event
+/// when the usage moves across one of the budget thresholds.
+#[instrument]
+pub fn record_repo_usage(repo_id: &str, total_size: u64, budget: Option<u64>) -> Result<Option<QuotaEvent>> {
+    debug!("Recording repository usage for repo: {}", repo_id);
+
+    let conn = get_connection()?;
+
+    let level = match budget {
+        Some(budget) if budget > 0 => {
+            let percent = total_size as f64 / budget as f64 * 100.0;
+            QUOTA_THRESHOLDS.iter()
+                .copied()
+                .find(|t| percent >= *t as f64)
+                .unwrap_or(0)
+        }
+        _ => 0,
+    };
+
+    let previous_level: i64 = conn.query_row(
+        "SELECT quota_level FROM repo_usage WHERE repo_id = ?1",
+        params![repo_id],
+        |row| row.get(0)
+    ).or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(0),
+        e => Err(e),
+    }).map_err(|e| AppError::Storage(format!("Failed to read repository usage: {}", e)))?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+
+    tx.execute(
+        "INSERT OR REPLACE INTO repo_usage (repo_id, total_size, quota_level, measured_at)
+         VALUES (?1, ?2, ?3, strftime('%s', 'now'))",
+        params![repo_id, total_size, level],
+    ).map_err(|e| AppError::Storage(format!("Failed to store repository usage: {}", e)))?;
+
+    tx.execute(
+        "UPDATE meta SET aggregates_stale = 0 WHERE repo_id = ?1",
+        params![repo_id],
+    ).map_err(|e| AppError::Storage(format!("Failed to clear stale aggregates flag: {}", e)))?;
+
+    let event = match budget {
+        Some(budget) if level != previous_level => {
+            tx.execute(
+                "INSERT INTO quota_events (repo_id, threshold, total_size, budget) VALUES (?1, ?2, ?3, ?4)",
+                params![repo_id, level, total_size, budget],
+            ).map_err(|e| AppError::Storage(format!("Failed to record quota event: {}", e)))?;
+
+            info!("Repository {} moved from quota level {}% to {}%", repo_id, previous_level, level);
+            Some(QuotaEvent {
+                repo_id: repo_id.to_string(),
+                threshold: level,
+                total_size,
+                budget,
+                recorded_at: chrono::Utc::now().timestamp(),
+            })
+        }
+        _ => None,
+    };
+
     tx.commit()
         .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
 
-    info!("Cache cleared successfully");
+    Ok(event)
+}
+
+#[instrument]
+pub fn get_repo_usage(repo_id: &str) -> Result<Option<RepoUsage>> {
+    debug!("Getting usage for repo: {}", repo_id);
+
+    let conn = get_connection()?;
+
+    let usage = conn.query_row(
+        "SELECT repo_id, total_size, quota_level, measured_at FROM repo_usage WHERE repo_id = ?1",
+        params![repo_id],
+        |row| Ok(RepoUsage {
+            repo_id: row.get(0)?,
+            total_size: row.get(1)?,
+            quota_level: row.get(2)?,
+            measured_at: row.get(3)?,
+        })
+    );
+
+    match usage {
+        Ok(u) => Ok(Some(u)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to get repository usage: {}", e)))
+    }
+}
+
+#[instrument]
+pub fn get_quota_events(repo_id: &str, limit: i64) -> Result<Vec<QuotaEvent>> {
+    debug!("Getting quota events for repo: {}", repo_id);
+
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT repo_id, threshold, total_size, budget, recorded_at FROM quota_events
+         WHERE repo_id = ?1
+         ORDER BY recorded_at DESC, id DESC
+         LIMIT ?2"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let events_iter = stmt.query_map(params![repo_id, limit], |row| {
+        Ok(QuotaEvent {
+            repo_id: row.get(0)?,
+            threshold: row.get(1)?,
+            total_size: row.get(2)?,
+            budget: row.get(3)?,
+            recorded_at: row.get(4)?,
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to query quota events: {}", e)))?;
+
+    let events: std::result::Result<Vec<_>, _> = events_iter.collect();
+    events.map_err(|e| AppError::Storage(format!("Failed to fetch quota events: {}", e)))
+}
+
+#[instrument(skip(record), fields(snapshot_id = record.snapshot_id, outcome = record.outcome.as_str()))]
+pub fn record_restore(record: &RestoreRecord) -> Result<()> {
+    debug!("Recording restore of {} to {}", record.snapshot_id, record.target);
+
+    let conn = get_connection()?;
+
+    let paths_json = serde_json::to_string(record.source_paths)
+        .map_err(|e| AppError::Storage(format!("Failed to serialize paths: {}", e)))?;
+    let warnings_json = serde_json::to_string(record.warnings)
+        .map_err(|e| AppError::Storage(format!("Failed to serialize restore warnings: {}", e)))?;
+
+    conn.execute(
+        "INSERT INTO restore_history
+            (repo_id, snapshot_id, source_paths, target, started_at, bytes_restored, outcome, warnings, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            record.repo_id,
+            record.snapshot_id,
+            paths_json,
+            record.target,
+            record.started_at,
+            record.bytes_restored,
+            record.outcome.as_str(),
+            warnings_json,
+            record.error,
+        ],
+    ).map_err(|e| AppError::Storage(format!("Failed to record restore: {}", e)))?;
+
     Ok(())
 }
 
+fn query_restore_history(conn: &Connection, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<RestoreHistoryEntry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, repo_id, snapshot_id, source_paths, target, restored_at,
+                started_at, bytes_restored, outcome, warnings, error
+         FROM restore_history
+         {}
+         ORDER BY restored_at DESC, id DESC
+         LIMIT ?{}",
+        filter,
+        params.len()
+    )).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let entries_iter = stmt.query_map(params, |row| {
+        let paths_json: String = row.get(3)?;
+        let warnings_json: Option<String> = row.get(9)?;
+        Ok(RestoreHistoryEntry {
+            id: row.get(0)?,
+            repo_id: row.get(1)?,
+            snapshot_id: row.get(2)?,
+            source_paths: serde_json::from_str(&paths_json).unwrap_or_default(),
+            target: row.get(4)?,
+            restored_at: row.get(5)?,
+            started_at: row.get(6)?,
+            bytes_restored: row.get(7)?,
+            outcome: RestoreOutcome::parse(row.get::<_, Option<String>>(8)?.as_deref()),
+            warnings: warnings_json.and_then(|w| serde_json::from_str(&w).ok()).unwrap_or_default(),
+            error: row.get(10)?,
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to query restore history: {}", e)))?;
+
+    let entries: std::result::Result<Vec<_>, _> = entries_iter.collect();
+    entries.map_err(|e| AppError::Storage(format!("Failed to fetch restore history: {}", e)))
+}
+
+/// Most recent restores that finished first, for suggesting targets
+#[instrument]
+pub fn get_restore_history(repo_id: &str, limit: i64) -> Result<Vec<RestoreHistoryEntry>> {
+    debug!("Getting restore history for repo: {}", repo_id);
+
+    let conn = get_connection()?;
+
+    query_restore_history(
+        &conn,
+        "WHERE repo_id = ?1 AND (outcome IS NULL OR outcome IN ('success', 'completed_with_warnings'))",
+        &[&repo_id, &limit],
+    )
+}
+
+/// Every recorded restore, most recent first, for one repository or all of them
+#[instrument]
+pub fn list_restore_history(repo_id: Option<&str>, limit: i64) -> Result<Vec<RestoreHistoryEntry>> {
+    let conn = get_connection()?;
+
+    match repo_id {
+        Some(repo_id) => query_restore_history(&conn, "WHERE repo_id = ?1", &[&repo_id, &limit]),
+        None => query_restore_history(&conn, "", &[&limit]),
+    }
+}
+
+/// Returns how many entries were removed
+#[instrument]
+pub fn clear_restore_history(repo_id: Option<&str>) -> Result<usize> {
+    info!("Clearing restore history (repo: {:?})", repo_id);
+
+    let conn = get_connection()?;
+
+    let removed = match repo_id {
+        Some(repo_id) => conn.execute("DELETE FROM restore_history WHERE repo_id = ?1", params![repo_id]),
+        None => conn.execute("DELETE FROM restore_history", []),
+    };
+    removed.map_err(|e| AppError::Storage(format!("Failed to clear restore history: {}", e)))
+}
+
+/// Paths of a cached snapshot, if it is in the cache
+#[instrument]
+pub fn get_snapshot_paths(repo_id: &str, snapshot_id: &str) -> Result<Option<Vec<String>>> {
+    let conn = get_connection()?;
+
+    let paths = conn.query_row(
+        "SELECT paths FROM snapshots WHERE repo_id = ?1 AND (id = ?2 OR short_id = ?2)",
+        params![repo_id, snapshot_id],
+        |row| row.get::<_, Option<String>>(0)
+    );
+
+    match paths {
+        Ok(json) => Ok(json.and_then(|j| serde_json::from_str(&j).ok())),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to get snapshot paths: {}", e)))
+    }
+}
+
 fn parse_iso_to_unix(iso_time: &str) -> i64 {
     if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(iso_time) {
         return dt.timestamp();
@@ -428,6 +2136,642 @@ fn parse_iso_to_unix(iso_time: &str) -> i64 {
 fn format_unix_timestamp(unix_time: i64) -> String {
     use chrono::{DateTime, Utc};
     let dt = DateTime::from_timestamp(unix_time, 0)
-        .unwrap_or_else(|| Utc::now());
+        .unwrap_or_else(Utc::now);
     dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotPin {
+    pub snapshot_id: String,
+    pub position: i64,
+    pub pinned_at: i64,
+}
+
+#[instrument]
+pub fn get_snapshot_pins(repo_id: &str) -> Result<Vec<SnapshotPin>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT snapshot_id, position, pinned_at FROM snapshot_pins
+         WHERE repo_id = ?1
+         ORDER BY position"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let pins: std::result::Result<Vec<_>, _> = stmt.query_map([repo_id], |row| {
+        Ok(SnapshotPin {
+            snapshot_id: row.get(0)?,
+            position: row.get(1)?,
+            pinned_at: row.get(2)?,
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to query snapshot pins: {}", e)))?
+        .collect();
+
+    pins.map_err(|e| AppError::Storage(format!("Failed to fetch snapshot pins: {}", e)))
+}
+
+/// Pins a snapshot at the end of the pinned list, or unpins it
+#[instrument]
+pub fn set_snapshot_pinned(repo_id: &str, snapshot_id: &str, pinned: bool) -> Result<()> {
+    let conn = get_connection()?;
+
+    if pinned {
+        conn.execute(
+            "INSERT OR IGNORE INTO snapshot_pins (repo_id, snapshot_id, position)
+             SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM snapshot_pins WHERE repo_id = ?1",
+            params![repo_id, snapshot_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to pin snapshot: {}", e)))?;
+    } else {
+        conn.execute(
+            "DELETE FROM snapshot_pins WHERE repo_id = ?1 AND snapshot_id = ?2",
+            params![repo_id, snapshot_id],
+        ).map_err(|e| AppError::Storage(format!("Failed to unpin snapshot: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Replaces the pinned shortlist with `snapshot_ids`, in that order
+#[instrument(skip(snapshot_ids), fields(count = snapshot_ids.len()))]
+pub fn set_pinned_order(repo_id: &str, snapshot_ids: &[String]) -> Result<()> {
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+
+    // Keep the original pin time of snapshots that stay pinned
+    tx.execute(
+        "DELETE FROM snapshot_pins WHERE repo_id = ?1 AND snapshot_id NOT IN (SELECT value FROM json_each(?2))",
+        params![repo_id, serde_json::to_string(snapshot_ids).unwrap_or_default()],
+    ).map_err(|e| AppError::Storage(format!("Failed to update snapshot pins: {}", e)))?;
+
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO snapshot_pins (repo_id, snapshot_id, position) VALUES (?1, ?2, ?3)
+             ON CONFLICT(repo_id, snapshot_id) DO UPDATE SET position = excluded.position"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare statement: {}", e)))?;
+
+        for (position, snapshot_id) in snapshot_ids.iter().enumerate() {
+            stmt.execute(params![repo_id, snapshot_id, position as i64])
+                .map_err(|e| AppError::Storage(format!("Failed to update snapshot pins: {}", e)))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+    Ok(())
+}
+
+/// Drops pins of a removed repository. Cache clears keep them.
+#[instrument]
+pub fn delete_snapshot_pins(repo_id: &str) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute("DELETE FROM snapshot_pins WHERE repo_id = ?1", params![repo_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete snapshot pins: {}", e)))?;
+    Ok(())
+}
+
+/// `EXPLAIN QUERY PLAN` details for a logged statement. Parameters are left unbound.
+pub fn explain_query_plan(sql: &str) -> Result<Vec<String>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+        .map_err(|e| AppError::Storage(format!("Failed to explain query: {}", e)))?;
+    let plan: std::result::Result<Vec<String>, _> = stmt.query_map([], |row| row.get(3))
+        .map_err(|e| AppError::Storage(format!("Failed to explain query: {}", e)))?
+        .collect();
+
+    plan.map_err(|e| AppError::Storage(format!("Failed to explain query: {}", e)))
+}
+
+/// Full ID of a cached snapshot from a short or full ID
+pub fn resolve_snapshot_id(repo_id: &str, snapshot_id: &str) -> Result<Option<String>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id FROM snapshots WHERE repo_id = ?1 AND (id = ?2 OR short_id = ?2 OR id LIKE ?2 || '%') LIMIT 2"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let ids: std::result::Result<Vec<String>, _> = stmt.query_map(params![repo_id, snapshot_id], |row| row.get(0))
+        .map_err(|e| AppError::Storage(format!("Failed to resolve snapshot ID: {}", e)))?
+        .collect();
+    let ids = ids.map_err(|e| AppError::Storage(format!("Failed to resolve snapshot ID: {}", e)))?;
+
+    // An ambiguous prefix doesn't resolve
+    Ok(if ids.len() == 1 { ids.into_iter().next() } else { None })
+}
+
+fn delete_node_tree_in(conn: &Connection, repo_id: &str, snapshot_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM node_dirs WHERE repo_id = ?1 AND snapshot_id = ?2", params![repo_id, snapshot_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete cached listing: {}", e)))?;
+    conn.execute("DELETE FROM node_names WHERE repo_id = ?1 AND snapshot_id = ?2", params![repo_id, snapshot_id])
+        .map_err(|e| AppError::Storage(format!("Failed to delete cached listing: {}", e)))?;
+    Ok(())
+}
+
+/// Replaces the cached listing of a snapshot
+#[instrument(skip(nodes), fields(count = nodes.len()))]
+pub fn save_node_tree(repo_id: &str, snapshot_id: &str, nodes: &[FileNode], compress: bool) -> Result<()> {
+    let blobs = node_cache::encode_tree(nodes, compress)?;
+
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+    delete_node_tree_in(&tx, repo_id, snapshot_id)?;
+
+    {
+        let mut dir_stmt = tx.prepare(
+            "INSERT INTO node_dirs (repo_id, snapshot_id, dir_path, node_count, raw_size, compressed, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare statement: {}", e)))?;
+
+        for DirBlob { dir_path, node_count, raw_size, compressed, data } in &blobs {
+            dir_stmt.execute(params![repo_id, snapshot_id, dir_path, *node_count as i64, *raw_size as i64, compressed, data])
+                .map_err(|e| AppError::Storage(format!("Failed to cache listing of {}: {}", dir_path, e)))?;
+        }
+
+        let mut name_stmt = tx.prepare(
+            "INSERT INTO node_names (repo_id, snapshot_id, name, path, mtime) VALUES (?1, ?2, ?3, ?4, ?5)"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare statement: {}", e)))?;
+
+        for node in nodes {
+            let name = node_cache::node_name(&node.path).to_lowercase();
+            name_stmt.execute(params![repo_id, snapshot_id, name, node.path, node.mtime])
+                .map_err(|e| AppError::Storage(format!("Failed to index {}: {}", node.path, e)))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+
+    info!("Cached {} nodes in {} directories for snapshot {}", nodes.len(), blobs.len(), snapshot_id);
+    Ok(())
+}
+
+pub fn has_node_tree(repo_id: &str, snapshot_id: &str) -> Result<bool> {
+    let conn = get_connection()?;
+
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM node_dirs WHERE repo_id = ?1 AND snapshot_id = ?2)",
+        params![repo_id, snapshot_id],
+        |row| row.get(0),
+    ).map_err(|e| AppError::Storage(format!("Failed to check cached listing: {}", e)))
+}
+
+/// Direct children of `dir_path`, or None when the directory isn't cached
+pub fn load_node_dir(repo_id: &str, snapshot_id: &str, dir_path: &str) -> Result<Option<Vec<FileNode>>> {
+    let conn = get_connection()?;
+
+    let blob = conn.query_row(
+        "SELECT compressed, data FROM node_dirs WHERE repo_id = ?1 AND snapshot_id = ?2 AND dir_path = ?3",
+        params![repo_id, snapshot_id, dir_path],
+        |row| Ok((row.get::<_, bool>(0)?, row.get::<_, Vec<u8>>(1)?)),
+    );
+
+    match blob {
+        Ok((compressed, data)) => Ok(Some(node_cache::decode_dir(&data, compressed)?)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to load cached listing: {}", e))),
+    }
+}
+
+/// Every cached node of a snapshot, directory by directory
+pub fn load_node_tree(repo_id: &str, snapshot_id: &str) -> Result<Vec<FileNode>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT compressed, data FROM node_dirs WHERE repo_id = ?1 AND snapshot_id = ?2 ORDER BY dir_path"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let blobs: std::result::Result<Vec<(bool, Vec<u8>)>, _> = stmt.query_map(params![repo_id, snapshot_id], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    }).map_err(|e| AppError::Storage(format!("Failed to load cached listing: {}", e)))?
+        .collect();
+    let blobs = blobs.map_err(|e| AppError::Storage(format!("Failed to load cached listing: {}", e)))?;
+
+    let mut nodes = Vec::new();
+    for (compressed, data) in blobs {
+        nodes.extend(node_cache::decode_dir(&data, compressed)?);
+    }
+    Ok(nodes)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedNodeMatch {
+    pub snapshot_id: String,
+    pub path: String,
+    pub mtime: Option<String>,
+}
+
+/// Bounds for matching a node's mtime in SQL. `after` and `before` are RFC 3339;
+/// `days_before_snapshot` is measured back from each row's snapshot time.
+#[derive(Debug, Clone, Default)]
+pub struct MtimeBounds {
+    pub after: Option<String>,
+    pub before: Option<String>,
+    pub days_before_snapshot: Option<u32>,
+}
+
+/// Cached paths whose name contains `query` (case-insensitive)
+pub fn search_node_names(repo_id: &str, snapshot_id: Option<&str>, query: &str, mtime: &MtimeBounds, limit: i64) -> Result<Vec<CachedNodeMatch>> {
+    let conn = get_connection()?;
+
+    let pattern = format!("%{}%", query.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    let mut stmt = conn.prepare(
+        "SELECT n.snapshot_id, n.path, n.mtime FROM node_names n
+         LEFT JOIN snapshots s ON s.repo_id = n.repo_id AND s.id = n.snapshot_id
+         WHERE n.repo_id = ?1 AND (?2 IS NULL OR n.snapshot_id = ?2) AND n.name LIKE ?3 ESCAPE '\\'
+           AND (?4 IS NULL OR julianday(n.mtime) >= julianday(?4))
+           AND (?5 IS NULL OR julianday(n.mtime) <= julianday(?5))
+           AND (?6 IS NULL OR julianday(n.mtime) BETWEEN julianday(s.time) - ?6 AND julianday(s.time))
+         ORDER BY n.path, n.snapshot_id
+         LIMIT ?7"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let matches: std::result::Result<Vec<_>, _> = stmt.query_map(
+        params![repo_id, snapshot_id, pattern, mtime.after, mtime.before, mtime.days_before_snapshot, limit],
+        |row| Ok(CachedNodeMatch { snapshot_id: row.get(0)?, path: row.get(1)?, mtime: row.get(2)? }),
+    ).map_err(|e| AppError::Storage(format!("Failed to search cached listings: {}", e)))?
+        .collect();
+
+    matches.map_err(|e| AppError::Storage(format!("Failed to search cached listings: {}", e)))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NodeCacheStats {
+    pub snapshots: i64,
+    pub directories: i64,
+    pub nodes: i64,
+    pub raw_bytes: i64,
+    pub stored_bytes: i64,
+}
+
+pub fn get_node_cache_stats(repo_id: &str) -> Result<NodeCacheStats> {
+    let conn = get_connection()?;
+
+    conn.query_row(
+        "SELECT COUNT(DISTINCT snapshot_id), COUNT(*), COALESCE(SUM(node_count), 0),
+                COALESCE(SUM(raw_size), 0), COALESCE(SUM(LENGTH(data)), 0)
+         FROM node_dirs WHERE repo_id = ?1",
+        params![repo_id],
+        |row| Ok(NodeCacheStats {
+            snapshots: row.get(0)?,
+            directories: row.get(1)?,
+            nodes: row.get(2)?,
+            raw_bytes: row.get(3)?,
+            stored_bytes: row.get(4)?,
+        }),
+    ).map_err(|e| AppError::Storage(format!("Failed to read node cache stats: {}", e)))
+}
+
+/// The last health report measured for the repository
+pub fn load_repo_health(repo_id: &str) -> Result<Option<RepoHealth>> {
+    let conn = get_connection()?;
+
+    let report = conn.query_row(
+        "SELECT report FROM repo_health WHERE repo_id = ?1",
+        params![repo_id],
+        |row| row.get::<_, String>(0),
+    );
+    match report {
+        Ok(report) => match serde_json::from_str(&report) {
+            Ok(health) => Ok(Some(health)),
+            Err(e) => {
+                // Written by an older version with a different shape; it gets re-measured
+                debug!("Ignoring unreadable health report of {}: {}", repo_id, e);
+                Ok(None)
+            }
+        },
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to load repository health: {}", e))),
+    }
+}
+
+pub fn save_repo_health(repo_id: &str, health: &RepoHealth) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO repo_health (repo_id, report, measured_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(repo_id) DO UPDATE SET report = excluded.report, measured_at = excluded.measured_at",
+        params![repo_id, serde_json::to_string(health)?, health.measured_at],
+    ).map_err(|e| AppError::Storage(format!("Failed to save repository health: {}", e)))?;
+    Ok(())
+}
+
+pub fn get_snapshot_pk(repo_id: &str, snapshot_id: &str) -> Result<Option<i64>> {
+    let conn = get_connection()?;
+
+    match conn.query_row(
+        "SELECT pk FROM snapshots WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, snapshot_id],
+        |row| row.get(0),
+    ) {
+        Ok(pk) => Ok(Some(pk)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to get snapshot pk: {}", e))),
+    }
+}
+
+/// Drops a snapshot's file index before it's rebuilt
+pub fn reset_file_index(snapshot_pk: i64) -> Result<()> {
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+    tx.execute("UPDATE snapshots SET files_indexed_at = NULL WHERE pk = ?1", params![snapshot_pk])
+        .map_err(|e| AppError::Storage(format!("Failed to reset file index: {}", e)))?;
+    tx.execute("DELETE FROM files WHERE snapshot_pk = ?1", params![snapshot_pk])
+        .map_err(|e| AppError::Storage(format!("Failed to reset file index: {}", e)))?;
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+    Ok(())
+}
+
+/// Adds one batch of nodes to a snapshot's file index
+pub fn insert_indexed_files(snapshot_pk: i64, nodes: &[FileNode]) -> Result<()> {
+    let conn = get_connection()?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO files (snapshot_pk, path, parent_path, name, type, size, mtime)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(snapshot_pk, path) DO UPDATE SET
+                parent_path = excluded.parent_path, name = excluded.name, type = excluded.type,
+                size = excluded.size, mtime = excluded.mtime"
+        ).map_err(|e| AppError::Storage(format!("Failed to prepare statement: {}", e)))?;
+
+        for node in nodes {
+            stmt.execute(params![
+                snapshot_pk,
+                node.path,
+                node_cache::parent_dir(&node.path),
+                node.name,
+                node.node_type,
+                node.size,
+                node.mtime,
+            ]).map_err(|e| AppError::Storage(format!("Failed to index {}: {}", node.path, e)))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+    Ok(())
+}
+
+pub fn mark_file_index_complete(snapshot_pk: i64) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "UPDATE snapshots SET files_indexed_at = strftime('%s', 'now') WHERE pk = ?1",
+        params![snapshot_pk],
+    ).map_err(|e| AppError::Storage(format!("Failed to finish file index: {}", e)))?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FileIndexStatus {
+    pub total_snapshots: i64,
+    pub indexed_snapshots: i64,
+    pub indexed_files: i64,
+    /// Share of snapshots whose files are searchable, 0 to 100
+    pub coverage_percent: f64,
+}
+
+pub fn get_file_index_status(repo_id: &str) -> Result<FileIndexStatus> {
+    let conn = get_connection()?;
+
+    let (total_snapshots, indexed_snapshots): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COUNT(files_indexed_at) FROM snapshots WHERE repo_id = ?1",
+        params![repo_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| AppError::Storage(format!("Failed to query file index status: {}", e)))?;
+    let indexed_files: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM files f JOIN snapshots s ON s.pk = f.snapshot_pk
+         WHERE s.repo_id = ?1 AND s.files_indexed_at IS NOT NULL",
+        params![repo_id],
+        |row| row.get(0),
+    ).map_err(|e| AppError::Storage(format!("Failed to count indexed files: {}", e)))?;
+
+    Ok(FileIndexStatus {
+        total_snapshots,
+        indexed_snapshots,
+        indexed_files,
+        coverage_percent: if total_snapshots == 0 {
+            0.0
+        } else {
+            indexed_snapshots as f64 * 100.0 / total_snapshots as f64
+        },
+    })
+}
+
+/// The snapshot's pk when its file index is complete
+pub fn indexed_snapshot_pk(repo_id: &str, snapshot_id: &str) -> Result<Option<i64>> {
+    let conn = get_connection()?;
+
+    match conn.query_row(
+        "SELECT pk FROM snapshots WHERE repo_id = ?1 AND id = ?2 AND files_indexed_at IS NOT NULL",
+        params![repo_id, snapshot_id],
+        |row| row.get(0),
+    ) {
+        Ok(pk) => Ok(Some(pk)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(AppError::Storage(format!("Failed to check file index: {}", e))),
+    }
+}
+
+fn file_node_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileNode> {
+    Ok(FileNode {
+        path: row.get(0)?,
+        name: row.get(1)?,
+        node_type: row.get(2)?,
+        size: row.get(3)?,
+        mtime: row.get(4)?,
+        ..Default::default()
+    })
+}
+
+/// A directory and its direct children from the file index, or every indexed node without a directory
+pub fn load_indexed_dir(snapshot_pk: i64, dir_path: Option<&str>) -> Result<Vec<FileNode>> {
+    let conn = get_connection()?;
+
+    let nodes: std::result::Result<Vec<FileNode>, _> = match dir_path {
+        Some(dir) => {
+            let mut stmt = conn.prepare(
+                "SELECT path, name, type, size, mtime FROM files
+                 WHERE snapshot_pk = ?1 AND (path = ?2 OR parent_path = ?2)
+                 ORDER BY path = ?2 DESC, path"
+            ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+            let rows = stmt.query_map(params![snapshot_pk, dir], file_node_from_row)
+                .map_err(|e| AppError::Storage(format!("Failed to query file index: {}", e)))?;
+            rows.collect()
+        }
+        None => {
+            let mut stmt = conn.prepare(
+                "SELECT path, name, type, size, mtime FROM files WHERE snapshot_pk = ?1 ORDER BY path"
+            ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+            let rows = stmt.query_map(params![snapshot_pk], file_node_from_row)
+                .map_err(|e| AppError::Storage(format!("Failed to query file index: {}", e)))?;
+            rows.collect()
+        }
+    };
+
+    nodes.map_err(|e| AppError::Storage(format!("Failed to read file index: {}", e)))
+}
+
+/// Narrows a file search; unset fields don't filter
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileSearchFilters {
+    pub snapshot_ids: Vec<String>,
+    /// `file`, `dir` or `symlink`
+    pub node_type: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Dates like `2024-01-31` or `2024-01-31 15:04`, or RFC 3339 timestamps
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+    /// Only files modified in the given number of days up to their snapshot's time
+    pub modified_days_before_snapshot: Option<u32>,
+    /// Only paths under this directory
+    pub path_prefix: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileSearchHit {
+    pub snapshot_id: String,
+    pub short_id: String,
+    pub snapshot_time: String,
+    pub path: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub node_type: String,
+    pub size: Option<u64>,
+    pub mtime: Option<String>,
+}
+
+fn like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Indexed files whose name contains `query`, newest snapshot first for each path.
+/// Queries shorter than a trigram can't use the search index and scan names instead.
+pub fn search_indexed_files(repo_id: &str, query: &str, filters: &FileSearchFilters, limit: i64) -> Result<Vec<FileSearchHit>> {
+    let conn = get_connection()?;
+
+    let (name_filter, name_arg) = if query.chars().count() >= 3 {
+        ("f.id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?2)",
+         format!("\"{}\"", query.replace('"', "\"\"")))
+    } else {
+        ("f.name LIKE ?2 ESCAPE '\\'", format!("%{}%", like_pattern(query)))
+    };
+    let snapshot_ids = serde_json::to_string(&filters.snapshot_ids)?;
+    let path_prefix = filters.path_prefix.as_deref()
+        .map(|p| format!("{}/%", like_pattern(p.trim_end_matches('/'))));
+
+    let sql = format!(
+        "SELECT s.id, s.short_id, s.time, f.path, f.name, f.type, f.size, f.mtime
+         FROM files f
+         JOIN snapshots s ON s.pk = f.snapshot_pk
+         WHERE s.repo_id = ?1 AND s.files_indexed_at IS NOT NULL AND {}
+           AND (json_array_length(?3) = 0 OR s.id IN (SELECT value FROM json_each(?3)))
+           AND (?4 IS NULL OR f.type = ?4)
+           AND (?5 IS NULL OR f.size >= ?5)
+           AND (?6 IS NULL OR f.size <= ?6)
+           AND (?7 IS NULL OR julianday(f.mtime) >= julianday(?7))
+           AND (?8 IS NULL OR julianday(f.mtime) <= julianday(?8))
+           AND (?9 IS NULL OR f.path LIKE ?9 ESCAPE '\\')
+           AND (?11 IS NULL OR julianday(f.mtime) BETWEEN julianday(s.time) - ?11 AND julianday(s.time))
+         ORDER BY f.path, s.time DESC
+         LIMIT ?10",
+        name_filter
+    );
+
+    let mut stmt = conn.prepare(&sql)
+        .map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let hits: std::result::Result<Vec<_>, _> = stmt.query_map(params![
+        repo_id,
+        name_arg,
+        snapshot_ids,
+        filters.node_type,
+        filters.min_size,
+        filters.max_size,
+        filters.modified_after,
+        filters.modified_before,
+        path_prefix,
+        limit,
+        filters.modified_days_before_snapshot,
+    ], |row| {
+        Ok(FileSearchHit {
+            snapshot_id: row.get(0)?,
+            short_id: row.get(1)?,
+            snapshot_time: row.get(2)?,
+            path: row.get(3)?,
+            name: row.get(4)?,
+            node_type: row.get(5)?,
+            size: row.get(6)?,
+            mtime: row.get(7)?,
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to search file index: {}", e)))?
+        .collect();
+
+    hits.map_err(|e| AppError::Storage(format!("Failed to search file index: {}", e)))
+}
+
+#[instrument]
+pub fn record_audit_event(event: &str, detail: Option<&str>) -> Result<()> {
+    let conn = get_connection()?;
+
+    conn.execute(
+        "INSERT INTO audit_log (event, detail) VALUES (?1, ?2)",
+        params![event, detail],
+    ).map_err(|e| AppError::Storage(format!("Failed to record audit event: {}", e)))?;
+    Ok(())
+}
+
+pub fn get_audit_log(limit: i64) -> Result<Vec<AuditEvent>> {
+    let conn = get_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, event, detail, recorded_at FROM audit_log ORDER BY id DESC LIMIT ?1"
+    ).map_err(|e| AppError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+    let events: std::result::Result<Vec<_>, _> = stmt.query_map(params![limit], |row| {
+        Ok(AuditEvent {
+            id: row.get(0)?,
+            event: row.get(1)?,
+            detail: row.get(2)?,
+            recorded_at: row.get(3)?,
+        })
+    }).map_err(|e| AppError::Storage(format!("Failed to query audit log: {}", e)))?
+        .collect();
+
+    events.map_err(|e| AppError::Storage(format!("Failed to read audit log: {}", e)))
+}
+
+/// Points the cached snapshot at the ID restic gave it after rewriting its metadata.
+/// The row keeps its pk, so stats and the file index stay attached.
+#[instrument(skip(snapshot))]
+pub fn replace_snapshot_id(repo_id: &str, old_id: &str, snapshot: &Snapshot) -> Result<()> {
+    let conn = get_connection()?;
+
+    let tags_json = snapshot.tags.as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| AppError::Storage(format!("Failed to serialize tags: {}", e)))?;
+
+    let tx = write_transaction(&conn)
+        .map_err(|e| AppError::Storage(format!("Failed to begin transaction: {}", e)))?;
+    tx.execute(
+        "UPDATE snapshots SET id = ?3, short_id = ?4, tags = ?5 WHERE repo_id = ?1 AND id = ?2",
+        params![repo_id, old_id, snapshot.id, snapshot.short_id, tags_json],
+    ).map_err(|e| AppError::Storage(format!("Failed to update snapshot: {}", e)))?;
+    for table in ["snapshot_pins", "node_dirs", "node_names"] {
+        tx.execute(
+            &format!("UPDATE {} SET snapshot_id = ?3 WHERE repo_id = ?1 AND snapshot_id = ?2", table),
+            params![repo_id, old_id, snapshot.id],
+        ).map_err(|e| AppError::Storage(format!("Failed to update {}: {}", table, e)))?;
+    }
+    tx.commit()
+        .map_err(|e| AppError::Storage(format!("Failed to commit transaction: {}", e)))?;
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::database;
+use crate::error::{AppError, Result};
+use crate::logs;
+use crate::restic_download;
+use crate::secrets::SecretString;
+use crate::storage::{load_config, AppConfig, PasswordSource, PreConnectHook};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use tracing::{info, instrument, warn};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Serialize)]
+struct PlatformInfo {
+    app_version: &'static str,
+    os: &'static str,
+    os_family: &'static str,
+    arch: &'static str,
+    restic_binary: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DebugBundle {
+    pub path: String,
+    /// Names of the files in the archive
+    pub entries: Vec<String>,
+}
+
+/// The configuration without passwords, credentials, URL passwords or hook arguments
+fn sanitized_config() -> Result<AppConfig> {
+    let mut config = load_config().map_err(AppError::Storage)?;
+    for repo in &mut config.repositories {
+        repo.password = SecretString::default();
+        repo.path = logs::redact_url(&repo.path);
+        if let Some(credentials) = &mut repo.backend_credentials {
+            for secret in [&mut credentials.secret, &mut credentials.sas_token] {
+                if secret.is_some() {
+                    *secret = Some(REDACTED.to_string());
+                }
+            }
+        }
+        if let PasswordSource::PasswordCommand { command } = &mut repo.password_source {
+            *command = REDACTED.to_string();
+        }
+        for value in repo.extra_env.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        for hook in &mut repo.pre_connect {
+            if let PreConnectHook::Command { args, .. } = hook {
+                let redacted: Vec<&str> = args.iter().map(String::as_str).collect();
+                *args = logs::redact_args(&redacted);
+            }
+        }
+    }
+    Ok(config)
+}
+
+fn zip_error(e: zip::result::ZipError) -> AppError {
+    AppError::Storage(format!("Failed to write debug bundle: {}", e))
+}
+
+struct Bundle {
+    zip: ZipWriter<File>,
+    entries: Vec<String>,
+}
+
+impl Bundle {
+    fn add(&mut self, name: &str, contents: &[u8]) -> Result<()> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file(name, options).map_err(zip_error)?;
+        self.zip.write_all(contents)?;
+        self.entries.push(name.to_string());
+        Ok(())
+    }
+
+    fn add_json(&mut self, name: &str, value: &impl Serialize) -> Result<()> {
+        self.add(name, serde_json::to_string_pretty(value)?.as_bytes())
+    }
+
+    fn add_file(&mut self, name: &str, path: &Path) -> Result<()> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file(name, options).map_err(zip_error)?;
+        io::copy(&mut File::open(path)?, &mut self.zip)?;
+        self.entries.push(name.to_string());
+        Ok(())
+    }
+}
+
+/// Writes a zip of what's needed to look into a bug report: the sanitized
+/// configuration, a database summary, the operation logs, `restic version`
+/// and the platform. A part that can't be gathered is noted in its place.
+#[instrument]
+pub fn export(dest: &Path, restic_binary: &str) -> Result<Vec<String>> {
+    let mut bundle = Bundle { zip: ZipWriter::new(File::create(dest)?), entries: Vec::new() };
+
+    bundle.add_json("platform.json", &PlatformInfo {
+        app_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        os_family: std::env::consts::FAMILY,
+        arch: std::env::consts::ARCH,
+        restic_binary: restic_binary.to_string(),
+    })?;
+
+    match sanitized_config() {
+        Ok(config) => bundle.add_json("config.json", &config)?,
+        Err(e) => bundle.add("config.error.txt", e.to_string().as_bytes())?,
+    }
+
+    match database::database_summary() {
+        Ok(summary) => bundle.add_json("database.json", &summary)?,
+        Err(e) => bundle.add("database.error.txt", e.to_string().as_bytes())?,
+    }
+
+    bundle.add("restic-version.txt", restic_download::version_output(restic_binary).as_bytes())?;
+
+    let log_dir = logs::log_dir()?;
+    let mut log_files: Vec<_> = match fs::read_dir(&log_dir) {
+        Ok(entries) => entries.flatten().map(|e| e.path()).filter(|p| p.is_file()).collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    log_files.sort();
+    for path in log_files {
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if let Err(e) = bundle.add_file(&format!("logs/{}", name), &path) {
+            warn!("Failed to add {:?} to the debug bundle: {}", path, e);
+        }
+    }
+
+    bundle.zip.finish().map_err(zip_error)?;
+    info!("Wrote debug bundle with {} entries", bundle.entries.len());
+    Ok(bundle.entries)
+}
@@ -0,0 +1,52 @@
+use crate::error::Result;
+use std::fs;
+use std::path::Path;
+
+pub const DEMO_REPO_ID: &str = "demo-repository";
+pub const DEMO_REPO_NAME: &str = "Demo Repository";
+// Not a secret: the demo repository only ever contains the generated sample files
+pub const DEMO_PASSWORD: &str = "restic-restore-demo";
+pub const DEMO_HOST: &str = "demo-host";
+pub const DEMO_GENERATIONS: u32 = 3;
+
+fn write(root: &Path, relative: &str, contents: &str) -> Result<()> {
+    let path = root.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Writes the sample file tree for one demo snapshot. Each generation edits,
+/// adds and removes a few files so diffs and the timeline have something to show.
+pub fn write_sample_files(root: &Path, generation: u32) -> Result<()> {
+    write(root, "README.txt", "Sample files generated by Restic Restore's demo mode.\n")?;
+    write(
+        root,
+        "documents/notes.md",
+        &format!("# Notes\n\nRevision {}\n\n- Buy milk\n- Water the plants\n", generation + 1),
+    )?;
+    write(root, "documents/letters/welcome.txt", "Dear user,\n\nWelcome to the demo repository.\n")?;
+    write(root, "config/settings.toml", &format!("[app]\ntheme = \"dark\"\nrevision = {}\n", generation + 1))?;
+
+    let report: String = (1..=50 * (generation + 1))
+        .map(|i| format!("{},item-{},{}\n", i, i, i * 7 % 100))
+        .collect();
+    write(root, "reports/data.csv", &format!("id,name,value\n{}", report))?;
+
+    if generation >= 1 {
+        write(root, "documents/todo.txt", "1. Try a selective restore\n2. Compare snapshots\n")?;
+    }
+
+    let draft = root.join("documents/draft.txt");
+    if generation >= 2 {
+        if draft.exists() {
+            fs::remove_file(draft)?;
+        }
+    } else {
+        write(root, "documents/draft.txt", "An early draft that gets deleted later.\n")?;
+    }
+
+    Ok(())
+}
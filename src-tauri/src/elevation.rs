@@ -0,0 +1,244 @@
+use crate::error::{AppError, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// A new, empty file in the temp directory that is removed when this is dropped
+struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    fn create(extension: &str) -> Result<(Self, fs::File)> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir()
+            .join(format!("restic-restore-{}-{}.{}", std::process::id(), nanos, extension));
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let file = options.open(&path)?;
+        Ok((Self { path }, file))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove temporary file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Password handed to an elevated restic via `--password-file`.
+/// Environment variables don't survive pkexec/osascript/UAC, so the password
+/// is written to a private temp file that is removed when this is dropped.
+pub struct PasswordFile {
+    file: TempFile,
+}
+
+impl PasswordFile {
+    pub fn create(password: &str) -> Result<Self> {
+        let (file, mut handle) = TempFile::create("pw")?;
+        #[cfg(target_os = "windows")]
+        restrict_access(&file.path)?;
+        handle.write_all(password.as_bytes())?;
+        Ok(Self { file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.file.path
+    }
+}
+
+/// Limits the file to the current user and the Administrators group the elevated
+/// restic runs as. Files in the temp directory otherwise inherit its wider ACL.
+#[cfg(target_os = "windows")]
+fn restrict_access(path: &Path) -> Result<()> {
+    let user = std::env::var("USERNAME")
+        .map_err(|_| AppError::ElevationUnavailable("USERNAME is not set".to_string()))?;
+    let account = match std::env::var("USERDOMAIN") {
+        Ok(domain) => format!("{}\\{}", domain, user),
+        Err(_) => user,
+    };
+    let output = Command::new("icacls")
+        .arg(path)
+        .args(["/inheritance:r", "/grant:r"])
+        .arg(format!("{}:F", account))
+        // Administrators, by SID so it doesn't depend on the system language
+        .arg("*S-1-5-32-544:R")
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Err(AppError::ElevationUnavailable(format!("Failed to restrict access to the password file: {}", message)));
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "''"))
+}
+
+/// Quotes `arg` so that CommandLineToArgvW, which restic and most other Windows
+/// programs split their command line with, gives it back unchanged
+#[cfg(any(target_os = "windows", test))]
+fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\u{0b}', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            // Backslashes only escape when a quote follows, so those are doubled
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            c => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Trailing backslashes would otherwise escape the closing quote
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Runs `program args...` with administrator rights and collects its output.
+/// On Windows, where an elevated process can't inherit the caller's pipes, the
+/// elevated side redirects restic's output into temp files that are read back.
+#[cfg(target_os = "windows")]
+pub fn run_elevated(program: &str, args: &[String]) -> std::io::Result<Output> {
+    let to_io = |e: AppError| std::io::Error::other(e.to_string());
+    let (stdout_file, _) = TempFile::create("out").map_err(to_io)?;
+    let (stderr_file, _) = TempFile::create("err").map_err(to_io)?;
+
+    let command_line = args.iter().map(|a| quote_windows_arg(a)).collect::<Vec<_>>().join(" ");
+    let elevated_script = format!(
+        "$p = Start-Process -FilePath {} -ArgumentList {} -RedirectStandardOutput {} -RedirectStandardError {} -NoNewWindow -Wait -PassThru; exit $p.ExitCode",
+        powershell_quote(program),
+        powershell_quote(&command_line),
+        powershell_quote(&stdout_file.path.to_string_lossy()),
+        powershell_quote(&stderr_file.path.to_string_lossy()),
+    );
+    let mut output = elevated_command(&elevated_script).output()?;
+
+    output.stdout = fs::read(&stdout_file.path).unwrap_or_default();
+    // After the helper's own stderr, which says why elevation failed if it did
+    output.stderr.extend(fs::read(&stderr_file.path).unwrap_or_default());
+    Ok(output)
+}
+
+/// Runs `program args...` with administrator rights and collects its output
+#[cfg(not(target_os = "windows"))]
+pub fn run_elevated(program: &str, args: &[String]) -> std::io::Result<Output> {
+    elevated_command(program, args).output()
+}
+
+/// Builds a command that runs `script` in an elevated PowerShell after a UAC prompt.
+/// The script is passed base64-encoded, so it needs no further quoting.
+#[cfg(target_os = "windows")]
+fn elevated_command(script: &str) -> Command {
+    use base64::Engine;
+
+    let utf16: Vec<u8> = script.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(utf16);
+    let launcher = format!(
+        "$p = Start-Process -FilePath powershell -ArgumentList '-NoProfile -NonInteractive -EncodedCommand {}' -Verb RunAs -WindowStyle Hidden -Wait -PassThru; exit $p.ExitCode",
+        encoded
+    );
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", &launcher]);
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    debug!("Prepared elevated PowerShell command");
+    cmd
+}
+
+/// Builds a command that runs `program args...` with administrator rights:
+/// osascript on macOS and pkexec elsewhere.
+#[cfg(not(target_os = "windows"))]
+fn elevated_command(program: &str, args: &[String]) -> Command {
+    #[cfg(target_os = "macos")]
+    let cmd = {
+        let shell_line = std::iter::once(program)
+            .chain(args.iter().map(|a| a.as_str()))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let script = format!(
+            "do shell script \"{}\" with administrator privileges",
+            shell_line.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(script);
+        cmd
+    };
+
+    #[cfg(not(target_os = "macos"))]
+    let cmd = {
+        let mut cmd = Command::new("pkexec");
+        cmd.arg(program).args(args);
+        cmd
+    };
+
+    debug!("Prepared elevated command for {}", program);
+    cmd
+}
+
+/// Maps the helper's own failure modes (prompt dismissed, not authorized) to errors.
+/// Returns `None` when the failure came from restic itself.
+pub fn elevation_failure(exit_code: Option<i32>, stderr: &str) -> Option<AppError> {
+    let lower = stderr.to_lowercase();
+
+    // pkexec: 126 = authorization dismissed, 127 = not authorized
+    if cfg!(not(any(target_os = "macos", target_os = "windows")))
+        && matches!(exit_code, Some(126) | Some(127))
+    {
+        return Some(AppError::ElevationCancelled);
+    }
+
+    // osascript reports a dismissed password prompt as error -128
+    if lower.contains("user canceled") || lower.contains("(-128)") {
+        return Some(AppError::ElevationCancelled);
+    }
+
+    if lower.contains("operation was canceled by the user") {
+        return Some(AppError::ElevationCancelled);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod quoting_tests;
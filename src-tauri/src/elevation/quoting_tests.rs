@@ -0,0 +1,30 @@
+//! Command lines for the elevated restic on Windows, checked against how
+//! CommandLineToArgvW splits them.
+
+use super::*;
+
+#[test]
+fn plain_arguments_are_left_alone() {
+    assert_eq!(quote_windows_arg("restore"), "restore");
+    assert_eq!(quote_windows_arg(r"C:\backups\repo"), r"C:\backups\repo");
+    assert_eq!(quote_windows_arg(r"\\nas\share\"), r"\\nas\share\");
+}
+
+#[test]
+fn arguments_with_spaces_or_nothing_are_quoted() {
+    assert_eq!(quote_windows_arg(""), r#""""#);
+    assert_eq!(quote_windows_arg(r"C:\My Files\repo"), r#""C:\My Files\repo""#);
+}
+
+#[test]
+fn trailing_backslashes_are_doubled_before_the_closing_quote() {
+    assert_eq!(quote_windows_arg(r"C:\My Files\"), r#""C:\My Files\\""#);
+    assert_eq!(quote_windows_arg(r"C:\My Files\\"), r#""C:\My Files\\\\""#);
+}
+
+#[test]
+fn quotes_and_the_backslashes_before_them_are_escaped() {
+    assert_eq!(quote_windows_arg(r#"say "hi""#), r#""say \"hi\"""#);
+    assert_eq!(quote_windows_arg(r#"a\"b"#), r#""a\\\"b""#);
+    assert_eq!(quote_windows_arg(r#"-i "C:\key file""#), r#""-i \"C:\key file\"""#);
+}
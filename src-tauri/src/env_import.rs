@@ -0,0 +1,200 @@
+use crate::error::{AppError, Result};
+use crate::secrets::SecretString;
+use crate::storage::BackendCredentials;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+use tracing::debug;
+use zeroize::Zeroizing;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+/// Variables restic's backends read their credentials and settings from
+pub const BACKEND_VARIABLES: &[&str] = &[
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "AWS_DEFAULT_REGION",
+    "AWS_PROFILE",
+    "RESTIC_AWS_ASSUME_ROLE_ARN",
+    "B2_ACCOUNT_ID",
+    "B2_ACCOUNT_KEY",
+    "AZURE_ACCOUNT_NAME",
+    "AZURE_ACCOUNT_KEY",
+    "AZURE_ACCOUNT_SAS",
+    "AZURE_ENDPOINT_SUFFIX",
+    "GOOGLE_PROJECT_ID",
+    "GOOGLE_APPLICATION_CREDENTIALS",
+    "GOOGLE_ACCESS_TOKEN",
+    "OS_AUTH_URL",
+    "OS_USERNAME",
+    "OS_PASSWORD",
+    "OS_REGION_NAME",
+    "RCLONE_CONFIG",
+    "RESTIC_REST_USERNAME",
+    "RESTIC_REST_PASSWORD",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordOrigin {
+    Password,
+    PasswordFile,
+    PasswordCommand,
+}
+
+/// Parses a pasted `.env` block: `KEY=value` lines, optionally prefixed with
+/// `export`, with `#` comments and single or double quoted values.
+pub fn parse_env_block(block: &str) -> HashMap<String, String> {
+    block.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return None;
+            }
+            Some((key.to_string(), unquote(value.trim()).to_string()))
+        })
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+/// The restic-related variables of the app's own environment
+pub fn process_environment() -> HashMap<String, String> {
+    std::env::vars()
+        .filter(|(key, _)| key.starts_with("RESTIC_") || BACKEND_VARIABLES.contains(&key.as_str()))
+        .collect()
+}
+
+pub fn backend_variables(env: &HashMap<String, String>) -> Vec<String> {
+    let mut found: Vec<String> = BACKEND_VARIABLES.iter()
+        .filter(|name| env.get(**name).is_some_and(|v| !v.is_empty()))
+        .map(|name| name.to_string())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Credentials for the repository's backend, read from the same variables restic uses
+pub fn backend_credentials(env: &HashMap<String, String>) -> Option<BackendCredentials> {
+    let var = |names: &[&str]| names.iter()
+        .find_map(|name| env.get(*name).filter(|v| !v.is_empty()).cloned());
+
+    let credentials = BackendCredentials {
+        access_key_id: var(&["AWS_ACCESS_KEY_ID"]),
+        secret: var(&["AWS_SECRET_ACCESS_KEY", "B2_ACCOUNT_KEY", "AZURE_ACCOUNT_KEY", "RESTIC_REST_PASSWORD"]),
+        username: var(&["RESTIC_REST_USERNAME"]),
+        region: var(&["AWS_DEFAULT_REGION"]),
+        account_id: var(&["B2_ACCOUNT_ID", "AZURE_ACCOUNT_NAME", "GOOGLE_PROJECT_ID"]),
+        sas_token: var(&["AZURE_ACCOUNT_SAS"]),
+        service_account_json: var(&["GOOGLE_APPLICATION_CREDENTIALS"]),
+    };
+    (credentials != BackendCredentials::default()).then_some(credentials)
+}
+
+// Splits a password command the way a shell would for simple cases: whitespace
+// separated words, with single or double quotes grouping words together.
+fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_word = false;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+fn run_password_command(command: &str) -> Result<SecretString> {
+    let words = split_command(command);
+    let (program, args) = words.split_first()
+        .ok_or_else(|| AppError::EnvironmentImport("RESTIC_PASSWORD_COMMAND is empty".to_string()))?;
+    debug!("Running password command {}", program);
+
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = cmd.output()
+        .map_err(|e| AppError::EnvironmentImport(format!("Failed to run RESTIC_PASSWORD_COMMAND: {}", e)))?;
+    if !output.status.success() {
+        return Err(AppError::EnvironmentImport(format!(
+            "RESTIC_PASSWORD_COMMAND failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let stdout = Zeroizing::new(output.stdout);
+    Ok(first_line(&String::from_utf8_lossy(&stdout)).into())
+}
+
+// restic only uses the first line of password files and command output
+fn first_line(text: &str) -> String {
+    text.lines().next().unwrap_or_default().to_string()
+}
+
+/// Resolves the repository password the same way restic would, in restic's order of precedence.
+pub fn resolve_password(env: &HashMap<String, String>) -> Result<(SecretString, PasswordOrigin)> {
+    if let Some(path) = env.get("RESTIC_PASSWORD_FILE").filter(|p| !p.is_empty()) {
+        let contents = Zeroizing::new(std::fs::read_to_string(path)
+            .map_err(|e| AppError::EnvironmentImport(format!("Failed to read RESTIC_PASSWORD_FILE {}: {}", path, e)))?);
+        return Ok((first_line(&contents).into(), PasswordOrigin::PasswordFile));
+    }
+
+    if let Some(command) = env.get("RESTIC_PASSWORD_COMMAND").filter(|c| !c.is_empty()) {
+        return Ok((run_password_command(command)?, PasswordOrigin::PasswordCommand));
+    }
+
+    env.get("RESTIC_PASSWORD")
+        .filter(|p| !p.is_empty())
+        .map(|p| (p.as_str().into(), PasswordOrigin::Password))
+        .ok_or_else(|| AppError::EnvironmentImport(
+            "None of RESTIC_PASSWORD, RESTIC_PASSWORD_FILE or RESTIC_PASSWORD_COMMAND is set".to_string()
+        ))
+}
+
+/// A readable default name: the last segment of the repository location
+pub fn default_name(repository: &str) -> String {
+    repository.trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\', ':'])
+        .find(|segment| !segment.is_empty())
+        .unwrap_or(repository)
+        .to_string()
+}
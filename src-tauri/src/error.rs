@@ -1,3 +1,4 @@
+use serde::Serialize;
 use std::io;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -70,6 +71,9 @@ pub enum AppError {
     #[error("Repository ID too long (maximum 100 characters)")]
     RepoIdTooLong,
 
+    #[error("Repository not found: {0}")]
+    RepositoryNotFound(String),
+
     #[error("Password cannot be empty")]
     EmptyPassword,
 
@@ -85,6 +89,237 @@ pub enum AppError {
     #[error("At least one include path is required for selective restore")]
     NoIncludePaths,
 
+    #[error("Restic binary not found at: {0}")]
+    ResticBinaryNotFound(String),
+
+    #[error("File is not a valid restic binary: {0}")]
+    InvalidResticBinary(String),
+
+    #[error("Unsupported language: {0}")]
+    UnsupportedLanguage(String),
+
+    #[error("Window operation failed: {0}")]
+    WindowOperation(String),
+
+    #[error("Repository at {0} is not the same repository")]
+    RepositoryMismatch(String),
+
+    #[error("Cannot verify repository identity: no fingerprint or cached snapshots are available")]
+    RepositoryIdentityUnknown,
+
+    #[error("Operation ID can only contain letters, numbers, hyphens, and underscores (maximum 100 characters)")]
+    InvalidOperationId,
+
+    #[error("An operation with ID {0} is already running")]
+    OperationAlreadyRunning(String),
+
+    #[error("No running operation with ID {0}")]
+    OperationNotFound(String),
+
+    #[error("Operation {0} was cancelled")]
+    OperationCancelled(String),
+
+    #[error("Invalid search pattern: {0}")]
+    InvalidSearchPattern(String),
+
+    #[error("Snapshot path is invalid: {0}")]
+    InvalidSnapshotPath(String),
+
+    #[error("A repository already exists at {0}")]
+    RepositoryAlreadyInitialized(String),
+
+    #[error("Permission denied while creating repository at {0}")]
+    RepositoryPermissionDenied(String),
+
+    #[error("At least one keep rule is required to forget snapshots")]
+    EmptyForgetPolicy,
+
+    #[error("Invalid tag or host filter: {0}")]
+    InvalidFilterValue(String),
+
+    #[error("Secret store error: {0}")]
+    SecretStore(String),
+
+    #[error("Read data subset must be a percentage between 0 and 100, got {0}")]
+    InvalidReadDataSubset(String),
+
+    #[error("Cannot import repository from environment: {0}")]
+    EnvironmentImport(String),
+
+    #[error("Invalid password source: {0}")]
+    InvalidPasswordSource(String),
+
+    #[error("Invalid backend credentials: {0}")]
+    InvalidBackendCredentials(String),
+
+    #[error("Cannot write to {0}")]
+    DestinationNotWritable(String),
+
+    #[error("Cannot restore {0} to its original location")]
+    InPlaceRestoreUnsupported(String),
+
+    #[error("Snapshot {0} is not in the local cache; load the repository's snapshots first")]
+    SnapshotNotCached(String),
+
+    #[error("Mounting snapshots is not supported on this platform")]
+    MountUnsupported,
+
+    #[error("Something is already mounted at {0}")]
+    MountpointInUse(String),
+
+    #[error("No repository is mounted at {0}")]
+    MountNotFound(String),
+
+    #[error("Mount point {0} is not an empty directory")]
+    MountpointNotEmpty(PathBuf),
+
+    #[error("The confirmation has expired or doesn't match; request a new one")]
+    InvalidConfirmationToken,
+
+    #[error("Restore point {0} doesn't exist")]
+    RestorePointNotFound(String),
+
+    #[error("No snapshot matches {0}")]
+    NoMatchingSnapshot(String),
+
+    #[error("Failed to copy to the clipboard: {0}")]
+    Clipboard(String),
+
+    #[error("Invalid key ID: {0}")]
+    InvalidKeyId(String),
+
+    #[error("Cannot create the restore target: {0} is not writable")]
+    TargetNotWritable(PathBuf),
+
+    #[error("The repository is locked by another restic process: {0}")]
+    Locked(String),
+
+    #[error("Wrong repository password")]
+    WrongPassword,
+
+    #[error("No restic repository found: {0}")]
+    RepoNotFound(String),
+
+    #[error("The repository did not respond in time: {0}")]
+    NetworkTimeout(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Not enough disk space: {0}")]
+    OutOfSpace(String),
+
+    #[error("Invalid environment variable: {0}")]
+    InvalidEnvironmentVariable(String),
+
+    #[error("Invalid label: {0}")]
+    InvalidLabel(String),
+
+    #[error("Invalid policy: {0}")]
+    InvalidPolicy(String),
+
+    #[error("Policy template not found: {0}")]
+    PolicyTemplateNotFound(String),
+
+    #[error("Safe mode is on for repository {0}; this operation would delete or overwrite data")]
+    SafeModeEnabled(String),
+
+    #[error("Failed to download restic: {0}")]
+    ResticDownloadFailed(String),
+
+    #[error("The downloaded restic file {0} doesn't match its published checksum")]
+    ResticChecksumMismatch(String),
+
+    #[error("The restic release signature couldn't be verified: {0}")]
+    ResticSignatureInvalid(String),
+
+    #[error("No restic release is published for {0}")]
+    UnsupportedPlatform(String),
+
+    #[error("{0} requires restic {1} or newer")]
+    ResticTooOld(String, String),
+
+    #[error("Not a valid configuration export: {0}")]
+    InvalidConfigBundle(String),
+
+    #[error("The passphrase is wrong or the file was modified")]
+    WrongPassphrase,
+
+    #[error("The passphrase must be at least {0} characters long")]
+    PassphraseTooShort(String),
+
+    #[error("Pre-connect hook failed: {0}")]
+    PreConnectHookFailed(String),
+
+    #[error("Invalid pre-connect hook: {0}")]
+    InvalidPreConnectHook(String),
+
+    #[error("Can't create a thumbnail for {0}")]
+    ThumbnailUnavailable(String),
+
+    #[error("File is too large: {0}")]
+    FileTooLarge(String),
+
+    #[error("Maintenance not possible: {0}")]
+    MaintenanceBlocked(String),
+
+    #[error("Migration not available for this repository: {0}")]
+    UnknownMigration(String),
+
+    #[error("Adjusting owners and permissions after a restore isn't supported on this platform")]
+    MetadataNormalizationUnsupported,
+
+    #[error("Operation is not stalled: {0}")]
+    OperationNotStalled(String),
+
+    #[error("Operation stopped responding: {0}")]
+    OperationStalled(String),
+
+    #[error("Timed out after {0} seconds")]
+    Timeout(u64),
+
+    #[error("The repository could not be reached: {0}")]
+    NetworkUnavailable(String),
+
+    #[error("No cache backup exists for repository {0}")]
+    NoCacheBackup(String),
+
+    #[error("Unknown experiment: {0}")]
+    UnknownExperiment(String),
+
+    #[error("The experimental feature {0} is turned off")]
+    ExperimentDisabled(String),
+
+    #[error("Quick open session {0} has been closed; open the repository again")]
+    QuickOpenSessionNotFound(String),
+
+    #[error("The data directory can't be moved while {0} is running")]
+    DataDirBusy(String),
+
+    #[error("The new data directory must be empty: {0}")]
+    DataDirNotEmpty(String),
+
+    #[error("Moving the data directory failed and was undone: {0}")]
+    RelocationFailed(String),
+
+    #[error("SSH key file not found: {0}")]
+    SftpKeyFileNotFound(String),
+
+    #[error("Invalid SFTP setting: {0}")]
+    InvalidSftpOption(String),
+
+    #[error("CA certificate not found: {0}")]
+    CaCertNotFound(String),
+
+    #[error("Invalid command arguments: {0}")]
+    InvalidIpcPayload(String),
+
+    #[error("Set these variables before keeping secrets in the environment: {0}")]
+    SecretsNotInEnvironment(String),
+
+    #[error("Restoring with administrator rights can't pass {0} to restic; restore without elevation instead")]
+    ElevationNeedsEnvironment(String),
+
     #[error("Failed to execute restic: {0}")]
     ResticExecution(String),
 
@@ -94,6 +329,12 @@ pub enum AppError {
     #[error("Restore failed: {0}")]
     RestoreFailed(String),
 
+    #[error("Administrator authorization was cancelled")]
+    ElevationCancelled,
+
+    #[error("Could not run restic with administrator rights: {0}")]
+    ElevationUnavailable(String),
+
     #[error("Failed to parse snapshots JSON: {0}")]
     SnapshotJsonParse(String),
 
@@ -113,9 +354,248 @@ pub enum AppError {
     Storage(String),
 }
 
+impl AppError {
+    /// Stable identifier used to look up translations and by the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::EmptyRepositoryPath => "empty_repository_path",
+            AppError::InvalidRepositoryPath => "invalid_repository_path",
+            AppError::UnsupportedProtocol(_) => "unsupported_protocol",
+            AppError::RemotePathTooShort => "remote_path_too_short",
+            AppError::PathTraversal => "path_traversal",
+            AppError::EmptySnapshotId => "empty_snapshot_id",
+            AppError::InvalidSnapshotId => "invalid_snapshot_id",
+            AppError::SnapshotIdTooShort => "snapshot_id_too_short",
+            AppError::SnapshotIdTooLong => "snapshot_id_too_long",
+            AppError::SnapshotIdNotHex => "snapshot_id_not_hex",
+            AppError::EmptyTargetPath => "empty_target_path",
+            AppError::InvalidTargetPath => "invalid_target_path",
+            AppError::RelativeTargetPath => "relative_target_path",
+            AppError::ParentDirectoryNotFound(_) => "parent_directory_not_found",
+            AppError::EmptyIncludePath => "empty_include_path",
+            AppError::InvalidIncludePath => "invalid_include_path",
+            AppError::ExcessiveParentTraversal => "excessive_parent_traversal",
+            AppError::AbsoluteIncludePath => "absolute_include_path",
+            AppError::EmptyRepoId => "empty_repo_id",
+            AppError::InvalidRepoId => "invalid_repo_id",
+            AppError::InvalidRepoIdCharacters => "invalid_repo_id_characters",
+            AppError::RepoIdTooLong => "repo_id_too_long",
+            AppError::RepositoryNotFound(_) => "repository_not_found",
+            AppError::EmptyPassword => "empty_password",
+            AppError::InvalidPassword => "invalid_password",
+            AppError::EmptyRepositoryName => "empty_repository_name",
+            AppError::RepositoryNameTooLong => "repository_name_too_long",
+            AppError::NoIncludePaths => "no_include_paths",
+            AppError::ResticBinaryNotFound(_) => "restic_binary_not_found",
+            AppError::InvalidResticBinary(_) => "invalid_restic_binary",
+            AppError::UnsupportedLanguage(_) => "unsupported_language",
+            AppError::WindowOperation(_) => "window_operation",
+            AppError::RepositoryMismatch(_) => "repository_mismatch",
+            AppError::RepositoryIdentityUnknown => "repository_identity_unknown",
+            AppError::InvalidOperationId => "invalid_operation_id",
+            AppError::OperationAlreadyRunning(_) => "operation_already_running",
+            AppError::OperationNotFound(_) => "operation_not_found",
+            AppError::OperationCancelled(_) => "operation_cancelled",
+            AppError::InvalidSearchPattern(_) => "invalid_search_pattern",
+            AppError::InvalidSnapshotPath(_) => "invalid_snapshot_path",
+            AppError::RepositoryAlreadyInitialized(_) => "repository_already_initialized",
+            AppError::RepositoryPermissionDenied(_) => "repository_permission_denied",
+            AppError::EmptyForgetPolicy => "empty_forget_policy",
+            AppError::InvalidFilterValue(_) => "invalid_filter_value",
+            AppError::SecretStore(_) => "secret_store",
+            AppError::InvalidReadDataSubset(_) => "invalid_read_data_subset",
+            AppError::EnvironmentImport(_) => "environment_import",
+            AppError::InvalidPasswordSource(_) => "invalid_password_source",
+            AppError::InvalidBackendCredentials(_) => "invalid_backend_credentials",
+            AppError::DestinationNotWritable(_) => "destination_not_writable",
+            AppError::InPlaceRestoreUnsupported(_) => "in_place_restore_unsupported",
+            AppError::SnapshotNotCached(_) => "snapshot_not_cached",
+            AppError::MountUnsupported => "mount_unsupported",
+            AppError::MountpointInUse(_) => "mountpoint_in_use",
+            AppError::MountNotFound(_) => "mount_not_found",
+            AppError::MountpointNotEmpty(_) => "mountpoint_not_empty",
+            AppError::InvalidConfirmationToken => "invalid_confirmation_token",
+            AppError::RestorePointNotFound(_) => "restore_point_not_found",
+            AppError::NoMatchingSnapshot(_) => "no_matching_snapshot",
+            AppError::Clipboard(_) => "clipboard",
+            AppError::InvalidKeyId(_) => "invalid_key_id",
+            AppError::TargetNotWritable(_) => "target_not_writable",
+            AppError::Locked(_) => "locked",
+            AppError::WrongPassword => "wrong_password",
+            AppError::RepoNotFound(_) => "repo_not_found",
+            AppError::NetworkTimeout(_) => "network_timeout",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::OutOfSpace(_) => "out_of_space",
+            AppError::InvalidEnvironmentVariable(_) => "invalid_environment_variable",
+            AppError::InvalidLabel(_) => "invalid_label",
+            AppError::InvalidPolicy(_) => "invalid_policy",
+            AppError::PolicyTemplateNotFound(_) => "policy_template_not_found",
+            AppError::SafeModeEnabled(_) => "safe_mode_enabled",
+            AppError::ResticDownloadFailed(_) => "restic_download_failed",
+            AppError::ResticChecksumMismatch(_) => "restic_checksum_mismatch",
+            AppError::ResticSignatureInvalid(_) => "restic_signature_invalid",
+            AppError::UnsupportedPlatform(_) => "unsupported_platform",
+            AppError::ResticTooOld(..) => "restic_too_old",
+            AppError::InvalidConfigBundle(_) => "invalid_config_bundle",
+            AppError::WrongPassphrase => "wrong_passphrase",
+            AppError::PassphraseTooShort(_) => "passphrase_too_short",
+            AppError::PreConnectHookFailed(_) => "pre_connect_hook_failed",
+            AppError::InvalidPreConnectHook(_) => "invalid_pre_connect_hook",
+            AppError::ThumbnailUnavailable(_) => "thumbnail_unavailable",
+            AppError::FileTooLarge(_) => "file_too_large",
+            AppError::MaintenanceBlocked(_) => "maintenance_blocked",
+            AppError::UnknownMigration(_) => "unknown_migration",
+            AppError::MetadataNormalizationUnsupported => "metadata_normalization_unsupported",
+            AppError::OperationNotStalled(_) => "operation_not_stalled",
+            AppError::OperationStalled(_) => "operation_stalled",
+            AppError::Timeout(_) => "timeout",
+            AppError::NetworkUnavailable(_) => "network_unavailable",
+            AppError::NoCacheBackup(_) => "no_cache_backup",
+            AppError::UnknownExperiment(_) => "unknown_experiment",
+            AppError::ExperimentDisabled(_) => "experiment_disabled",
+            AppError::QuickOpenSessionNotFound(_) => "quick_open_session_not_found",
+            AppError::DataDirBusy(_) => "data_dir_busy",
+            AppError::DataDirNotEmpty(_) => "data_dir_not_empty",
+            AppError::RelocationFailed(_) => "relocation_failed",
+            AppError::SftpKeyFileNotFound(_) => "sftp_key_file_not_found",
+            AppError::InvalidSftpOption(_) => "invalid_sftp_option",
+            AppError::CaCertNotFound(_) => "ca_cert_not_found",
+            AppError::InvalidIpcPayload(_) => "invalid_ipc_payload",
+            AppError::SecretsNotInEnvironment(_) => "secrets_not_in_environment",
+            AppError::ElevationNeedsEnvironment(_) => "elevation_needs_environment",
+            AppError::ResticExecution(_) => "restic_execution",
+            AppError::ResticError(_) => "restic_error",
+            AppError::RestoreFailed(_) => "restore_failed",
+            AppError::ElevationCancelled => "elevation_cancelled",
+            AppError::ElevationUnavailable(_) => "elevation_unavailable",
+            AppError::SnapshotJsonParse(_) => "snapshot_json_parse",
+            AppError::StatsJsonParse(_) => "stats_json_parse",
+            AppError::RepoStatsJsonParse(_) => "repo_stats_json_parse",
+            AppError::Io(_) => "io",
+            AppError::Json(_) => "json",
+            AppError::Storage(_) => "storage",
+        }
+    }
+
+    /// Values substituted into the `{0}` placeholder of translated messages
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            AppError::UnsupportedProtocol(detail) => vec![detail.clone()],
+            AppError::ParentDirectoryNotFound(path) => vec![path.display().to_string()],
+            AppError::RepositoryNotFound(detail) => vec![detail.clone()],
+            AppError::ResticBinaryNotFound(detail) => vec![detail.clone()],
+            AppError::InvalidResticBinary(detail) => vec![detail.clone()],
+            AppError::UnsupportedLanguage(detail) => vec![detail.clone()],
+            AppError::WindowOperation(detail) => vec![detail.clone()],
+            AppError::RepositoryMismatch(detail) => vec![detail.clone()],
+            AppError::OperationAlreadyRunning(detail) => vec![detail.clone()],
+            AppError::OperationNotFound(detail) => vec![detail.clone()],
+            AppError::OperationCancelled(detail) => vec![detail.clone()],
+            AppError::InvalidSearchPattern(detail) => vec![detail.clone()],
+            AppError::InvalidSnapshotPath(detail) => vec![detail.clone()],
+            AppError::RepositoryAlreadyInitialized(detail) => vec![detail.clone()],
+            AppError::RepositoryPermissionDenied(detail) => vec![detail.clone()],
+            AppError::InvalidFilterValue(detail) => vec![detail.clone()],
+            AppError::SecretStore(detail) => vec![detail.clone()],
+            AppError::InvalidReadDataSubset(detail) => vec![detail.clone()],
+            AppError::EnvironmentImport(detail) => vec![detail.clone()],
+            AppError::InvalidPasswordSource(detail) => vec![detail.clone()],
+            AppError::InvalidBackendCredentials(detail) => vec![detail.clone()],
+            AppError::DestinationNotWritable(detail) => vec![detail.clone()],
+            AppError::InPlaceRestoreUnsupported(detail) => vec![detail.clone()],
+            AppError::SnapshotNotCached(detail) => vec![detail.clone()],
+            AppError::MountpointInUse(detail) => vec![detail.clone()],
+            AppError::MountNotFound(detail) => vec![detail.clone()],
+            AppError::MountpointNotEmpty(path) => vec![path.display().to_string()],
+            AppError::RestorePointNotFound(detail) => vec![detail.clone()],
+            AppError::NoMatchingSnapshot(detail) => vec![detail.clone()],
+            AppError::Clipboard(detail) => vec![detail.clone()],
+            AppError::InvalidKeyId(detail) => vec![detail.clone()],
+            AppError::TargetNotWritable(path) => vec![path.display().to_string()],
+            AppError::Locked(detail) => vec![detail.clone()],
+            AppError::RepoNotFound(detail) => vec![detail.clone()],
+            AppError::NetworkTimeout(detail) => vec![detail.clone()],
+            AppError::PermissionDenied(detail) => vec![detail.clone()],
+            AppError::OutOfSpace(detail) => vec![detail.clone()],
+            AppError::InvalidEnvironmentVariable(detail) => vec![detail.clone()],
+            AppError::InvalidLabel(detail) => vec![detail.clone()],
+            AppError::InvalidPolicy(detail) => vec![detail.clone()],
+            AppError::PolicyTemplateNotFound(detail) => vec![detail.clone()],
+            AppError::SafeModeEnabled(detail) => vec![detail.clone()],
+            AppError::ResticDownloadFailed(detail) => vec![detail.clone()],
+            AppError::ResticChecksumMismatch(detail) => vec![detail.clone()],
+            AppError::ResticSignatureInvalid(detail) => vec![detail.clone()],
+            AppError::UnsupportedPlatform(detail) => vec![detail.clone()],
+            AppError::ResticTooOld(feature, required) => vec![feature.clone(), required.clone()],
+            AppError::InvalidConfigBundle(detail) => vec![detail.clone()],
+            AppError::PassphraseTooShort(detail) => vec![detail.clone()],
+            AppError::PreConnectHookFailed(detail) => vec![detail.clone()],
+            AppError::InvalidPreConnectHook(detail) => vec![detail.clone()],
+            AppError::ThumbnailUnavailable(detail) => vec![detail.clone()],
+            AppError::FileTooLarge(detail) => vec![detail.clone()],
+            AppError::MaintenanceBlocked(detail) => vec![detail.clone()],
+            AppError::UnknownMigration(detail) => vec![detail.clone()],
+            AppError::OperationNotStalled(detail) => vec![detail.clone()],
+            AppError::OperationStalled(detail) => vec![detail.clone()],
+            AppError::Timeout(secs) => vec![secs.to_string()],
+            AppError::NetworkUnavailable(detail) => vec![detail.clone()],
+            AppError::NoCacheBackup(detail) => vec![detail.clone()],
+            AppError::UnknownExperiment(detail) => vec![detail.clone()],
+            AppError::ExperimentDisabled(detail) => vec![detail.clone()],
+            AppError::QuickOpenSessionNotFound(detail) => vec![detail.clone()],
+            AppError::DataDirBusy(detail) => vec![detail.clone()],
+            AppError::DataDirNotEmpty(detail) => vec![detail.clone()],
+            AppError::RelocationFailed(detail) => vec![detail.clone()],
+            AppError::SftpKeyFileNotFound(detail) => vec![detail.clone()],
+            AppError::InvalidSftpOption(detail) => vec![detail.clone()],
+            AppError::CaCertNotFound(detail) => vec![detail.clone()],
+            AppError::InvalidIpcPayload(detail) => vec![detail.clone()],
+            AppError::SecretsNotInEnvironment(detail) => vec![detail.clone()],
+            AppError::ElevationNeedsEnvironment(detail) => vec![detail.clone()],
+            AppError::ResticExecution(detail) => vec![detail.clone()],
+            AppError::ResticError(detail) => vec![detail.clone()],
+            AppError::RestoreFailed(detail) => vec![detail.clone()],
+            AppError::ElevationUnavailable(detail) => vec![detail.clone()],
+            AppError::SnapshotJsonParse(detail) => vec![detail.clone()],
+            AppError::StatsJsonParse(detail) => vec![detail.clone()],
+            AppError::RepoStatsJsonParse(detail) => vec![detail.clone()],
+            AppError::Io(e) => vec![e.to_string()],
+            AppError::Json(e) => vec![e.to_string()],
+            AppError::Storage(detail) => vec![detail.clone()],
+            _ => Vec::new(),
+        }
+    }
+}
+
 impl From<AppError> for String {
     fn from(error: AppError) -> Self {
-        error.to_string()
+        crate::messages::error_text(&error)
+    }
+}
+
+/// What a failed command hands the frontend: the error's stable code for
+/// tailoring the UI, and the message already translated for display
+#[derive(Debug, Serialize, Clone)]
+pub struct CommandError {
+    pub code: String,
+    pub message: String,
+    pub args: Vec<String>,
+}
+
+impl From<AppError> for CommandError {
+    fn from(error: AppError) -> Self {
+        CommandError {
+            code: error.code().to_string(),
+            message: crate::messages::error_text(&error),
+            args: error.args(),
+        }
+    }
+}
+
+// Errors that never were an AppError carry no code of their own
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError { code: "other".to_string(), message, args: Vec::new() }
     }
 }
 
@@ -0,0 +1,69 @@
+use crate::error::{AppError, Result};
+use crate::messages::tr;
+use crate::storage::{load_config, AppConfig};
+use serde::Serialize;
+
+/// Features that ship switched off until they've proven themselves. The user
+/// turns them on in the config's `experiments` map, keyed by `name()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Experiment {
+    /// `restore_in_place`, which writes over the snapshot's original paths
+    InPlaceRestore,
+}
+
+impl Experiment {
+    pub const ALL: &'static [Experiment] = &[Experiment::InPlaceRestore];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Experiment::InPlaceRestore => "in_place_restore",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        Self::ALL.iter()
+            .copied()
+            .find(|e| e.name() == name)
+            .ok_or_else(|| AppError::UnknownExperiment(name.to_string()))
+    }
+
+    pub fn enabled_by_default(self) -> bool {
+        match self {
+            Experiment::InPlaceRestore => false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ExperimentState {
+    pub name: &'static str,
+    pub description: String,
+    pub enabled: bool,
+    pub enabled_by_default: bool,
+}
+
+pub fn is_enabled(config: &AppConfig, experiment: Experiment) -> bool {
+    config.experiments.get(experiment.name()).copied().unwrap_or(experiment.enabled_by_default())
+}
+
+/// Fails with `ExperimentDisabled` unless the user turned the experiment on
+pub fn require(experiment: Experiment) -> Result<()> {
+    let config = load_config().map_err(AppError::Storage)?;
+    match is_enabled(&config, experiment) {
+        true => Ok(()),
+        false => Err(AppError::ExperimentDisabled(experiment.name().to_string())),
+    }
+}
+
+pub fn state(config: &AppConfig, experiment: Experiment) -> ExperimentState {
+    ExperimentState {
+        name: experiment.name(),
+        description: tr(&format!("experiment.{}", experiment.name()), &[]),
+        enabled: is_enabled(config, experiment),
+        enabled_by_default: experiment.enabled_by_default(),
+    }
+}
+
+pub fn states(config: &AppConfig) -> Vec<ExperimentState> {
+    Experiment::ALL.iter().map(|e| state(config, *e)).collect()
+}
@@ -0,0 +1,111 @@
+use crate::models::{RepoLock, Snapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How long a measured health report is served from the cache
+pub const TTL_SECS: i64 = 15 * 60;
+
+/// `restic stats --mode raw-data`; the compression fields need repository format 2
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RawDataStats {
+    pub total_size: u64,
+    pub total_uncompressed_size: Option<u64>,
+    pub compression_ratio: Option<f64>,
+    pub total_blob_count: Option<u64>,
+}
+
+/// `restic stats --mode restore-size`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RestoreSizeStats {
+    pub total_size: u64,
+    pub total_file_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostBackup {
+    pub hostname: String,
+    pub last_backup: String,
+    pub age_secs: i64,
+    pub snapshot_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoHealth {
+    pub raw_data: RawDataStats,
+    pub restore_size: RestoreSizeStats,
+    pub snapshot_count: usize,
+    /// Newest snapshot per host, the stalest host first
+    pub hosts: Vec<HostBackup>,
+    pub lock_count: usize,
+    pub stale_lock_count: usize,
+    pub exclusive_locked: bool,
+    /// Size of restic's local cache for the repository, None when it has none
+    pub restic_cache_bytes: Option<u64>,
+    /// Size of the app's cached file listings for the repository
+    pub app_cache_bytes: u64,
+    pub measured_at: i64,
+    /// Served from the `repo_health` table rather than measured for this request
+    #[serde(default)]
+    pub cached: bool,
+}
+
+pub fn host_backups(snapshots: &[Snapshot], now: i64) -> Vec<HostBackup> {
+    let mut newest: BTreeMap<&str, (i64, &str, usize)> = BTreeMap::new();
+    for snapshot in snapshots {
+        let Some(time) = chrono::DateTime::parse_from_rfc3339(&snapshot.time).ok().map(|t| t.timestamp()) else {
+            continue;
+        };
+        let entry = newest.entry(snapshot.hostname.as_str()).or_insert((time, snapshot.time.as_str(), 0));
+        entry.2 += 1;
+        if time > entry.0 {
+            entry.0 = time;
+            entry.1 = snapshot.time.as_str();
+        }
+    }
+
+    let mut hosts: Vec<HostBackup> = newest.into_iter()
+        .map(|(hostname, (time, last_backup, snapshot_count))| HostBackup {
+            hostname: hostname.to_string(),
+            last_backup: last_backup.to_string(),
+            age_secs: (now - time).max(0),
+            snapshot_count,
+        })
+        .collect();
+    hosts.sort_by_key(|h| std::cmp::Reverse(h.age_secs));
+    hosts
+}
+
+pub fn lock_summary(locks: &[RepoLock]) -> (usize, usize, bool) {
+    (locks.len(), locks.iter().filter(|l| l.stale).count(), locks.iter().any(|l| l.exclusive && !l.stale))
+}
+
+/// restic keeps one cache directory per repository, named after the repository ID
+pub fn restic_cache_dir(cache_override: Option<&str>, fingerprint: &str) -> Option<PathBuf> {
+    let base = match cache_override {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::cache_dir()?.join("restic"),
+    };
+    Some(base.join(fingerprint))
+}
+
+pub fn dir_size(path: &Path) -> Option<u64> {
+    if !path.is_dir() {
+        return None;
+    }
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            match entry.metadata() {
+                Ok(m) if m.is_dir() => pending.push(entry.path()),
+                Ok(m) => total += m.len(),
+                Err(_) => {}
+            }
+        }
+    }
+    Some(total)
+}
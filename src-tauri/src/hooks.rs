@@ -0,0 +1,185 @@
+use crate::error::{AppError, Result};
+use crate::storage::{PreConnectHook, SavedRepository};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::UdpSocket;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+const DEFAULT_BROADCAST: &str = "255.255.255.255";
+// The discard port; wake-on-LAN listeners don't care which port the packet arrives on
+const WAKE_ON_LAN_PORT: u16 = 9;
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_WAIT_SECS: u64 = 600;
+
+// Keyed by repository id. The inner lock is held while hooks run, so a second restic
+// call waits for the first one's hooks instead of running them again.
+static READY: Lazy<Mutex<HashMap<String, Arc<Mutex<bool>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn state(repo_id: &str) -> Result<Arc<Mutex<bool>>> {
+    let mut ready = READY.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock pre-connect hook state: {}", e)))?;
+    Ok(ready.entry(repo_id.to_string()).or_default().clone())
+}
+
+/// Runs the repository's hooks unless they already succeeded this session.
+/// A failed hook is retried on the next restic call.
+pub fn ensure_ready(repo: &SavedRepository) -> Result<()> {
+    if repo.pre_connect.is_empty() {
+        return Ok(());
+    }
+    let state = state(&repo.id)?;
+    let mut ready = state.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock pre-connect hook state: {}", e)))?;
+    if *ready {
+        return Ok(());
+    }
+
+    info!("Running {} pre-connect hooks for repository {}", repo.pre_connect.len(), repo.id);
+    run_all(&repo.pre_connect)?;
+    *ready = true;
+    Ok(())
+}
+
+/// Makes the hooks run again before the repository's next restic call
+pub fn forget(repo_id: &str) {
+    if let Ok(mut ready) = READY.lock() {
+        ready.remove(repo_id);
+    }
+}
+
+pub fn run_all(hooks: &[PreConnectHook]) -> Result<()> {
+    for hook in hooks {
+        match hook {
+            PreConnectHook::WakeOnLan { mac, broadcast, wait_secs } => {
+                wake_on_lan(mac, broadcast.as_deref().unwrap_or(DEFAULT_BROADCAST))?;
+                if let Some(secs) = wait_secs.filter(|&s| s > 0) {
+                    debug!("Waiting {} s for {} to wake up", secs, mac);
+                    std::thread::sleep(Duration::from_secs(secs));
+                }
+            }
+            PreConnectHook::Command { command, args, timeout_secs } => {
+                let timeout = timeout_secs.map(Duration::from_secs).unwrap_or(DEFAULT_COMMAND_TIMEOUT);
+                run_command(command, args, timeout)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn validate(hooks: &[PreConnectHook]) -> Result<()> {
+    for hook in hooks {
+        match hook {
+            PreConnectHook::WakeOnLan { mac, broadcast, wait_secs } => {
+                parse_mac(mac)?;
+                if let Some(broadcast) = broadcast {
+                    broadcast.parse::<std::net::Ipv4Addr>()
+                        .map_err(|_| AppError::InvalidPreConnectHook(format!("{} is not an IPv4 address", broadcast)))?;
+                }
+                if wait_secs.is_some_and(|s| s > MAX_WAIT_SECS) {
+                    return Err(AppError::InvalidPreConnectHook(format!("waits are limited to {} s", MAX_WAIT_SECS)));
+                }
+            }
+            PreConnectHook::Command { command, args, timeout_secs } => {
+                if command.trim().is_empty() {
+                    return Err(AppError::InvalidPreConnectHook("the command is empty".to_string()));
+                }
+                if command.contains('\0') || args.iter().any(|a| a.contains('\0')) {
+                    return Err(AppError::InvalidPreConnectHook(format!("{} contains invalid characters", command)));
+                }
+                if timeout_secs.is_some_and(|s| s == 0 || s > MAX_WAIT_SECS) {
+                    return Err(AppError::InvalidPreConnectHook(format!("timeouts must be between 1 and {} s", MAX_WAIT_SECS)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Accepts `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff` and `aabbccddeeff`
+fn parse_mac(mac: &str) -> Result<[u8; 6]> {
+    let digits: String = mac.chars().filter(|c| !matches!(c, ':' | '-')).collect();
+    let invalid = || AppError::InvalidPreConnectHook(format!("{} is not a MAC address", mac));
+    if digits.len() != 12 {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 6];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+fn wake_on_lan(mac: &str, broadcast: &str) -> Result<()> {
+    let target = parse_mac(mac)?;
+    // Six 0xFF bytes followed by the MAC address sixteen times
+    let mut packet = vec![0xFFu8; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&target);
+    }
+
+    let failed = |e: std::io::Error| AppError::PreConnectHookFailed(format!("wake-on-LAN for {}: {}", mac, e));
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(failed)?;
+    socket.set_broadcast(true).map_err(failed)?;
+    socket.send_to(&packet, (broadcast, WAKE_ON_LAN_PORT)).map_err(failed)?;
+    info!("Sent wake-on-LAN packet for {} to {}", mac, broadcast);
+    Ok(())
+}
+
+fn run_command(command: &str, args: &[String], timeout: Duration) -> Result<()> {
+    debug!("Running pre-connect hook: {} {}", command, args.join(" "));
+    let mut cmd = Command::new(command);
+    cmd.args(args)
+       .stdin(Stdio::null())
+       .stdout(Stdio::null())
+       .stderr(Stdio::piped());
+
+    #[cfg(target_os = "windows")]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = cmd.spawn()
+        .map_err(|e| AppError::PreConnectHookFailed(format!("{}: {}", command, e)))?;
+
+    // Read stderr on the side so a chatty hook can't fill the pipe and stall
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        })
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                warn!("Pre-connect hook {} timed out after {} s", command, timeout.as_secs());
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::PreConnectHookFailed(format!("{} timed out after {} s", command, timeout.as_secs())));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+            Err(e) => return Err(AppError::PreConnectHookFailed(format!("{}: {}", command, e))),
+        }
+    };
+
+    if !status.success() {
+        let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+        let detail = match stderr.trim() {
+            "" => status.to_string(),
+            message => message.to_string(),
+        };
+        return Err(AppError::PreConnectHookFailed(format!("{}: {}", command, detail)));
+    }
+    Ok(())
+}
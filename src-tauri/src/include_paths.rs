@@ -0,0 +1,95 @@
+use crate::error::{AppError, Result};
+use std::path::PathBuf;
+
+/// Turns a path into the form restic uses inside the snapshot tree: forward
+/// slashes, a leading slash, and Windows drives as a first segment (`C:\Users` -> `/C/Users`).
+/// Relative paths stay relative.
+pub fn to_tree_path(path: &str) -> Result<String> {
+    let unified = path.trim().replace('\\', "/");
+    let mut chars = unified.chars();
+    let (absolute, rest) = match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            (true, format!("{}/{}", drive.to_ascii_uppercase(), &unified[2..]))
+        }
+        (Some('/'), _) => (true, unified[1..].to_string()),
+        _ => (false, unified.clone()),
+    };
+
+    let segments: Vec<&str> = rest.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+    if segments.contains(&"..") || path.contains('\0') {
+        return Err(AppError::InvalidSnapshotPath(path.to_string()));
+    }
+
+    let joined = segments.join("/");
+    Ok(if absolute { format!("/{}", joined) } else { joined })
+}
+
+/// Maps a path inside the snapshot tree back to where it was backed up from,
+/// the reverse of `to_tree_path` (`/C/Users` -> `C:\Users` on Windows)
+pub fn to_original_path(tree_path: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        let mut parts = tree_path.trim_start_matches('/').splitn(2, '/');
+        let drive = parts.next().unwrap_or_default();
+        let rest = parts.next().unwrap_or_default().replace('/', "\\");
+        PathBuf::from(format!("{}:\\{}", drive, rest))
+    } else {
+        PathBuf::from(tree_path)
+    }
+}
+
+fn is_within(path: &str, root: &str) -> bool {
+    root == "/" || path == root || path.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'))
+}
+
+// A relative selection belongs to the root whose last segment it starts with,
+// or to the only root when there is just one
+fn resolve_relative(relative: &str, roots: &[String]) -> Option<String> {
+    if let [root] = roots {
+        return Some(format!("{}/{}", root.trim_end_matches('/'), relative));
+    }
+    let first = relative.split('/').next()?;
+    let mut matching = roots.iter().filter(|root| root.rsplit('/').next() == Some(first));
+    let root = matching.next()?;
+    if matching.next().is_some() {
+        return None;
+    }
+    let parent = root.rsplit_once('/').map(|(parent, _)| parent).unwrap_or_default();
+    Some(format!("{}/{}", parent, relative))
+}
+
+/// Maps browsed selections to `--include` arguments: snapshot-tree paths without the
+/// leading slash, with selections inside other selected directories left out.
+/// `roots` are the snapshot's backup paths; when empty, paths aren't checked against them.
+pub fn build(roots: &[String], selected: &[String]) -> Result<Vec<String>> {
+    let roots: Vec<String> = roots.iter().map(|r| to_tree_path(r)).collect::<Result<_>>()?;
+
+    let mut paths = Vec::with_capacity(selected.len());
+    for selection in selected {
+        let tree_path = to_tree_path(selection)?;
+        let absolute = if tree_path.starts_with('/') {
+            tree_path
+        } else {
+            resolve_relative(&tree_path, &roots)
+                .ok_or_else(|| AppError::InvalidSnapshotPath(selection.clone()))?
+        };
+
+        if absolute == "/" || (!roots.is_empty() && !roots.iter().any(|root| is_within(&absolute, root))) {
+            return Err(AppError::InvalidSnapshotPath(selection.clone()));
+        }
+        paths.push(absolute);
+    }
+
+    // Shorter paths sort first, so parents are kept before their children are seen
+    paths.sort();
+    paths.dedup();
+    let mut minimal: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        if !minimal.iter().any(|kept| is_within(&path, kept)) {
+            minimal.push(path);
+        }
+    }
+
+    Ok(minimal.into_iter()
+        .map(|p| p.trim_start_matches('/').to_string())
+        .collect())
+}
@@ -3,6 +3,49 @@ mod models;
 mod commands;
 mod storage;
 mod database;
+mod anomalies;
+mod messages;
+mod window_scope;
+mod operations;
+mod demo;
+mod content_search;
+mod secrets;
+mod env_import;
+mod restore_suggestions;
+mod elevation;
+mod query_log;
+mod binary_arch;
+mod manifest;
+mod notifications;
+mod node_cache;
+mod include_paths;
+mod scheduler;
+mod restic_args;
+mod mounts;
+mod clipboard;
+mod limiter;
+mod target_dirs;
+mod background;
+mod restic_errors;
+mod verbosity;
+mod policies;
+mod restic_download;
+mod restic_version;
+mod hooks;
+mod browse;
+mod thumbnails;
+mod compatibility;
+mod restore_metadata;
+mod health;
+mod repo_status;
+mod logs;
+mod cache_backup;
+mod debug_bundle;
+mod experiments;
+mod quick_open;
+mod win_paths;
+mod relocate;
+mod strict_ipc;
 
 use commands::*;
 
@@ -18,9 +61,32 @@ pub fn run() {
 
     tracing::info!("Starting Restic-Restore application");
 
+    if let Ok(config) = storage::load_config() {
+        if let Some(language) = config.language {
+            messages::set_language(&language);
+        }
+        if let Some(threshold_ms) = config.slow_query_threshold_ms {
+            query_log::set_threshold_ms(threshold_ms);
+        }
+        if let Some(limit) = config.max_concurrent_restic {
+            limiter::set_max_per_repo(limit);
+        }
+        if let Some(secs) = config.stall_threshold_secs {
+            operations::set_stall_threshold_secs(secs);
+        }
+        if let Some(settings) = config.verbosity {
+            verbosity::set(settings);
+        }
+    }
+
+    if let Err(e) = secrets::choose_initial_backend() {
+        tracing::error!("Failed to set up password storage: {}", e);
+    }
+
     match database::init_database() {
         Ok(_) => {
             tracing::info!("Database initialized successfully");
+            purge_expired_repositories();
         }
         Err(e) => {
             tracing::error!("CRITICAL: Failed to initialize database: {}", e);
@@ -32,39 +98,202 @@ pub fn run() {
         }
     }
 
+    let handler: fn(tauri::ipc::Invoke<tauri::Wry>) -> bool = tauri::generate_handler![
+        connect_repository,
+        quick_open_repository,
+        quick_open_snapshots,
+        quick_open_browse,
+        quick_open_restore,
+        close_quick_open,
+        save_quick_open,
+        init_repository,
+        import_from_environment,
+        list_snapshots,
+        list_snapshot_groups,
+        query_cached_snapshots,
+        prime_snapshot_cache,
+        sync_snapshots,
+        get_snapshot_facets,
+        get_snapshot_details,
+        restore_snapshot,
+        restore_selective,
+        plan_multi_snapshot_restore,
+        restore_cart,
+        restore_in_place,
+        build_include_paths,
+        list_restore_points,
+        save_restore_point,
+        delete_restore_point,
+        execute_restore_point,
+        preview_restore,
+        verify_restore,
+        suggest_restore_target,
+        browse_snapshot,
+        browse_snapshot_page,
+        get_file_thumbnail,
+        cache_snapshot_tree,
+        index_snapshot_files,
+        get_index_status,
+        search_files,
+        render_command_preview,
+        search_cached_nodes,
+        get_node_cache_stats,
+        set_node_cache_compression,
+        search_file_contents,
+        diff_snapshots,
+        get_latest_changes,
+        find_in_repository,
+        export_snapshot_manifest,
+        get_snapshot_stats,
+        prefetch_snapshot_stats,
+        compute_snapshot_deltas,
+        add_snapshot_tags,
+        remove_snapshot_tags,
+        set_snapshot_tags,
+        mount_repository,
+        unmount_repository,
+        list_mounts,
+        get_recent_operations,
+        get_operation_log_dir,
+        export_debug_bundle,
+        relocate_app_data,
+        cancel_operation,
+        list_operations,
+        retry_operation,
+        get_stall_threshold,
+        set_stall_threshold,
+        get_repository_stats,
+        get_repository_health,
+        get_repository_status,
+        get_repository_statuses,
+        get_backup_health,
+        list_keys,
+        add_key,
+        remove_key,
+        change_password,
+        list_locks,
+        unlock_repository,
+        forget_snapshots,
+        simulate_retention,
+        prune_repository,
+        get_compatibility_report,
+        migrate_repository,
+        check_repository,
+        set_repository_budget,
+        set_refresh_interval,
+        set_repo_priority,
+        list_policy_templates,
+        save_policy_template,
+        delete_policy_template,
+        set_repository_labels,
+        set_repository_policy,
+        get_effective_policy,
+        list_background_jobs,
+        save_repositories,
+        load_repositories,
+        load_repositories_with_status,
+        get_config_path,
+        export_config,
+        import_config,
+        remove_repository,
+        undo_remove_repository,
+        list_removed_repositories,
+        purge_repository,
+        set_deletion_grace_days,
+        relink_repository,
+        get_secret_backend,
+        store_repo_password,
+        get_repo_password,
+        set_password_source,
+        set_backend_credentials,
+        get_backend_credentials,
+        set_repository_env,
+        set_repository_hooks,
+        set_repository_sftp,
+        set_repository_tls,
+        test_pre_connect_hooks,
+        get_repository_env,
+        set_secret_backend,
+        copy_to_clipboard,
+        prepare_secret_wipe,
+        wipe_all_secrets,
+        list_restore_history,
+        clear_restore_history,
+        get_audit_log,
+        get_restic_binary_path,
+        set_restic_binary_path,
+        get_detected_restic_path,
+        download_restic,
+        check_restic_update,
+        get_restic_capabilities,
+        check_restic_setup_status,
+        mark_setup_completed,
+        create_demo_repository,
+        get_language,
+        set_language,
+        // SQLite database commands
+        init_database_command,
+        load_snapshots_from_db,
+        load_all_snapshots,
+        get_cached_snapshot_ids,
+        save_snapshots_batch,
+        save_snapshots_metadata_only,
+        update_last_delta_check,
+        get_repo_meta,
+        clear_repo_cache,
+        list_cache_backups,
+        restore_cache_backup,
+        get_slow_queries,
+        get_notification_settings,
+        get_experiments,
+        set_experiment,
+        set_notification_settings,
+        reset_notification_cooldowns,
+        set_slow_query_threshold,
+        get_max_concurrent_restic,
+        set_max_concurrent_restic,
+        get_verbosity_settings,
+        set_verbosity_settings,
+        clear_slow_queries,
+        get_snapshot_pins,
+        pin_snapshot,
+        set_pinned_snapshots,
+        get_snapshot_anomalies,
+        get_cached_stats,
+        batch_invoke,
+        bind_window_repository,
+        get_window_repository,
+        open_repository_window
+    ];
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![
-            connect_repository,
-            list_snapshots,
-            get_snapshot_details,
-            restore_snapshot,
-            restore_selective,
-            browse_snapshot,
-            get_snapshot_stats,
-            get_repository_stats,
-            save_repositories,
-            load_repositories,
-            get_config_path,
-            remove_repository,
-            get_restic_binary_path,
-            set_restic_binary_path,
-            get_detected_restic_path,
-            check_restic_setup_status,
-            mark_setup_completed,
-            // SQLite database commands
-            init_database_command,
-            load_snapshots_from_db,
-            get_cached_snapshot_ids,
-            save_snapshots_batch,
-            save_snapshots_metadata_only,
-            update_last_delta_check,
-            get_repo_meta,
-            clear_repo_cache
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .setup(|app| {
+            scheduler::start(app.handle().clone());
+            operations::start_watchdog(app.handle().clone());
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window_scope::unbind(window.label());
+            }
+        })
+        .invoke_handler(move |invoke| {
+            if let Some(error) = strict_ipc::validate(invoke.message.command(), invoke.message.payload()) {
+                invoke.resolver.reject(error::CommandError::from(error));
+                return true;
+            }
+            handler(invoke)
+        })
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                mounts::unmount_all();
+            }
+        });
 }
@@ -0,0 +1,67 @@
+use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tracing::{debug, info};
+
+/// restic processes allowed to run against one repository at a time when the config doesn't say
+pub const DEFAULT_MAX_PER_REPO: usize = 2;
+
+static MAX_PER_REPO: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PER_REPO);
+
+// One semaphore per repository path; requests beyond the limit wait in its queue
+static SEMAPHORES: Lazy<Mutex<HashMap<String, Arc<Semaphore>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn semaphore(repo: &str) -> Result<Arc<Semaphore>> {
+    let mut semaphores = SEMAPHORES.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock restic limiter: {}", e)))?;
+    Ok(semaphores.entry(repo.trim().to_string())
+        .or_insert_with(|| Arc::new(Semaphore::new(MAX_PER_REPO.load(Ordering::Relaxed))))
+        .clone())
+}
+
+/// Waits for a free restic slot for the repository. The slot is released when the permit drops.
+pub async fn acquire(repo: &str) -> Result<OwnedSemaphorePermit> {
+    let semaphore = semaphore(repo)?;
+    if semaphore.available_permits() == 0 {
+        info!("Queueing restic command until another one for {} finishes", repo);
+    }
+    semaphore.acquire_owned().await
+        .map_err(|e| AppError::ResticExecution(e.to_string()))
+}
+
+/// Like `acquire`, for code that runs on its own thread and can't await
+pub fn acquire_blocking(repo: &str) -> Result<OwnedSemaphorePermit> {
+    let semaphore = semaphore(repo)?;
+    let mut queued = false;
+    loop {
+        match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => return Ok(permit),
+            Err(TryAcquireError::NoPermits) => {
+                if !queued {
+                    info!("Queueing restic command until another one for {} finishes", repo);
+                    queued = true;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(AppError::ResticExecution(e.to_string())),
+        }
+    }
+}
+
+pub fn max_per_repo() -> usize {
+    MAX_PER_REPO.load(Ordering::Relaxed)
+}
+
+/// Applies to commands started afterwards; ones already running or queued keep their old limit
+pub fn set_max_per_repo(limit: usize) {
+    let limit = limit.max(1);
+    debug!("Limiting restic to {} processes per repository", limit);
+    MAX_PER_REPO.store(limit, Ordering::Relaxed);
+    if let Ok(mut semaphores) = SEMAPHORES.lock() {
+        semaphores.clear();
+    }
+}
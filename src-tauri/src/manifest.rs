@@ -0,0 +1,92 @@
+use crate::error::{AppError, Result};
+use crate::models::FileNode;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ManifestFormat {
+    Csv,
+    Jsonl,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManifestExport {
+    pub out_path: String,
+    pub entries: u64,
+    /// Sum of file sizes in the listing
+    pub total_size: u64,
+}
+
+#[derive(Serialize)]
+struct ManifestRow<'a> {
+    path: &'a str,
+    #[serde(rename = "type")]
+    node_type: &'a str,
+    size: Option<u64>,
+    mtime: Option<&'a str>,
+}
+
+// Quotes fields that contain separators, quotes or line breaks (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes one manifest row per node as the listing streams in
+pub struct ManifestWriter {
+    format: ManifestFormat,
+    out: BufWriter<File>,
+    entries: u64,
+    total_size: u64,
+}
+
+impl ManifestWriter {
+    pub fn new(file: File, format: ManifestFormat) -> Result<Self> {
+        let mut out = BufWriter::new(file);
+        if format == ManifestFormat::Csv {
+            writeln!(out, "path,type,size,mtime")?;
+        }
+        Ok(ManifestWriter { format, out, entries: 0, total_size: 0 })
+    }
+
+    pub fn write(&mut self, node: &FileNode) -> Result<()> {
+        match self.format {
+            ManifestFormat::Csv => writeln!(
+                self.out,
+                "{},{},{},{}",
+                csv_field(&node.path),
+                csv_field(&node.node_type),
+                node.size.map(|s| s.to_string()).unwrap_or_default(),
+                csv_field(node.mtime.as_deref().unwrap_or_default()),
+            )?,
+            ManifestFormat::Jsonl => {
+                let row = ManifestRow {
+                    path: &node.path,
+                    node_type: &node.node_type,
+                    size: node.size,
+                    mtime: node.mtime.as_deref(),
+                };
+                serde_json::to_writer(&mut self.out, &row)?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        self.entries += 1;
+        if node.node_type == "file" {
+            self.total_size += node.size.unwrap_or(0);
+        }
+        Ok(())
+    }
+
+    /// Flushes the file and returns (entries, total size)
+    pub fn finish(mut self) -> Result<(u64, u64)> {
+        self.out.flush()?;
+        self.out.get_ref().sync_all()
+            .map_err(|e| AppError::Storage(format!("Failed to write manifest: {}", e)))?;
+        Ok((self.entries, self.total_size))
+    }
+}
@@ -0,0 +1,262 @@
+use crate::error::AppError;
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+use tracing::{debug, warn};
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+pub const SUPPORTED_LANGUAGES: [&str; 2] = ["en", "de"];
+
+static LANGUAGE: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(DEFAULT_LANGUAGE.to_string()));
+
+// Error texts in English come from AppError's Display, so only non-error messages live here
+const EN: &[(&str, &str)] = &[
+    ("repository.connected", "Connected successfully"),
+    ("restore.completed", "Restore completed"),
+    ("restore.selective_completed", "Restored {0} item(s) successfully"),
+    ("restore.completed_with_warnings", "Restored with warnings:\n{0}"),
+    ("restore.in_place_completed", "Restored to the original location"),
+    ("restore.in_place_renamed", "Restored to the original location, {0} existing file(s) were renamed to .bak"),
+    ("health.budget_exceeded", "Repository exceeds its size budget ({0}% used)"),
+    ("health.budget_approaching", "Repository is approaching its size budget ({0}% used)"),
+    ("quota.exceeded", "{0} has exceeded its size budget"),
+    ("quota.approaching", "{0} has used {1}% of its size budget"),
+    ("quota.cleared", "{0} is back under its size budget warning level"),
+    ("setup.arch_emulated", "This restic binary is built for {0} and runs under emulation on this {1} computer. A native {1} build is faster."),
+    ("setup.arch_incompatible", "This restic binary is built for {0} and can't run on this {1} computer. Download the {1} build of restic."),
+    ("status.unreachable", "Repository can't be reached: {0}"),
+    ("status.no_snapshots", "Repository has no snapshots yet"),
+    ("status.backup_overdue", "Last backup is {0} hours old"),
+    ("status.check_failed", "Last integrity check found errors"),
+    ("status.stale_locks", "{0} stale lock(s) are left in the repository"),
+    ("status.locked", "Repository is locked exclusively"),
+    ("status.budget_exceeded", "Repository exceeds its size budget"),
+    ("status.budget_approaching", "Repository is approaching its size budget"),
+    ("status.error", "{0} needs attention: {1}"),
+    ("experiment.in_place_restore", "Restore snapshots over their original location"),
+];
+
+const DE: &[(&str, &str)] = &[
+    ("repository.connected", "Verbindung erfolgreich hergestellt"),
+    ("restore.completed", "Wiederherstellung abgeschlossen"),
+    ("restore.selective_completed", "{0} Element(e) erfolgreich wiederhergestellt"),
+    ("restore.completed_with_warnings", "Mit Warnungen wiederhergestellt:\n{0}"),
+    ("restore.in_place_completed", "Am ursprünglichen Ort wiederhergestellt"),
+    ("restore.in_place_renamed", "Am ursprünglichen Ort wiederhergestellt, {0} vorhandene Datei(en) wurden in .bak umbenannt"),
+    ("health.budget_exceeded", "Das Repository überschreitet sein Speicherbudget ({0}% belegt)"),
+    ("health.budget_approaching", "Das Repository nähert sich seinem Speicherbudget ({0}% belegt)"),
+    ("quota.exceeded", "{0} hat sein Speicherbudget überschritten"),
+    ("quota.approaching", "{0} hat {1}% seines Speicherbudgets belegt"),
+    ("quota.cleared", "{0} liegt wieder unter der Warnschwelle seines Speicherbudgets"),
+    ("setup.arch_emulated", "Dieses restic-Programm ist für {0} erstellt und läuft auf diesem {1}-Computer in einer Emulation. Eine native {1}-Version ist schneller."),
+    ("setup.arch_incompatible", "Dieses restic-Programm ist für {0} erstellt und kann auf diesem {1}-Computer nicht ausgeführt werden. Laden Sie die {1}-Version von restic herunter."),
+    ("status.unreachable", "Das Repository ist nicht erreichbar: {0}"),
+    ("status.no_snapshots", "Das Repository enthält noch keine Snapshots"),
+    ("status.backup_overdue", "Die letzte Sicherung ist {0} Stunden alt"),
+    ("status.check_failed", "Die letzte Integritätsprüfung hat Fehler gefunden"),
+    ("status.stale_locks", "Im Repository sind {0} veraltete Sperre(n) zurückgeblieben"),
+    ("status.locked", "Das Repository ist exklusiv gesperrt"),
+    ("status.budget_exceeded", "Das Repository überschreitet sein Speicherbudget"),
+    ("status.budget_approaching", "Das Repository nähert sich seinem Speicherbudget"),
+    ("status.error", "{0} erfordert Aufmerksamkeit: {1}"),
+    ("experiment.in_place_restore", "Snapshots an ihrem ursprünglichen Ort wiederherstellen"),
+    ("error.empty_repository_path", "Der Repository-Pfad darf nicht leer sein"),
+    ("error.invalid_repository_path", "Der Repository-Pfad enthält ungültige Zeichen"),
+    ("error.unsupported_protocol", "Nicht unterstütztes Repository-Protokoll. Erwartet wird eines von: {0}"),
+    ("error.remote_path_too_short", "Der entfernte Repository-Pfad ist zu kurz"),
+    ("error.path_traversal", "Der Repository-Pfad darf keine '..'-Komponenten enthalten"),
+    ("error.empty_snapshot_id", "Die Snapshot-ID darf nicht leer sein"),
+    ("error.invalid_snapshot_id", "Die Snapshot-ID enthält ungültige Zeichen"),
+    ("error.snapshot_id_too_short", "Die Snapshot-ID ist zu kurz (mindestens 8 Zeichen)"),
+    ("error.snapshot_id_too_long", "Die Snapshot-ID ist zu lang (höchstens 64 Zeichen)"),
+    ("error.snapshot_id_not_hex", "Die Snapshot-ID muss hexadezimal sein (0-9, a-f)"),
+    ("error.empty_target_path", "Der Zielpfad darf nicht leer sein"),
+    ("error.invalid_target_path", "Der Zielpfad enthält ungültige Zeichen"),
+    ("error.relative_target_path", "Der Zielpfad muss absolut sein (z. B. C:\\restore oder /home/user/restore)"),
+    ("error.parent_directory_not_found", "Das übergeordnete Verzeichnis existiert nicht: {0}"),
+    ("error.empty_include_path", "Der Include-Pfad darf nicht leer sein"),
+    ("error.invalid_include_path", "Der Include-Pfad enthält ungültige Zeichen"),
+    ("error.excessive_parent_traversal", "Der Include-Pfad enthält zu viele '..'-Komponenten (maximal 3)"),
+    ("error.absolute_include_path", "Der Include-Pfad muss relativ sein, nicht absolut"),
+    ("error.empty_repo_id", "Die Repository-ID darf nicht leer sein"),
+    ("error.invalid_repo_id", "Die Repository-ID enthält ungültige Zeichen"),
+    ("error.invalid_repo_id_characters", "Die Repository-ID darf nur Buchstaben, Ziffern, Bindestriche und Unterstriche enthalten"),
+    ("error.repo_id_too_long", "Die Repository-ID ist zu lang (höchstens 100 Zeichen)"),
+    ("error.repository_not_found", "Repository nicht gefunden: {0}"),
+    ("error.empty_password", "Das Passwort darf nicht leer sein"),
+    ("error.invalid_password", "Das Passwort enthält ungültige Zeichen"),
+    ("error.empty_repository_name", "Der Repository-Name darf nicht leer sein"),
+    ("error.repository_name_too_long", "Der Repository-Name ist zu lang (höchstens 200 Zeichen)"),
+    ("error.no_include_paths", "Für eine selektive Wiederherstellung ist mindestens ein Include-Pfad erforderlich"),
+    ("error.restic_binary_not_found", "restic-Programm nicht gefunden unter: {0}"),
+    ("error.invalid_restic_binary", "Die Datei ist kein gültiges restic-Programm: {0}"),
+    ("error.unsupported_language", "Nicht unterstützte Sprache: {0}"),
+    ("error.window_operation", "Fensteroperation fehlgeschlagen: {0}"),
+    ("error.repository_mismatch", "Das Repository unter {0} ist nicht dasselbe Repository"),
+    ("error.repository_identity_unknown", "Die Identität des Repositorys kann nicht überprüft werden: Es sind weder Fingerabdruck noch zwischengespeicherte Snapshots vorhanden"),
+    ("error.invalid_operation_id", "Die Vorgangs-ID darf nur Buchstaben, Ziffern, Bindestriche und Unterstriche enthalten (höchstens 100 Zeichen)"),
+    ("error.operation_already_running", "Ein Vorgang mit der ID {0} läuft bereits"),
+    ("error.operation_not_found", "Kein laufender Vorgang mit der ID {0}"),
+    ("error.operation_cancelled", "Vorgang {0} wurde abgebrochen"),
+    ("error.invalid_search_pattern", "Ungültiges Suchmuster: {0}"),
+    ("error.invalid_snapshot_path", "Ungültiger Snapshot-Pfad: {0}"),
+    ("error.repository_already_initialized", "Unter {0} existiert bereits ein Repository"),
+    ("error.repository_permission_denied", "Keine Berechtigung, das Repository unter {0} anzulegen"),
+    ("error.empty_forget_policy", "Zum Entfernen von Snapshots ist mindestens eine Aufbewahrungsregel erforderlich"),
+    ("error.invalid_filter_value", "Ungültiger Tag- oder Host-Filter: {0}"),
+    ("error.secret_store", "Fehler im Passwortspeicher: {0}"),
+    ("error.invalid_read_data_subset", "Der zu lesende Datenanteil muss ein Prozentsatz zwischen 0 und 100 sein, erhalten: {0}"),
+    ("error.environment_import", "Repository kann nicht aus der Umgebung importiert werden: {0}"),
+    ("error.invalid_password_source", "Ungültige Passwortquelle: {0}"),
+    ("error.invalid_backend_credentials", "Ungültige Backend-Zugangsdaten: {0}"),
+    ("error.destination_not_writable", "Keine Schreibberechtigung für {0}"),
+    ("error.in_place_restore_unsupported", "{0} kann nicht am ursprünglichen Ort wiederhergestellt werden"),
+    ("error.snapshot_not_cached", "Snapshot {0} ist nicht im lokalen Cache; laden Sie zuerst die Snapshots des Repositorys"),
+    ("error.mount_unsupported", "Das Einhängen von Snapshots wird auf dieser Plattform nicht unterstützt"),
+    ("error.mountpoint_in_use", "An {0} ist bereits etwas eingehängt"),
+    ("error.mount_not_found", "An {0} ist kein Repository eingehängt"),
+    ("error.mountpoint_not_empty", "Der Einhängepunkt {0} ist kein leeres Verzeichnis"),
+    ("error.invalid_confirmation_token", "Die Bestätigung ist abgelaufen oder stimmt nicht überein; fordern Sie eine neue an"),
+    ("error.restore_point_not_found", "Wiederherstellungspunkt {0} existiert nicht"),
+    ("error.no_matching_snapshot", "Kein Snapshot entspricht {0}"),
+    ("error.clipboard", "Kopieren in die Zwischenablage fehlgeschlagen: {0}"),
+    ("error.invalid_key_id", "Ungültige Schlüssel-ID: {0}"),
+    ("error.target_not_writable", "Das Wiederherstellungsziel kann nicht angelegt werden: {0} ist nicht beschreibbar"),
+    ("error.locked", "Das Repository ist durch einen anderen restic-Prozess gesperrt: {0}"),
+    ("error.wrong_password", "Falsches Repository-Passwort"),
+    ("error.repo_not_found", "Kein restic-Repository gefunden: {0}"),
+    ("error.network_timeout", "Das Repository hat nicht rechtzeitig geantwortet: {0}"),
+    ("error.permission_denied", "Zugriff verweigert: {0}"),
+    ("error.out_of_space", "Nicht genügend Speicherplatz: {0}"),
+    ("error.invalid_environment_variable", "Ungültige Umgebungsvariable: {0}"),
+    ("error.invalid_label", "Ungültiges Label: {0}"),
+    ("error.invalid_policy", "Ungültige Richtlinie: {0}"),
+    ("error.policy_template_not_found", "Richtlinienvorlage nicht gefunden: {0}"),
+    ("error.safe_mode_enabled", "Für das Repository {0} ist der abgesicherte Modus aktiv; dieser Vorgang würde Daten löschen oder überschreiben"),
+    ("error.restic_download_failed", "restic konnte nicht heruntergeladen werden: {0}"),
+    ("error.restic_checksum_mismatch", "Die heruntergeladene restic-Datei {0} stimmt nicht mit der veröffentlichten Prüfsumme überein"),
+    ("error.restic_signature_invalid", "Die Signatur der restic-Version konnte nicht geprüft werden: {0}"),
+    ("error.unsupported_platform", "Für {0} gibt es keine restic-Version"),
+    ("error.restic_too_old", "{0} erfordert restic {1} oder neuer"),
+    ("error.invalid_config_bundle", "Keine gültige exportierte Konfiguration: {0}"),
+    ("error.wrong_passphrase", "Die Passphrase ist falsch oder die Datei wurde verändert"),
+    ("error.passphrase_too_short", "Die Passphrase muss mindestens {0} Zeichen lang sein"),
+    ("error.pre_connect_hook_failed", "Vorbereitungsschritt vor dem Verbinden fehlgeschlagen: {0}"),
+    ("error.invalid_pre_connect_hook", "Ungültiger Vorbereitungsschritt: {0}"),
+    ("error.thumbnail_unavailable", "Für {0} kann keine Vorschau erstellt werden"),
+    ("error.file_too_large", "Datei ist zu groß: {0}"),
+    ("error.maintenance_blocked", "Wartung nicht möglich: {0}"),
+    ("error.unknown_migration", "Migration für dieses Repository nicht verfügbar: {0}"),
+    ("error.metadata_normalization_unsupported", "Das Anpassen von Besitzern und Berechtigungen nach einer Wiederherstellung wird auf dieser Plattform nicht unterstützt"),
+    ("error.operation_not_stalled", "Vorgang hängt nicht: {0}"),
+    ("error.operation_stalled", "Vorgang reagiert nicht mehr: {0}"),
+    ("error.timeout", "Zeitüberschreitung nach {0} Sekunden"),
+    ("error.network_unavailable", "Das Repository ist nicht erreichbar: {0}"),
+    ("error.no_cache_backup", "Für das Repository {0} gibt es keine Cache-Sicherung"),
+    ("error.unknown_experiment", "Unbekanntes Experiment: {0}"),
+    ("error.experiment_disabled", "Die experimentelle Funktion {0} ist ausgeschaltet"),
+    ("error.quick_open_session_not_found", "Die Schnellansicht {0} wurde geschlossen; öffnen Sie das Repository erneut"),
+    ("error.data_dir_busy", "Das Datenverzeichnis kann nicht verschoben werden, solange {0} läuft"),
+    ("error.data_dir_not_empty", "Das neue Datenverzeichnis muss leer sein: {0}"),
+    ("error.relocation_failed", "Das Verschieben des Datenverzeichnisses ist fehlgeschlagen und wurde rückgängig gemacht: {0}"),
+    ("error.sftp_key_file_not_found", "SSH-Schlüsseldatei nicht gefunden: {0}"),
+    ("error.invalid_sftp_option", "Ungültige SFTP-Einstellung: {0}"),
+    ("error.ca_cert_not_found", "CA-Zertifikat nicht gefunden: {0}"),
+    ("error.invalid_ipc_payload", "Ungültige Befehlsargumente: {0}"),
+    ("error.secrets_not_in_environment", "Setzen Sie diese Variablen, bevor Geheimnisse aus der Umgebung gelesen werden: {0}"),
+    ("error.elevation_needs_environment", "Eine Wiederherstellung mit Administratorrechten kann {0} nicht an restic übergeben; stellen Sie ohne Administratorrechte wieder her"),
+    ("error.restic_execution", "restic konnte nicht ausgeführt werden: {0}"),
+    ("error.restic_error", "restic-Fehler: {0}"),
+    ("error.restore_failed", "Wiederherstellung fehlgeschlagen: {0}"),
+    ("error.elevation_cancelled", "Die Administrator-Autorisierung wurde abgebrochen"),
+    ("error.elevation_unavailable", "restic konnte nicht mit Administratorrechten ausgeführt werden: {0}"),
+    ("error.snapshot_json_parse", "Snapshot-JSON konnte nicht gelesen werden: {0}"),
+    ("error.stats_json_parse", "Statistik-JSON konnte nicht gelesen werden: {0}"),
+    ("error.repo_stats_json_parse", "Repository-Statistik-JSON konnte nicht gelesen werden: {0}"),
+    ("error.io", "E/A-Fehler: {0}"),
+    ("error.json", "JSON-Fehler: {0}"),
+];
+
+fn catalog(language: &str) -> Option<&'static [(&'static str, &'static str)]> {
+    match language {
+        "en" => Some(EN),
+        "de" => Some(DE),
+        _ => None,
+    }
+}
+
+fn lookup(language: &str, key: &str) -> Option<&'static str> {
+    catalog(language)?
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, template)| *template)
+}
+
+// One pass over the template, so a `{1}` inside an argument (a path, restic's stderr)
+// is left as it is instead of being filled in too
+fn fill(template: &str, args: &[String]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after.find('}')
+            .and_then(|end| after[..end].parse::<usize>().ok().map(|i| (i, end)))
+            .and_then(|(i, end)| args.get(i).map(|arg| (arg, end)));
+        match arg {
+            Some((arg, end)) => {
+                text.push_str(arg);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+pub fn is_supported(language: &str) -> bool {
+    SUPPORTED_LANGUAGES.contains(&language)
+}
+
+pub fn set_language(language: &str) {
+    let language = if is_supported(language) {
+        language
+    } else {
+        warn!("Unsupported language '{}', falling back to {}", language, DEFAULT_LANGUAGE);
+        DEFAULT_LANGUAGE
+    };
+
+    match LANGUAGE.write() {
+        Ok(mut current) => *current = language.to_string(),
+        Err(e) => warn!("Failed to set message language: {}", e),
+    }
+    debug!("Message language set to {}", language);
+}
+
+pub fn language() -> String {
+    LANGUAGE.read()
+        .map(|l| l.clone())
+        .unwrap_or_else(|_| DEFAULT_LANGUAGE.to_string())
+}
+
+/// Translates a message code into the configured language, falling back to English.
+pub fn tr(key: &str, args: &[String]) -> String {
+    let template = lookup(&language(), key)
+        .or_else(|| lookup(DEFAULT_LANGUAGE, key))
+        .unwrap_or(key);
+    fill(template, args)
+}
+
+/// User-facing text for an error; untranslated codes fall back to the English Display text.
+pub fn error_text(error: &AppError) -> String {
+    let language = language();
+    if language != DEFAULT_LANGUAGE {
+        if let Some(template) = lookup(&language, &format!("error.{}", error.code())) {
+            return fill(template, &error.args());
+        }
+    }
+    error.to_string()
+}
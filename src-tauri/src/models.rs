@@ -1,20 +1,59 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Snapshot {
     pub id: String,
     #[serde(rename = "short_id")]
     pub short_id: String,
     pub time: String,
+    // restic leaves out empty fields, e.g. snapshots taken without a hostname or user
+    #[serde(default)]
     pub hostname: String,
+    #[serde(default)]
     pub paths: Vec<String>,
     pub tags: Option<Vec<String>>,
+    #[serde(default)]
     pub username: String,
     pub tree: Option<String>,
     pub parent: Option<String>,
+    /// ID of the snapshot this one was rewritten from, e.g. by `restic tag`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<String>,
+    /// The backup client, e.g. `restic 0.17.3`; older clients don't record it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excludes: Vec<String>,
+    /// What the backup did; recorded by restic 0.17 and newer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SnapshotSummary>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SnapshotSummary {
+    pub backup_start: Option<String>,
+    pub backup_end: Option<String>,
+    pub files_new: u64,
+    pub files_changed: u64,
+    pub files_unmodified: u64,
+    pub dirs_new: u64,
+    pub dirs_changed: u64,
+    pub dirs_unmodified: u64,
+    pub data_blobs: u64,
+    pub tree_blobs: u64,
+    pub data_added: u64,
+    pub data_added_packed: u64,
+    pub total_files_processed: u64,
+    pub total_bytes_processed: u64,
+}
+
+// Everything past `mtime` depends on the restic version and platform, so it's all optional
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct FileNode {
     pub name: String,
     pub path: String,
@@ -22,4 +61,592 @@ pub struct FileNode {
     pub node_type: String,
     pub size: Option<u64>,
     pub mtime: Option<String>,
+    /// Go `os.FileMode` bits as restic prints them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// e.g. `drwxr-xr-x`; derived from `mode` when restic doesn't print it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, rename = "linktarget", alias = "link_target", skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inode: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub atime: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ctime: Option<String>,
+}
+
+impl FileNode {
+    const MODE_DIR: u32 = 1 << 31;
+    const MODE_SYMLINK: u32 = 1 << 27;
+
+    /// Fills in `permissions` from `mode` the way Go formats a file mode
+    pub fn fill_permissions(&mut self) {
+        let Some(mode) = self.mode.filter(|_| self.permissions.is_none()) else { return };
+        let kind = if mode & Self::MODE_DIR != 0 {
+            'd'
+        } else if mode & Self::MODE_SYMLINK != 0 {
+            'L'
+        } else {
+            '-'
+        };
+        let mut permissions = String::from(kind);
+        for (bit, c) in (0..9).rev().zip("rwxrwxrwx".chars()) {
+            permissions.push(if mode & (1 << bit) != 0 { c } else { '-' });
+        }
+        self.permissions = Some(permissions);
+    }
+}
+
+/// Order of a directory listing; directories always come before files
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowseSort {
+    #[default]
+    Name,
+    Size,
+    Mtime,
+    Type,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Narrows a listing. Name and size only apply to non-directories so the tree stays
+/// navigable; `node_type` applies to everything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrowseFilter {
+    /// Glob on the entry name, case-insensitive: `*` matches any run of characters, `?` one
+    pub name: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// restic's node type, e.g. `file`, `dir` or `symlink`
+    pub node_type: Option<String>,
+}
+
+/// One page of a directory's entries
+#[derive(Debug, Serialize, Clone)]
+pub struct FilePage {
+    pub path: String,
+    pub entries: Vec<FileNode>,
+    pub offset: usize,
+    /// Entries in the whole directory
+    pub total: usize,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreErrorKind {
+    Permissions,
+    PathTooLong,
+    DiskFull,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestorePathError {
+    pub path: String,
+    pub error_kind: RestoreErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestoreOptions {
+    /// Run restic through the platform's elevation prompt for this restore only
+    pub elevate: bool,
+    /// Caller-chosen ID for cancelling the restore while it runs
+    pub operation_id: Option<String>,
+    /// Create the target and any missing parent folders instead of rejecting the target
+    pub create_missing_dirs: bool,
+    /// Write files sparsely (`restore --sparse`)
+    pub sparse: bool,
+    /// Adjust ownership and permissions of the restored entries once restic is done
+    pub metadata: Option<MetadataNormalization>,
+}
+
+/// Post-restore metadata changes, for restores whose original owners or modes don't
+/// make sense on this machine. Unix only.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataNormalization {
+    /// Give restored entries to the user running the app instead of the snapshot's owners
+    pub no_same_owner: bool,
+    /// Permission bits for restored files, e.g. 0o644
+    pub file_mode: Option<u32>,
+    /// Permission bits for restored directories, e.g. 0o755
+    pub dir_mode: Option<u32>,
+}
+
+impl MetadataNormalization {
+    pub fn is_empty(&self) -> bool {
+        !self.no_same_owner && self.file_mode.is_none() && self.dir_mode.is_none()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MetadataSummary {
+    /// Entries whose metadata was changed
+    pub adjusted: u64,
+    /// Entries whose metadata couldn't be applied
+    pub failed: Vec<RestorePathError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreResult {
+    pub message: String,
+    pub errors: Vec<RestorePathError>,
+    pub elevated: bool,
+    pub operation_id: String,
+    /// Set when the restore was asked to normalize metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<MetadataSummary>,
+}
+
+/// One restic restore in a multi-snapshot restore: the paths whose newest version is in this snapshot
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlannedRestore {
+    pub snapshot_id: String,
+    pub snapshot_time: String,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MultiRestoreResult {
+    pub dry_run: bool,
+    /// Restores in the order they run, oldest snapshot first
+    pub plan: Vec<PlannedRestore>,
+    /// Paths no snapshot contains; they are skipped
+    pub unresolved: Vec<String>,
+    pub errors: Vec<RestorePathError>,
+    /// Unset for dry runs and when nothing could be resolved
+    pub operation_id: Option<String>,
+}
+
+/// One entry of a restore cart: a file or directory from any snapshot
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CartItem {
+    /// The frontend's ID for the item, echoed in the report
+    pub id: String,
+    pub snapshot_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CartItemStatus {
+    Restored,
+    /// Restored, but restic reported errors for some entries under the item
+    RestoredWithErrors,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CartItemResult {
+    pub id: String,
+    pub snapshot_id: String,
+    pub path: String,
+    pub status: CartItemStatus,
+    pub errors: Vec<RestorePathError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CartRestoreResult {
+    /// Restores in the order they ran, one per snapshot, oldest first
+    pub plan: Vec<PlannedRestore>,
+    /// In cart order
+    pub items: Vec<CartItemResult>,
+    pub operation_id: String,
+}
+
+/// Retention rules passed to `restic forget`. Tag and host filters limit which
+/// snapshots the rules are applied to.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ForgetPolicy {
+    pub keep_last: Option<u32>,
+    pub keep_daily: Option<u32>,
+    pub keep_weekly: Option<u32>,
+    pub keep_monthly: Option<u32>,
+    pub tags: Vec<String>,
+    pub hosts: Vec<String>,
+}
+
+impl ForgetPolicy {
+    pub fn has_keep_rule(&self) -> bool {
+        [self.keep_last, self.keep_daily, self.keep_weekly, self.keep_monthly]
+            .iter()
+            .any(|rule| rule.is_some_and(|n| n > 0))
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let rules = [
+            ("--keep-last", self.keep_last),
+            ("--keep-daily", self.keep_daily),
+            ("--keep-weekly", self.keep_weekly),
+            ("--keep-monthly", self.keep_monthly),
+        ];
+        for (flag, value) in rules {
+            if let Some(n) = value.filter(|n| *n > 0) {
+                args.push(flag.to_string());
+                args.push(n.to_string());
+            }
+        }
+        for tag in &self.tags {
+            args.push("--tag".to_string());
+            args.push(tag.clone());
+        }
+        for host in &self.hosts {
+            args.push("--host".to_string());
+            args.push(host.clone());
+        }
+        args
+    }
+}
+
+/// Narrows a snapshot listing; unset fields don't filter
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnapshotFilter {
+    /// Snapshots from any of these hosts
+    pub hosts: Vec<String>,
+    /// Snapshots with any of these tags
+    pub tags: Vec<String>,
+    /// Snapshots with a backed up path at or under this directory
+    pub path_prefix: Option<String>,
+    /// Dates like `2024-01-31` or `2024-01-31 15:04`, or RFC 3339 timestamps
+    pub after: Option<String>,
+    pub before: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotGroupBy {
+    Host,
+    /// A snapshot is listed under each of its tags; untagged snapshots come last
+    Tag,
+    /// A snapshot is listed under each of its paths
+    Path,
+}
+
+/// Snapshots sharing a host, tag or path, newest first. `key` is unset for the
+/// untagged group and when the listing isn't grouped.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotGroup<T> {
+    pub key: Option<String>,
+    pub snapshots: Vec<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForgetResult {
+    pub dry_run: bool,
+    pub kept: Vec<Snapshot>,
+    pub removed: Vec<Snapshot>,
+}
+
+/// A snapshot a retention policy keeps, with the rules that keep it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KeptSnapshot {
+    pub snapshot: Snapshot,
+    /// restic's wording, e.g. "last snapshot" or "daily snapshot"
+    pub reasons: Vec<String>,
+}
+
+/// The snapshots one retention rule keeps
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetentionReason {
+    pub reason: String,
+    pub snapshot_ids: Vec<String>,
+}
+
+/// What `restic forget` would do with a policy, without doing it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetentionReport {
+    pub kept: Vec<KeptSnapshot>,
+    pub removed: Vec<Snapshot>,
+    /// Kept snapshots per rule, in the order restic reports the rules. A snapshot
+    /// kept by several rules is listed under each of them.
+    pub by_reason: Vec<RetentionReason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PruneResult {
+    pub dry_run: bool,
+    pub output: String,
+    pub operation_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MigrationResult {
+    pub migration: String,
+    pub output: String,
+    pub operation_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckResult {
+    /// No errors were found
+    pub ok: bool,
+    pub errors: Vec<String>,
+    pub snapshots_checked: Option<u64>,
+    pub packs_checked: Option<u64>,
+    /// Percentage of pack data that was read and verified, if any
+    pub read_data_subset: Option<f64>,
+    pub operation_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffEntry {
+    pub path: String,
+    pub kind: DiffKind,
+    pub is_dir: bool,
+    pub content_changed: bool,
+    pub type_changed: bool,
+    pub metadata_changed: bool,
+}
+
+impl DiffEntry {
+    /// Builds an entry from a `restic diff` change message. The modifier is `+`, `-`,
+    /// or a combination of `T` (type), `M` (content), `U` (metadata) and `?` (content
+    /// changed without a metadata change).
+    pub fn from_change(path: &str, modifier: &str) -> Self {
+        let kind = match modifier {
+            "+" => DiffKind::Added,
+            "-" => DiffKind::Removed,
+            _ => DiffKind::Modified,
+        };
+        let modified = kind == DiffKind::Modified;
+        DiffEntry {
+            path: path.trim_end_matches('/').to_string(),
+            kind,
+            is_dir: path.ends_with('/'),
+            content_changed: modified && (modifier.contains('M') || modifier.contains('?')),
+            type_changed: modified && modifier.contains('T'),
+            metadata_changed: modified && modifier.contains('U'),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SnapshotDiff {
+    pub snapshot_a: String,
+    pub snapshot_b: String,
+    pub subpath: Option<String>,
+    pub entries: Vec<DiffEntry>,
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+    /// Totals reported by restic for the whole snapshots; not set when limited to a subpath
+    pub bytes_added: Option<u64>,
+    pub bytes_removed: Option<u64>,
+}
+
+/// What a snapshot changed over its parent, from the summary restic recorded in the
+/// snapshot or the totals of `restic diff`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeSummary {
+    pub snapshot_id: String,
+    pub snapshot_time: String,
+    pub parent_id: String,
+    pub files_added: u64,
+    pub files_changed: u64,
+    /// Only known from a diff; snapshot summaries don't record removals
+    pub files_removed: Option<u64>,
+    pub bytes_added: u64,
+    pub bytes_removed: Option<u64>,
+}
+
+/// Limits a listing to entries modified in a time window. Bounds are dates like
+/// `2024-01-31` or `2024-01-31 15:04`, or RFC 3339 timestamps.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MtimeRange {
+    pub after: Option<String>,
+    pub before: Option<String>,
+    /// Only entries modified in the given number of days up to the snapshot's time
+    pub days_before_snapshot: Option<u32>,
+}
+
+impl MtimeRange {
+    pub fn is_empty(&self) -> bool {
+        self.after.is_none() && self.before.is_none() && self.days_before_snapshot.is_none()
+    }
+}
+
+/// Filters for `restic find`. Times use restic's formats, e.g. `2024-01-31` or `2024-01-31 15:04`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FindOptions {
+    pub ignore_case: bool,
+    pub oldest: Option<String>,
+    pub newest: Option<String>,
+    pub snapshots: Vec<String>,
+    pub tags: Vec<String>,
+    pub hosts: Vec<String>,
+    pub paths: Vec<String>,
+}
+
+impl FindOptions {
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.ignore_case {
+            args.push("--ignore-case".to_string());
+        }
+        let filters = [
+            ("--oldest", self.oldest.iter().collect::<Vec<_>>()),
+            ("--newest", self.newest.iter().collect()),
+            ("--snapshot", self.snapshots.iter().collect()),
+            ("--tag", self.tags.iter().collect()),
+            ("--host", self.hosts.iter().collect()),
+            ("--path", self.paths.iter().collect()),
+        ];
+        for (flag, values) in filters {
+            for value in values {
+                args.push(flag.to_string());
+                args.push(value.clone());
+            }
+        }
+        args
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileOccurrence {
+    pub snapshot_id: String,
+    pub size: Option<u64>,
+    pub mtime: Option<String>,
+}
+
+/// A path matched by `restic find` and every snapshot it was found in
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FoundFile {
+    pub path: String,
+    pub node_type: String,
+    pub occurrences: Vec<FileOccurrence>,
+}
+
+/// What to do with files that already exist at the original location
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    SkipExisting,
+    Overwrite,
+    OverwriteIfNewer,
+    /// Existing files are renamed to `<name>.bak` before restoring
+    RenameExisting,
+}
+
+impl ConflictPolicy {
+    /// Value for restic's `--overwrite` flag
+    pub fn overwrite_arg(self) -> &'static str {
+        match self {
+            ConflictPolicy::SkipExisting => "never",
+            ConflictPolicy::Overwrite | ConflictPolicy::RenameExisting => "always",
+            ConflictPolicy::OverwriteIfNewer => "if-newer",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreviewItem {
+    pub path: String,
+    /// restic's planned action: `restored`, `updated`, `unchanged` or `deleted`
+    pub action: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RestorePreview {
+    pub target: String,
+    pub items: Vec<PreviewItem>,
+    /// More items would be written than are listed in `items`
+    pub truncated: bool,
+    pub total_files: u64,
+    pub total_bytes: u64,
+    pub files_skipped: u64,
+    pub bytes_skipped: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchKind {
+    Missing,
+    TypeMismatch,
+    SizeMismatch,
+    MtimeMismatch,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerifyMismatch {
+    /// Path inside the snapshot
+    pub path: String,
+    pub kind: MismatchKind,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct VerifyReport {
+    pub checked: u64,
+    pub mismatches: Vec<VerifyMismatch>,
+    /// More mismatches were found than are listed
+    pub truncated: bool,
+}
+
+/// A key that can open the repository, from `restic key list --json`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoKey {
+    pub id: String,
+    /// Whether this is the key the app's password opened
+    #[serde(default)]
+    pub current: bool,
+    #[serde(rename = "userName", default)]
+    pub user_name: String,
+    #[serde(rename = "hostName", default)]
+    pub host_name: String,
+    pub created: String,
+}
+
+/// A lock file in the repository, from `restic cat lock`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RepoLock {
+    #[serde(default)]
+    pub id: String,
+    pub time: String,
+    #[serde(default)]
+    pub exclusive: bool,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub pid: u32,
+    /// Old enough that restic would remove it on a plain `restic unlock`
+    #[serde(default)]
+    pub stale: bool,
 }
@@ -0,0 +1,132 @@
+use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::{Child, Command};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// How long an unmounted `restic mount` gets to exit on its own before it's killed
+const EXIT_GRACE: Duration = Duration::from_secs(5);
+
+struct Mount {
+    repo: String,
+    started_at: i64,
+    child: Child,
+}
+
+// Keyed by mount point; a mount point holds at most one repository
+static MOUNTS: Lazy<Mutex<HashMap<String, Mount>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MountStatus {
+    pub mountpoint: String,
+    pub repo: String,
+    pub started_at: i64,
+    pub running: bool,
+}
+
+fn lock() -> Result<std::sync::MutexGuard<'static, HashMap<String, Mount>>> {
+    MOUNTS.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock mount registry: {}", e)))
+}
+
+pub fn is_mounted(mountpoint: &str) -> bool {
+    lock().is_ok_and(|mounts| mounts.contains_key(mountpoint))
+}
+
+/// Tracks a running `restic mount` so it can be unmounted later and on exit
+pub fn register(mountpoint: &str, repo: &str, mut child: Child) -> Result<MountStatus> {
+    let mut mounts = lock()?;
+    if mounts.contains_key(mountpoint) {
+        let _ = child.kill();
+        return Err(AppError::MountpointInUse(mountpoint.to_string()));
+    }
+
+    let started_at = chrono::Utc::now().timestamp();
+    mounts.insert(mountpoint.to_string(), Mount { repo: repo.to_string(), started_at, child });
+    info!("Mounted {} at {}", repo, mountpoint);
+    Ok(MountStatus {
+        mountpoint: mountpoint.to_string(),
+        repo: repo.to_string(),
+        started_at,
+        running: true,
+    })
+}
+
+/// Current mounts. Ones whose restic process has exited are reported once and then forgotten.
+pub fn status() -> Vec<MountStatus> {
+    let Ok(mut mounts) = lock() else { return Vec::new() };
+    let statuses: Vec<MountStatus> = mounts.iter_mut()
+        .map(|(mountpoint, mount)| MountStatus {
+            mountpoint: mountpoint.clone(),
+            repo: mount.repo.clone(),
+            started_at: mount.started_at,
+            running: matches!(mount.child.try_wait(), Ok(None)),
+        })
+        .collect();
+    for stale in statuses.iter().filter(|s| !s.running) {
+        debug!("restic mount at {} has exited", stale.mountpoint);
+        mounts.remove(&stale.mountpoint);
+    }
+    statuses
+}
+
+// Unmounting through the OS lets restic exit cleanly; killing it would leave a stale FUSE mount
+fn release_mountpoint(mountpoint: &str) {
+    let result = if cfg!(target_os = "linux") {
+        Command::new("fusermount").args(["-u", mountpoint]).status()
+            .or_else(|_| Command::new("umount").arg(mountpoint).status())
+    } else {
+        Command::new("umount").arg(mountpoint).status()
+    };
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("Unmounting {} exited with {}", mountpoint, status),
+        Err(e) => warn!("Failed to unmount {}: {}", mountpoint, e),
+    }
+}
+
+fn stop(mountpoint: &str, mut mount: Mount) {
+    if matches!(mount.child.try_wait(), Ok(None)) {
+        release_mountpoint(mountpoint);
+    }
+
+    let deadline = Instant::now() + EXIT_GRACE;
+    while Instant::now() < deadline {
+        if !matches!(mount.child.try_wait(), Ok(None)) {
+            info!("Unmounted {}", mountpoint);
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    warn!("restic mount at {} didn't exit after unmounting, killing it", mountpoint);
+    if let Err(e) = mount.child.kill() {
+        warn!("Failed to kill restic mount at {}: {}", mountpoint, e);
+    }
+    let _ = mount.child.wait();
+}
+
+pub fn unmount(mountpoint: &str) -> Result<()> {
+    let mount = lock()?
+        .remove(mountpoint)
+        .ok_or_else(|| AppError::MountNotFound(mountpoint.to_string()))?;
+    stop(mountpoint, mount);
+    Ok(())
+}
+
+/// Called when the app exits so no mount outlives it
+pub fn unmount_all() {
+    let mounts: Vec<(String, Mount)> = match lock() {
+        Ok(mut mounts) => mounts.drain().collect(),
+        Err(e) => {
+            warn!("{}", e);
+            return;
+        }
+    };
+    for (mountpoint, mount) in mounts {
+        stop(&mountpoint, mount);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::error::{AppError, Result};
+use crate::models::FileNode;
+use std::collections::BTreeMap;
+
+// Listings repeat the same keys and path prefixes, so a low level already shrinks them well
+const ZSTD_LEVEL: i32 = 3;
+
+/// A directory's direct children, encoded for storage
+pub struct DirBlob {
+    pub dir_path: String,
+    pub node_count: usize,
+    /// Size of the uncompressed JSON
+    pub raw_size: usize,
+    pub compressed: bool,
+    pub data: Vec<u8>,
+}
+
+pub fn parent_dir(path: &str) -> &str {
+    match path.trim_end_matches('/').rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+pub fn node_name(path: &str) -> &str {
+    path.trim_end_matches('/').rsplit('/').next().unwrap_or(path)
+}
+
+/// Splits a full listing into one blob per directory
+pub fn encode_tree(nodes: &[FileNode], compress: bool) -> Result<Vec<DirBlob>> {
+    let mut dirs: BTreeMap<&str, Vec<&FileNode>> = BTreeMap::new();
+    for node in nodes {
+        dirs.entry(parent_dir(&node.path)).or_default().push(node);
+    }
+
+    dirs.into_iter()
+        .map(|(dir_path, children)| {
+            let json = serde_json::to_vec(&children)?;
+            let raw_size = json.len();
+            let data = if compress {
+                zstd::encode_all(json.as_slice(), ZSTD_LEVEL)
+                    .map_err(|e| AppError::Storage(format!("Failed to compress listing: {}", e)))?
+            } else {
+                json
+            };
+            Ok(DirBlob {
+                dir_path: dir_path.to_string(),
+                node_count: children.len(),
+                raw_size,
+                compressed: compress,
+                data,
+            })
+        })
+        .collect()
+}
+
+pub fn decode_dir(data: &[u8], compressed: bool) -> Result<Vec<FileNode>> {
+    if !compressed {
+        return Ok(serde_json::from_slice(data)?);
+    }
+    let json = zstd::decode_all(data)
+        .map_err(|e| AppError::Storage(format!("Failed to decompress cached listing: {}", e)))?;
+    Ok(serde_json::from_slice(&json)?)
+}
@@ -0,0 +1,144 @@
+use crate::window_scope;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::debug;
+
+/// Limits how often alerts reach the user. Each repository and kind of alert has
+/// its own cooldown, and a repository's alerts arriving close together go out as one digest.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Minimum time between two alerts of the same kind for the same repository
+    pub cooldown_minutes: u64,
+    /// Cooldowns for specific kinds, e.g. `"quota.approaching": 1440`
+    pub cooldown_overrides: HashMap<String, u64>,
+    /// How long to collect alerts before sending them as one digest; 0 sends right away
+    pub digest_seconds: u64,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            enabled: true,
+            cooldown_minutes: 360,
+            cooldown_overrides: HashMap::new(),
+            digest_seconds: 30,
+        }
+    }
+}
+
+impl NotificationSettings {
+    fn cooldown_secs(&self, kind: &str) -> i64 {
+        let minutes = self.cooldown_overrides.get(kind).copied().unwrap_or(self.cooldown_minutes);
+        minutes.saturating_mul(60).min(i64::MAX as u64) as i64
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Notification {
+    pub repo_id: String,
+    /// Message key of the alert, e.g. `quota.exceeded`
+    pub kind: String,
+    pub message: String,
+    pub created_at: i64,
+}
+
+/// One repository's alerts, sent to the windows showing that repository
+#[derive(Debug, Serialize, Clone)]
+pub struct NotificationDigest {
+    pub repo_id: String,
+    pub notifications: Vec<Notification>,
+    /// The repository's alerts held back by cooldowns since its previous digest
+    pub suppressed: usize,
+}
+
+#[derive(Default)]
+struct Governor {
+    last_sent: HashMap<(String, String), i64>,
+    pending: Vec<Notification>,
+    suppressed: HashMap<String, usize>,
+    flush_scheduled: bool,
+}
+
+static GOVERNOR: Lazy<Mutex<Governor>> = Lazy::new(|| Mutex::new(Governor::default()));
+
+/// Queues an alert unless its cooldown is still running. Returns whether it was queued.
+pub fn submit(app: &AppHandle, settings: &NotificationSettings, repo_id: &str, kind: &str, message: String) -> bool {
+    if !settings.enabled {
+        return false;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let Ok(mut governor) = GOVERNOR.lock() else {
+        return false;
+    };
+
+    let key = (repo_id.to_string(), kind.to_string());
+    if let Some(last) = governor.last_sent.get(&key) {
+        if now - last < settings.cooldown_secs(kind) {
+            debug!("Suppressing {} alert for {} during cooldown", kind, repo_id);
+            *governor.suppressed.entry(repo_id.to_string()).or_default() += 1;
+            return false;
+        }
+    }
+    governor.last_sent.insert(key, now);
+    governor.pending.push(Notification {
+        repo_id: repo_id.to_string(),
+        kind: kind.to_string(),
+        message,
+        created_at: now,
+    });
+
+    if settings.digest_seconds == 0 {
+        drop(governor);
+        flush(app);
+    } else if !governor.flush_scheduled {
+        governor.flush_scheduled = true;
+        let app = app.clone();
+        let delay = Duration::from_secs(settings.digest_seconds);
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            flush(&app);
+        });
+    }
+    true
+}
+
+fn flush(app: &AppHandle) {
+    let digests: Vec<NotificationDigest> = {
+        let Ok(mut governor) = GOVERNOR.lock() else { return };
+        governor.flush_scheduled = false;
+        let mut by_repo: BTreeMap<String, Vec<Notification>> = BTreeMap::new();
+        for notification in std::mem::take(&mut governor.pending) {
+            by_repo.entry(notification.repo_id.clone()).or_default().push(notification);
+        }
+        by_repo.into_iter()
+            .map(|(repo_id, notifications)| NotificationDigest {
+                suppressed: governor.suppressed.remove(&repo_id).unwrap_or(0),
+                repo_id,
+                notifications,
+            })
+            .collect()
+    };
+
+    for digest in digests {
+        debug!("Sending digest of {} alert(s) for {}", digest.notifications.len(), digest.repo_id);
+        let repo_id = digest.repo_id.clone();
+        window_scope::emit_repo_event(app, &repo_id, "notification-digest", digest);
+    }
+}
+
+/// Forgets cooldowns so the next alert of every kind goes out again
+pub fn reset_cooldowns(repo_id: Option<&str>) {
+    if let Ok(mut governor) = GOVERNOR.lock() {
+        match repo_id {
+            Some(id) => governor.last_sent.retain(|(repo, _), _| repo != id),
+            None => governor.last_sent.clear(),
+        }
+    }
+}
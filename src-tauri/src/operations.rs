@@ -0,0 +1,241 @@
+use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, info, warn};
+
+const DEFAULT_STALL_THRESHOLD_SECS: u64 = 300;
+const WATCHDOG_TICK: Duration = Duration::from_secs(10);
+
+struct RunningOperation {
+    kind: String,
+    started_at: i64,
+    child: Mutex<Child>,
+    cancelled: AtomicBool,
+    /// Unix time of the last output line
+    last_output_at: AtomicI64,
+    stalled: AtomicBool,
+    retry_requested: AtomicBool,
+}
+
+static OPERATIONS: Lazy<Mutex<HashMap<String, Arc<RunningOperation>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+// 0 turns the watchdog off
+static STALL_THRESHOLD_SECS: AtomicU64 = AtomicU64::new(DEFAULT_STALL_THRESHOLD_SECS);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OperationInfo {
+    pub operation_id: String,
+    pub kind: String,
+    pub started_at: i64,
+    /// No output for longer than the stall threshold
+    pub stalled: bool,
+    pub idle_secs: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct OperationStalled<'a> {
+    operation_id: &'a str,
+    kind: &'a str,
+    idle_secs: i64,
+}
+
+pub fn new_operation_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("op-{}-{}", millis, NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+pub fn validate_operation_id(operation_id: &str) -> Result<()> {
+    if operation_id.is_empty()
+        || operation_id.len() > 100
+        || !operation_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::InvalidOperationId);
+    }
+    Ok(())
+}
+
+/// Keeps a spawned restic process cancellable until it is dropped.
+pub struct OperationHandle {
+    id: String,
+    operation: Arc<RunningOperation>,
+}
+
+impl OperationHandle {
+    // Polls instead of blocking in wait() so cancel() can take the lock to kill the child
+    pub fn wait(&self) -> io::Result<ExitStatus> {
+        loop {
+            {
+                let mut child = self.operation.child.lock()
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                if let Some(status) = child.try_wait()? {
+                    return Ok(status);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    pub fn was_cancelled(&self) -> bool {
+        self.operation.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// The process was killed by `retry` and should be started again
+    pub fn was_retried(&self) -> bool {
+        self.operation.retry_requested.load(Ordering::SeqCst)
+    }
+
+    /// Records output from the process, which clears a stall
+    pub fn touch(&self) {
+        self.operation.last_output_at.store(chrono::Utc::now().timestamp(), Ordering::Relaxed);
+        if self.operation.stalled.swap(false, Ordering::Relaxed) {
+            info!("Operation {} is producing output again", self.id);
+        }
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        if let Ok(mut operations) = OPERATIONS.lock() {
+            operations.remove(&self.id);
+        }
+        debug!("Operation {} finished", self.id);
+    }
+}
+
+/// Registers a spawned child. Its stdout/stderr must already have been taken,
+/// since the registry holds the child while the caller reads the pipes.
+pub fn register(id: &str, kind: &str, mut child: Child) -> Result<OperationHandle> {
+    let mut operations = OPERATIONS.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock operations registry: {}", e)))?;
+
+    if operations.contains_key(id) {
+        let _ = child.kill();
+        // Reaped here, as nothing else holds the child
+        let _ = child.wait();
+        return Err(AppError::OperationAlreadyRunning(id.to_string()));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let operation = Arc::new(RunningOperation {
+        kind: kind.to_string(),
+        started_at: now,
+        child: Mutex::new(child),
+        cancelled: AtomicBool::new(false),
+        last_output_at: AtomicI64::new(now),
+        stalled: AtomicBool::new(false),
+        retry_requested: AtomicBool::new(false),
+    });
+    operations.insert(id.to_string(), operation.clone());
+    debug!("Registered {} operation {}", kind, id);
+
+    Ok(OperationHandle {
+        id: id.to_string(),
+        operation,
+    })
+}
+
+fn find(id: &str) -> Result<Arc<RunningOperation>> {
+    OPERATIONS.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock operations registry: {}", e)))?
+        .get(id)
+        .cloned()
+        .ok_or_else(|| AppError::OperationNotFound(id.to_string()))
+}
+
+fn kill(id: &str, operation: &RunningOperation) -> Result<()> {
+    let mut child = operation.child.lock()
+        .map_err(|e| AppError::Storage(format!("Failed to lock operation: {}", e)))?;
+    if let Err(e) = child.kill() {
+        // The process may already have exited on its own
+        warn!("Failed to kill operation {}: {}", id, e);
+    }
+    Ok(())
+}
+
+pub fn cancel(id: &str) -> Result<()> {
+    let operation = find(id)?;
+    info!("Cancelling {} operation {}", operation.kind, id);
+    operation.cancelled.store(true, Ordering::SeqCst);
+    kill(id, &operation)
+}
+
+/// Kills a stalled operation so whoever started it runs the command again
+pub fn retry(id: &str) -> Result<()> {
+    let operation = find(id)?;
+    if !operation.stalled.load(Ordering::Relaxed) {
+        return Err(AppError::OperationNotStalled(id.to_string()));
+    }
+    info!("Restarting stalled {} operation {}", operation.kind, id);
+    operation.retry_requested.store(true, Ordering::SeqCst);
+    kill(id, &operation)
+}
+
+pub fn stall_threshold_secs() -> u64 {
+    STALL_THRESHOLD_SECS.load(Ordering::Relaxed)
+}
+
+pub fn set_stall_threshold_secs(secs: u64) {
+    debug!("Operations without output for {} s count as stalled", secs);
+    STALL_THRESHOLD_SECS.store(secs, Ordering::Relaxed);
+}
+
+/// Marks operations that have gone quiet for longer than the threshold as stalled and
+/// tells the frontend, which can then offer to cancel or retry them
+pub fn start_watchdog(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(WATCHDOG_TICK).await;
+            let threshold = stall_threshold_secs() as i64;
+            if threshold == 0 {
+                continue;
+            }
+            let now = chrono::Utc::now().timestamp();
+            let operations: Vec<(String, Arc<RunningOperation>)> = match OPERATIONS.lock() {
+                Ok(operations) => operations.iter().map(|(id, op)| (id.clone(), op.clone())).collect(),
+                Err(e) => {
+                    warn!("Failed to lock operations registry: {}", e);
+                    continue;
+                }
+            };
+            for (id, operation) in operations {
+                let idle_secs = now - operation.last_output_at.load(Ordering::Relaxed);
+                if idle_secs < threshold || operation.stalled.swap(true, Ordering::Relaxed) {
+                    continue;
+                }
+                warn!("{} operation {} has had no output for {} s", operation.kind, id, idle_secs);
+                let payload = OperationStalled { operation_id: &id, kind: &operation.kind, idle_secs };
+                if let Err(e) = app.emit("operation-stalled", payload) {
+                    warn!("Failed to emit operation-stalled: {}", e);
+                }
+            }
+        }
+    });
+}
+
+pub fn list() -> Vec<OperationInfo> {
+    match OPERATIONS.lock() {
+        Ok(operations) => operations.iter()
+            .map(|(id, op)| OperationInfo {
+                operation_id: id.clone(),
+                kind: op.kind.clone(),
+                started_at: op.started_at,
+                stalled: op.stalled.load(Ordering::Relaxed),
+                idle_secs: chrono::Utc::now().timestamp() - op.last_output_at.load(Ordering::Relaxed),
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to lock operations registry: {}", e);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::models::ForgetPolicy;
+use crate::notifications::NotificationSettings;
+use crate::restic_args;
+use crate::storage::{AppConfig, SavedRepository};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Settings shared by a policy template and a repository's own overrides.
+/// Unset fields fall through to the next layer.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RepositoryPolicy {
+    /// Seconds a restic command may run before it's stopped; 0 means no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Replaces `timeout_secs` for commands that only read repository metadata, e.g. `snapshots` or `ls`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+    /// Replaces `timeout_secs` for restores and other commands that move file data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore_timeout_secs: Option<u64>,
+    /// Times a command is retried after a network error; defaults to `DEFAULT_MAX_RETRIES`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Upload limit in KiB/s, passed as `--limit-upload`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_upload_kib: Option<u64>,
+    /// Download limit in KiB/s, passed as `--limit-download`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_download_kib: Option<u64>,
+    /// Used when snapshots are forgotten without keep rules of their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<ForgetPolicy>,
+    /// Refuses commands that delete or overwrite data
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safe_mode: Option<bool>,
+    /// Replaces the app-wide notification settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationSettings>,
+    /// Hours after the newest snapshot before the repository counts as overdue
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_backup_age_hours: Option<u64>,
+}
+
+impl RepositoryPolicy {
+    /// Replaces our fields with the ones `other` sets
+    fn overlay(&mut self, other: &RepositoryPolicy) {
+        if other.timeout_secs.is_some() {
+            self.timeout_secs = other.timeout_secs;
+        }
+        if other.connect_timeout_secs.is_some() {
+            self.connect_timeout_secs = other.connect_timeout_secs;
+        }
+        if other.restore_timeout_secs.is_some() {
+            self.restore_timeout_secs = other.restore_timeout_secs;
+        }
+        if other.max_retries.is_some() {
+            self.max_retries = other.max_retries;
+        }
+        if other.limit_upload_kib.is_some() {
+            self.limit_upload_kib = other.limit_upload_kib;
+        }
+        if other.limit_download_kib.is_some() {
+            self.limit_download_kib = other.limit_download_kib;
+        }
+        if other.retention.is_some() {
+            self.retention = other.retention.clone();
+        }
+        if other.safe_mode.is_some() {
+            self.safe_mode = other.safe_mode;
+        }
+        if other.notifications.is_some() {
+            self.notifications = other.notifications.clone();
+        }
+        if other.max_backup_age_hours.is_some() {
+            self.max_backup_age_hours = other.max_backup_age_hours;
+        }
+    }
+
+    pub fn is_safe_mode(&self) -> bool {
+        self.safe_mode.unwrap_or(false)
+    }
+
+    /// The timeout for one restic command, by whether it moves file data
+    pub fn timeout_for(&self, args: &[&str]) -> Option<u64> {
+        let specific = if restic_args::moves_file_data(args) {
+            self.restore_timeout_secs
+        } else {
+            self.connect_timeout_secs
+        };
+        specific.or(self.timeout_secs).filter(|secs| *secs > 0)
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
+    /// Global restic flags for the bandwidth limits
+    pub fn restic_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        for (flag, limit) in [("--limit-upload", self.limit_upload_kib), ("--limit-download", self.limit_download_kib)] {
+            if let Some(kib) = limit.filter(|kib| *kib > 0) {
+                flags.push(flag.to_string());
+                flags.push(kib.to_string());
+            }
+        }
+        flags
+    }
+}
+
+/// Settings applied to every repository carrying one of its labels
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyTemplate {
+    /// Left empty when creating; assigned on save
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub labels: Vec<String>,
+    pub policy: RepositoryPolicy,
+}
+
+/// A repository's policy after templates and its own overrides are applied
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct EffectivePolicy {
+    pub policy: RepositoryPolicy,
+    /// Names of the templates that contributed, in the order they were applied
+    pub templates: Vec<String>,
+}
+
+/// Templates apply in the order they're configured, so a later template wins over
+/// an earlier one; the repository's own overrides are applied last.
+pub fn effective(config: &AppConfig, repo: &SavedRepository) -> EffectivePolicy {
+    let mut effective = EffectivePolicy::default();
+    for template in &config.policy_templates {
+        if template.labels.iter().any(|label| repo.labels.contains(label)) {
+            effective.policy.overlay(&template.policy);
+            effective.templates.push(template.name.clone());
+        }
+    }
+    if let Some(overrides) = &repo.policy {
+        effective.policy.overlay(overrides);
+    }
+    effective
+}
+
+/// The policy of the saved repository at `path`; repositories that aren't saved get none
+pub fn for_path(config: &AppConfig, path: &str) -> RepositoryPolicy {
+    config.repositories.iter()
+        .find(|r| !r.is_deleted() && r.path.trim() == path.trim())
+        .map(|repo| effective(config, repo).policy)
+        .unwrap_or_default()
+}
+
+/// Notification settings for a repository, falling back to the app-wide ones
+pub fn notification_settings(config: &AppConfig, repo_id: &str) -> NotificationSettings {
+    config.repositories.iter()
+        .find(|r| r.id == repo_id)
+        .and_then(|repo| effective(config, repo).policy.notifications)
+        .unwrap_or_else(|| config.notifications.clone())
+}
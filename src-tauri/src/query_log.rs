@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+pub const DEFAULT_SLOW_QUERY_MS: u64 = 100;
+// Most recent slow queries kept for get_slow_queries
+const MAX_SLOW_QUERIES: usize = 100;
+// Distinct statements tracked in the latency summary
+const MAX_TRACKED_STATEMENTS: usize = 500;
+
+static THRESHOLD_MS: AtomicU64 = AtomicU64::new(DEFAULT_SLOW_QUERY_MS);
+static SLOW_QUERIES: Lazy<Mutex<VecDeque<SlowQuery>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+static LATENCIES: Lazy<Mutex<HashMap<String, QueryLatency>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static CONNECTION_WAITS: Lazy<Mutex<ConnectionWaits>> = Lazy::new(|| Mutex::new(ConnectionWaits::default()));
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SlowQuery {
+    pub sql: String,
+    pub duration_ms: f64,
+    pub recorded_at: i64,
+    /// `EXPLAIN QUERY PLAN` output, filled in when the log is read
+    pub plan: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct QueryLatency {
+    pub sql: String,
+    pub count: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Time spent waiting for a free database connection; high totals mean
+/// queries contend for the pool
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ConnectionWaits {
+    pub count: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SlowQueryReport {
+    pub threshold_ms: u64,
+    pub slow_queries: Vec<SlowQuery>,
+    /// Slowest statements first, by total time spent
+    pub latencies: Vec<QueryLatency>,
+    pub connection_waits: ConnectionWaits,
+}
+
+pub fn set_threshold_ms(threshold_ms: u64) {
+    THRESHOLD_MS.store(threshold_ms, Ordering::Relaxed);
+}
+
+pub fn threshold_ms() -> u64 {
+    THRESHOLD_MS.load(Ordering::Relaxed)
+}
+
+fn normalize(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// SQLite profile hook; called after every statement with its run time.
+/// Runs while the statement's connection is checked out, so it must not touch the database.
+pub fn record(sql: &str, duration: Duration) {
+    let sql = normalize(sql);
+    if sql.starts_with("EXPLAIN") {
+        return;
+    }
+    let duration_ms = duration.as_secs_f64() * 1000.0;
+
+    if let Ok(mut latencies) = LATENCIES.lock() {
+        if latencies.len() < MAX_TRACKED_STATEMENTS || latencies.contains_key(&sql) {
+            let latency = latencies.entry(sql.clone()).or_insert_with(|| QueryLatency {
+                sql: sql.clone(),
+                ..Default::default()
+            });
+            latency.count += 1;
+            latency.total_ms += duration_ms;
+            latency.max_ms = latency.max_ms.max(duration_ms);
+        }
+    }
+
+    if duration_ms < threshold_ms() as f64 {
+        return;
+    }
+    warn!("Slow query ({:.1} ms): {}", duration_ms, sql);
+
+    if let Ok(mut slow) = SLOW_QUERIES.lock() {
+        if slow.len() >= MAX_SLOW_QUERIES {
+            slow.pop_front();
+        }
+        slow.push_back(SlowQuery {
+            sql,
+            duration_ms,
+            recorded_at: chrono::Utc::now().timestamp(),
+            plan: Vec::new(),
+        });
+    }
+}
+
+pub fn record_connection_wait(wait: Duration) {
+    let wait_ms = wait.as_secs_f64() * 1000.0;
+    if let Ok(mut waits) = CONNECTION_WAITS.lock() {
+        waits.count += 1;
+        waits.total_ms += wait_ms;
+        waits.max_ms = waits.max_ms.max(wait_ms);
+    }
+    if wait_ms >= threshold_ms() as f64 {
+        warn!("Waited {:.1} ms for a database connection", wait_ms);
+    }
+}
+
+pub fn connection_waits() -> ConnectionWaits {
+    CONNECTION_WAITS.lock().map(|w| w.clone()).unwrap_or_default()
+}
+
+/// Slow queries newest first, without plans
+pub fn slow_queries() -> Vec<SlowQuery> {
+    SLOW_QUERIES.lock()
+        .map(|slow| slow.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn latencies() -> Vec<QueryLatency> {
+    let mut latencies: Vec<QueryLatency> = LATENCIES.lock()
+        .map(|l| l.values().cloned().collect())
+        .unwrap_or_default();
+    latencies.sort_by(|a, b| b.total_ms.total_cmp(&a.total_ms));
+    latencies
+}
+
+pub fn clear() {
+    if let Ok(mut slow) = SLOW_QUERIES.lock() {
+        slow.clear();
+    }
+    if let Ok(mut latencies) = LATENCIES.lock() {
+        latencies.clear();
+    }
+    if let Ok(mut waits) = CONNECTION_WAITS.lock() {
+        *waits = ConnectionWaits::default();
+    }
+}
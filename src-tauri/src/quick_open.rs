@@ -0,0 +1,63 @@
+use crate::error::{AppError, Result};
+use crate::models::Snapshot;
+use crate::secrets::SecretString;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{debug, info};
+
+// Sessions kept at once; opening another closes the oldest
+const MAX_SESSIONS: usize = 8;
+
+/// An unsaved repository opened for a one-off recovery. Nothing about it is
+/// written to the config or the database unless it's saved; the password is
+/// wiped when the session closes.
+pub struct QuickSession {
+    pub repo: String,
+    pub password: SecretString,
+    pub snapshots: Vec<Snapshot>,
+    opened_at: i64,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, QuickSession>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn sessions() -> Result<std::sync::MutexGuard<'static, HashMap<String, QuickSession>>> {
+    SESSIONS.lock().map_err(|e| AppError::Storage(format!("Failed to lock quick open sessions: {}", e)))
+}
+
+/// Keeps the session in memory and returns its ID
+pub fn open(repo: String, password: SecretString, snapshots: Vec<Snapshot>) -> Result<String> {
+    let mut sessions = sessions()?;
+    while sessions.len() >= MAX_SESSIONS {
+        let Some(oldest) = sessions.iter().min_by_key(|(_, s)| s.opened_at).map(|(id, _)| id.clone()) else {
+            break;
+        };
+        debug!("Closing quick open session {} to make room", oldest);
+        sessions.remove(&oldest);
+    }
+    let session_id = uuid::Uuid::new_v4().to_string();
+    sessions.insert(session_id.clone(), QuickSession {
+        repo,
+        password,
+        snapshots,
+        opened_at: chrono::Utc::now().timestamp(),
+    });
+    info!("Opened quick open session {}", session_id);
+    Ok(session_id)
+}
+
+/// Runs `f` with the session, failing with `QuickOpenSessionNotFound` once it's closed
+pub fn with_session<T>(session_id: &str, f: impl FnOnce(&QuickSession) -> T) -> Result<T> {
+    sessions()?
+        .get(session_id)
+        .map(f)
+        .ok_or_else(|| AppError::QuickOpenSessionNotFound(session_id.to_string()))
+}
+
+/// Closes the session; closing one that's already gone is not an error
+pub fn close(session_id: &str) -> Result<()> {
+    if sessions()?.remove(session_id).is_some() {
+        info!("Closed quick open session {}", session_id);
+    }
+    Ok(())
+}
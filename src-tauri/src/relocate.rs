@@ -0,0 +1,177 @@
+use crate::database;
+use crate::error::{AppError, Result};
+use crate::mounts;
+use crate::operations;
+use crate::storage::{default_config_dir, edit_config, get_config_dir, is_location_file, set_data_location};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RelocationReport {
+    pub from: String,
+    pub to: String,
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Files to copy, relative to `dir`, with their sizes. The pointer to a relocated
+/// directory stays behind in the default one, so it is never part of the data.
+fn list_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(dir.join(&relative))? {
+            let entry = entry?;
+            let name = relative.join(entry.file_name());
+            if relative.as_os_str().is_empty() && is_location_file(&entry.file_name()) {
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(name);
+            } else if file_type.is_file() {
+                files.push((name, entry.metadata()?.len()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Refuses a destination with anything in it besides the location pointer, or one
+/// inside the current directory or containing it
+fn check_destination(from: &Path, to: &Path) -> Result<()> {
+    if to.starts_with(from) || from.starts_with(to) {
+        return Err(AppError::InvalidTargetPath);
+    }
+    if to.exists() {
+        let occupied = fs::read_dir(to)?
+            .flatten()
+            .any(|e| !is_location_file(&e.file_name()));
+        if occupied {
+            return Err(AppError::DataDirNotEmpty(to.display().to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn copy_and_verify(from: &Path, to: &Path, files: &[(PathBuf, u64)]) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for (relative, size) in files {
+        let dest = to.join(relative);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let copied = fs::copy(from.join(relative), &dest)?;
+        if copied != *size || fs::metadata(&dest)?.len() != *size {
+            return Err(AppError::Storage(format!("{} was not copied completely", relative.display())));
+        }
+    }
+    let db = to.join("snapshots.db");
+    if db.exists() {
+        database::quick_check(&db)?;
+    }
+    Ok(())
+}
+
+/// Removes what was copied to `to`, leaving the directory itself if it was there before
+fn remove_copied(to: &Path, created: bool) {
+    let cleaned = match created {
+        true => fs::remove_dir_all(to),
+        false => fs::read_dir(to).and_then(|entries| {
+            entries.flatten()
+                .filter(|e| !is_location_file(&e.file_name()))
+                .try_for_each(|e| match e.path().is_dir() {
+                    true => fs::remove_dir_all(e.path()),
+                    false => fs::remove_file(e.path()),
+                })
+        }),
+    };
+    if let Err(e) = cleaned {
+        warn!("Failed to clean up the partial copy in {}: {}", to.display(), e);
+    }
+}
+
+/// `path` moved from under `from` to under `to`, if it was there
+fn rebase(path: &str, from: &Path, to: &Path) -> Option<String> {
+    Path::new(path).strip_prefix(from).ok().map(|rest| to.join(rest).to_string_lossy().into_owned())
+}
+
+/// Moves the config, database, logs and everything else the app keeps to `to`, and
+/// points future launches there. The copy is verified before the switch; if anything
+/// fails up to then, the copy is removed and the app keeps using the old directory.
+/// Refused while an operation or mount is running, as those write to the directory.
+#[instrument]
+pub fn relocate(to: &Path) -> Result<RelocationReport> {
+    if let Some(op) = operations::list().first() {
+        return Err(AppError::DataDirBusy(op.kind.clone()));
+    }
+    if mounts::status().iter().any(|m| m.running) {
+        return Err(AppError::DataDirBusy("a mount".to_string()));
+    }
+    let from = get_config_dir().map_err(AppError::Storage)?;
+    check_destination(&from, to)?;
+
+    // Held until the end so no command writes the config into the old directory meanwhile
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    database::close_database()?;
+
+    let created = !to.exists();
+    let moved = list_files(&from).and_then(|files| {
+        copy_and_verify(&from, to, &files)?;
+        set_data_location(to).map_err(AppError::Storage)?;
+        Ok(files)
+    });
+    let files = match moved {
+        Ok(files) => files,
+        Err(e) => {
+            warn!("Relocating the data directory to {} failed: {}", to.display(), e);
+            remove_copied(to, created);
+            database::init_database()?;
+            return Err(AppError::RelocationFailed(e.to_string()));
+        }
+    };
+
+    // Paths into the data directory itself, like the demo repository or a downloaded restic
+    for repo in &mut config.repositories {
+        if let Some(path) = rebase(&repo.path, &from, to) {
+            repo.path = path;
+        }
+    }
+    if let Some(path) = config.restic_binary_path.as_deref().and_then(|p| rebase(p, &from, to)) {
+        config.restic_binary_path = Some(path);
+    }
+    config.save().map_err(AppError::Storage)?;
+    database::init_database()?;
+
+    // The move already happened; leftovers in the old directory only take up space
+    let default_dir = default_config_dir().map_err(AppError::Storage)?;
+    for (relative, _) in &files {
+        if let Err(e) = fs::remove_file(from.join(relative)) {
+            warn!("Failed to remove {} from the old data directory: {}", relative.display(), e);
+        }
+    }
+    if let Ok(entries) = fs::read_dir(&from) {
+        for dir in entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                warn!("Failed to remove {} from the old data directory: {}", dir.display(), e);
+            }
+        }
+    }
+    if from != default_dir {
+        if let Err(e) = fs::remove_dir(&from) {
+            warn!("Failed to remove the old data directory {}: {}", from.display(), e);
+        }
+    }
+
+    let bytes = files.iter().map(|(_, size)| size).sum();
+    info!("Moved {} files ({} bytes) from {} to {}", files.len(), bytes, from.display(), to.display());
+    Ok(RelocationReport {
+        from: from.to_string_lossy().into_owned(),
+        to: to.to_string_lossy().into_owned(),
+        files: files.len() as u64,
+        bytes,
+    })
+}
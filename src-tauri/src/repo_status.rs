@@ -0,0 +1,185 @@
+use crate::database::{self, StatusSignals};
+use crate::error::{AppError, Result};
+use crate::messages::tr;
+use crate::notifications;
+use crate::policies;
+use crate::storage::load_config;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, info, warn};
+
+/// Used when no policy sets `max_backup_age_hours`; 0 there turns the check off
+pub const DEFAULT_MAX_BACKUP_AGE_HOURS: u64 = 48;
+// Reason code of the freshness check, also the kind of the alert it raises
+const BACKUP_OVERDUE: &str = "status.backup_overdue";
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusLevel {
+    Healthy,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct StatusReason {
+    pub level: StatusLevel,
+    /// Message key, e.g. `status.backup_overdue`
+    pub code: String,
+    pub message: String,
+}
+
+/// The one status every view shows for a repository. Reasons come worst first.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "level", rename_all = "snake_case")]
+pub enum RepoStatus {
+    Healthy,
+    Warning { reasons: Vec<StatusReason> },
+    Error { reasons: Vec<StatusReason> },
+}
+
+impl RepoStatus {
+    pub fn level(&self) -> StatusLevel {
+        match self {
+            RepoStatus::Healthy => StatusLevel::Healthy,
+            RepoStatus::Warning { .. } => StatusLevel::Warning,
+            RepoStatus::Error { .. } => StatusLevel::Error,
+        }
+    }
+
+    pub fn reasons(&self) -> &[StatusReason] {
+        match self {
+            RepoStatus::Healthy => &[],
+            RepoStatus::Warning { reasons } | RepoStatus::Error { reasons } => reasons,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct RepoStatusReport {
+    pub repo_id: String,
+    #[serde(flatten)]
+    pub status: RepoStatus,
+    pub evaluated_at: i64,
+}
+
+fn reason(level: StatusLevel, code: &str, args: &[String]) -> StatusReason {
+    StatusReason { level, code: code.to_string(), message: tr(code, args) }
+}
+
+/// Turns the cached signals into a status. `max_backup_age_secs` of None skips the freshness check.
+pub fn evaluate(signals: &StatusSignals, max_backup_age_secs: Option<i64>, now: i64) -> RepoStatus {
+    let mut reasons = Vec::new();
+
+    // A failure only counts until the repository is reached again
+    if let Some((failed_at, error)) = &signals.last_error {
+        if signals.last_connected_at.is_none_or(|connected| *failed_at > connected) {
+            reasons.push(reason(StatusLevel::Error, "status.unreachable", std::slice::from_ref(error)));
+        }
+    }
+
+    if signals.last_check.is_some_and(|(_, ok)| !ok) {
+        reasons.push(reason(StatusLevel::Error, "status.check_failed", &[]));
+    }
+
+    match (signals.newest_snapshot_at, max_backup_age_secs) {
+        (Some(newest), Some(max_age)) if now - newest > max_age => {
+            let hours = ((now - newest) / 3600).to_string();
+            reasons.push(reason(StatusLevel::Warning, BACKUP_OVERDUE, &[hours]));
+        }
+        (None, _) if signals.last_connected_at.is_some() => {
+            reasons.push(reason(StatusLevel::Warning, "status.no_snapshots", &[]));
+        }
+        _ => {}
+    }
+
+    if let Some(health) = &signals.health {
+        if health.stale_lock_count > 0 {
+            reasons.push(reason(StatusLevel::Warning, "status.stale_locks", &[health.stale_lock_count.to_string()]));
+        }
+        if health.exclusive_locked {
+            reasons.push(reason(StatusLevel::Warning, "status.locked", &[]));
+        }
+    }
+
+    match signals.quota_level {
+        l if l >= 100 => reasons.push(reason(StatusLevel::Error, "status.budget_exceeded", &[])),
+        l if l >= 80 => reasons.push(reason(StatusLevel::Warning, "status.budget_approaching", &[])),
+        _ => {}
+    }
+
+    reasons.sort_by_key(|r| std::cmp::Reverse(r.level));
+    match reasons.first().map(|r| r.level) {
+        Some(StatusLevel::Error) => RepoStatus::Error { reasons },
+        Some(_) => RepoStatus::Warning { reasons },
+        None => RepoStatus::Healthy,
+    }
+}
+
+fn overdue_reason(status: &RepoStatus) -> Option<&StatusReason> {
+    status.reasons().iter().find(|r| r.code == BACKUP_OVERDUE)
+}
+
+// Last status emitted per repository, to only emit changes
+static LAST_STATUS: Lazy<Mutex<HashMap<String, RepoStatus>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Evaluates a repository's status and emits `repo-status-changed` to every
+/// window when it differs from the last one. Turning red or falling behind on
+/// backups also notifies the user, through the notification governor.
+pub fn update(app: &AppHandle, repo_id: &str) -> Result<RepoStatusReport> {
+    let config = load_config().map_err(AppError::Storage)?;
+    let repo = config.repositories.iter().find(|r| r.id == repo_id && !r.is_deleted());
+    let max_age_hours = repo
+        .and_then(|repo| policies::effective(&config, repo).policy.max_backup_age_hours)
+        .unwrap_or(DEFAULT_MAX_BACKUP_AGE_HOURS);
+    let max_age_secs = (max_age_hours > 0).then(|| max_age_hours.saturating_mul(3600).min(i64::MAX as u64) as i64);
+
+    let now = chrono::Utc::now().timestamp();
+    let signals = database::get_status_signals(repo_id)?;
+    let report = RepoStatusReport {
+        repo_id: repo_id.to_string(),
+        status: evaluate(&signals, max_age_secs, now),
+        evaluated_at: now,
+    };
+
+    let previous = match LAST_STATUS.lock() {
+        Ok(mut last) => last.insert(repo_id.to_string(), report.status.clone()),
+        Err(e) => {
+            warn!("Failed to lock repository statuses: {}", e);
+            return Ok(report);
+        }
+    };
+    if previous.as_ref() == Some(&report.status) {
+        return Ok(report);
+    }
+
+    debug!("Status of repo {} is now {:?}", repo_id, report.status.level());
+    if let Err(e) = app.emit("repo-status-changed", report.clone()) {
+        warn!("Failed to emit repo-status-changed: {}", e);
+    }
+    let Some(repo) = repo else { return Ok(report) };
+    let settings = policies::notification_settings(&config, repo_id);
+    let worsened = previous.as_ref().is_none_or(|p| p.level() < StatusLevel::Error);
+    if report.status.level() == StatusLevel::Error && worsened {
+        info!("Repo {} turned to error status", repo_id);
+        let message = tr("status.error", &[repo.name.clone(), report.status.reasons()[0].message.clone()]);
+        notifications::submit(app, &settings, repo_id, "status.error", message);
+    }
+    let was_overdue = previous.as_ref().is_some_and(|p| overdue_reason(p).is_some());
+    if let (Some(overdue), false) = (overdue_reason(&report.status), was_overdue) {
+        info!("Repo {} is overdue for a backup", repo_id);
+        let message = format!("{}: {}", repo.name, overdue.message);
+        notifications::submit(app, &settings, repo_id, BACKUP_OVERDUE, message);
+    }
+    Ok(report)
+}
+
+/// `update` for callers that only want the event; failures are logged
+pub fn refresh(app: &AppHandle, repo_id: &str) {
+    if let Err(e) = update(app, repo_id) {
+        warn!("Failed to evaluate status of repo {}: {}", repo_id, e);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::models::{FindOptions, ForgetPolicy, SnapshotFilter};
+use crate::secrets::SecretString;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Builds each operation's own restic arguments. The commands that run restic and
+/// `render_command_preview` both start from these and add the repository, option
+/// and verbosity flags through the same helpers in `commands`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "operation", content = "params", rename_all = "snake_case")]
+pub enum ResticOperation {
+    Snapshots {},
+    Browse {
+        snapshot_id: String,
+        #[serde(default)]
+        path: Option<String>,
+    },
+    Restore {
+        snapshot_id: String,
+        target: String,
+        #[serde(default)]
+        include_paths: Vec<String>,
+        /// Elevated restores run without the progress output
+        #[serde(default)]
+        elevate: bool,
+        #[serde(default)]
+        sparse: bool,
+    },
+    Forget {
+        policy: ForgetPolicy,
+        #[serde(default)]
+        dry_run: bool,
+    },
+    Prune {
+        #[serde(default)]
+        dry_run: bool,
+    },
+    Check {
+        #[serde(default)]
+        read_data_subset: Option<f64>,
+    },
+    Stats {
+        snapshot_id: String,
+    },
+    Diff {
+        snapshot_a: String,
+        snapshot_b: String,
+    },
+    Find {
+        pattern: String,
+        #[serde(default)]
+        options: FindOptions,
+    },
+}
+
+impl ResticOperation {
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            ResticOperation::Snapshots {} => snapshots(),
+            ResticOperation::Browse { snapshot_id, path } => ls(snapshot_id, path.as_deref()),
+            ResticOperation::Restore { snapshot_id, target, include_paths, .. } => restore(snapshot_id, target, include_paths),
+            ResticOperation::Forget { policy, dry_run } => forget(policy, *dry_run),
+            ResticOperation::Prune { dry_run } => prune(*dry_run),
+            ResticOperation::Check { read_data_subset } => check(*read_data_subset),
+            ResticOperation::Stats { snapshot_id } => stats(snapshot_id),
+            ResticOperation::Diff { snapshot_a, snapshot_b } => diff(snapshot_a, snapshot_b),
+            ResticOperation::Find { pattern, options } => find(pattern, options),
+        }
+    }
+}
+
+pub fn snapshots() -> Vec<String> {
+    vec!["snapshots".to_string(), "--json".to_string()]
+}
+
+/// The newest snapshot of each host and path set
+pub fn latest_snapshots() -> Vec<String> {
+    let mut args = snapshots();
+    args.extend(["--latest".to_string(), "1".to_string()]);
+    args
+}
+
+/// `snapshots --json` narrowed by the filter's hosts and tags; restic's `--path`
+/// only matches whole paths, so prefixes and dates are applied to the result
+pub fn snapshots_filtered(filter: &SnapshotFilter) -> Vec<String> {
+    let mut args = snapshots();
+    for host in &filter.hosts {
+        args.push("--host".to_string());
+        args.push(host.clone());
+    }
+    for tag in &filter.tags {
+        args.push("--tag".to_string());
+        args.push(tag.clone());
+    }
+    args
+}
+
+pub fn ls(snapshot_id: &str, path: Option<&str>) -> Vec<String> {
+    let mut args = vec!["ls".to_string(), "--json".to_string(), snapshot_id.to_string()];
+    args.extend(path.map(str::to_string));
+    args
+}
+
+pub fn restore(snapshot_id: &str, target: &str, include_paths: &[String]) -> Vec<String> {
+    let mut args = vec!["restore".to_string(), snapshot_id.to_string(), "--target".to_string(), target.to_string()];
+    for path in include_paths {
+        args.push("--include".to_string());
+        args.push(path.clone());
+    }
+    args
+}
+
+pub fn forget(policy: &ForgetPolicy, dry_run: bool) -> Vec<String> {
+    let mut args = vec!["forget".to_string(), "--json".to_string()];
+    if dry_run {
+        args.push("--dry-run".to_string());
+    }
+    args.extend(policy.to_args());
+    args
+}
+
+pub fn prune(dry_run: bool) -> Vec<String> {
+    let mut args = vec!["prune".to_string()];
+    if dry_run {
+        args.push("--dry-run".to_string());
+    }
+    args
+}
+
+pub fn check(read_data_subset: Option<f64>) -> Vec<String> {
+    let mut args = vec!["check".to_string()];
+    args.extend(read_data_subset.map(|percent| format!("--read-data-subset={}%", percent)));
+    args
+}
+
+pub fn stats(snapshot_id: &str) -> Vec<String> {
+    vec!["stats".to_string(), "--json".to_string(), snapshot_id.to_string()]
+}
+
+pub fn diff(snapshot_a: &str, snapshot_b: &str) -> Vec<String> {
+    vec!["diff".to_string(), "--json".to_string(), snapshot_a.to_string(), snapshot_b.to_string()]
+}
+
+pub fn find(pattern: &str, options: &FindOptions) -> Vec<String> {
+    let mut args = vec!["find".to_string(), "--json".to_string()];
+    args.extend(options.to_args());
+    // Keeps patterns starting with a dash from being read as flags
+    args.push("--".to_string());
+    args.push(pattern.to_string());
+    args
+}
+
+/// Finds exact snapshot paths. restic matches patterns starting with `/` against
+/// the whole path, so each path only matches itself (barring glob characters in it).
+pub fn find_paths(paths: &[String]) -> Vec<String> {
+    let mut args = vec!["find".to_string(), "--json".to_string(), "--".to_string()];
+    args.extend(paths.iter().cloned());
+    args
+}
+
+/// `flag` is `--add`, `--remove` or `--set`
+pub fn tag(flag: &str, tags: &[String], snapshot_id: &str) -> Vec<String> {
+    let mut args = vec!["tag".to_string()];
+    for tag in tags {
+        args.push(flag.to_string());
+        args.push(tag.clone());
+    }
+    args.push(snapshot_id.to_string());
+    args
+}
+
+/// Without `remove_all`, restic only removes locks it considers stale
+pub fn unlock(remove_all: bool) -> Vec<String> {
+    let mut args = vec!["unlock".to_string()];
+    if remove_all {
+        args.push("--remove-all".to_string());
+    }
+    args
+}
+
+// Subcommands that read or write file data, as opposed to repository metadata
+const DATA_COMMANDS: &[&str] = &["backup", "check", "copy", "dump", "migrate", "mount", "prune", "restore", "rewrite"];
+
+/// Whether the command moves file data and so may run for a long time
+pub fn moves_file_data(args: &[&str]) -> bool {
+    args.first().is_some_and(|command| DATA_COMMANDS.contains(command))
+}
+
+pub fn as_strs(args: &[String]) -> Vec<&str> {
+    args.iter().map(String::as_str).collect()
+}
+
+/// An environment variable restic would get; secret values are replaced by `<NAME>`
+#[derive(Debug, Serialize, Clone)]
+pub struct PreviewEnvVar {
+    pub name: String,
+    pub value: String,
+    pub redacted: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommandPreview {
+    pub program: String,
+    /// Full argument vector, starting with the program
+    pub argv: Vec<String>,
+    pub env: Vec<PreviewEnvVar>,
+    /// The same command as a single POSIX shell line
+    pub shell: String,
+}
+
+// Variables holding passwords or keys; the rest (IDs, regions, file paths) are shown as-is
+const SECRET_VARS: &[&str] = &[
+    "RESTIC_PASSWORD",
+    "AWS_SECRET_ACCESS_KEY",
+    "B2_ACCOUNT_KEY",
+    "AZURE_ACCOUNT_KEY",
+    "AZURE_ACCOUNT_SAS",
+];
+
+pub fn redact_env(vars: Vec<(&'static str, SecretString)>) -> Vec<PreviewEnvVar> {
+    vars.into_iter()
+        .map(|(name, value)| {
+            let redacted = SECRET_VARS.contains(&name);
+            PreviewEnvVar {
+                name: name.to_string(),
+                value: if redacted { format!("<{}>", name) } else { value.to_string() },
+                redacted,
+            }
+        })
+        .collect()
+}
+
+/// A repository's extra variables, always redacted since proxy URLs and rclone
+/// settings often carry credentials
+pub fn redact_extra_env(env: &HashMap<String, String>) -> Vec<PreviewEnvVar> {
+    let mut vars: Vec<PreviewEnvVar> = env.keys()
+        .map(|name| PreviewEnvVar { name: name.clone(), value: format!("<{}>", name), redacted: true })
+        .collect();
+    vars.sort_by(|a, b| a.name.cmp(&b.name));
+    vars
+}
+
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=%@+,".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+pub fn render(program: &str, args: &[String], env: Vec<PreviewEnvVar>) -> CommandPreview {
+    let mut argv = vec![program.to_string()];
+    argv.extend(args.iter().cloned());
+
+    let mut parts: Vec<String> = env.iter()
+        .map(|var| format!("{}={}", var.name, shell_quote(&var.value)))
+        .collect();
+    parts.extend(argv.iter().map(|arg| shell_quote(arg)));
+
+    CommandPreview {
+        program: program.to_string(),
+        argv,
+        env,
+        shell: parts.join(" "),
+    }
+}
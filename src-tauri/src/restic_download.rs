@@ -0,0 +1,268 @@
+use crate::error::{AppError, Result};
+use crate::storage::get_config_dir;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const RELEASES_API: &str = "https://api.github.com/repos/restic/restic/releases/latest";
+const RELEASE_DOWNLOADS: &str = "https://github.com/restic/restic/releases/download";
+/// Key restic's releases are signed with (Alexander Neumann, 0x91A6868BD3F7A907)
+const SIGNING_KEY_FINGERPRINT: &str = "CF8F18F2844575973F79D4E191A6868BD3F7A907";
+const SIGNING_KEY_URL: &str = "https://keys.openpgp.org/vks/v1/by-fingerprint/CF8F18F2844575973F79D4E191A6868BD3F7A907";
+
+/// A restic binary the app downloaded into its data directory
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InstalledRestic {
+    pub version: String,
+    pub path: String,
+    /// False when gpg wasn't available and only the checksum was checked
+    pub signature_verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+fn client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("restic-restore/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(300))
+        .build()
+        .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    debug!("Downloading {}", url);
+    let response = client.get(url).send().await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))?;
+    let body = response.bytes().await
+        .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))?;
+    Ok(body.to_vec())
+}
+
+#[cfg(target_os = "windows")]
+fn hide_window(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+    cmd.creation_flags(CREATE_NO_WINDOW);
+}
+
+#[cfg(not(target_os = "windows"))]
+fn hide_window(_cmd: &mut Command) {}
+
+pub fn validate_version(version: &str) -> Result<()> {
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() != 3 || parts.iter().any(|p| p.is_empty() || !p.chars().all(|c| c.is_ascii_digit())) {
+        return Err(AppError::InvalidFilterValue(version.to_string()));
+    }
+    Ok(())
+}
+
+/// Release file for this OS and architecture, e.g. `restic_0.17.3_linux_amd64.bz2`
+fn asset_name(version: &str) -> Result<String> {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os @ ("linux" | "windows" | "freebsd" | "openbsd" | "netbsd") => os,
+        other => return Err(AppError::UnsupportedPlatform(other.to_string())),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        "arm" => "arm",
+        other => return Err(AppError::UnsupportedPlatform(format!("{} {}", os, other))),
+    };
+    let extension = if os == "windows" { "zip" } else { "bz2" };
+    Ok(format!("restic_{}_{}_{}.{}", version, os, arch, extension))
+}
+
+/// Newest restic release, without the leading `v`
+pub async fn latest_version() -> Result<String> {
+    let body = fetch(&client()?, RELEASES_API).await?;
+    let release: Release = serde_json::from_slice(&body)
+        .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))?;
+    let version = release.tag_name.trim_start_matches('v').to_string();
+    validate_version(&version)?;
+    Ok(version)
+}
+
+/// Version of the restic binary at `binary`, from `restic version`
+pub fn installed_version(binary: &str) -> Option<String> {
+    let mut cmd = Command::new(binary);
+    cmd.arg("version");
+    hide_window(&mut cmd);
+    let output = cmd.output().ok().filter(|o| o.status.success())?;
+    // "restic 0.17.3 compiled with go1.23.3 on linux/amd64"
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
+/// Everything `restic version` prints, or why it couldn't be run
+pub fn version_output(binary: &str) -> String {
+    let mut cmd = Command::new(binary);
+    cmd.arg("version");
+    hide_window(&mut cmd);
+    match cmd.output() {
+        Ok(output) => format!(
+            "{}{}exit status: {}\n",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+            output.status,
+        ),
+        Err(e) => format!("Failed to run {}: {}\n", binary, e),
+    }
+}
+
+/// Directory downloaded binaries are kept in, one subdirectory per version
+pub fn install_dir() -> Result<PathBuf> {
+    Ok(get_config_dir().map_err(AppError::Storage)?.join("restic"))
+}
+
+/// Whether `binary` is one the app downloaded
+pub fn is_managed(binary: &str) -> bool {
+    install_dir().is_ok_and(|dir| Path::new(binary).starts_with(dir))
+}
+
+fn expected_checksum(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        (name.trim().trim_start_matches('*') == asset).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// Checks the signature on SHA256SUMS with gpg in a throwaway keyring holding only
+/// restic's signing key. Returns false when gpg isn't installed.
+async fn verify_signature(client: &reqwest::Client, work_dir: &Path, sums: &Path, signature: &Path) -> Result<bool> {
+    let mut probe = Command::new("gpg");
+    probe.arg("--version").stdout(Stdio::null()).stderr(Stdio::null());
+    hide_window(&mut probe);
+    if tokio::process::Command::from(probe).status().await.is_err() {
+        return Ok(false);
+    }
+
+    let key_file = work_dir.join("restic.asc");
+    fs::write(&key_file, fetch(client, SIGNING_KEY_URL).await?)?;
+    let home = work_dir.join("gnupg");
+    fs::create_dir_all(&home)?;
+
+    let gpg = |args: &[&std::ffi::OsStr]| {
+        let mut cmd = Command::new("gpg");
+        cmd.arg("--batch").arg("--homedir").arg(&home).args(args);
+        hide_window(&mut cmd);
+        tokio::process::Command::from(cmd).output()
+    };
+    gpg(&["--import".as_ref(), key_file.as_os_str()]).await
+        .map_err(|e| AppError::ResticSignatureInvalid(e.to_string()))?;
+    let output = gpg(&["--status-fd".as_ref(), "1".as_ref(), "--verify".as_ref(), signature.as_os_str(), sums.as_os_str()]).await
+        .map_err(|e| AppError::ResticSignatureInvalid(e.to_string()))?;
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    let valid = status.lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .any(|fields| fields.split_whitespace().any(|f| f.eq_ignore_ascii_case(SIGNING_KEY_FINGERPRINT)));
+    if !output.status.success() || !valid {
+        return Err(AppError::ResticSignatureInvalid(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+    Ok(true)
+}
+
+/// Writes the binary inside the release archive to `dest`
+async fn extract(archive: &Path, work_dir: &Path, dest: &Path) -> Result<()> {
+    if archive.extension().is_some_and(|e| e == "zip") {
+        // Windows 10 and later ship bsdtar, which reads zip archives
+        let mut cmd = Command::new("tar");
+        cmd.arg("-xf").arg(archive).arg("-C").arg(work_dir);
+        hide_window(&mut cmd);
+        let status = tokio::process::Command::from(cmd).status().await
+            .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))?;
+        let binary = fs::read_dir(work_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .find(|p| p.extension().is_some_and(|e| e == "exe"));
+        match binary {
+            Some(binary) if status.success() => fs::rename(binary, dest)?,
+            _ => return Err(AppError::ResticDownloadFailed("the archive has no restic binary".to_string())),
+        }
+    } else {
+        let mut cmd = Command::new("bzip2");
+        cmd.arg("-dc").arg(archive).stdout(File::create(dest)?);
+        hide_window(&mut cmd);
+        let status = tokio::process::Command::from(cmd).status().await
+            .map_err(|e| AppError::ResticDownloadFailed(e.to_string()))?;
+        if !status.success() {
+            return Err(AppError::ResticDownloadFailed(format!("bzip2 exited with {}", status)));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dest, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    Ok(())
+}
+
+/// Downloads a restic release, checks it against the published SHA256SUMS and
+/// their signature, and unpacks it into the app's data directory.
+/// Without gpg the signature can't be checked; that's only accepted when
+/// `require_signature` is off.
+pub async fn install(version: &str, require_signature: bool) -> Result<InstalledRestic> {
+    validate_version(version)?;
+    let asset = asset_name(version)?;
+    let version_dir = install_dir()?.join(version);
+    let work_dir = install_dir()?.join(format!(".download-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work_dir)?;
+
+    let result = install_into(version, &asset, &version_dir, &work_dir, require_signature).await;
+    if let Err(e) = fs::remove_dir_all(&work_dir) {
+        warn!("Failed to remove download directory {}: {}", work_dir.display(), e);
+    }
+    result
+}
+
+async fn install_into(version: &str, asset: &str, version_dir: &Path, work_dir: &Path, require_signature: bool) -> Result<InstalledRestic> {
+    let client = client()?;
+    let base = format!("{}/v{}", RELEASE_DOWNLOADS, version);
+    info!("Downloading restic {} ({})", version, asset);
+
+    let sums = fetch(&client, &format!("{}/SHA256SUMS", base)).await?;
+    let signature = fetch(&client, &format!("{}/SHA256SUMS.asc", base)).await?;
+    let sums_file = work_dir.join("SHA256SUMS");
+    let signature_file = work_dir.join("SHA256SUMS.asc");
+    fs::write(&sums_file, &sums)?;
+    fs::write(&signature_file, &signature)?;
+
+    let signature_verified = verify_signature(&client, work_dir, &sums_file, &signature_file).await?;
+    if !signature_verified {
+        if require_signature {
+            return Err(AppError::ResticSignatureInvalid("gpg is not installed".to_string()));
+        }
+        warn!("gpg is not installed; restic {} is only checked against SHA256SUMS", version);
+    }
+
+    let expected = expected_checksum(&String::from_utf8_lossy(&sums), asset)
+        .ok_or_else(|| AppError::ResticDownloadFailed(format!("{} is not listed in SHA256SUMS", asset)))?;
+    let archive = fetch(&client, &format!("{}/{}", base, asset)).await?;
+    if hex::encode(Sha256::digest(&archive)) != expected {
+        return Err(AppError::ResticChecksumMismatch(asset.to_string()));
+    }
+    let archive_file = work_dir.join(asset);
+    fs::write(&archive_file, &archive)?;
+
+    fs::create_dir_all(version_dir)?;
+    let binary = version_dir.join(if cfg!(target_os = "windows") { "restic.exe" } else { "restic" });
+    extract(&archive_file, work_dir, &binary).await?;
+    info!("Installed restic {} at {}", version, binary.display());
+
+    Ok(InstalledRestic {
+        version: version.to_string(),
+        path: binary.to_string_lossy().to_string(),
+        signature_verified,
+    })
+}
@@ -0,0 +1,112 @@
+use crate::error::AppError;
+use serde::Deserialize;
+
+// Exit codes restic 0.17 and later use for the failures they name
+const EXIT_REPO_NOT_FOUND: i32 = 10;
+const EXIT_LOCKED: i32 = 11;
+const EXIT_WRONG_PASSWORD: i32 = 12;
+
+/// The final error line restic prints on stderr when run with `--json`
+#[derive(Debug, Deserialize)]
+struct ExitError {
+    message_type: String,
+    #[serde(default)]
+    code: Option<i32>,
+    message: String,
+}
+
+fn exit_error(stderr: &str) -> Option<ExitError> {
+    stderr.lines()
+        .rev()
+        .filter(|line| line.trim_start().starts_with('{'))
+        .filter_map(|line| serde_json::from_str::<ExitError>(line).ok())
+        .find(|e| e.message_type == "exit_error")
+}
+
+// restic ends a failed run with "Fatal: ...", possibly spanning lines; warnings
+// printed before it are about individual files and say nothing about the cause
+fn fatal_message(stderr: &str) -> &str {
+    stderr.rfind("Fatal:").map_or(stderr, |start| &stderr[start..]).trim()
+}
+
+fn contains_any(haystack: &str, needles: &[&str]) -> bool {
+    needles.iter().any(|needle| haystack.contains(needle))
+}
+
+/// Maps a failed restic run onto the typed error for its cause. Exit codes are
+/// trusted first, then restic's message text for older versions and for
+/// causes without an exit code of their own. Returns None for anything else.
+pub fn classify(exit_code: Option<i32>, stderr: &str) -> Option<AppError> {
+    let json = exit_error(stderr);
+    let code = json.as_ref().and_then(|e| e.code).or(exit_code);
+    let message = json.map(|e| e.message).unwrap_or_else(|| fatal_message(stderr).to_string());
+
+    match code {
+        Some(EXIT_REPO_NOT_FOUND) => return Some(AppError::RepoNotFound(message)),
+        Some(EXIT_LOCKED) => return Some(AppError::Locked(message)),
+        Some(EXIT_WRONG_PASSWORD) => return Some(AppError::WrongPassword),
+        _ => {}
+    }
+
+    // Order matters: restic asks "Is there a repository" whenever it can't read the
+    // config, so a permission problem reads like a missing repository too
+    let lower = message.to_lowercase();
+    if contains_any(&lower, &["wrong password", "no key found"]) {
+        Some(AppError::WrongPassword)
+    } else if contains_any(&lower, &["repository is already locked", "unable to create lock"]) {
+        Some(AppError::Locked(message))
+    } else if contains_any(&lower, &["no space left on device", "not enough space on the disk", "disk quota exceeded"]) {
+        Some(AppError::OutOfSpace(message))
+    } else if contains_any(&lower, &["permission denied", "access is denied", "operation not permitted"]) {
+        Some(AppError::PermissionDenied(message))
+    } else if contains_any(&lower, &[
+        "connection refused",
+        "connection reset",
+        "no such host",
+        "temporary failure in name resolution",
+        "network is unreachable",
+        "no route to host",
+        "502 bad gateway",
+        "503 service unavailable",
+    ]) {
+        // Checked before the missing-repository texts, which restic adds to these too
+        Some(AppError::NetworkUnavailable(message))
+    } else if contains_any(&lower, &[
+        "repository does not exist",
+        "unable to open config file",
+        "is there a repository at the following location",
+    ]) {
+        Some(AppError::RepoNotFound(message))
+    } else if contains_any(&lower, &[
+        "i/o timeout",
+        "timed out",
+        "deadline exceeded",
+        "tls handshake timeout",
+    ]) {
+        Some(AppError::NetworkTimeout(message))
+    } else {
+        None
+    }
+}
+
+/// Whether the error means restic couldn't work with the repository at all,
+/// as opposed to a problem with individual files
+pub fn is_repository_failure(error: &AppError) -> bool {
+    matches!(error, AppError::RepoNotFound(_) | AppError::Locked(_) | AppError::WrongPassword)
+}
+
+/// Whether running the command again may succeed, e.g. after a dropped connection
+pub fn is_transient(error: &AppError) -> bool {
+    matches!(error, AppError::NetworkTimeout(_) | AppError::NetworkUnavailable(_))
+}
+
+/// Whether restic failed because a snapshot it was given doesn't exist (anymore)
+pub fn is_snapshot_missing(error: &AppError) -> bool {
+    match error {
+        AppError::ResticError(message) => {
+            let lower = message.to_lowercase();
+            lower.contains("no matching id found") || (lower.contains("snapshot") && lower.contains("not found"))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,158 @@
+use crate::error::{AppError, Result};
+use crate::restic_download::installed_version;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{PoisonError, RwLock};
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ResticVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ResticVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        ResticVersion { major, minor, patch }
+    }
+
+    /// Parses `0.17.3`; development builds like `0.17.3-dev` count as their release
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches('v');
+        let core = version.split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse().ok());
+        Some(ResticVersion::new(parts.next()??, parts.next()??, parts.next()??))
+    }
+}
+
+impl fmt::Display for ResticVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// restic features the app relies on that older releases don't have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `restore --dry-run`, used for restore previews
+    RestoreDryRun,
+    /// `restore --json` status messages, used for restore progress
+    RestoreJsonProgress,
+    /// `restore --overwrite`, used to handle existing files during in-place restores
+    RestoreOverwrite,
+    /// `restore --sparse`
+    RestoreSparse,
+    /// Repository format version 2, which adds compression
+    RepositoryV2,
+    /// `restic rewrite`
+    Rewrite,
+}
+
+impl Feature {
+    pub fn min_version(self) -> ResticVersion {
+        match self {
+            Feature::RepositoryV2 => ResticVersion::new(0, 14, 0),
+            Feature::Rewrite | Feature::RestoreSparse => ResticVersion::new(0, 15, 0),
+            Feature::RestoreDryRun | Feature::RestoreJsonProgress => ResticVersion::new(0, 16, 0),
+            Feature::RestoreOverwrite => ResticVersion::new(0, 17, 0),
+        }
+    }
+
+    pub fn flag(self) -> &'static str {
+        match self {
+            Feature::RestoreDryRun => "restore --dry-run",
+            Feature::RestoreJsonProgress => "restore --json",
+            Feature::RestoreOverwrite => "restore --overwrite",
+            Feature::RepositoryV2 => "repository format version 2",
+            Feature::Rewrite => "rewrite",
+            Feature::RestoreSparse => "restore --sparse",
+        }
+    }
+}
+
+/// What the restic binary in use supports
+#[derive(Debug, Serialize, Clone)]
+pub struct ResticCapabilities {
+    pub binary: String,
+    /// None when `restic version` couldn't be run or parsed
+    pub version: Option<ResticVersion>,
+    pub restore_dry_run: bool,
+    pub restore_json_progress: bool,
+    pub restore_overwrite: bool,
+    pub repository_v2: bool,
+    pub rewrite: bool,
+    pub restore_sparse: bool,
+}
+
+impl ResticCapabilities {
+    fn for_version(binary: &str, version: Option<ResticVersion>) -> Self {
+        // Unknown versions are given the benefit of the doubt; restic's own error is
+        // still reported if the feature turns out to be missing
+        let supports = |feature: Feature| version.is_none_or(|v| v >= feature.min_version());
+        ResticCapabilities {
+            binary: binary.to_string(),
+            version,
+            restore_dry_run: supports(Feature::RestoreDryRun),
+            restore_json_progress: supports(Feature::RestoreJsonProgress),
+            restore_overwrite: supports(Feature::RestoreOverwrite),
+            repository_v2: supports(Feature::RepositoryV2),
+            rewrite: supports(Feature::Rewrite),
+            restore_sparse: supports(Feature::RestoreSparse),
+        }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::RestoreDryRun => self.restore_dry_run,
+            Feature::RestoreJsonProgress => self.restore_json_progress,
+            Feature::RestoreOverwrite => self.restore_overwrite,
+            Feature::RepositoryV2 => self.repository_v2,
+            Feature::Rewrite => self.rewrite,
+            Feature::RestoreSparse => self.restore_sparse,
+        }
+    }
+
+    /// Fails with a readable error instead of letting restic reject an unknown flag
+    pub fn require(&self, feature: Feature) -> Result<()> {
+        if self.supports(feature) {
+            return Ok(());
+        }
+        Err(AppError::ResticTooOld(feature.flag().to_string(), feature.min_version().to_string()))
+    }
+}
+
+struct Probe {
+    /// The binary's modification time, to notice one upgraded in place
+    modified: Option<SystemTime>,
+    capabilities: ResticCapabilities,
+}
+
+// Keyed by binary path
+static CAPABILITIES: Lazy<RwLock<HashMap<String, Probe>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn modified(binary: &str) -> Option<SystemTime> {
+    std::fs::metadata(binary).and_then(|m| m.modified()).ok()
+}
+
+/// Capabilities of the restic binary at `binary`, probed once per binary
+pub fn capabilities(binary: &str) -> ResticCapabilities {
+    let stamp = modified(binary);
+    if let Some(probe) = CAPABILITIES.read().unwrap_or_else(PoisonError::into_inner).get(binary) {
+        if probe.modified == stamp {
+            return probe.capabilities.clone();
+        }
+    }
+
+    let version = installed_version(binary).and_then(|v| ResticVersion::parse(&v));
+    match version {
+        Some(v) => info!("restic at {} is version {}", binary, v),
+        None => debug!("Couldn't determine the version of restic at {}", binary),
+    }
+    let capabilities = ResticCapabilities::for_version(binary, version);
+    CAPABILITIES.write().unwrap_or_else(PoisonError::into_inner).insert(binary.to_string(), Probe { modified: stamp, capabilities: capabilities.clone() });
+    capabilities
+}
@@ -0,0 +1,103 @@
+use crate::error::{AppError, Result};
+use crate::models::{MetadataNormalization, MetadataSummary, RestoreErrorKind, RestorePathError};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+const MAX_MODE: u32 = 0o7777;
+
+pub fn validate(normalization: &MetadataNormalization) -> Result<()> {
+    if cfg!(not(unix)) && !normalization.is_empty() {
+        return Err(AppError::MetadataNormalizationUnsupported);
+    }
+    for mode in [normalization.file_mode, normalization.dir_mode].into_iter().flatten() {
+        if mode > MAX_MODE {
+            return Err(AppError::InvalidFilterValue(format!("{:o}", mode)));
+        }
+    }
+    Ok(())
+}
+
+/// Where a restore put each of `paths`; restic recreates the full original path under the target
+pub fn restored_roots(target: &Path, paths: &[String]) -> Vec<PathBuf> {
+    if paths.is_empty() {
+        return vec![target.to_path_buf()];
+    }
+    paths.iter()
+        .map(|p| target.join(p.trim_start_matches('/')))
+        .collect()
+}
+
+fn failure(path: &Path, error: std::io::Error) -> RestorePathError {
+    let error_kind = match error.kind() {
+        std::io::ErrorKind::PermissionDenied => RestoreErrorKind::Permissions,
+        _ => RestoreErrorKind::Other,
+    };
+    RestorePathError { path: path.to_string_lossy().to_string(), error_kind, message: error.to_string() }
+}
+
+/// Applies `normalization` to everything under `roots`. Symlinks are re-owned but
+/// never followed or chmod-ed. Failures are collected rather than stopping the walk.
+#[cfg(unix)]
+pub fn apply(roots: &[PathBuf], normalization: &MetadataNormalization) -> MetadataSummary {
+    use std::os::unix::fs::PermissionsExt;
+
+    // SAFETY: getuid and getgid can't fail and have no preconditions
+    let owner = normalization.no_same_owner.then(|| unsafe { (libc::getuid(), libc::getgid()) });
+    let mut summary = MetadataSummary::default();
+    let mut pending: Vec<PathBuf> = roots.to_vec();
+    debug!("Normalizing metadata under {} restored paths", roots.len());
+
+    while let Some(path) = pending.pop() {
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            // Roots for paths restic didn't restore, e.g. excluded by a filter
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                summary.failed.push(failure(&path, e));
+                continue;
+            }
+        };
+
+        let mut changed = false;
+        if let Some((uid, gid)) = owner {
+            match std::os::unix::fs::lchown(&path, Some(uid), Some(gid)) {
+                Ok(()) => changed = true,
+                Err(e) => summary.failed.push(failure(&path, e)),
+            }
+        }
+
+        let file_type = metadata.file_type();
+        let mode = if file_type.is_dir() {
+            normalization.dir_mode
+        } else if file_type.is_file() {
+            normalization.file_mode
+        } else {
+            None
+        };
+        if let Some(mode) = mode {
+            match std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)) {
+                Ok(()) => changed = true,
+                Err(e) => summary.failed.push(failure(&path, e)),
+            }
+        }
+        if changed {
+            summary.adjusted += 1;
+        }
+
+        if file_type.is_dir() {
+            match std::fs::read_dir(&path) {
+                Ok(entries) => pending.extend(entries.filter_map(|e| e.ok()).map(|e| e.path())),
+                Err(e) => summary.failed.push(failure(&path, e)),
+            }
+        }
+    }
+
+    info!("Adjusted metadata of {} restored entries, {} failed", summary.adjusted, summary.failed.len());
+    summary
+}
+
+#[cfg(not(unix))]
+pub fn apply(_roots: &[PathBuf], _normalization: &MetadataNormalization) -> MetadataSummary {
+    debug!("Metadata normalization isn't supported on this platform");
+    MetadataSummary::default()
+}
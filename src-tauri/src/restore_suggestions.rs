@@ -0,0 +1,95 @@
+use crate::database::RestoreHistoryEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How many past restores are considered
+pub const HISTORY_WINDOW: i64 = 200;
+// Older restores count a little less than newer ones
+const RECENCY_DECAY: f64 = 0.95;
+// Confidence when nothing similar was restored before and we fall back to the last target
+const FALLBACK_CONFIDENCE: f64 = 0.2;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestoreTargetSuggestion {
+    pub target: String,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    /// Past restores of similar paths to this target
+    pub matching_restores: usize,
+    pub last_used_at: i64,
+}
+
+fn components(path: &str) -> Vec<String> {
+    path.split(['/', '\\'])
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Share of leading components two paths have in common, 1.0 for identical paths
+fn path_similarity(a: &[String], b: &[String]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    let shared = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    shared as f64 / longest as f64
+}
+
+fn entry_similarity(query: &[Vec<String>], entry: &RestoreHistoryEntry) -> f64 {
+    entry.source_paths.iter()
+        .map(|p| components(p))
+        .flat_map(|p| query.iter().map(move |q| path_similarity(q, &p)))
+        .fold(0.0, f64::max)
+}
+
+struct Candidate {
+    weight: f64,
+    best_similarity: f64,
+    matching_restores: usize,
+    last_used_at: i64,
+}
+
+/// Picks the target that similar source paths were restored to most often and most recently.
+/// `history` must be ordered newest first.
+pub fn suggest(history: &[RestoreHistoryEntry], snapshot_paths: &[String]) -> Option<RestoreTargetSuggestion> {
+    let latest = history.first()?;
+    let query: Vec<Vec<String>> = snapshot_paths.iter().map(|p| components(p)).collect();
+
+    let mut candidates: HashMap<&str, Candidate> = HashMap::new();
+    for (i, entry) in history.iter().enumerate() {
+        let similarity = entry_similarity(&query, entry);
+        if similarity <= 0.0 {
+            continue;
+        }
+
+        let candidate = candidates.entry(entry.target.as_str()).or_insert(Candidate {
+            weight: 0.0,
+            best_similarity: 0.0,
+            matching_restores: 0,
+            last_used_at: entry.restored_at,
+        });
+        candidate.weight += similarity * RECENCY_DECAY.powi(i as i32);
+        candidate.best_similarity = candidate.best_similarity.max(similarity);
+        candidate.matching_restores += 1;
+    }
+
+    let total_weight: f64 = candidates.values().map(|c| c.weight).sum();
+    let best = candidates.into_iter()
+        .max_by(|(_, a), (_, b)| a.weight.total_cmp(&b.weight));
+
+    match best {
+        Some((target, candidate)) => Some(RestoreTargetSuggestion {
+            target: target.to_string(),
+            confidence: candidate.weight / total_weight * candidate.best_similarity,
+            matching_restores: candidate.matching_restores,
+            last_used_at: candidate.last_used_at,
+        }),
+        None => Some(RestoreTargetSuggestion {
+            target: latest.target.clone(),
+            confidence: FALLBACK_CONFIDENCE,
+            matching_restores: 0,
+            last_used_at: latest.restored_at,
+        }),
+    }
+}
@@ -0,0 +1,126 @@
+use crate::background::{self, JobKind};
+use crate::commands::{purge_expired_repositories, record_connection_error, refresh_repository_snapshots};
+use crate::database;
+use crate::secrets;
+use crate::storage::{load_config, SavedRepository};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::{debug, info, warn};
+
+/// Minutes between background snapshot refreshes when a repository doesn't set its own
+pub const DEFAULT_REFRESH_MINUTES: u64 = 60;
+// How often the scheduler looks for repositories that are due
+const TICK: Duration = Duration::from_secs(60);
+// A failed refresh is retried after this long, doubling with each further failure
+const RETRY_BASE_SECS: i64 = 5 * 60;
+
+/// When a repository's last refresh failed, and how many failed in a row
+#[derive(Debug, Clone, Copy)]
+struct Failure {
+    at: i64,
+    count: u32,
+}
+
+// A failed refresh leaves `last_delta_check` alone, so retries are timed from here
+static FAILURES: Lazy<Mutex<HashMap<String, Failure>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn refresh_secs(repo: &SavedRepository) -> Option<i64> {
+    match repo.refresh_interval_minutes.unwrap_or(DEFAULT_REFRESH_MINUTES) {
+        0 => None,
+        minutes => Some(minutes.saturating_mul(60).min(i64::MAX as u64) as i64),
+    }
+}
+
+// Repositories that need a password typed in can't be refreshed unattended
+fn can_refresh_unattended(repo: &SavedRepository) -> bool {
+    repo.password_source.is_external() || !repo.password.is_empty()
+}
+
+/// Seconds to wait after `failures` failed refreshes in a row, at most the interval itself
+fn retry_secs(failures: u32, interval: i64) -> i64 {
+    let doublings = failures.saturating_sub(1).min(16);
+    RETRY_BASE_SECS.saturating_mul(1 << doublings).min(interval)
+}
+
+fn is_due(repo: &SavedRepository, now: i64) -> bool {
+    let Some(interval) = refresh_secs(repo) else { return false };
+    let failure = FAILURES.lock().unwrap_or_else(PoisonError::into_inner).get(&repo.id).copied();
+    if let Some(failure) = failure {
+        return now - failure.at >= retry_secs(failure.count, interval);
+    }
+    match database::get_repo_meta(&repo.id) {
+        Ok(meta) => now - meta.last_delta_check >= interval,
+        Err(e) => {
+            warn!("Skipping background refresh of {}: {}", repo.id, e);
+            false
+        }
+    }
+}
+
+fn record_outcome(repo_id: &str, failed: bool) {
+    let mut failures = FAILURES.lock().unwrap_or_else(PoisonError::into_inner);
+    if failed {
+        let failure = failures.entry(repo_id.to_string()).or_insert(Failure { at: 0, count: 0 });
+        failure.at = chrono::Utc::now().timestamp();
+        failure.count += 1;
+    } else {
+        failures.remove(repo_id);
+    }
+}
+
+// Passwords are only read from the secret store for the repositories that are due
+fn due_repositories(now: i64) -> Vec<SavedRepository> {
+    let Ok(mut config) = load_config() else { return Vec::new() };
+    config.repositories.retain(|r| !r.is_deleted() && is_due(r, now));
+    if config.repositories.is_empty() {
+        return Vec::new();
+    }
+    secrets::hydrate_passwords(&mut config);
+
+    config.repositories.into_iter()
+        .filter(can_refresh_unattended)
+        .collect()
+}
+
+// Refreshes run on the background worker, which orders them by repository priority
+fn queue_due_refreshes(app: &AppHandle) {
+    for repo in due_repositories(chrono::Utc::now().timestamp()) {
+        let app = app.clone();
+        let repo_id = repo.id.clone();
+        let queued = background::submit(&repo_id, JobKind::SnapshotRefresh, move || {
+            debug!("Refreshing snapshots of {} in the background", repo.id);
+            let result = refresh_repository_snapshots(&app, &repo);
+            record_outcome(&repo.id, result.is_err());
+            if let Err(e) = result {
+                warn!("Background refresh of {} failed: {}", repo.id, e);
+                record_connection_error(&app, &repo.id, &e);
+            }
+        });
+        if !queued {
+            debug!("Refresh of {} is still waiting from an earlier tick", repo_id);
+        }
+    }
+}
+
+/// Starts the background task that keeps every repository's snapshot cache current
+/// and purges removed repositories once their undo window has passed
+pub fn start(app: AppHandle) {
+    info!("Starting background snapshot refresh");
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK).await;
+            let app = app.clone();
+            // Reading the config and cache blocks, so it runs off the async workers
+            let tick = move || {
+                purge_expired_repositories();
+                queue_due_refreshes(&app);
+            };
+            if let Err(e) = tauri::async_runtime::spawn_blocking(tick).await {
+                warn!("Background refresh task failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,590 @@
+use crate::error::{AppError, Result};
+use crate::storage::{get_config_dir, edit_config, load_config, AppConfig, BackendCredentials, SavedRepository};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::PathBuf;
+use tracing::{debug, info, warn};
+use zeroize::{Zeroize, Zeroizing};
+
+const KEYCHAIN_SERVICE: &str = "app.restic-restore";
+const KEYCHAIN_PROBE_KEY: &str = "probe";
+const VAULT_FILE: &str = "secrets.json";
+const ENV_PREFIX: &str = "RESTIC_RESTORE_";
+
+/// A password or credential. The buffer is wiped when the value is dropped, and
+/// Debug output never shows it. Copies made by serde or handed to a child
+/// process's environment are outside its reach.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(Zeroizing<String>);
+
+impl SecretString {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(Zeroizing::new(secret))
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(Zeroizing::new(secret.to_string()))
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.is_empty() { "\"\"" } else { "<redacted>" })
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretString::from)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    /// Passwords stay in config.json, as in earlier versions
+    Plaintext,
+    #[default]
+    Keychain,
+    FileVault,
+    Environment,
+}
+
+/// The configured backend, with what the settings page needs to warn about it
+#[derive(Debug, Serialize, Clone)]
+pub struct SecretBackendStatus {
+    pub backend: SecretBackend,
+    /// Whether the OS keychain answers; when it doesn't, the app falls back to config.json
+    pub keychain_available: bool,
+    /// Passwords are written unencrypted to config.json
+    pub plaintext: bool,
+}
+
+pub fn backend_status(config: &AppConfig) -> SecretBackendStatus {
+    let backend = config.secret_backend.unwrap_or_default();
+    SecretBackendStatus {
+        backend,
+        keychain_available: KeychainStore::is_available(),
+        plaintext: backend == SecretBackend::Plaintext,
+    }
+}
+
+/// Somewhere repository secrets can live outside of the repository list.
+/// Keys are namespaced strings such as `password/<repo id>`.
+pub trait SecretStore {
+    fn get(&self, key: &str) -> Result<Option<SecretString>>;
+    fn set(&self, key: &str, secret: &str) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+
+    /// Whether secrets are kept in the `password` field of config.json itself
+    fn persists_in_config(&self) -> bool {
+        false
+    }
+
+    /// Whether `set` really stores anything; the environment can only be read
+    fn is_writable(&self) -> bool {
+        true
+    }
+}
+
+/// Hands `secret` to `store` and tells whether the store now has one for `key`. For the
+/// read-only environment store that means its variable is set.
+fn store_secret(store: &dyn SecretStore, key: &str, secret: &str) -> Result<bool> {
+    if !store.is_writable() {
+        return Ok(store.get(key)?.is_some());
+    }
+    store.set(key, secret)?;
+    Ok(true)
+}
+
+pub fn password_key(repo_id: &str) -> String {
+    format!("password/{}", repo_id)
+}
+
+pub fn credentials_key(repo_id: &str) -> String {
+    format!("credentials/{}", repo_id)
+}
+
+fn repo_id_from_key(key: &str) -> Result<&str> {
+    key.split_once('/')
+        .map(|(_, repo_id)| repo_id)
+        .ok_or_else(|| AppError::SecretStore(format!("Invalid secret key {}", key)))
+}
+
+/// The secrets a config entry can hold, by key, with their values in the entry itself
+fn config_secrets(repo: &SavedRepository) -> Vec<(String, Option<SecretString>)> {
+    vec![
+        (password_key(&repo.id), Some(repo.password.clone()).filter(|p| !p.is_empty())),
+        (
+            credentials_key(&repo.id),
+            repo.backend_credentials.as_ref().and_then(|c| serde_json::to_string(c).ok()).map(SecretString::from),
+        ),
+    ]
+}
+
+fn set_config_secret(repo: &mut SavedRepository, key: &str, value: Option<&str>) -> Result<()> {
+    if key == password_key(&repo.id) {
+        repo.password = value.unwrap_or_default().into();
+    } else if key == credentials_key(&repo.id) {
+        repo.backend_credentials = value
+            .map(|json| serde_json::from_str(json)
+                .map_err(|e| AppError::SecretStore(format!("Invalid stored credentials: {}", e))))
+            .transpose()?;
+    } else {
+        return Err(AppError::SecretStore(format!("The config file can't hold {}", key)));
+    }
+    Ok(())
+}
+
+/// Legacy backend: the password is stored in the repository entry in config.json.
+pub struct PlaintextConfigStore;
+
+impl SecretStore for PlaintextConfigStore {
+    fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        let repo_id = repo_id_from_key(key)?;
+        let config = load_config().map_err(AppError::Storage)?;
+        Ok(config.repositories.iter()
+            .find(|r| r.id == repo_id)
+            .and_then(|r| config_secrets(r).into_iter().find(|(k, _)| k == key))
+            .and_then(|(_, value)| value))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let repo_id = repo_id_from_key(key)?;
+        let mut config = edit_config().map_err(AppError::Storage)?;
+        let repo = config.repositories.iter_mut()
+            .find(|r| r.id == repo_id)
+            .ok_or_else(|| AppError::RepositoryNotFound(repo_id.to_string()))?;
+        set_config_secret(repo, key, Some(secret))?;
+        config.save().map_err(AppError::Storage)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let repo_id = repo_id_from_key(key)?;
+        let mut config = edit_config().map_err(AppError::Storage)?;
+        if let Some(repo) = config.repositories.iter_mut().find(|r| r.id == repo_id) {
+            set_config_secret(repo, key, None)?;
+            config.save().map_err(AppError::Storage)?;
+        }
+        Ok(())
+    }
+
+    fn persists_in_config(&self) -> bool {
+        true
+    }
+}
+
+/// macOS Keychain, Windows Credential Manager or the Linux Secret Service.
+pub struct KeychainStore;
+
+impl KeychainStore {
+    fn entry(key: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, key)
+            .map_err(|e| AppError::SecretStore(format!("Failed to open keychain entry: {}", e)))
+    }
+
+    /// Some Linux desktops run no Secret Service. Looking up a key that is never
+    /// written tells without leaving anything behind: a missing entry means it answered.
+    pub fn is_available() -> bool {
+        match KeychainStore.get(KEYCHAIN_PROBE_KEY) {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("OS keychain is not available: {}", e);
+                false
+            }
+        }
+    }
+}
+
+impl SecretStore for KeychainStore {
+    fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        match Self::entry(key)?.get_password() {
+            Ok(secret) => Ok(Some(secret.into())),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(AppError::SecretStore(format!("Failed to read from keychain: {}", e))),
+        }
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        Self::entry(key)?.set_password(secret)
+            .map_err(|e| AppError::SecretStore(format!("Failed to write to keychain: {}", e)))
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        match Self::entry(key)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(AppError::SecretStore(format!("Failed to delete from keychain: {}", e))),
+        }
+    }
+}
+
+/// A JSON file next to config.json that only the current user can read.
+/// For systems without a keychain service; it is not encrypted.
+pub struct FileVaultStore {
+    path: PathBuf,
+}
+
+impl FileVaultStore {
+    pub fn open() -> Result<Self> {
+        let dir = get_config_dir().map_err(AppError::Storage)?;
+        Ok(Self { path: dir.join(VAULT_FILE) })
+    }
+
+    fn read(&self) -> Result<HashMap<String, SecretString>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let json = Zeroizing::new(fs::read_to_string(&self.path)?);
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::SecretStore(format!("Failed to parse secrets file: {}", e)))
+    }
+
+    fn write(&self, secrets: &HashMap<String, SecretString>) -> Result<()> {
+        let json = Zeroizing::new(serde_json::to_string_pretty(secrets)?);
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(&self.path)?;
+        file.write_all(json.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl SecretStore for FileVaultStore {
+    fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), secret.into());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads secrets from variables such as `RESTIC_RESTORE_PASSWORD_<REPO_ID>` set by
+/// whoever launches the app. Nothing is ever written, so set and delete are no-ops.
+pub struct EnvironmentStore;
+
+impl EnvironmentStore {
+    pub fn variable_name(key: &str) -> String {
+        let suffix: String = key.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+        format!("{}{}", ENV_PREFIX, suffix)
+    }
+}
+
+impl SecretStore for EnvironmentStore {
+    fn get(&self, key: &str) -> Result<Option<SecretString>> {
+        Ok(std::env::var(Self::variable_name(key)).ok().filter(|v| !v.is_empty()).map(SecretString::from))
+    }
+
+    fn set(&self, key: &str, _secret: &str) -> Result<()> {
+        debug!("Not storing {}: secrets come from {}", key, Self::variable_name(key));
+        Ok(())
+    }
+
+    fn delete(&self, _key: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+}
+
+pub fn store_for(backend: SecretBackend) -> Result<Box<dyn SecretStore>> {
+    Ok(match backend {
+        SecretBackend::Plaintext => Box::new(PlaintextConfigStore),
+        SecretBackend::Keychain => Box::new(KeychainStore),
+        SecretBackend::FileVault => Box::new(FileVaultStore::open()?),
+        SecretBackend::Environment => Box::new(EnvironmentStore),
+    })
+}
+
+pub fn active_store(config: &AppConfig) -> Result<Box<dyn SecretStore>> {
+    store_for(config.secret_backend.unwrap_or_default())
+}
+
+/// Moves the password and backend credentials of a repository about to be saved into
+/// the configured store, leaving the config entry without them unless the store is config.json.
+/// A secret the store doesn't take, like one with no environment variable, stays in the entry.
+pub fn stash_password(config: &AppConfig, repo: &mut SavedRepository) -> Result<()> {
+    if !repo.password_source.is_stored() {
+        repo.password.clear();
+    }
+
+    let store = active_store(config)?;
+    if store.persists_in_config() {
+        return Ok(());
+    }
+    for (key, value) in config_secrets(repo) {
+        if let Some(value) = value {
+            if store_secret(&*store, &key, &value)? {
+                set_config_secret(repo, &key, None)?;
+            } else {
+                warn!("{} is not set; keeping {} in the config file", EnvironmentStore::variable_name(&key), key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fills in passwords held outside config.json so callers see complete entries.
+pub fn hydrate_passwords(config: &mut AppConfig) {
+    let store = match active_store(config) {
+        Ok(store) => store,
+        Err(e) => {
+            warn!("Failed to open secret store: {}", e);
+            return;
+        }
+    };
+    if store.persists_in_config() {
+        return;
+    }
+
+    for repo in config.repositories.iter_mut() {
+        if repo.password.is_empty() && repo.password_source.is_stored() {
+            match store.get(&password_key(&repo.id)) {
+                Ok(Some(password)) => repo.password = password,
+                Ok(None) => warn!("No stored password for repository {}", repo.id),
+                Err(e) => warn!("Failed to read password for repository {}: {}", repo.id, e),
+            }
+        }
+
+        if repo.backend_credentials.is_none() {
+            let key = credentials_key(&repo.id);
+            match store.get(&key).and_then(|json| json.map(|j| set_config_secret(repo, &key, Some(&j))).transpose()) {
+                Ok(_) => {}
+                Err(e) => warn!("Failed to read backend credentials for repository {}: {}", repo.id, e),
+            }
+        }
+    }
+}
+
+/// The saved repository with its password resolved from the configured store
+pub fn saved_repository(repo_id: &str) -> Result<SavedRepository> {
+    let mut config = load_config().map_err(AppError::Storage)?;
+    config.repositories.retain(|r| r.id == repo_id);
+    if config.repositories.is_empty() {
+        return Err(AppError::RepositoryNotFound(repo_id.to_string()));
+    }
+    hydrate_passwords(&mut config);
+    Ok(config.repositories.remove(0))
+}
+
+/// Copies every repository password from the current backend into `backend`
+/// and removes it from the old one, then records the new backend in the config.
+/// Moving to the environment is refused unless a variable is set for every secret.
+pub fn migrate_backend(backend: SecretBackend) -> Result<()> {
+    let config = load_config().map_err(AppError::Storage)?;
+    if config.secret_backend == Some(backend) {
+        return Ok(());
+    }
+    let current = config.secret_backend.unwrap_or_default();
+    info!("Moving repository passwords from {:?} to {:?}", current, backend);
+
+    let from = store_for(current)?;
+    let to = store_for(backend)?;
+
+    let mut secrets: Vec<(String, SecretString)> = Vec::new();
+    for repo in &config.repositories {
+        for (key, in_config) in config_secrets(repo) {
+            let secret = match in_config {
+                Some(secret) => Some(secret),
+                None => from.get(&key)?,
+            };
+            if let Some(secret) = secret {
+                secrets.push((key, secret));
+            }
+        }
+    }
+
+    if !to.is_writable() {
+        let mut missing = Vec::new();
+        for (key, _) in &secrets {
+            if to.get(key)?.is_none() {
+                missing.push(EnvironmentStore::variable_name(key));
+            }
+        }
+        if !missing.is_empty() {
+            return Err(AppError::SecretsNotInEnvironment(missing.join(", ")));
+        }
+    }
+
+    // Write everything to the new backend before deleting anything from the old one,
+    // and only touch the secrets it actually took
+    let mut moved: Vec<(String, SecretString)> = Vec::new();
+    for (key, secret) in secrets {
+        if to.persists_in_config() || store_secret(&*to, &key, &secret)? {
+            moved.push((key, secret));
+        }
+    }
+
+    if !from.persists_in_config() {
+        for (key, _) in &moved {
+            if let Err(e) = from.delete(key) {
+                warn!("Failed to remove {} from the previous secret store: {}", key, e);
+            }
+        }
+    }
+
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    for repo in &mut config.repositories {
+        for (key, _) in config_secrets(repo) {
+            let Some((_, secret)) = moved.iter().find(|(k, _)| *k == key) else { continue };
+            let secret = Some(&**secret).filter(|_| to.persists_in_config());
+            set_config_secret(repo, &key, secret)?;
+        }
+    }
+    config.secret_backend = Some(backend);
+    config.save().map_err(AppError::Storage)?;
+
+    info!("Moved {} secrets to {:?}", moved.len(), backend);
+    Ok(())
+}
+
+/// Runs once per config: picks the keychain when it works (plaintext otherwise)
+/// and moves passwords saved by earlier versions out of config.json.
+pub fn choose_initial_backend() -> Result<()> {
+    let config = load_config().map_err(AppError::Storage)?;
+    if config.secret_backend.is_some() {
+        return Ok(());
+    }
+
+    if KeychainStore::is_available() {
+        match migrate_backend(SecretBackend::Keychain) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Failed to move passwords to the keychain: {}", e),
+        }
+    }
+
+    warn!("Keeping repository passwords in the config file");
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    config.secret_backend = Some(SecretBackend::Plaintext);
+    config.save().map_err(AppError::Storage)
+}
+
+/// Backend credentials of a saved repository, wherever they are stored
+pub fn backend_credentials(repo: &SavedRepository) -> Option<BackendCredentials> {
+    if repo.backend_credentials.is_some() {
+        return repo.backend_credentials.clone();
+    }
+
+    let config = load_config().ok()?;
+    let json = match active_store(&config).and_then(|store| store.get(&credentials_key(&repo.id))) {
+        Ok(json) => json?,
+        Err(e) => {
+            warn!("Failed to read backend credentials for repository {}: {}", repo.id, e);
+            return None;
+        }
+    };
+    serde_json::from_str(&json).ok()
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SecretWipeReport {
+    pub repositories: usize,
+    pub secrets_removed: usize,
+    /// Stores that couldn't be cleared, with the reason
+    pub failures: Vec<String>,
+}
+
+/// Deletes every repository password and backend credential from every store the
+/// app can write to, not just the active one. Repository entries and caches stay;
+/// secrets supplied through environment variables can't be removed by the app.
+pub fn wipe_all() -> Result<SecretWipeReport> {
+    let mut config = edit_config().map_err(AppError::Storage)?;
+    let mut report = SecretWipeReport { repositories: config.repositories.len(), ..Default::default() };
+
+    for backend in [SecretBackend::Keychain, SecretBackend::FileVault] {
+        let store = match store_for(backend) {
+            Ok(store) => store,
+            Err(e) => {
+                report.failures.push(format!("{:?}: {}", backend, e));
+                continue;
+            }
+        };
+        for repo in &config.repositories {
+            for key in [password_key(&repo.id), credentials_key(&repo.id)] {
+                match store.get(&key).and_then(|existing| {
+                    store.delete(&key)?;
+                    Ok(existing.is_some())
+                }) {
+                    Ok(true) => report.secrets_removed += 1,
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!("Failed to wipe {} from {:?}: {}", key, backend, e);
+                        report.failures.push(format!("{:?} {}: {}", backend, key, e));
+                    }
+                }
+            }
+        }
+    }
+
+    if let Ok(vault) = FileVaultStore::open() {
+        if vault.read().is_ok_and(|secrets| secrets.is_empty()) && vault.path.exists() {
+            if let Err(e) = fs::remove_file(&vault.path) {
+                warn!("Failed to remove empty secrets file: {}", e);
+            }
+        }
+    }
+
+    for repo in &mut config.repositories {
+        for (key, value) in config_secrets(repo) {
+            if value.is_some() {
+                set_config_secret(repo, &key, None)?;
+                report.secrets_removed += 1;
+            }
+        }
+    }
+    config.save().map_err(AppError::Storage)?;
+
+    info!("Wiped {} secrets of {} repositories", report.secrets_removed, report.repositories);
+    Ok(report)
+}
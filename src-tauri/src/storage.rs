@@ -1,36 +1,469 @@
+use crate::error::{AppError, Result as AppResult};
+use crate::notifications::NotificationSettings;
+use crate::policies::{PolicyTemplate, RepositoryPolicy};
+use crate::secrets::{SecretBackend, SecretString};
+use crate::verbosity::VerbositySettings;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use zeroize::Zeroizing;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// How restic gets the repository password
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PasswordSource {
+    /// Saved with the repository in the configured secret store
+    #[default]
+    Inline,
+    /// Passed to restic as RESTIC_PASSWORD_FILE
+    PasswordFile { path: String },
+    /// Passed to restic as RESTIC_PASSWORD_COMMAND
+    PasswordCommand { command: String },
+    /// Never saved; the user is asked for it every session
+    Prompt,
+}
+
+impl PasswordSource {
+    pub fn is_inline(&self) -> bool {
+        *self == PasswordSource::Inline
+    }
+
+    /// Whether the password is saved by the app rather than supplied each time
+    pub fn is_stored(&self) -> bool {
+        self.is_inline()
+    }
+
+    /// Whether restic obtains the password itself, so the app never sees it
+    pub fn is_external(&self) -> bool {
+        matches!(self, PasswordSource::PasswordFile { .. } | PasswordSource::PasswordCommand { .. })
+    }
+}
+
+/// Order in which repositories get background cache work done
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    /// The user's main repository; at most one repository has it
+    Primary,
+}
+
+/// Something that has to happen before restic can reach a repository, like waking a NAS
+/// or mounting the drive it lives on. Runs once per session, before the first restic call.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreConnectHook {
+    /// Sends a wake-on-LAN magic packet, then waits for the machine to boot
+    WakeOnLan {
+        mac: String,
+        /// Broadcast address, 255.255.255.255 when unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        broadcast: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        wait_secs: Option<u64>,
+    },
+    /// Runs a program, e.g. `mount` or a VPN script; a non-zero exit fails the hook
+    Command {
+        command: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        args: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
+}
+
+/// Credentials for cloud backends, handed to restic as the environment variables it expects.
+/// Which fields matter depends on the backend in the repository URL.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct BackendCredentials {
+    /// S3 access key ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    /// S3 secret access key, B2 account key, Azure account key or REST server password
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// REST server user for basic auth
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// B2 account ID, Azure account name or Google Cloud project ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sas_token: Option<String>,
+    /// Path to a Google Cloud service account JSON key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_account_json: Option<String>,
+}
+
+impl BackendCredentials {
+    pub fn env_vars(&self, repo: &str) -> Vec<(&'static str, String)> {
+        let backend = repo.split_once(':').map(|(scheme, _)| scheme).unwrap_or_default();
+        let mapping: &[(&'static str, &Option<String>)] = match backend {
+            "s3" => &[
+                ("AWS_ACCESS_KEY_ID", &self.access_key_id),
+                ("AWS_SECRET_ACCESS_KEY", &self.secret),
+                ("AWS_DEFAULT_REGION", &self.region),
+            ],
+            "b2" => &[
+                ("B2_ACCOUNT_ID", &self.account_id),
+                ("B2_ACCOUNT_KEY", &self.secret),
+            ],
+            "azure" => &[
+                ("AZURE_ACCOUNT_NAME", &self.account_id),
+                ("AZURE_ACCOUNT_KEY", &self.secret),
+                ("AZURE_ACCOUNT_SAS", &self.sas_token),
+            ],
+            "gs" => &[
+                ("GOOGLE_PROJECT_ID", &self.account_id),
+                ("GOOGLE_APPLICATION_CREDENTIALS", &self.service_account_json),
+            ],
+            "rest" => &[
+                ("RESTIC_REST_USERNAME", &self.username),
+                ("RESTIC_REST_PASSWORD", &self.secret),
+            ],
+            _ => &[],
+        };
+
+        mapping.iter()
+            .filter_map(|(name, value)| {
+                value.as_ref().filter(|v| !v.is_empty()).map(|v| (*name, v.clone()))
+            })
+            .collect()
+    }
+}
+
+/// How restic's ssh connection to an `sftp:` repository is made. Handed to restic
+/// as `-o sftp.args=...`, which it appends to its own ssh command.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct SftpOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Private key passed to ssh with `-i`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Used instead of the user's `~/.ssh/known_hosts`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub known_hosts_file: Option<String>,
+    /// Further ssh `-o` options, each as `Name=value`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ssh_options: Vec<String>,
+}
+
+impl SftpOptions {
+    /// The ssh arguments, quoted for restic's shell-style splitting of `sftp.args`
+    pub fn ssh_args(&self) -> Vec<String> {
+        let quote = |value: &str| match value.contains(char::is_whitespace) {
+            true => format!("\"{}\"", value),
+            false => value.to_string(),
+        };
+        let mut args = Vec::new();
+        if let Some(port) = self.port {
+            args.push(format!("-p {}", port));
+        }
+        if let Some(identity) = self.identity_file.as_deref().filter(|p| !p.is_empty()) {
+            args.push(format!("-i {}", quote(identity)));
+        }
+        if let Some(known_hosts) = self.known_hosts_file.as_deref().filter(|p| !p.is_empty()) {
+            args.push(format!("-o {}", quote(&format!("UserKnownHostsFile={}", known_hosts))));
+        }
+        for option in &self.ssh_options {
+            args.push(format!("-o {}", quote(option)));
+        }
+        args
+    }
+
+    /// Global restic flags for `repo`; nothing unless it's an `sftp:` repository
+    pub fn restic_flags(&self, repo: &str) -> Vec<String> {
+        let args = self.ssh_args();
+        if !repo.starts_with("sftp:") || args.is_empty() {
+            return Vec::new();
+        }
+        vec!["-o".to_string(), format!("sftp.args={}", args.join(" "))]
+    }
+}
+
+/// TLS settings for HTTPS backends such as a REST server with a self-signed certificate
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct TlsOptions {
+    /// CA certificate to trust in addition to the system ones, passed as `--cacert`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cacert: Option<String>,
+    /// Skips certificate verification altogether
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub insecure_tls: bool,
+}
+
+impl TlsOptions {
+    pub fn restic_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(cacert) = self.cacert.as_deref().filter(|p| !p.is_empty()) {
+            flags.push("--cacert".to_string());
+            flags.push(cacert.to_string());
+        }
+        if self.insecure_tls {
+            flags.push("--insecure-tls".to_string());
+        }
+        flags
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SavedRepository {
     pub id: String,
     pub name: String,
     pub path: String,
-    pub password: String,
+    /// Only written to disk by the plaintext secret backend; otherwise kept in the secret store
+    #[serde(default, skip_serializing_if = "SecretString::is_empty")]
+    pub password: SecretString,
+    /// Size budget in bytes for the repository's raw data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_budget: Option<u64>,
+    /// Restic's repository ID, used to recognise the repository after it moves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    #[serde(default, skip_serializing_if = "PasswordSource::is_inline")]
+    pub password_source: PasswordSource,
+    /// Only written to disk by the plaintext secret backend, like `password`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend_credentials: Option<BackendCredentials>,
+    /// Set when the repository was removed; it stays hidden until undone or purged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
+    /// Minutes between background snapshot refreshes, 0 turns them off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval_minutes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<RepoPriority>,
+    /// Extra environment for restic, e.g. `RCLONE_CONFIG` or `HTTPS_PROXY`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra_env: HashMap<String, String>,
+    /// Groups the repository belongs to; policy templates attach to these
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Overrides for settings the repository's templates set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<RepositoryPolicy>,
+    /// Run in order before the first restic call of the session
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_connect: Vec<PreConnectHook>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sftp: Option<SftpOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsOptions>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+impl SavedRepository {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Carries over settings the frontend doesn't send back when it saves the repository list.
+    /// A removal is always kept: only `undo_remove_repository` clears `deleted_at`, so a
+    /// save from a window that still lists the repository can't bring it back.
+    pub fn merge_backend_settings(&mut self, existing: &SavedRepository) {
+        self.deleted_at = existing.deleted_at;
+        if self.size_budget.is_none() {
+            self.size_budget = existing.size_budget;
+        }
+        if self.fingerprint.is_none() {
+            self.fingerprint = existing.fingerprint.clone();
+        }
+        if self.password_source.is_inline() {
+            self.password_source = existing.password_source.clone();
+        }
+        if self.backend_credentials.is_none() {
+            self.backend_credentials = existing.backend_credentials.clone();
+        }
+        if self.refresh_interval_minutes.is_none() {
+            self.refresh_interval_minutes = existing.refresh_interval_minutes;
+        }
+        if self.priority.is_none() {
+            self.priority = existing.priority;
+        }
+        if self.extra_env.is_empty() {
+            self.extra_env = existing.extra_env.clone();
+        }
+        if self.labels.is_empty() {
+            self.labels = existing.labels.clone();
+        }
+        if self.policy.is_none() {
+            self.policy = existing.policy.clone();
+        }
+        if self.pre_connect.is_empty() {
+            self.pre_connect = existing.pre_connect.clone();
+        }
+        if self.sftp.is_none() {
+            self.sftp = existing.sftp.clone();
+        }
+        if self.tls.is_none() {
+            self.tls = existing.tls.clone();
+        }
+    }
+}
+
+/// Which snapshot a restore point restores from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SnapshotSelector {
+    Id { id: String },
+    /// The newest snapshot matching all given filters at the time the restore point runs
+    LatestMatching {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        host: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+    },
+}
+
+/// A saved recovery recipe: what to restore from which snapshot, and where to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RestorePoint {
+    /// Left empty when creating; assigned on save
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub repo_id: String,
+    pub snapshot: SnapshotSelector,
+    /// Restores the whole snapshot when empty
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    pub target: String,
+    #[serde(default)]
+    pub elevate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AppConfig {
     pub repositories: Vec<SavedRepository>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub restic_binary_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub setup_completed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Where repository passwords are kept; chosen on first start when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_backend: Option<SecretBackend>,
+    /// Database queries slower than this are logged with their query plan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_query_threshold_ms: Option<u64>,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    /// Compress cached file listings; unset means enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_node_cache: Option<bool>,
+    /// Days a removed repository can be restored before it's purged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deletion_grace_days: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restore_points: Vec<RestorePoint>,
+    /// restic processes allowed per repository at once; further commands wait their turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_restic: Option<usize>,
+    /// Seconds without output before a restic process counts as stalled; 0 turns the check off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stall_threshold_secs: Option<u64>,
+    /// restic verbosity per kind of operation; unset uses the defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<VerbositySettings>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_templates: Vec<PolicyTemplate>,
+    /// Experimental features the user switched on or off; unset ones keep their default
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub experiments: HashMap<String, bool>,
 }
 
-pub fn get_config_dir() -> Result<PathBuf, String> {
+pub const DEFAULT_DELETION_GRACE_DAYS: u64 = 7;
+
+impl AppConfig {
+    pub fn deletion_grace_secs(&self) -> i64 {
+        let days = self.deletion_grace_days.unwrap_or(DEFAULT_DELETION_GRACE_DAYS);
+        days.saturating_mul(24 * 60 * 60).min(i64::MAX as u64) as i64
+    }
+}
+
+// Kept in the default directory when the data was moved elsewhere; holds the new path
+const LOCATION_FILE: &str = "data_location";
+
+// Where the data lives, resolved once and changed only by `set_data_location`
+static DATA_DIR: Lazy<RwLock<Option<PathBuf>>> = Lazy::new(|| RwLock::new(None));
+
+/// Where the app keeps its data unless it was relocated
+pub fn default_config_dir() -> Result<PathBuf, String> {
     let data_dir = dirs::data_local_dir()
         .ok_or_else(|| "Could not find Application Support directory".to_string())?;
-    
-    let config_dir = data_dir.join("app.restic-restore");
+    Ok(data_dir.join("app.restic-restore"))
+}
+
+fn resolve_config_dir() -> Result<PathBuf, String> {
+    let default_dir = default_config_dir()?;
+    let location = match fs::read_to_string(default_dir.join(LOCATION_FILE)) {
+        Ok(location) if !location.trim().is_empty() => PathBuf::from(location.trim()),
+        _ => return Ok(default_dir),
+    };
+    // Creating it would silently start over with empty data, e.g. while an external drive is unplugged
+    if !location.is_dir() {
+        return Err(format!("Data directory {} is not available", location.display()));
+    }
+    Ok(location)
+}
+
+pub fn get_config_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = DATA_DIR.read().map_err(|e| format!("Failed to lock data directory: {}", e))?.as_ref() {
+        return Ok(dir.clone());
+    }
+    let config_dir = resolve_config_dir()?;
 
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir)
             .map_err(|e| format!("Failed to create config directory: {}", e))?;
     }
-    
-    Ok(config_dir)
+
+    let mut cached = DATA_DIR.write().map_err(|e| format!("Failed to lock data directory: {}", e))?;
+    Ok(cached.get_or_insert(config_dir).clone())
+}
+
+/// Points future launches, and this one from now on, at `dir`. Moving back to the
+/// default directory removes the pointer.
+pub fn set_data_location(dir: &Path) -> Result<(), String> {
+    let default_dir = default_config_dir()?;
+    let pointer = default_dir.join(LOCATION_FILE);
+    if dir == default_dir {
+        if pointer.exists() {
+            fs::remove_file(&pointer).map_err(|e| format!("Failed to remove data location: {}", e))?;
+        }
+    } else {
+        fs::create_dir_all(&default_dir).map_err(|e| format!("Failed to create config directory: {}", e))?;
+        // Written aside and renamed so a crash can't leave half a path behind
+        let partial = default_dir.join(format!("{}.partial", LOCATION_FILE));
+        fs::write(&partial, dir.to_string_lossy().as_bytes())
+            .and_then(|_| fs::rename(&partial, &pointer))
+            .map_err(|e| format!("Failed to write data location: {}", e))?;
+    }
+    *DATA_DIR.write().map_err(|e| format!("Failed to lock data directory: {}", e))? = Some(dir.to_path_buf());
+    Ok(())
+}
+
+/// Whether `name` in the default directory is the pointer written by `set_data_location`
+pub fn is_location_file(name: &std::ffi::OsStr) -> bool {
+    name == LOCATION_FILE
 }
 
 pub fn get_config_file_path() -> Result<PathBuf, String> {
@@ -38,7 +471,52 @@ pub fn get_config_file_path() -> Result<PathBuf, String> {
     Ok(config_dir.join("config.json"))
 }
 
-pub fn save_config(config: &AppConfig) -> Result<(), String> {
+// Commands run concurrently, so every read-modify-write of the config holds the
+// writer lock from reading to saving; otherwise one command's save could drop
+// another's change. Readers only take the cache lock, for as long as a clone takes.
+// Both are plain locks: no edit awaits, and config is also read from worker threads.
+static CONFIG_WRITER: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static CONFIG_CACHE: Lazy<RwLock<Option<AppConfig>>> = Lazy::new(|| RwLock::new(None));
+
+/// A config being edited. Other edits wait until it is saved or dropped;
+/// dropping it without saving discards the changes.
+pub struct ConfigEdit {
+    config: AppConfig,
+    _writer: MutexGuard<'static, ()>,
+}
+
+impl ConfigEdit {
+    /// Writes the config to disk, then makes it what `load_config` returns
+    pub fn save(self) -> Result<(), String> {
+        write_config_file(&self.config)?;
+        let mut cache = CONFIG_CACHE.write().map_err(|e| format!("Failed to lock config: {}", e))?;
+        *cache = Some(self.config);
+        Ok(())
+    }
+}
+
+impl Deref for ConfigEdit {
+    type Target = AppConfig;
+
+    fn deref(&self) -> &AppConfig {
+        &self.config
+    }
+}
+
+impl DerefMut for ConfigEdit {
+    fn deref_mut(&mut self) -> &mut AppConfig {
+        &mut self.config
+    }
+}
+
+/// Starts an edit of the config. Must not be called again on the same thread
+/// before the edit is saved or dropped.
+pub fn edit_config() -> Result<ConfigEdit, String> {
+    let writer = CONFIG_WRITER.lock().map_err(|e| format!("Failed to lock config: {}", e))?;
+    Ok(ConfigEdit { config: load_config()?, _writer: writer })
+}
+
+fn write_config_file(config: &AppConfig) -> Result<(), String> {
     let config_path = get_config_file_path()?;
     
     let json = serde_json::to_string_pretty(config)
@@ -50,7 +528,7 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
     Ok(())
 }
 
-pub fn load_config() -> Result<AppConfig, String> {
+fn read_config_file() -> Result<AppConfig, String> {
     let config_path = get_config_file_path()?;
     
     if !config_path.exists() {
@@ -66,3 +544,142 @@ pub fn load_config() -> Result<AppConfig, String> {
     Ok(config)
 }
 
+/// The saved config, read from disk on first use and kept in step by `ConfigEdit::save`
+pub fn load_config() -> Result<AppConfig, String> {
+    if let Some(config) = CONFIG_CACHE.read().map_err(|e| format!("Failed to lock config: {}", e))?.as_ref() {
+        return Ok(config.clone());
+    }
+    let config = read_config_file()?;
+    let mut cache = CONFIG_CACHE.write().map_err(|e| format!("Failed to lock config: {}", e))?;
+    Ok(cache.get_or_insert(config).clone())
+}
+
+pub fn password_source_for(path: &str) -> PasswordSource {
+    find_repository_by_path(path)
+        .map(|r| r.password_source)
+        .unwrap_or_default()
+}
+
+pub fn find_repository_by_path(path: &str) -> Option<SavedRepository> {
+    let config = load_config().ok()?;
+    config.repositories.into_iter().find(|r| !r.is_deleted() && r.path.trim() == path.trim())
+}
+
+/// Everything needed to set the app up on another machine, passwords included.
+/// Only ever written to disk encrypted, by `seal_bundle`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ConfigBundle {
+    pub repositories: Vec<SavedRepository>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restic_binary_path: Option<String>,
+    #[serde(default)]
+    pub restore_points: Vec<RestorePoint>,
+    #[serde(default)]
+    pub policy_templates: Vec<PolicyTemplate>,
+    /// Pinned snapshot IDs per repository ID, in pinned order
+    #[serde(default)]
+    pub pins: HashMap<String, Vec<String>>,
+}
+
+const BUNDLE_FORMAT: &str = "restic-restore-config";
+const BUNDLE_VERSION: u32 = 1;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+// Stronger than scrypt's interactive default (2^15): the file may end up in cloud storage
+const KDF_LOG_N: u8 = 17;
+// Refuse work factors a tampered file could use to stall the app
+const MAX_KDF_LOG_N: u8 = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KdfParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+/// The file `export_config` writes: AES-256-GCM over the bundle's JSON, with the
+/// key derived from the passphrase by scrypt
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedBundle {
+    format: String,
+    version: u32,
+    exported_at: i64,
+    kdf: KdfParams,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn bundle_key(passphrase: &str, kdf: &KdfParams) -> AppResult<Zeroizing<[u8; 32]>> {
+    if kdf.log_n > MAX_KDF_LOG_N {
+        return Err(AppError::InvalidConfigBundle(format!("key derivation cost 2^{} is too high", kdf.log_n)));
+    }
+    let salt = hex::decode(&kdf.salt).map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p, 32)
+        .map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, key.as_mut())
+        .map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    Ok(key)
+}
+
+// Ties the ciphertext to the header it was written with
+fn bundle_aad(version: u32) -> String {
+    format!("{}/{}", BUNDLE_FORMAT, version)
+}
+
+/// Encrypts `bundle` with `passphrase` and writes it to `path`
+pub fn seal_bundle(bundle: &ConfigBundle, passphrase: &str, path: &Path) -> AppResult<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(AppError::PassphraseTooShort(MIN_PASSPHRASE_LEN.to_string()));
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams { log_n: KDF_LOG_N, r: 8, p: 1, salt: hex::encode(salt) };
+    let key = bundle_key(passphrase, &kdf)?;
+
+    let plaintext = Zeroizing::new(serde_json::to_vec(bundle)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let aad = bundle_aad(BUNDLE_VERSION);
+    let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()))
+        .encrypt(&nonce, Payload { msg: &plaintext, aad: aad.as_bytes() })
+        .map_err(|e| AppError::Storage(format!("Failed to encrypt config export: {}", e)))?;
+
+    let sealed = SealedBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        kdf,
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    };
+    fs::write(path, serde_json::to_string_pretty(&sealed)?)?;
+    Ok(())
+}
+
+/// Reads and decrypts a file written by `seal_bundle`
+pub fn open_bundle(path: &Path, passphrase: &str) -> AppResult<ConfigBundle> {
+    let json = fs::read_to_string(path)?;
+    let sealed: SealedBundle = serde_json::from_str(&json)
+        .map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    if sealed.format != BUNDLE_FORMAT {
+        return Err(AppError::InvalidConfigBundle(sealed.format));
+    }
+    if sealed.version != BUNDLE_VERSION {
+        return Err(AppError::InvalidConfigBundle(format!("unsupported version {}", sealed.version)));
+    }
+
+    let key = bundle_key(passphrase, &sealed.kdf)?;
+    let nonce = hex::decode(&sealed.nonce).map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    if nonce.len() != 12 {
+        return Err(AppError::InvalidConfigBundle("bad nonce".to_string()));
+    }
+    let ciphertext = hex::decode(&sealed.ciphertext).map_err(|e| AppError::InvalidConfigBundle(e.to_string()))?;
+    let aad = bundle_aad(sealed.version);
+    let plaintext = Zeroizing::new(
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key.as_ref()))
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: aad.as_bytes() })
+            .map_err(|_| AppError::WrongPassphrase)?,
+    );
+    serde_json::from_slice(&plaintext).map_err(|e| AppError::InvalidConfigBundle(e.to_string()))
+}
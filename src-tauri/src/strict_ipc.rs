@@ -0,0 +1,105 @@
+//! A check on what the frontend sends, to catch the two sides drifting apart. Tauri
+//! matches command arguments by their camelCase names and quietly treats any other key
+//! as absent, so `{ repo_id }` for `repoId` turns an optional argument into `None`
+//! instead of an error. Every call is checked against the command's arguments, which
+//! the build script reads from the `#[command]` signatures in commands.rs: unknown keys
+//! and missing required ones are rejected.
+//!
+//! Struct arguments only taken from the frontend reject unknown fields themselves
+//! through `#[serde(deny_unknown_fields)]`, in every build. Those also read back from
+//! the config file or restic's output, like `SavedRepository` or `Snapshot`, don't,
+//! so a file written by another version still loads.
+
+use crate::error::{AppError, Result};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use tauri::ipc::InvokeBody;
+use tracing::warn;
+
+/// On in debug builds; `RESTIC_RESTORE_STRICT_IPC=1` or `0` turns the argument
+/// check on or off in any build
+const STRICT_IPC_VAR: &str = "RESTIC_RESTORE_STRICT_IPC";
+
+static ENABLED: Lazy<bool> = Lazy::new(|| match std::env::var(STRICT_IPC_VAR).as_deref() {
+    Ok("1") | Ok("true") => true,
+    Ok("0") | Ok("false") => false,
+    _ => cfg!(debug_assertions),
+});
+
+pub fn enabled() -> bool {
+    *ENABLED
+}
+
+/// Each command with its snake_case arguments and whether they are optional
+static COMMAND_ARGS: &[(&str, &[(&str, bool)])] = include!(concat!(env!("OUT_DIR"), "/command_args.rs"));
+
+fn command_args(command: &str) -> Option<&'static [(&'static str, bool)]> {
+    COMMAND_ARGS.iter().find(|(name, _)| *name == command).map(|(_, args)| *args)
+}
+
+/// The camelCase name Tauri looks an argument up by
+fn camel_case(name: &str) -> String {
+    let mut camel = String::with_capacity(name.len());
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                camel.extend(c.to_uppercase());
+                upper = false;
+            }
+            c => camel.push(c),
+        }
+    }
+    camel
+}
+
+/// Fails with `InvalidIpcPayload` naming the first argument of `command` it doesn't
+/// take or the first required one that's missing, or when the arguments aren't an
+/// object at all. Commands that aren't in the list are left to Tauri to reject.
+pub fn check(command: &str, payload: &InvokeBody) -> Result<()> {
+    let Some(expected) = command_args(command) else {
+        return Ok(());
+    };
+    let empty = serde_json::Map::new();
+    let args = match payload {
+        InvokeBody::Json(Value::Object(args)) => args,
+        InvokeBody::Json(Value::Null) => &empty,
+        InvokeBody::Raw(_) => return Ok(()),
+        InvokeBody::Json(other) => {
+            return Err(AppError::InvalidIpcPayload(format!(
+                "{} takes an object of named arguments, got {}", command, other
+            )));
+        }
+    };
+    for name in args.keys() {
+        if expected.iter().any(|(arg, _)| camel_case(arg) == *name) {
+            continue;
+        }
+        let message = if expected.iter().any(|(arg, _)| arg == name) {
+            format!("argument `{}` of {} would be ignored; it is passed as `{}`", name, command, camel_case(name))
+        } else {
+            format!("{} has no argument `{}`", command, name)
+        };
+        return Err(AppError::InvalidIpcPayload(message));
+    }
+    if let Some((arg, _)) = expected.iter().find(|(arg, optional)| !optional && !args.contains_key(&camel_case(arg))) {
+        return Err(AppError::InvalidIpcPayload(format!(
+            "{} is missing its `{}` argument", command, camel_case(arg)
+        )));
+    }
+    Ok(())
+}
+
+/// Runs `check` when strict mode is on. Returns the error to reject the call with.
+pub fn validate(command: &str, payload: &InvokeBody) -> Option<AppError> {
+    if !enabled() {
+        return None;
+    }
+    let error = check(command, payload).err()?;
+    warn!("Rejected call to {}: {}", command, error);
+    Some(error)
+}
+
+#[cfg(test)]
+mod payload_tests;
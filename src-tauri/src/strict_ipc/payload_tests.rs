@@ -0,0 +1,76 @@
+//! Which payloads the argument check lets through.
+
+use super::*;
+use serde_json::json;
+
+fn payload(value: Value) -> InvokeBody {
+    InvokeBody::Json(value)
+}
+
+#[test]
+fn camel_case_matches_tauri_argument_names() {
+    assert_eq!(camel_case("repo_id"), "repoId");
+    assert_eq!(camel_case("snapshot_id_prefix"), "snapshotIdPrefix");
+    assert_eq!(camel_case("repo"), "repo");
+    assert_eq!(camel_case("trailing_"), "trailing");
+}
+
+#[test]
+fn lists_every_command_with_its_payload_arguments() {
+    assert!(COMMAND_ARGS.len() > 100);
+    assert_eq!(command_args("list_snapshots"), Some(&[("repo", false), ("password", false), ("filter", true)][..]));
+    // `app` and `window` are filled in by Tauri
+    assert_eq!(command_args("get_backup_health"), Some(&[][..]));
+    assert!(command_args("restore_snapshot").unwrap().iter().all(|(arg, _)| *arg != "window"));
+}
+
+#[test]
+fn accepts_camel_case_and_empty_payloads() {
+    let args = json!({ "repo": "/srv/repo", "password": "secret", "filter": null });
+    assert!(check("list_snapshots", &payload(args)).is_ok());
+    assert!(check("list_snapshots", &payload(json!({ "repo": "/srv/repo", "password": "secret" }))).is_ok());
+    assert!(check("get_backup_health", &payload(json!({}))).is_ok());
+    assert!(check("get_backup_health", &payload(Value::Null)).is_ok());
+    assert!(check("list_snapshots", &InvokeBody::Raw(vec![1, 2, 3])).is_ok());
+}
+
+#[test]
+fn rejects_snake_case_arguments_with_their_camel_case_name() {
+    let args = json!({ "repoId": "a", "snapshot_ids": ["abc"] });
+    let error = check("get_cached_stats", &payload(args)).unwrap_err();
+    let AppError::InvalidIpcPayload(message) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert!(message.contains("`snapshot_ids`") && message.contains("`snapshotIds`"), "{}", message);
+}
+
+#[test]
+fn rejects_payloads_that_are_not_objects() {
+    assert!(matches!(check("list_snapshots", &payload(json!([1, 2]))), Err(AppError::InvalidIpcPayload(_))));
+    assert!(matches!(check("list_snapshots", &payload(json!("repo"))), Err(AppError::InvalidIpcPayload(_))));
+}
+
+#[test]
+fn rejects_unknown_camel_case_arguments() {
+    let args = json!({ "repo": "/srv/repo", "password": "secret", "repoIdd": "a" });
+    let error = check("list_snapshots", &payload(args)).unwrap_err();
+    let AppError::InvalidIpcPayload(message) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert!(message.contains("`repoIdd`"), "{}", message);
+}
+
+#[test]
+fn rejects_missing_required_arguments() {
+    let error = check("list_snapshots", &payload(json!({ "repo": "/srv/repo" }))).unwrap_err();
+    let AppError::InvalidIpcPayload(message) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert!(message.contains("`password`"), "{}", message);
+    assert!(check("get_repo_meta", &payload(Value::Null)).is_err());
+}
+
+#[test]
+fn leaves_unlisted_commands_to_tauri() {
+    assert!(check("no_such_command", &payload(json!({ "anything": 1 }))).is_ok());
+}
@@ -0,0 +1,81 @@
+use crate::error::{AppError, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Directories created for a restore target, kept so they can be removed again
+/// when the restore is cancelled before restic writes anything into them
+#[derive(Debug, Default)]
+pub struct CreatedDirs {
+    root: Option<PathBuf>,
+}
+
+// Permission bits don't account for ACLs or read-only mounts, so this actually writes
+fn check_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(format!(".restic-restore-probe-{}", uuid::Uuid::new_v4()));
+    fs::create_dir(&probe)
+        .and_then(|_| fs::remove_dir(&probe))
+        .map_err(|e| {
+            debug!("Write check in {} failed: {}", dir.display(), e);
+            AppError::TargetNotWritable(dir.to_path_buf())
+        })
+}
+
+/// Creates the target and every missing parent. The chain is built next to its
+/// final location and moved in with a single rename, so a failure halfway leaves nothing behind.
+pub fn create_missing(target: &Path) -> Result<CreatedDirs> {
+    let ancestor = target.ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| AppError::ParentDirectoryNotFound(target.to_path_buf()))?;
+    if ancestor == target {
+        return Ok(CreatedDirs::default());
+    }
+    if !ancestor.is_dir() {
+        return Err(AppError::TargetNotWritable(ancestor.to_path_buf()));
+    }
+    check_writable(ancestor)?;
+
+    let relative = target.strip_prefix(ancestor).map_err(|_| AppError::InvalidTargetPath)?;
+    let mut components = relative.components();
+    let Some(Component::Normal(first)) = components.next() else {
+        return Err(AppError::InvalidTargetPath);
+    };
+    let top = ancestor.join(first);
+
+    let staging = ancestor.join(format!(".restic-restore-{}", uuid::Uuid::new_v4()));
+    let built = fs::create_dir_all(staging.join(components.as_path()))
+        .and_then(|_| fs::rename(&staging, &top));
+    if let Err(e) = built {
+        if let Err(cleanup) = fs::remove_dir_all(&staging) {
+            debug!("Nothing to clean up at {}: {}", staging.display(), cleanup);
+        }
+        return Err(e.into());
+    }
+
+    info!("Created missing directories up to {}", target.display());
+    Ok(CreatedDirs { root: Some(top) })
+}
+
+// Anything that isn't a directory counts, and unreadable directories are assumed to hold files
+fn contains_files(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else { return true };
+    entries.into_iter().any(|entry| match entry.and_then(|e| e.file_type().map(|t| (e.path(), t))) {
+        Ok((path, file_type)) if file_type.is_dir() => contains_files(&path),
+        _ => true,
+    })
+}
+
+impl CreatedDirs {
+    /// Removes the created directories unless restic already wrote files into them
+    pub fn roll_back(self) {
+        let Some(root) = self.root else { return };
+        if contains_files(&root) {
+            warn!("Keeping {} because the restore already wrote files into it", root.display());
+            return;
+        }
+        match fs::remove_dir_all(&root) {
+            Ok(()) => info!("Removed directories created for the cancelled restore at {}", root.display()),
+            Err(e) => warn!("Failed to remove {}: {}", root.display(), e),
+        }
+    }
+}
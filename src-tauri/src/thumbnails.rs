@@ -0,0 +1,97 @@
+use crate::error::{AppError, Result};
+use crate::storage::get_config_dir;
+use base64::Engine;
+use image::ImageFormat;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+/// Larger files aren't dumped just to show a preview
+pub const MAX_SOURCE_BYTES: u64 = 50 * 1024 * 1024;
+pub const DEFAULT_MAX_DIM: u32 = 256;
+const MAX_DIM: u32 = 1024;
+const JPEG_QUALITY: u8 = 80;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "bmp"];
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Thumbnail {
+    pub mime: String,
+    /// Base64 of the encoded image
+    pub data: String,
+    pub width: u32,
+    pub height: u32,
+    pub cached: bool,
+}
+
+pub fn is_image(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+pub fn clamp_dim(max_dim: Option<u32>) -> u32 {
+    max_dim.unwrap_or(DEFAULT_MAX_DIM).clamp(16, MAX_DIM)
+}
+
+fn cache_dir() -> Result<PathBuf> {
+    Ok(get_config_dir().map_err(AppError::Storage)?.join("thumbnails"))
+}
+
+/// Snapshots never change, so a thumbnail stays valid for as long as the snapshot exists
+fn cache_path(repo_id: &str, snapshot_id: &str, path: &str, max_dim: u32) -> Result<PathBuf> {
+    let mut hasher = Sha256::new();
+    for part in [repo_id, snapshot_id, path, &max_dim.to_string()] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    Ok(cache_dir()?.join(format!("{}.jpg", hex::encode(hasher.finalize()))))
+}
+
+fn encode(jpeg: &[u8], cached: bool) -> Result<Thumbnail> {
+    let (width, height) = image::ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg)
+        .into_dimensions()
+        .map_err(|e| AppError::ThumbnailUnavailable(e.to_string()))?;
+    Ok(Thumbnail {
+        mime: "image/jpeg".to_string(),
+        data: base64::engine::general_purpose::STANDARD.encode(jpeg),
+        width,
+        height,
+        cached,
+    })
+}
+
+pub fn load_cached(repo_id: &str, snapshot_id: &str, path: &str, max_dim: u32) -> Option<Thumbnail> {
+    let file = cache_path(repo_id, snapshot_id, path, max_dim).ok()?;
+    let jpeg = std::fs::read(&file).ok()?;
+    match encode(&jpeg, true) {
+        Ok(thumbnail) => Some(thumbnail),
+        Err(e) => {
+            warn!("Discarding unreadable cached thumbnail {}: {}", file.display(), e);
+            let _ = std::fs::remove_file(&file);
+            None
+        }
+    }
+}
+
+/// Downscales `source` to fit `max_dim` and caches the result
+pub fn render(repo_id: &str, snapshot_id: &str, path: &str, source: &[u8], max_dim: u32) -> Result<Thumbnail> {
+    let image = image::load_from_memory(source)
+        .map_err(|e| AppError::ThumbnailUnavailable(format!("{}: {}", path, e)))?;
+    let small = image.thumbnail(max_dim, max_dim).into_rgb8();
+
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&small)
+        .map_err(|e| AppError::ThumbnailUnavailable(format!("{}: {}", path, e)))?;
+
+    // A failed cache write only costs a re-render next time
+    let file = cache_path(repo_id, snapshot_id, path, max_dim)?;
+    let written = std::fs::create_dir_all(cache_dir()?).and_then(|_| std::fs::write(&file, &jpeg));
+    match written {
+        Ok(()) => debug!("Cached thumbnail of {} at {}", path, file.display()),
+        Err(e) => warn!("Failed to cache thumbnail of {}: {}", path, e),
+    }
+    encode(&jpeg, false)
+}
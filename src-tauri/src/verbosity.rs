@@ -0,0 +1,141 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use tracing::debug;
+
+/// How much restic reports while it works
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Errors and the final summary only; restores report no progress
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    /// restic's `--verbose=2`, which lists every file it touches
+    Debug,
+}
+
+impl Verbosity {
+    pub fn flags(self) -> &'static [&'static str] {
+        match self {
+            Verbosity::Quiet => &["--quiet"],
+            Verbosity::Normal => &[],
+            Verbosity::Verbose => &["--verbose"],
+            Verbosity::Debug => &["--verbose=2"],
+        }
+    }
+
+    /// Lines of text output kept for the result, newest last; None keeps everything
+    pub fn captured_lines(self) -> Option<usize> {
+        match self {
+            Verbosity::Quiet => Some(100),
+            Verbosity::Normal => Some(1_000),
+            Verbosity::Verbose | Verbosity::Debug => None,
+        }
+    }
+
+    fn at_most_normal(self) -> Self {
+        match self {
+            Verbosity::Quiet => Verbosity::Quiet,
+            _ => Verbosity::Normal,
+        }
+    }
+}
+
+/// What a restic command is run for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationKind {
+    /// Scheduled refreshes, stats backfills and file indexing
+    Background,
+    Restore,
+    /// Check and prune
+    Maintenance,
+    /// Commands the user waits on for JSON results; always run at restic's default level
+    Interactive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct VerbositySettings {
+    pub background: Verbosity,
+    pub restore: Verbosity,
+    pub maintenance: Verbosity,
+}
+
+impl Default for VerbositySettings {
+    fn default() -> Self {
+        VerbositySettings {
+            background: Verbosity::Quiet,
+            restore: Verbosity::Verbose,
+            maintenance: Verbosity::Normal,
+        }
+    }
+}
+
+static SETTINGS: Lazy<RwLock<VerbositySettings>> = Lazy::new(|| RwLock::new(VerbositySettings::default()));
+
+pub fn current() -> VerbositySettings {
+    SETTINGS.read().map(|s| *s).unwrap_or_default()
+}
+
+pub fn set(settings: VerbositySettings) {
+    debug!("Setting restic verbosity to {:?}", settings);
+    if let Ok(mut current) = SETTINGS.write() {
+        *current = settings;
+    }
+}
+
+pub fn for_kind(kind: OperationKind) -> Verbosity {
+    let settings = current();
+    match kind {
+        // Background commands print JSON documents, which verbose messages would interleave with
+        OperationKind::Background => settings.background.at_most_normal(),
+        OperationKind::Restore => settings.restore,
+        OperationKind::Maintenance => settings.maintenance,
+        OperationKind::Interactive => Verbosity::Normal,
+    }
+}
+
+/// `args` followed by the verbosity flags configured for the kind of operation
+pub fn with_flags<'a>(args: &[&'a str], kind: OperationKind) -> Vec<&'a str> {
+    let mut args = args.to_vec();
+    args.extend_from_slice(for_kind(kind).flags());
+    args
+}
+
+/// Collects text output up to the line budget of a verbosity, dropping the oldest
+/// lines first since restic ends with its summary
+#[derive(Debug)]
+pub struct CapturedOutput {
+    lines: VecDeque<String>,
+    limit: Option<usize>,
+    dropped: usize,
+}
+
+impl CapturedOutput {
+    pub fn new(verbosity: Verbosity) -> Self {
+        CapturedOutput { lines: VecDeque::new(), limit: verbosity.captured_lines(), dropped: 0 }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        if self.limit.is_some_and(|limit| self.lines.len() >= limit) {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    pub fn finish(self) -> String {
+        let mut output = String::new();
+        if self.dropped > 0 {
+            output.push_str(&format!("[{} earlier lines not kept]\n", self.dropped));
+        }
+        for line in self.lines {
+            output.push_str(&line);
+            output.push('\n');
+        }
+        output
+    }
+}
@@ -0,0 +1,62 @@
+//! Windows path forms the validators have to accept: drive paths (`C:\data`),
+//! UNC shares (`\\server\share\repo`) and verbatim paths (`\\?\C:\data`,
+//! `\\?\UNC\server\share`), which lift the 260 character limit.
+
+use std::path::{Component, Path, Prefix};
+
+/// Longest path most Windows APIs take without the verbatim prefix
+pub const MAX_PATH: usize = 260;
+
+const VERBATIM: &str = r"\\?\";
+const DEVICE: &str = r"\\.\";
+
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Whether `path` is a drive, UNC or verbatim path rather than a `backend:` repository.
+/// Backends all have names longer than one letter, so `X:` is always a drive.
+pub fn is_windows_path(path: &str) -> bool {
+    has_drive_letter(path) || path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Backslashes throughout, since verbatim paths take `/` literally
+pub fn normalize_separators(path: &str) -> String {
+    if is_windows_path(path) {
+        path.replace('/', "\\")
+    } else {
+        path.to_string()
+    }
+}
+
+/// The verbatim form of an absolute path too long for MAX_PATH; anything else is returned as it is.
+/// Expects backslashes, see `normalize_separators`.
+pub fn long_path_form(path: &str) -> String {
+    if path.len() < MAX_PATH || path.starts_with(VERBATIM) || path.starts_with(DEVICE) {
+        return path.to_string();
+    }
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return format!(r"{}UNC\{}", VERBATIM, share);
+    }
+    if has_drive_letter(path) && path[2..].starts_with('\\') {
+        return format!("{}{}", VERBATIM, path);
+    }
+    path.to_string()
+}
+
+/// The form Windows APIs take: backslashes, with the verbatim prefix past MAX_PATH.
+/// Elsewhere the path is left alone, as `//` and `\` mean something else there.
+pub fn native_form(path: &str) -> String {
+    if cfg!(windows) {
+        long_path_form(&normalize_separators(path))
+    } else {
+        path.to_string()
+    }
+}
+
+/// Whether the path names a device (`\\.\COM1`, `\\.\PhysicalDrive0`) rather than a file or
+/// directory. Only Windows parses prefixes, so this is always false elsewhere.
+pub fn is_device_path(path: &Path) -> bool {
+    matches!(path.components().next(), Some(Component::Prefix(p)) if matches!(p.kind(), Prefix::DeviceNS(_)))
+}
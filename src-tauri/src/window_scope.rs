@@ -0,0 +1,73 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+use tracing::{debug, warn};
+
+pub const REPOSITORY_WINDOW_PREFIX: &str = "repo-";
+
+// Window label -> repository ID. Windows without an entry are unscoped and see every repository.
+static WINDOW_REPOS: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+pub fn bind(label: &str, repo_id: &str) {
+    debug!("Binding window {} to repository {}", label, repo_id);
+    match WINDOW_REPOS.write() {
+        Ok(mut bindings) => {
+            bindings.insert(label.to_string(), repo_id.to_string());
+        }
+        Err(e) => warn!("Failed to bind window {}: {}", label, e),
+    }
+}
+
+pub fn unbind(label: &str) {
+    match WINDOW_REPOS.write() {
+        Ok(mut bindings) => {
+            if bindings.remove(label).is_some() {
+                debug!("Unbound window {}", label);
+            }
+        }
+        Err(e) => warn!("Failed to unbind window {}: {}", label, e),
+    }
+}
+
+pub fn repo_for_window(label: &str) -> Option<String> {
+    WINDOW_REPOS.read().ok()?.get(label).cloned()
+}
+
+/// Labels of open windows that should receive events for `repo_id`:
+/// those bound to it plus every unscoped window.
+pub fn windows_for_repo(app: &AppHandle, repo_id: &str) -> Vec<String> {
+    let bindings = match WINDOW_REPOS.read() {
+        Ok(bindings) => bindings.clone(),
+        Err(e) => {
+            warn!("Failed to read window bindings: {}", e);
+            HashMap::new()
+        }
+    };
+
+    app.webview_windows()
+        .into_keys()
+        .filter(|label| bindings.get(label).is_none_or(|bound| bound == repo_id))
+        .collect()
+}
+
+/// Emits a repository-scoped event so side-by-side windows don't receive each other's updates.
+pub fn emit_repo_event<S: Serialize + Clone>(app: &AppHandle, repo_id: &str, event: &str, payload: S) {
+    for label in windows_for_repo(app, repo_id) {
+        if let Err(e) = app.emit_to(label.as_str(), event, payload.clone()) {
+            warn!("Failed to emit {} to window {}: {}", event, label, e);
+        }
+    }
+}
+
+/// Emits a job event only to the window that started the job.
+pub fn emit_to_window<S: Serialize + Clone>(window: &WebviewWindow, event: &str, payload: S) {
+    if let Err(e) = window.emit_to(window.label(), event, payload) {
+        warn!("Failed to emit {} to window {}: {}", event, window.label(), e);
+    }
+}
+
+pub fn repository_window_label(repo_id: &str) -> String {
+    format!("{}{}", REPOSITORY_WINDOW_PREFIX, repo_id)
+}
@@ -2,6 +2,7 @@ import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { VALIDATION } from '../config/constants';
 import { REPO_PATH_PATTERNS } from '../config/patterns';
+import { errorMessage } from '../utils/errors';
 import styles from './ConnectionForm.module.css';
 
 interface ConnectionFormProps {
@@ -53,7 +54,7 @@ export function ConnectionForm({ onConnect }: ConnectionFormProps) {
             await invoke('connect_repository', { repo, password });
             onConnect(repo, password);
         } catch (err) {
-            setError(`Connection failed: ${err}`);
+            setError(`Connection failed: ${errorMessage(err)}`);
         } finally {
             setLoading(false);
         }
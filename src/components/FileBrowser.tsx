@@ -1,9 +1,10 @@
 import { useState, useEffect, useMemo, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
-import { Snapshot, FileNode } from '../types';
+import { Snapshot, FileNode, RestorePathError, RestoreErrorKind, RestoreResult } from '../types';
 import { FolderIcon, FileIcon, EmptyFolderIcon } from './Icons';
 import { formatSnapshotId } from '../utils/formatters';
+import { errorMessage } from '../utils/errors';
 import { TIMING } from '../config/constants';
 import styles from './FileBrowser.module.css';
 
@@ -27,6 +28,13 @@ interface SelectedItem {
     restorePath: string;
 }
 
+const RESTORE_ERROR_LABELS: Record<RestoreErrorKind, string> = {
+    permissions: 'permission denied',
+    path_too_long: 'path too long',
+    disk_full: 'disk full',
+    other: 'other error',
+};
+
 export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserProps) {
     const [allFiles, setAllFiles] = useState<FileNode[]>([]);
     const [currentPath, setCurrentPath] = useState<string>('/');
@@ -37,6 +45,7 @@ export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserPr
     
     const [statusMessage, setStatusMessage] = useState<string>('');
     const [statusType, setStatusType] = useState<'idle' | 'loading' | 'success' | 'error'>('idle');
+    const [restoreErrors, setRestoreErrors] = useState<RestorePathError[]>([]);
     const [showConfirmRestore, setShowConfirmRestore] = useState(false);
     const [pendingRestore, setPendingRestore] = useState<{path: string, paths: string[], count: number} | null>(null);
 
@@ -52,7 +61,7 @@ export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserPr
             });
             setAllFiles(result);
         } catch (err) {
-            setError(`Failed to load files: ${err}`);
+            setError(`Failed to load files: ${errorMessage(err)}`);
         } finally {
             setLoading(false);
         }
@@ -231,11 +240,12 @@ export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserPr
         if (!pendingRestore) return;
         
         setShowConfirmRestore(false);
+        setRestoreErrors([]);
         setStatusType('loading');
         setStatusMessage(`Restoring ${pendingRestore.count} item${pendingRestore.count !== 1 ? 's' : ''}...`);
 
         try {
-            await invoke<string>('restore_selective', {
+            const result = await invoke<RestoreResult>('restore_selective', {
                 repo,
                 password,
                 snapshotId: snapshot.id,
@@ -243,19 +253,29 @@ export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserPr
                 includePaths: pendingRestore.paths,
             });
 
-            setStatusType('success');
             const itemText = pendingRestore.count === 1 ? 'item' : 'items';
             const pathParts = pendingRestore.path.split(/[/\\]/);
             const folderName = pathParts[pathParts.length - 1] || 'restore folder';
-            setStatusMessage(`✓ Successfully restored ${pendingRestore.count} ${itemText} to "${folderName}"`);
 
             setSelectedItems(new Map());
 
+            if (result.errors.length > 0) {
+                // Leave the bar up so the skipped paths can be read
+                const errorText = result.errors.length === 1 ? 'path' : 'paths';
+                setRestoreErrors(result.errors);
+                setStatusType('error');
+                setStatusMessage(`⚠ Restored ${pendingRestore.count} ${itemText} to "${folderName}", but ${result.errors.length} ${errorText} could not be restored:`);
+                return;
+            }
+
+            setStatusType('success');
+            setStatusMessage(`✓ Successfully restored ${pendingRestore.count} ${itemText} to "${folderName}"`);
+
             setTimeout(() => setStatusType('idle'), TIMING.SUCCESS_MESSAGE_DURATION_MS);
             
         } catch (err) {
             setStatusType('error');
-            setStatusMessage(`✗ Restore failed: ${err}`);
+            setStatusMessage(`✗ Restore failed: ${errorMessage(err)}`);
             setTimeout(() => setStatusType('idle'), TIMING.ERROR_MESSAGE_DURATION_MS);
         } finally {
             setPendingRestore(null);
@@ -495,6 +515,17 @@ export function FileBrowser({ snapshot, repo, password, onClose }: FileBrowserPr
                             <span className={styles.statusText}>{statusMessage}</span>
                         )}
                     </div>
+                    {statusType === 'error' && restoreErrors.length > 0 && (
+                        <ul className={styles.restoreErrorList}>
+                            {restoreErrors.map(item => (
+                                <li key={item.path} title={item.message}>
+                                    <span className={styles.restoreErrorPath}>{item.path}</span>
+                                    {' — '}
+                                    {RESTORE_ERROR_LABELS[item.error_kind]}
+                                </li>
+                            ))}
+                        </ul>
+                    )}
                 </div>
                 </div>
 
@@ -1,6 +1,19 @@
 import { LoadingState } from '../types';
 import styles from './LoadingIndicator.module.css';
 
+function primingText(state: LoadingState): string {
+    switch (state.stage) {
+        case 'parsing':
+            return 'Reading snapshots...';
+        case 'writing':
+            return `Saving snapshots: ${state.current ?? 0}/${state.total ?? 0}`;
+        case 'building_facets':
+            return 'Building filters...';
+        default:
+            return 'Fetching snapshot list...';
+    }
+}
+
 interface LoadingIndicatorProps {
     state: LoadingState;
 }
@@ -15,6 +28,7 @@ export function LoadingIndicator({ state }: LoadingIndicatorProps) {
             <div className={styles.spinner} />
             <span className={styles.text}>
                 {state.type === 'background-sync' && 'Checking for new snapshots...'}
+                {state.type === 'priming' && primingText(state)}
                 {state.type === 'fetching-stats' && (
                     `Loading stats: ${state.processed ?? 0}`
                 )}
@@ -2,6 +2,7 @@ import { useState, useCallback, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { ask } from '@tauri-apps/plugin-dialog';
 import { Repository, SavedRepository, Snapshot } from '../types';
+import { errorMessage } from '../utils/errors';
 
 interface RepositoryConnection {
   id: string;
@@ -135,7 +136,7 @@ export function useRepositories(): UseRepositoriesReturn {
 
       onSuccess(repoId, snapshotList.length);
     } catch (err) {
-      setError(`Failed to connect: ${err}`);
+      setError(`Failed to connect: ${errorMessage(err)}`);
       console.error('Connection error:', err);
       setLoading(false);
     }
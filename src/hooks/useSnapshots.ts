@@ -1,7 +1,19 @@
 import { useState, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Snapshot, SnapshotWithStats, DbSnapshotWithStats, RepoMeta, LoadingState } from '../types';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import {
+  Snapshot,
+  SnapshotWithStats,
+  DbSnapshotWithStats,
+  RepoMeta,
+  LoadingState,
+  CachePrimingProgress,
+  CachePrimingResult,
+  SnapshotsDelta,
+  SnapshotStatsResult
+} from '../types';
 import { CACHE } from '../config/constants';
+import { errorMessage } from '../utils/errors';
 
 interface RepositoryConnection {
   id: string;
@@ -131,32 +143,37 @@ export function useSnapshots(): UseSnapshotsReturn {
     setRepoLoadingState(repoId, { type: 'background-sync' });
 
     try {
-      const freshSnapshots = await invoke<Snapshot[]>('list_snapshots', {
+      // Saves new snapshots and drops forgotten ones from the cache
+      const delta = await invoke<SnapshotsDelta>('sync_snapshots', {
+        repoId,
         repo: connection.path,
         password: connection.password
       });
 
       if (abortController.signal.aborted) return;
 
-      const cachedIds = await invoke<string[]>('get_cached_snapshot_ids', { repoId });
+      const newSnapshots = delta.added;
+
+      if (delta.removed.length > 0 && currentActiveRepoId === repoId) {
+        console.log(` ${delta.removed.length} snapshots were removed from ${repoId}`);
+        const updated = await invoke<DbSnapshotWithStats[]>('load_snapshots_from_db', { repoId });
+        const uiSnapshots = updated.map(s => convertDbSnapshotToUi(s, formatBytes));
+        safeSetSnapshots(repoId, uiSnapshots);
 
-      const cachedIdSet = new Set(cachedIds);
-      const newSnapshots = freshSnapshots.filter(s => !cachedIdSet.has(s.id));
+        const cache = memoryCacheMap.get(repoId);
+        if (cache) {
+          cache.snapshots = uiSnapshots;
+        }
+      }
 
       if (newSnapshots.length === 0) {
         console.log(` No new snapshots for ${repoId}`);
-        await invoke('update_last_delta_check', { repoId });
         setRepoLoadingState(repoId, { type: 'idle' });
         return;
       }
 
       console.log(` Found ${newSnapshots.length} new snapshots for ${repoId}`);
 
-      await invoke('save_snapshots_metadata_only', {
-        repoId,
-        snapshots: newSnapshots
-      });
-
       setRepoLoadingState(repoId, {
         type: 'fetching-stats',
         processed: 0
@@ -170,7 +187,7 @@ export function useSnapshots(): UseSnapshotsReturn {
 
         const batchWithStats = await Promise.all(
           batch.map(async (snapshot) => {
-            const stats = await invoke<{ total_size: number; total_file_count: number }>('get_snapshot_stats', {
+            const { stats } = await invoke<SnapshotStatsResult>('get_snapshot_stats', {
               repo: connection.path,
               password: connection.password,
               snapshotId: snapshot.id
@@ -244,11 +261,23 @@ export function useSnapshots(): UseSnapshotsReturn {
       });
     }
 
+    let unlistenPriming: UnlistenFn | null = null;
     try {
-      const allSnapshots = await invoke<Snapshot[]>('list_snapshots', {
+      setRepoLoadingState(repoId, { type: 'priming', stage: 'fetching_list' });
+      unlistenPriming = await listen<CachePrimingProgress>('cache-priming-progress', event => {
+        const { repo_id, stage, done, total } = event.payload;
+        if (repo_id === repoId && stage !== 'done') {
+          setRepoLoadingState(repoId, { type: 'priming', stage, current: done, total });
+        }
+      });
+
+      const { snapshots: allSnapshots } = await invoke<CachePrimingResult>('prime_snapshot_cache', {
+        repoId,
         repo: connection.path,
         password: connection.password
       });
+      unlistenPriming();
+      unlistenPriming = null;
 
       console.log(` Restic returned ${allSnapshots.length} snapshots for ${repoId}`);
 
@@ -258,17 +287,10 @@ export function useSnapshots(): UseSnapshotsReturn {
 
       const snapshotsWithoutStats: SnapshotWithStats[] = sortedByTime.map(s => ({ ...s }));
 
-      console.log(`  Displaying ${snapshotsWithoutStats.length} snapshots immediately (no stats yet)`);
+      console.log(`  Displaying ${snapshotsWithoutStats.length} snapshots (no stats yet)`);
       safeSetSnapshots(repoId, snapshotsWithoutStats);
       setLoading(false);
 
-      console.log(` Saving metadata for ${allSnapshots.length} snapshots to SQLite...`);
-      await invoke('save_snapshots_metadata_only', {
-        repoId,
-        snapshots: allSnapshots
-      });
-      console.log(` Metadata save command completed`);
-
       const prioritySnapshots = sortedByTime.slice(0, 20);
       const remainingSnapshots = sortedByTime.slice(20);
       console.log(` Will fetch stats: ${prioritySnapshots.length} priority + ${remainingSnapshots.length} remaining = ${allSnapshots.length} total`);
@@ -306,10 +328,11 @@ export function useSnapshots(): UseSnapshotsReturn {
 
     } catch (err) {
       console.error('Full sync error:', err);
-      setError(`Failed to sync snapshots: ${err}`);
+      setError(`Failed to sync snapshots: ${errorMessage(err)}`);
       setLoading(false);
       setRepoLoadingState(repoId, { type: 'idle' });
     } finally {
+      unlistenPriming?.();
       const cache = memoryCacheMap.get(repoId);
       if (cache) {
         cache.syncInProgress = false;
@@ -334,7 +357,7 @@ export function useSnapshots(): UseSnapshotsReturn {
 
       const batchWithStats = await Promise.all(
         batch.map(async (snapshot) => {
-          const stats = await invoke<{ total_size: number; total_file_count: number }>('get_snapshot_stats', {
+          const { stats } = await invoke<SnapshotStatsResult>('get_snapshot_stats', {
             repo: connection.path,
             password: connection.password,
             snapshotId: snapshot.id
@@ -538,7 +561,7 @@ export function useSnapshots(): UseSnapshotsReturn {
       console.timeEnd(`Load snapshots for ${repoId}`);
 
     } catch (err) {
-      setError(`Failed to load snapshots: ${err}`);
+      setError(`Failed to load snapshots: ${errorMessage(err)}`);
       console.error('Load snapshots error:', err);
       setLoading(false);
       setLoadingState({ type: 'idle' });
@@ -581,7 +604,7 @@ export function useSnapshots(): UseSnapshotsReturn {
     });
 
     try {
-      const stats = await invoke<{ total_size: number; total_file_count: number }>('get_snapshot_stats', {
+      const { stats } = await invoke<SnapshotStatsResult>('get_snapshot_stats', {
         repo: connection.path,
         password: connection.password,
         snapshotId
@@ -10,6 +10,28 @@ export interface Snapshot {
     parent?: string;
 }
 
+export interface SnapshotFilter {
+    hosts?: string[];
+    tags?: string[];
+    path_prefix?: string;
+    after?: string;
+    before?: string;
+}
+
+export type SnapshotGroupBy = 'host' | 'tag' | 'path';
+
+export interface SnapshotGroup<T> {
+    key: string | null;
+    snapshots: T[];
+}
+
+/** Payload of the `snapshots-delta` event and result of `sync_snapshots` */
+export interface SnapshotsDelta {
+    repo_id: string;
+    added: Snapshot[];
+    removed: Snapshot[];
+}
+
 export interface SnapshotWithStats extends Snapshot {
     size?: string;
     fileCount?: number;
@@ -26,11 +48,26 @@ export interface Repository {
     totalSize?: string;
 }
 
+/** Rejection value of every backend command */
+export interface CommandError {
+    code: string;
+    message: string;
+    args: string[];
+}
+
 export interface SavedRepository {
     id: string;
     name: string;
     path: string;
     password: string;
+    extra_env?: Record<string, string>;
+}
+
+export interface RepositoryWithStatus extends SavedRepository {
+    snapshot_count: number;
+    last_delta_check: number;
+    last_connected_at: number | null;
+    aggregates_stale: boolean;
 }
 
 export interface FileNode {
@@ -41,6 +78,29 @@ export interface FileNode {
     mtime?: string;
 }
 
+export type RestoreErrorKind = 'permissions' | 'path_too_long' | 'disk_full' | 'other';
+
+/** A path restic skipped during a restore */
+export interface RestorePathError {
+    path: string;
+    error_kind: RestoreErrorKind;
+    message: string;
+}
+
+export interface MetadataSummary {
+    adjusted: number;
+    failed: RestorePathError[];
+}
+
+/** Result of `restore_snapshot` and `restore_selective` */
+export interface RestoreResult {
+    message: string;
+    errors: RestorePathError[];
+    elevated: boolean;
+    operation_id: string;
+    metadata?: MetadataSummary;
+}
+
 export interface SnapshotStats {
     total_size: number;
     total_file_count: number;
@@ -51,6 +111,11 @@ export interface ResticSnapshotStats {
     total_file_count: number;
 }
 
+export interface SnapshotStatsResult {
+    operation_id: string;
+    stats: ResticSnapshotStats;
+}
+
 export interface ResticRepositoryStats {
     total_size: number;
 }
@@ -66,10 +131,14 @@ export interface RepoMeta {
     repo_id: string;
     last_delta_check: number;
     snapshot_count: number;
+    aggregates_stale: boolean;
+    last_connected_at: number | null;
 }
 
 // Loading indicator states
-export type LoadingStateType = 'idle' | 'background-sync' | 'fetching-stats' | 'manual-load';
+export type LoadingStateType = 'idle' | 'background-sync' | 'priming' | 'fetching-stats' | 'manual-load';
+
+export type PrimingStage = 'fetching_list' | 'parsing' | 'writing' | 'building_facets' | 'done';
 
 export interface LoadingState {
     type: LoadingStateType;
@@ -77,4 +146,28 @@ export interface LoadingState {
     total?: number;
     processed?: number;
     snapshotName?: string;
+    stage?: PrimingStage;
+}
+
+export interface CachePrimingProgress {
+    repo_id: string;
+    stage: PrimingStage;
+    done: number;
+    total: number;
+}
+
+export interface FacetCount {
+    value: string;
+    count: number;
+}
+
+export interface SnapshotFacets {
+    hosts: FacetCount[];
+    tags: FacetCount[];
+    paths: FacetCount[];
+}
+
+export interface CachePrimingResult {
+    snapshots: Snapshot[];
+    facets: SnapshotFacets;
 }
\ No newline at end of file
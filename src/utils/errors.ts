@@ -0,0 +1,15 @@
+import { CommandError } from '../types';
+
+function isCommandError(err: unknown): err is CommandError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+/** Text to show for an error thrown by `invoke` */
+export function errorMessage(err: unknown): string {
+  return isCommandError(err) ? err.message : String(err);
+}
+
+/** The backend's error code, or undefined for errors that didn't come from a command */
+export function errorCode(err: unknown): string | undefined {
+  return isCommandError(err) ? err.code : undefined;
+}